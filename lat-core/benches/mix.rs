@@ -0,0 +1,46 @@
+//! Logistic mixing throughput, SIMD and table lookups against scalar.
+//!
+//! Run with `cargo bench -p lat-core --bench mix`.
+
+use lat_core::cpu::{mix, mix_scalar, mix_with_table};
+use lat_core::logistic::LogisticTable;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const NUM_MODELS: usize = 8;
+const NUM_BITS: usize = 1 << 20;
+const ROUNDS: u32 = 10;
+
+fn time(f: impl Fn() -> Vec<f32>) -> Duration {
+    black_box(f());
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let len = NUM_MODELS * NUM_BITS;
+    let probs: Vec<f32> = (0..len)
+        .map(|i| ((i * 37 % 1000) as f32 + 0.5) / 1000.0)
+        .collect();
+    let weights: Vec<f32> = (0..len)
+        .map(|i| ((i * 13 % 200) as f32 - 100.0) / 50.0)
+        .collect();
+
+    let scalar = time(|| mix_scalar(black_box(&probs), black_box(&weights), NUM_BITS));
+    let simd = time(|| mix(black_box(&probs), black_box(&weights), NUM_BITS));
+    let table = LogisticTable::default();
+    let lookup = time(|| mix_with_table(black_box(&probs), black_box(&weights), NUM_BITS, &table));
+    println!(
+        "mix {} models x {} bits: scalar {:?}, simd {:?} ({:.1}x), table {:?} ({:.1}x)",
+        NUM_MODELS,
+        NUM_BITS,
+        scalar,
+        simd,
+        scalar.as_secs_f64() / simd.as_secs_f64(),
+        lookup,
+        scalar.as_secs_f64() / lookup.as_secs_f64()
+    );
+}
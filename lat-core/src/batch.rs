@@ -0,0 +1,266 @@
+//! Compressing many files into an archive each, named from an output template.
+
+use crate::{ArchiveEntry, Compressor, LatError};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Name,
+    Ext,
+    Date,
+}
+
+/// File name pattern for batch outputs, e.g. `{name}.{ext}` or `{date}-{name}.{ext}`.
+///
+/// `{name}` is the input's file name, `{ext}` the archive format's extension and `{date}`
+/// the UTC date of the run as `YYYY-MM-DD`. `{{` and `}}` are literal braces. Templates
+/// must use `{name}`, so inputs don't all map to one output, and can't contain path
+/// separators, so outputs stay in the chosen directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    parts: Vec<Part>,
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self, LatError> {
+        let invalid =
+            |why: String| LatError::InvalidInput(format!("template '{}': {}", template, why));
+        if template.contains(['/', '\\']) {
+            return Err(invalid("must not contain path separators".to_string()));
+        }
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(invalid("unclosed '{'".to_string())),
+                        }
+                    }
+                    let part = match name.as_str() {
+                        "name" => Part::Name,
+                        "ext" => Part::Ext,
+                        "date" => Part::Date,
+                        _ => return Err(invalid(format!("unknown placeholder {{{}}}", name))),
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(part);
+                }
+                '}' => return Err(invalid("unmatched '}'".to_string())),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        if !parts.contains(&Part::Name) {
+            return Err(invalid("must use {name}".to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// The output file name for an input called `name`.
+    pub fn resolve(&self, name: &str, ext: &str, date: &str) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Name => out.push_str(name),
+                Part::Ext => out.push_str(ext),
+                Part::Date => out.push_str(date),
+            }
+        }
+        out
+    }
+}
+
+/// Compresses each of `inputs` into its own archive in `out_dir`, named by `template`,
+/// and returns the paths written, in input order. An output that would overwrite an
+/// existing file or an earlier output of the batch gets " (2)", " (3)", ... before its
+/// extension instead. Stops at the first input that can't be read or compressed, with an
+/// error naming it and the stage that failed.
+pub fn compress_each(
+    compressor: &dyn Compressor,
+    inputs: &[PathBuf],
+    out_dir: &Path,
+    template: &OutputTemplate,
+) -> Result<Vec<PathBuf>, LatError> {
+    let ext = compressor.format().extension();
+    let date = utc_date(SystemTime::now());
+    let mut written = Vec::with_capacity(inputs.len());
+    let mut taken = HashSet::new();
+    for input in inputs {
+        let name = input
+            .file_name()
+            .ok_or_else(|| LatError::InvalidInput(format!("{} has no file name", input.display())))?
+            .to_string_lossy()
+            .into_owned();
+        let data =
+            fs::read(input).map_err(|e| LatError::from(e).context(Some(&name), "reading"))?;
+        let entry = ArchiveEntry::builder(name.clone()).data(data).build();
+        let archive = compressor
+            .compress(std::slice::from_ref(&entry), None)
+            .map_err(|e| e.context(Some(&name), "compression"))?;
+
+        let resolved = template.resolve(&name, ext, &date);
+        let (path, mut file) = create_unique(out_dir, &resolved, &mut taken)
+            .map_err(|e| e.context(Some(&name), "writing"))?;
+        file.write_all(&archive)
+            .map_err(|e| LatError::from(e).context(Some(&name), "writing"))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Creates `name` in `dir`, or the first free numbered variant of it. Creating with
+/// `create_new` means a file that appears meanwhile is never overwritten either.
+fn create_unique(
+    dir: &Path,
+    name: &str,
+    taken: &mut HashSet<PathBuf>,
+) -> Result<(PathBuf, fs::File), LatError> {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    for n in 1.. {
+        let candidate = match n {
+            1 => dir.join(name),
+            n => dir.join(format!("{} ({}){}", stem, n, ext)),
+        };
+        if taken.contains(&candidate) {
+            continue;
+        }
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => {
+                taken.insert(candidate.clone());
+                return Ok((candidate, file));
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!("ran out of numbered names")
+}
+
+/// `time` as a UTC `YYYY-MM-DD` date.
+fn utc_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86_400) as i64;
+    // Days to civil date (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::IndexCompressor;
+    use std::time::Duration;
+
+    #[test]
+    fn test_batch_names_outputs_from_template() {
+        let dir = std::env::temp_dir().join(format!("lat-batch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let inputs: Vec<PathBuf> = ["a.txt", "b.csv", "c"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, name.repeat(10)).unwrap();
+                path
+            })
+            .collect();
+        // Already there, so the batch must not overwrite it.
+        fs::write(out.join("c.test"), b"keep").unwrap();
+
+        let template = OutputTemplate::parse("{name}.{ext}").unwrap();
+        let written = compress_each(&IndexCompressor, &inputs, &out, &template).unwrap();
+        assert_eq!(
+            written,
+            [
+                out.join("a.txt.test"),
+                out.join("b.csv.test"),
+                out.join("c (2).test")
+            ]
+        );
+        assert_eq!(fs::read(out.join("c.test")).unwrap(), b"keep");
+        let entries = IndexCompressor
+            .decompress(&fs::read(&written[1]).unwrap(), None)
+            .unwrap();
+        assert_eq!(entries[0].name, "b.csv");
+        assert_eq!(entries[0].data, "b.csv".repeat(10).as_bytes());
+
+        let dated = OutputTemplate::parse("{date}_{name}.{ext}").unwrap();
+        assert_eq!(
+            dated.resolve("a.txt", "zip", "2024-02-29"),
+            "2024-02-29_a.txt.zip"
+        );
+
+        let missing = compress_each(&IndexCompressor, &[dir.join("gone.txt")], &out, &template);
+        let err = missing.unwrap_err();
+        assert!(err.to_string().starts_with("'gone.txt' during reading"));
+        assert!(matches!(err.root_cause(), LatError::Io(_)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        for template in [
+            "archive.zip",
+            "{name}.{size}",
+            "{name}/x.{ext}",
+            "{name}}",
+            "{name",
+        ] {
+            assert!(
+                matches!(
+                    OutputTemplate::parse(template),
+                    Err(LatError::InvalidInput(_))
+                ),
+                "{}",
+                template
+            );
+        }
+        let braces = OutputTemplate::parse("{{{name}}}").unwrap();
+        assert_eq!(braces.resolve("a", "zip", ""), "{a}");
+
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+        assert_eq!(utc_date(leap_day), "2024-02-29");
+        assert_eq!(utc_date(UNIX_EPOCH), "1970-01-01");
+    }
+}
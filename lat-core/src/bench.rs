@@ -0,0 +1,191 @@
+//! Ratio and throughput of several formats on the caller's own data.
+//!
+//! [`run`] compresses and decompresses the same entries with each format/level and
+//! [`render_table`] prints the results, so evaluating the formats doesn't need a
+//! benchmark harness.
+
+use crate::{ArchiveEntry, CompressorBuilder, FormatFactory, GpuAccelerator, LatError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// One format and level to measure.
+#[derive(Clone, Copy)]
+pub struct BenchTarget<'a> {
+    /// Name shown in the table.
+    pub format: &'a str,
+    pub factory: &'a dyn FormatFactory,
+    /// `None` uses the format's default level.
+    pub level: Option<u32>,
+}
+
+/// Measurements for one [`BenchTarget`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchRow {
+    pub format: String,
+    pub level: Option<u32>,
+    /// Archive size divided by input size, as in
+    /// [`CompressionResult::ratio`](crate::CompressionResult::ratio).
+    pub ratio: f64,
+    /// Input megabytes (10^6 bytes) per second.
+    pub compress_mbps: f64,
+    pub decompress_mbps: f64,
+    /// Why the target wasn't measured, e.g. "no GPU". The figures are 0 when set.
+    pub skipped: Option<String>,
+}
+
+/// Benchmarks each target on `entries`, one row per target. Targets whose format needs an
+/// accelerator get a skipped row when `accelerator` is `None`. Each archive is decompressed and checked against
+/// the input, so a format that doesn't round-trip fails the run with
+/// [`LatError::CorruptArchive`].
+pub fn run(
+    entries: &[ArchiveEntry],
+    targets: &[BenchTarget],
+    accelerator: Option<Arc<dyn GpuAccelerator>>,
+) -> Result<Vec<BenchRow>, LatError> {
+    let mut rows = Vec::with_capacity(targets.len());
+    for target in targets {
+        if target.factory.requires_accelerator() && accelerator.is_none() {
+            rows.push(BenchRow {
+                format: target.format.to_string(),
+                level: target.level,
+                ratio: 0.0,
+                compress_mbps: 0.0,
+                decompress_mbps: 0.0,
+                skipped: Some("no GPU".to_string()),
+            });
+            continue;
+        }
+        let mut builder = CompressorBuilder::new(target.factory).accelerator(accelerator.clone());
+        if let Some(level) = target.level {
+            builder = builder.level(level);
+        }
+        let compressor = builder.build()?;
+
+        let start = Instant::now();
+        let result = compressor.compress_detailed(entries, None)?;
+        let compress_time = start.elapsed();
+        let start = Instant::now();
+        let restored = compressor.decompress(&result.data, None)?;
+        let decompress_time = start.elapsed();
+
+        let same = restored.len() == entries.len()
+            && restored
+                .iter()
+                .zip(entries)
+                .all(|(a, b)| a.name == b.name && a.data == b.data);
+        if !same {
+            return Err(LatError::CorruptArchive(format!(
+                "{} did not round-trip the input",
+                target.format
+            )));
+        }
+        rows.push(BenchRow {
+            format: target.format.to_string(),
+            level: target.level,
+            ratio: result.ratio(),
+            compress_mbps: mbps(result.uncompressed_size, compress_time),
+            decompress_mbps: mbps(result.uncompressed_size, decompress_time),
+            skipped: None,
+        });
+    }
+    Ok(rows)
+}
+
+fn mbps(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / 1e6 / elapsed.as_secs_f64().max(1e-9)
+}
+
+/// Formats `rows` as a whitespace-aligned table with a header line. Levels left at the
+/// format's default show as `-`, and skipped targets show the reason instead of figures.
+pub fn render_table(rows: &[BenchRow]) -> String {
+    let mut out = format!(
+        "{:<10} {:>5} {:>7} {:>14} {:>16}\n",
+        "format", "level", "ratio", "compress MB/s", "decompress MB/s"
+    );
+    for row in rows {
+        let level = row.level.map_or("-".to_string(), |level| level.to_string());
+        if let Some(reason) = &row.skipped {
+            out.push_str(&format!(
+                "{:<10} {:>5} skipped ({})\n",
+                row.format, level, reason
+            ));
+            continue;
+        }
+        out.push_str(&format!(
+            "{:<10} {:>5} {:>7.3} {:>14.1} {:>16.1}\n",
+            row.format, level, row.ratio, row.compress_mbps, row.decompress_mbps
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::IndexCompressor;
+    use crate::{Compressor, CompressorConfig};
+
+    /// Builds [`IndexCompressor`]s, optionally claiming to need a GPU.
+    struct IndexFormat {
+        gpu: bool,
+    }
+
+    impl FormatFactory for IndexFormat {
+        fn build(&self, _: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+            Ok(Box::new(IndexCompressor))
+        }
+
+        fn requires_accelerator(&self) -> bool {
+            self.gpu
+        }
+    }
+
+    #[test]
+    fn test_bench_table_lists_available_formats() {
+        let entries = vec![
+            ArchiveEntry::builder("input.txt")
+                .data(b"benchmark me ".repeat(4096))
+                .build(),
+        ];
+        let targets = [
+            BenchTarget {
+                format: "index",
+                factory: &IndexFormat { gpu: false },
+                level: Some(1),
+            },
+            BenchTarget {
+                format: "index-gpu",
+                factory: &IndexFormat { gpu: true },
+                level: None,
+            },
+        ];
+        let rows = run(&entries, &targets, None).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].skipped, None);
+        assert_eq!(rows[1].skipped.as_deref(), Some("no GPU"));
+
+        let table = render_table(&rows);
+        let mut lines = table.lines();
+        assert_eq!(
+            lines.next().unwrap().split_whitespace().collect::<Vec<_>>(),
+            [
+                "format",
+                "level",
+                "ratio",
+                "compress",
+                "MB/s",
+                "decompress",
+                "MB/s"
+            ]
+        );
+        let fields: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+        assert_eq!(&fields[..2], ["index", "1"]);
+        // The index stores data as-is plus a small header.
+        let ratio: f64 = fields[2].parse().unwrap();
+        assert!(ratio > 1.0 && ratio < 1.1);
+        assert!(fields[3].parse::<f64>().unwrap() > 0.0);
+        assert!(fields[4].parse::<f64>().unwrap() > 0.0);
+        assert_eq!(lines.next(), Some("index-gpu      - skipped (no GPU)"));
+        assert!(lines.next().is_none());
+    }
+}
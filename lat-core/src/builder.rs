@@ -0,0 +1,432 @@
+use crate::path::OnDuplicate;
+use crate::sample::EntrySample;
+use crate::store::BlockStore;
+use crate::{
+    ArchiveEntry, ArchiveFormat, ArchiveInfo, ChecksumAlgo, CompressionResult, Compressor,
+    Deadline, GpuAccelerator, LatError, ListedEntry, TrailingData, VerifyReport,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Format-independent settings handed to a [`FormatFactory`].
+///
+/// Every field is optional; a format applies what it understands and ignores the rest
+/// (e.g. ZIP has no use for `threads`, `.lat` ignores `level`).
+#[derive(Clone, Default)]
+pub struct CompressorConfig {
+    pub level: Option<u32>,
+    /// Format-specific method name such as "deflated" (ZIP) or "lzma2" (7z).
+    pub method: Option<String>,
+    pub threads: Option<usize>,
+    /// Budget in bytes for the buffers in flight in formats that batch work. It doesn't
+    /// cover the input, which is held in memory regardless.
+    pub memory_limit: Option<u64>,
+    /// Produce byte-identical output for identical input (no timestamps etc.).
+    pub deterministic: bool,
+    /// Per-entry checksum; `None` keeps the format's default.
+    pub checksum: Option<ChecksumAlgo>,
+    pub accelerator: Option<Arc<dyn GpuAccelerator>>,
+    /// What decompression does with bytes after the archive's end.
+    pub trailing_data: TrailingData,
+}
+
+/// Constructs a format's compressor from a [`CompressorConfig`].
+pub trait FormatFactory: Send + Sync {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError>;
+
+    /// Whether compressing needs [`CompressorConfig::accelerator`], so front ends can
+    /// offer the format only when one is configured.
+    fn requires_accelerator(&self) -> bool {
+        false
+    }
+}
+
+/// Fluent configuration for any format:
+///
+/// ```ignore
+/// let zip = CompressorBuilder::new(&ZipFormat).level(9).deterministic(true).build()?;
+/// ```
+///
+/// A builder is a cheap snapshot of its settings: clone it to build more compressors
+/// configured the same way.
+#[derive(Clone)]
+pub struct CompressorBuilder<'a> {
+    factory: &'a dyn FormatFactory,
+    config: CompressorConfig,
+    password: Option<String>,
+}
+
+impl<'a> CompressorBuilder<'a> {
+    pub fn new(factory: &'a dyn FormatFactory) -> Self {
+        Self {
+            factory,
+            config: CompressorConfig::default(),
+            password: None,
+        }
+    }
+
+    /// Starts from previously captured settings, e.g. from [`CompressorBuilder::config`].
+    pub fn from_config(factory: &'a dyn FormatFactory, config: CompressorConfig) -> Self {
+        Self {
+            factory,
+            config,
+            password: None,
+        }
+    }
+
+    /// The settings collected so far.
+    pub fn config(&self) -> &CompressorConfig {
+        &self.config
+    }
+
+    pub fn level(mut self, level: u32) -> Self {
+        self.config.level = Some(level);
+        self
+    }
+
+    pub fn method(mut self, method: &str) -> Self {
+        self.config.method = Some(method.to_string());
+        self
+    }
+
+    /// Password used whenever `compress`/`decompress` is called without one.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = Some(threads);
+        self
+    }
+
+    pub fn memory_limit(mut self, bytes: u64) -> Self {
+        self.config.memory_limit = Some(bytes);
+        self
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.config.deterministic = deterministic;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: ChecksumAlgo) -> Self {
+        self.config.checksum = Some(checksum);
+        self
+    }
+
+    pub fn accelerator(mut self, accelerator: Option<Arc<dyn GpuAccelerator>>) -> Self {
+        self.config.accelerator = accelerator;
+        self
+    }
+
+    pub fn trailing_data(mut self, policy: TrailingData) -> Self {
+        self.config.trailing_data = policy;
+        self
+    }
+
+    /// Builds the compressor, failing if an option is invalid for the format or a password
+    /// was set on a format that can't use one.
+    pub fn build(self) -> Result<Box<dyn Compressor>, LatError> {
+        let inner = self.factory.build(&self.config)?;
+        match self.password {
+            Some(password) => {
+                inner.check_password(Some(&password))?;
+                Ok(Box::new(WithPassword { inner, password }))
+            }
+            None => Ok(inner),
+        }
+    }
+}
+
+/// Supplies a configured password to calls that don't pass their own. Every method is
+/// forwarded, so the inner format's overrides (header-only listing, sampled verify, ...)
+/// still apply.
+struct WithPassword {
+    inner: Box<dyn Compressor>,
+    password: String,
+}
+
+impl WithPassword {
+    fn password<'a>(&'a self, password: Option<&'a str>) -> Option<&'a str> {
+        password.or(Some(self.password.as_str()))
+    }
+}
+
+impl Compressor for WithPassword {
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        self.inner.compress(entries, self.password(password))
+    }
+
+    fn decompress(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.inner.decompress(archive, self.password(password))
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        self.inner.format()
+    }
+
+    fn supports_password(&self) -> bool {
+        true
+    }
+
+    fn compress_detailed(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<CompressionResult, LatError> {
+        self.inner
+            .compress_detailed(entries, self.password(password))
+    }
+
+    fn compress_with_deadline(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<Vec<u8>, LatError> {
+        self.inner
+            .compress_with_deadline(entries, self.password(password), deadline)
+    }
+
+    fn decompress_matching(
+        &self,
+        archive: &[u8],
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.inner
+            .decompress_matching(archive, pattern, self.password(password))
+    }
+
+    fn decompress_iter<'a>(
+        &'a self,
+        archive: &'a [u8],
+        password: Option<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<ArchiveEntry, LatError>> + 'a>, LatError> {
+        self.inner.decompress_iter(archive, self.password(password))
+    }
+
+    fn decompress_matching_from(
+        &self,
+        store: &dyn BlockStore,
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.inner
+            .decompress_matching_from(store, pattern, self.password(password))
+    }
+
+    fn decompress_to_map(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        on_duplicate: OnDuplicate,
+    ) -> Result<HashMap<String, Vec<u8>>, LatError> {
+        self.inner
+            .decompress_to_map(archive, self.password(password), on_duplicate)
+    }
+
+    fn archive_info(&self, archive: &[u8]) -> Result<ArchiveInfo, LatError> {
+        self.inner.archive_info(archive)
+    }
+
+    fn verify(&self, archive: &[u8], password: Option<&str>) -> Result<(), LatError> {
+        self.inner.verify(archive, self.password(password))
+    }
+
+    fn quick_verify(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        sample: &EntrySample,
+    ) -> Result<VerifyReport, LatError> {
+        self.inner
+            .quick_verify(archive, self.password(password), sample)
+    }
+
+    fn list(&self, archive: &[u8], password: Option<&str>) -> Result<Vec<ListedEntry>, LatError> {
+        self.inner.list(archive, self.password(password))
+    }
+
+    fn list_with_progress(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<Vec<ListedEntry>, LatError> {
+        self.inner
+            .list_with_progress(archive, self.password(password), on_progress)
+    }
+
+    fn extract_dirs_only(
+        &self,
+        archive: &[u8],
+        dest: &Path,
+        password: Option<&str>,
+    ) -> Result<Vec<PathBuf>, LatError> {
+        self.inner
+            .extract_dirs_only(archive, dest, self.password(password))
+    }
+
+    fn compress_to_file(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        path: &Path,
+        verify_after_write: bool,
+    ) -> Result<(), LatError> {
+        self.inner
+            .compress_to_file(entries, self.password(password), path, verify_after_write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format that records the config it was built with and echoes the password it sees.
+    struct EchoFormat {
+        supports_password: bool,
+    }
+
+    struct Echo {
+        config: CompressorConfig,
+        supports_password: bool,
+    }
+
+    impl FormatFactory for EchoFormat {
+        fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+            Ok(Box::new(Echo {
+                config: config.clone(),
+                supports_password: self.supports_password,
+            }))
+        }
+    }
+
+    impl Compressor for Echo {
+        fn compress(
+            &self,
+            _entries: &[ArchiveEntry],
+            password: Option<&str>,
+        ) -> Result<Vec<u8>, LatError> {
+            self.check_password(password)?;
+            let mut out = format!(
+                "{:?}/{:?}/{:?}/{}",
+                self.config.level,
+                self.config.method,
+                self.config.threads,
+                self.config.deterministic
+            )
+            .into_bytes();
+            out.extend_from_slice(password.unwrap_or("-").as_bytes());
+            Ok(out)
+        }
+
+        fn decompress(
+            &self,
+            _archive: &[u8],
+            _password: Option<&str>,
+        ) -> Result<Vec<ArchiveEntry>, LatError> {
+            Ok(Vec::new())
+        }
+
+        fn format(&self) -> ArchiveFormat {
+            ArchiveFormat::Test
+        }
+
+        fn supports_password(&self) -> bool {
+            self.supports_password
+        }
+
+        /// Header-only listing: never calls `decompress`, and names the password it got.
+        fn list_with_progress(
+            &self,
+            _archive: &[u8],
+            password: Option<&str>,
+            _on_progress: &mut dyn FnMut(usize),
+        ) -> Result<Vec<ListedEntry>, LatError> {
+            Ok(vec![ListedEntry {
+                name: password.unwrap_or("-").to_string(),
+                kind: crate::EntryKind::File,
+                size: 0,
+            }])
+        }
+
+        /// Sampled verify: claims to have checked one of ten entries, given the password.
+        fn quick_verify(
+            &self,
+            _archive: &[u8],
+            password: Option<&str>,
+            _sample: &EntrySample,
+        ) -> Result<VerifyReport, LatError> {
+            if password != Some("pw") {
+                return Err(LatError::WrongPassword);
+            }
+            Ok(VerifyReport {
+                entries_checked: 1,
+                entries_total: 10,
+            })
+        }
+    }
+
+    #[test]
+    fn test_builder_forwards_options() {
+        let format = EchoFormat {
+            supports_password: true,
+        };
+        let compressor = CompressorBuilder::new(&format)
+            .level(7)
+            .method("fast")
+            .threads(4)
+            .deterministic(true)
+            .password("pw")
+            .build()
+            .expect("Build failed");
+
+        let out = compressor.compress(&[], None).unwrap();
+        assert_eq!(out, b"Some(7)/Some(\"fast\")/Some(4)/truepw");
+
+        // An explicit password still wins over the configured one.
+        let out = compressor.compress(&[], Some("other")).unwrap();
+        assert!(out.ends_with(b"other"));
+    }
+
+    #[test]
+    fn test_password_wrapper_keeps_format_overrides() {
+        let format = EchoFormat {
+            supports_password: true,
+        };
+        let compressor = CompressorBuilder::new(&format)
+            .password("pw")
+            .build()
+            .expect("Build failed");
+
+        // The defaults would decompress (to nothing) instead of using Echo's overrides.
+        let listed = compressor.list(b"", None).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "pw");
+        let report = compressor
+            .quick_verify(b"", None, &EntrySample::new(0.1))
+            .unwrap();
+        assert_eq!((report.entries_checked, report.entries_total), (1, 10));
+    }
+
+    #[test]
+    fn test_builder_rejects_unsupported_password() {
+        let format = EchoFormat {
+            supports_password: false,
+        };
+        let result = CompressorBuilder::new(&format).password("pw").build();
+        assert!(matches!(result, Err(LatError::UnsupportedFormat(_))));
+    }
+}
@@ -0,0 +1,175 @@
+//! Content-defined chunking with a Gear rolling hash.
+//!
+//! Boundaries are chosen from the content itself, so inserting or removing bytes only moves
+//! the boundaries near the edit and the remaining chunks stay byte-identical.
+
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+/// Average chunk size for [`chunk_stream`]; chunks range from 256 KiB to 4 MiB.
+pub const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Gear table: 256 pseudo-random words generated from a fixed seed with SplitMix64, so
+/// chunk boundaries are stable across builds and platforms.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Chunker {
+    min_size: usize,
+    max_size: usize,
+    mask: u64,
+}
+
+impl Chunker {
+    /// Creates a chunker targeting `avg_size` bytes per chunk (rounded to a power of two).
+    /// Chunks are kept between a quarter and four times the average.
+    pub fn new(avg_size: usize) -> Self {
+        let avg = avg_size.max(64).next_power_of_two();
+        let bits = avg.trailing_zeros();
+        // Test the high bits: with the shift-left Gear update they depend on the last 64
+        // bytes, whereas the low bits only see the last few.
+        let mask = ((1u64 << bits) - 1) << (64 - bits);
+        Self {
+            min_size: avg / 4,
+            max_size: avg * 4,
+            mask,
+        }
+    }
+
+    /// Splits `data` into consecutive chunk ranges covering the whole input.
+    pub fn chunks(&self, data: &[u8]) -> Vec<Range<usize>> {
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < data.len() {
+            let end = start + self.next_boundary(&data[start..]);
+            chunks.push(start..end);
+            start = end;
+        }
+        chunks
+    }
+
+    /// Like [`Chunker::chunks`], but yields chunks lazily along with the SHA-256 of each.
+    pub fn hashed_chunks<'a>(
+        &self,
+        data: &'a [u8],
+    ) -> impl Iterator<Item = (Range<usize>, [u8; 32])> + use<'a> {
+        let chunker = *self;
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            if start >= data.len() {
+                return None;
+            }
+            let end = start + chunker.next_boundary(&data[start..]);
+            let range = start..end;
+            start = end;
+            let hash: [u8; 32] = Sha256::digest(&data[range.clone()]).into();
+            Some((range, hash))
+        })
+    }
+
+    fn next_boundary(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let limit = data.len().min(self.max_size);
+        let mut hash = 0u64;
+        for (i, &byte) in data[..limit].iter().enumerate().skip(self.min_size) {
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            if hash & self.mask == 0 {
+                return i + 1;
+            }
+        }
+        limit
+    }
+}
+
+/// Splits an archive byte stream into content-defined chunks of about
+/// [`UPLOAD_CHUNK_SIZE`] for resumable uploads, each with its SHA-256. When the archive is
+/// regenerated with a small change, only the chunks around the change get new hashes, so
+/// an interrupted upload can resume by sending just the chunks the server doesn't have.
+pub fn chunk_stream(data: &[u8]) -> impl Iterator<Item = (Range<usize>, [u8; 32])> + '_ {
+    Chunker::new(UPLOAD_CHUNK_SIZE).hashed_chunks(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::noise;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_chunks_cover_input() {
+        let data = noise(100_000, 7);
+        let chunker = Chunker::new(2048);
+        let chunks = chunker.chunks(&data);
+
+        assert_eq!(chunks.first().map(|c| c.start), Some(0));
+        assert_eq!(chunks.last().map(|c| c.end), Some(data.len()));
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= 512 && chunk.len() <= 8192);
+        }
+    }
+
+    #[test]
+    fn test_insertion_preserves_most_chunks() {
+        let original = noise(200_000, 42);
+        let mut shifted = b"an inserted prefix that shifts every offset".to_vec();
+        shifted.extend_from_slice(&original);
+
+        let chunker = Chunker::new(4096);
+        let original_chunks: HashSet<&[u8]> = chunker
+            .chunks(&original)
+            .into_iter()
+            .map(|r| &original[r])
+            .collect();
+        let shifted_chunks: Vec<&[u8]> = chunker
+            .chunks(&shifted)
+            .into_iter()
+            .map(|r| &shifted[r])
+            .collect();
+
+        let shared = shifted_chunks
+            .iter()
+            .filter(|chunk| original_chunks.contains(*chunk))
+            .count();
+        assert!(shared * 10 >= shifted_chunks.len() * 8);
+    }
+
+    #[test]
+    fn test_chunk_hashes_survive_an_early_insertion() {
+        let original = noise(12 * 1024 * 1024, 9);
+        let mut edited = original.clone();
+        edited.splice(
+            1000..1000,
+            b"a few bytes added near the start".iter().copied(),
+        );
+
+        let before: Vec<_> = chunk_stream(&original).collect();
+        let after: Vec<_> = chunk_stream(&edited).collect();
+        assert_eq!(before.last().unwrap().0.end, original.len());
+        assert!(before.len() > 4);
+
+        // Everything past the first couple of chunks is unchanged, just shifted.
+        let unchanged: HashSet<[u8; 32]> = after.iter().map(|(_, hash)| *hash).collect();
+        for (range, hash) in &before[2..] {
+            assert!(unchanged.contains(hash), "chunk {:?} changed", range);
+        }
+        assert_ne!(before[0].1, after[0].1);
+    }
+}
@@ -0,0 +1,313 @@
+//! CPU implementation of [`GpuAccelerator`], used when no GPU is available and as the
+//! fallback for a failing one.
+
+use crate::GpuAccelerator;
+use crate::logistic::{LogisticTable, PROB_EPSILON, squash, stretch};
+
+/// Runs everything on the host. Mixing uses 8-wide SIMD on x86_64 and aarch64 and plain
+/// scalar code elsewhere; the two agree to within float rounding of `ln`/`exp`.
+#[derive(Debug, Default)]
+pub struct CpuAccelerator {
+    logistic: Option<LogisticTable>,
+}
+
+impl CpuAccelerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mixes with table lookups instead of `ln`/`exp`. Probabilities differ slightly from
+    /// the exact ones, so a stream must be decoded with a table of the same size as it was
+    /// encoded with.
+    pub fn with_logistic_table(mut self, table: LogisticTable) -> Self {
+        self.logistic = Some(table);
+        self
+    }
+}
+
+impl GpuAccelerator for CpuAccelerator {
+    fn name(&self) -> &str {
+        "CPU"
+    }
+
+    fn run_kernel(&self, name: &str, _data: &mut [u8]) -> Result<(), String> {
+        Err(format!("no CPU implementation of kernel '{}'", name))
+    }
+
+    fn mix_probabilities(
+        &self,
+        model_probs: &[f32],
+        weights: &[f32],
+        num_bits: usize,
+    ) -> Result<Vec<f32>, String> {
+        check_layout(model_probs, weights, num_bits)?;
+        Ok(match &self.logistic {
+            Some(table) => mix_with_table(model_probs, weights, num_bits, table),
+            None => mix(model_probs, weights, num_bits),
+        })
+    }
+}
+
+fn check_layout(model_probs: &[f32], weights: &[f32], num_bits: usize) -> Result<(), String> {
+    if model_probs.len() != weights.len() {
+        return Err(format!(
+            "{} model probabilities but {} weights",
+            model_probs.len(),
+            weights.len()
+        ));
+    }
+    if num_bits == 0 || model_probs.len() % num_bits != 0 {
+        return Err(format!(
+            "{} model probabilities is not a whole number of models for {} bits",
+            model_probs.len(),
+            num_bits
+        ));
+    }
+    Ok(())
+}
+
+/// Logistic mixing, one bit at a time: `squash(sum over models of weight * stretch(p))`.
+/// Both inputs are `[num_models][num_bits]`, as for
+/// [`GpuAccelerator::mix_probabilities`].
+pub fn mix_scalar(model_probs: &[f32], weights: &[f32], num_bits: usize) -> Vec<f32> {
+    (0..num_bits)
+        .map(|bit| mix_bit(model_probs, weights, num_bits, bit))
+        .collect()
+}
+
+/// [`mix_scalar`] with the logistic functions looked up in `table`. Lookups don't
+/// vectorize, so there is no SIMD variant.
+pub fn mix_with_table(
+    model_probs: &[f32],
+    weights: &[f32],
+    num_bits: usize,
+    table: &LogisticTable,
+) -> Vec<f32> {
+    (0..num_bits)
+        .map(|bit| {
+            let dot: f32 = (bit..model_probs.len())
+                .step_by(num_bits)
+                .map(|i| weights[i] * table.stretch(model_probs[i]))
+                .sum();
+            table.squash(dot)
+        })
+        .collect()
+}
+
+fn mix_bit(model_probs: &[f32], weights: &[f32], num_bits: usize, bit: usize) -> f32 {
+    let dot: f32 = (bit..model_probs.len())
+        .step_by(num_bits)
+        .map(|i| weights[i] * stretch(model_probs[i]))
+        .sum();
+    squash(dot)
+}
+
+/// [`mix_scalar`] eight bits at a time. Because the layout is `[num_models][num_bits]`,
+/// each model contributes eight consecutive floats per step.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn mix(model_probs: &[f32], weights: &[f32], num_bits: usize) -> Vec<f32> {
+    use wide::f32x8;
+
+    const LANES: usize = 8;
+    let load = |values: &[f32]| f32x8::from(<[f32; LANES]>::try_from(values).unwrap());
+    let (lo, hi) = (f32x8::splat(PROB_EPSILON), f32x8::splat(1.0 - PROB_EPSILON));
+    let one = f32x8::splat(1.0);
+
+    let mut out = Vec::with_capacity(num_bits);
+    let whole = num_bits - num_bits % LANES;
+    for start in (0..whole).step_by(LANES) {
+        let mut dot = f32x8::splat(0.0);
+        for model in (0..model_probs.len()).step_by(num_bits) {
+            let at = model + start;
+            let p = load(&model_probs[at..at + LANES]).max(lo).min(hi);
+            let w = load(&weights[at..at + LANES]);
+            dot += w * (p / (one - p)).ln();
+        }
+        out.extend_from_slice(&(one / (one + (-dot).exp())).to_array());
+    }
+    out.extend((whole..num_bits).map(|bit| mix_bit(model_probs, weights, num_bits, bit)));
+    out
+}
+
+/// Scalar fallback for targets without a SIMD path.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn mix(model_probs: &[f32], weights: &[f32], num_bits: usize) -> Vec<f32> {
+    mix_scalar(model_probs, weights, num_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        // 4 models over 8 * 125 + 5 bits, so both the SIMD body and the scalar tail run.
+        let num_bits = 1005;
+        let probs: Vec<f32> = (0..4 * num_bits)
+            .map(|i| ((i * 37 % 1000) as f32 + 0.5) / 1000.0)
+            .collect();
+        let weights: Vec<f32> = (0..4 * num_bits)
+            .map(|i| ((i * 13 % 200) as f32 - 100.0) / 50.0)
+            .collect();
+
+        let scalar = mix_scalar(&probs, &weights, num_bits);
+        let fast = CpuAccelerator::new()
+            .mix_probabilities(&probs, &weights, num_bits)
+            .unwrap();
+        assert_eq!(fast.len(), num_bits);
+        for (bit, (a, b)) in scalar.iter().zip(&fast).enumerate() {
+            assert!((a - b).abs() < 1e-4, "bit {}: {} vs {}", bit, a, b);
+        }
+        // Equal weights on agreeing models push the mix further the same way.
+        let sure = mix_scalar(&[0.9, 0.9], &[1.0, 1.0], 1)[0];
+        assert!(sure > 0.9 && sure < 1.0);
+
+        assert!(
+            CpuAccelerator::new()
+                .mix_probabilities(&probs, &weights[1..], num_bits)
+                .is_err()
+        );
+        assert!(
+            CpuAccelerator::new()
+                .mix_probabilities(&probs, &weights, 7)
+                .is_err()
+        );
+    }
+
+    /// Order-0 and order-1 bit models, mixed by `accel`.
+    struct Model<'a> {
+        accel: &'a CpuAccelerator,
+        order0: Vec<f32>,
+        order1: Vec<f32>,
+        prev: usize,
+        partial: usize,
+    }
+
+    impl<'a> Model<'a> {
+        fn new(accel: &'a CpuAccelerator) -> Self {
+            Self {
+                accel,
+                order0: vec![0.5; 256],
+                order1: vec![0.5; 1 << 16],
+                prev: 0,
+                partial: 1,
+            }
+        }
+
+        /// Probability of a 1 bit, in 12 bits as an arithmetic coder takes it.
+        fn predict(&self) -> u32 {
+            let probs = [
+                self.order0[self.partial],
+                self.order1[self.prev << 8 | self.partial],
+            ];
+            let p = self
+                .accel
+                .mix_probabilities(&probs, &[0.6, 0.6], 1)
+                .unwrap()[0];
+            ((p * 4096.0) as u32).clamp(1, 4095)
+        }
+
+        fn update(&mut self, bit: u8) {
+            let target = bit as f32;
+            let p = &mut self.order0[self.partial];
+            *p += (target - *p) * 0.05;
+            let p = &mut self.order1[self.prev << 8 | self.partial];
+            *p += (target - *p) * 0.05;
+            self.partial = self.partial << 1 | bit as usize;
+            if self.partial >= 256 {
+                self.prev = self.partial & 255;
+                self.partial = 1;
+            }
+        }
+    }
+
+    /// Binary arithmetic coding of `data` under `accel`'s mixing, as in lpaq.
+    fn encode(data: &[u8], accel: &CpuAccelerator) -> Vec<u8> {
+        let mut model = Model::new(accel);
+        let (mut x1, mut x2) = (0u32, u32::MAX);
+        let mut out = Vec::new();
+        for &byte in data {
+            for shift in (0..8).rev() {
+                let bit = byte >> shift & 1;
+                let xmid = x1 + ((x2 - x1) >> 12) * model.predict();
+                if bit == 1 {
+                    x2 = xmid;
+                } else {
+                    x1 = xmid + 1;
+                }
+                model.update(bit);
+                while (x1 ^ x2) & 0xff00_0000 == 0 {
+                    out.push((x2 >> 24) as u8);
+                    x1 <<= 8;
+                    x2 = x2 << 8 | 255;
+                }
+            }
+        }
+        out.extend_from_slice(&x1.to_be_bytes());
+        out
+    }
+
+    fn decode(packed: &[u8], len: usize, accel: &CpuAccelerator) -> Vec<u8> {
+        let mut model = Model::new(accel);
+        let (mut x1, mut x2) = (0u32, u32::MAX);
+        let mut input = packed.iter().copied().chain(std::iter::repeat(0));
+        let mut x = 0u32;
+        for _ in 0..4 {
+            x = x << 8 | input.next().unwrap() as u32;
+        }
+        let mut out = Vec::with_capacity(len);
+        for _ in 0..len {
+            let mut byte = 0u8;
+            for _ in 0..8 {
+                let xmid = x1 + ((x2 - x1) >> 12) * model.predict();
+                let bit = (x <= xmid) as u8;
+                if bit == 1 {
+                    x2 = xmid;
+                } else {
+                    x1 = xmid + 1;
+                }
+                model.update(bit);
+                byte = byte << 1 | bit;
+                while (x1 ^ x2) & 0xff00_0000 == 0 {
+                    x1 <<= 8;
+                    x2 = x2 << 8 | 255;
+                    x = x << 8 | input.next().unwrap() as u32;
+                }
+            }
+            out.push(byte);
+        }
+        out
+    }
+
+    #[test]
+    fn test_table_mixing_round_trips_like_exact_mixing() {
+        let num_bits = 1000;
+        let probs: Vec<f32> = (0..4 * num_bits)
+            .map(|i| ((i * 37 % 1000) as f32 + 0.5) / 1000.0)
+            .collect();
+        let weights: Vec<f32> = (0..4 * num_bits)
+            .map(|i| ((i * 13 % 200) as f32 - 100.0) / 50.0)
+            .collect();
+        let exact = CpuAccelerator::new();
+        let table = CpuAccelerator::new().with_logistic_table(LogisticTable::default());
+        let a = exact.mix_probabilities(&probs, &weights, num_bits).unwrap();
+        let b = table.mix_probabilities(&probs, &weights, num_bits).unwrap();
+        for (bit, (a, b)) in a.iter().zip(&b).enumerate() {
+            assert!((a - b).abs() < 5e-3, "bit {}: {} vs {}", bit, a, b);
+        }
+
+        let text = b"the quick brown fox jumps over the lazy dog; ".repeat(200);
+        let exact_packed = encode(&text, &exact);
+        let table_packed = encode(&text, &table);
+        assert_eq!(decode(&exact_packed, text.len(), &exact), text);
+        assert_eq!(decode(&table_packed, text.len(), &table), text);
+        assert!(table_packed.len() < text.len() / 2);
+        let drift = table_packed.len().abs_diff(exact_packed.len()) as f64;
+        assert!(
+            drift / (exact_packed.len() as f64) < 0.01,
+            "{} vs {} bytes",
+            table_packed.len(),
+            exact_packed.len()
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{ArchiveEntry, ArchiveFormat, Compressor, GpuAccelerator, LatError, crypto};
+use std::sync::Arc;
+
+/// Adds password protection to any format by encrypting the inner compressor's output
+/// as a whole (see [`crypto::encrypt`]).
+///
+/// The wrapper consumes the password; the inner compressor always sees `None`, so formats
+/// without native encryption work unchanged. The result is an opaque blob that only this
+/// wrapper can open, not a natively encrypted ZIP/7z.
+#[derive(Clone)]
+pub struct EncryptedCompressor<C: Compressor> {
+    inner: C,
+    accelerator: Option<Arc<dyn GpuAccelerator>>,
+}
+
+impl<C: Compressor> EncryptedCompressor<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            accelerator: None,
+        }
+    }
+
+    /// Encrypts archives of at least [`crypto::GPU_ENCRYPT_THRESHOLD`] bytes with the
+    /// accelerator's AES-CTR kernel (see [`crypto::encrypt_accelerated`]). The output is
+    /// the same as without it, so decryption doesn't care.
+    pub fn with_accelerator(mut self, accelerator: Arc<dyn GpuAccelerator>) -> Self {
+        self.accelerator = Some(accelerator);
+        self
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+fn require_password(password: Option<&str>) -> Result<&str, LatError> {
+    password.ok_or_else(|| {
+        LatError::InvalidInput("a password is required for encrypted archives".to_string())
+    })
+}
+
+impl<C: Compressor> Compressor for EncryptedCompressor<C> {
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        let password = require_password(password)?;
+        let archive = self.inner.compress(entries, None)?;
+        crypto::encrypt_accelerated(&archive, password, b"", self.accelerator.as_deref())
+            .map_err(|e| LatError::Backend(e).context(None, "encryption"))
+    }
+
+    fn decompress(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let password = require_password(password)?;
+        let plain = crypto::decrypt(archive, password).map_err(|e| {
+            LatError::CorruptArchive(format!("wrong password or damaged archive: {}", e))
+        })?;
+        self.inner.decompress(&plain, None)
+    }
+
+    /// Checks the password and the archive's authentication tag without decrypting it
+    /// (see [`crypto::authenticate`]). The tag covers the whole inner archive, so an
+    /// archive that passes decrypts to exactly what was compressed.
+    fn verify(&self, archive: &[u8], password: Option<&str>) -> Result<(), LatError> {
+        crypto::authenticate(archive, require_password(password)?)
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        self.inner.format()
+    }
+
+    fn supports_password(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,113 @@
+//! Order-0 entropy of a sample, as a cheap stand-in for trial compression.
+
+use crate::GpuAccelerator;
+
+/// Occurrences of each byte value in `data`. The CPU reference for
+/// [`GpuAccelerator::byte_histogram`].
+pub fn byte_histogram(data: &[u8]) -> [u64; 256] {
+    let mut counts = [0u64; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    counts
+}
+
+/// Shannon entropy of a byte histogram in bits per byte: 0 when a single value repeats,
+/// 8 when all 256 values are equally likely.
+pub fn bits_per_byte(histogram: &[u64; 256]) -> f32 {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    let bits: f64 = histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    bits as f32
+}
+
+/// Order-0 entropy of `data` in bits per byte. The histogram is built on `accelerator`
+/// when one is given, falling back to the CPU if it fails.
+///
+/// Order-0 entropy ignores repeats longer than a byte, so it overstates what a real codec
+/// needs for structured data. Even near 8 it doesn't rule compression out: repeated runs
+/// of random-looking bytes score the same as noise.
+pub fn estimate_entropy_bits_per_byte(
+    data: &[u8],
+    accelerator: Option<&dyn GpuAccelerator>,
+) -> f32 {
+    let histogram = accelerator
+        .and_then(|accelerator| accelerator.byte_histogram(data).ok())
+        .unwrap_or_else(|| byte_histogram(data));
+    bits_per_byte(&histogram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuAccelerator;
+    use crate::testing::noise;
+
+    /// Stands in for a GPU by counting through a different route than the reference.
+    struct SplitHistogram;
+
+    impl GpuAccelerator for SplitHistogram {
+        fn name(&self) -> &str {
+            "split"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn mix_probabilities(&self, _: &[f32], _: &[f32], n: usize) -> Result<Vec<f32>, String> {
+            Ok(vec![0.5; n])
+        }
+
+        fn byte_histogram(&self, data: &[u8]) -> Result<[u64; 256], String> {
+            // One partial histogram per block, summed, as a GPU reduction would.
+            let mut total = [0u64; 256];
+            for block in data.chunks(4096) {
+                for (sum, count) in total.iter_mut().zip(byte_histogram(block)) {
+                    *sum += count;
+                }
+            }
+            Ok(total)
+        }
+    }
+
+    #[test]
+    fn test_entropy_of_random_and_repetitive_data() {
+        let random = noise(1 << 20, 0x2545_f491);
+        let repetitive = vec![b'a'; 1 << 20];
+
+        let cpu = estimate_entropy_bits_per_byte(&random, None);
+        assert!(cpu > 7.99 && cpu <= 8.0, "{}", cpu);
+        assert_eq!(estimate_entropy_bits_per_byte(&repetitive, None), 0.0);
+        assert_eq!(estimate_entropy_bits_per_byte(b"", None), 0.0);
+        assert!((estimate_entropy_bits_per_byte(b"abab", None) - 1.0).abs() < 1e-6);
+
+        for accelerator in [
+            &SplitHistogram as &dyn GpuAccelerator,
+            &CpuAccelerator::new(),
+        ] {
+            assert_eq!(
+                accelerator.byte_histogram(&random).unwrap(),
+                byte_histogram(&random)
+            );
+            assert_eq!(
+                estimate_entropy_bits_per_byte(&random, Some(accelerator)),
+                cpu
+            );
+            assert_eq!(
+                estimate_entropy_bits_per_byte(&repetitive, Some(accelerator)),
+                0.0
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum LatError {
+    /// The archive is malformed, truncated or failed an integrity check.
+    CorruptArchive(String),
+    /// No entry with the given name exists in the archive.
+    EntryNotFound(String),
+    /// The format cannot perform the requested operation.
+    UnsupportedFormat(String),
+    /// A caller-supplied argument (pattern, option value, ...) is invalid.
+    InvalidInput(String),
+    /// A codec or GPU backend failed.
+    Backend(String),
+    /// The operation exceeded its configured time budget.
+    Timeout(Duration),
+    /// The password doesn't open the encrypted data.
+    WrongPassword,
+    Io(std::io::Error),
+    /// `source` happened while working on `entry` (when there is one) in `stage`, such
+    /// as "reading", "compression" or "encryption". Nested contexts form a breadcrumb
+    /// trail from the outermost operation down to the failure.
+    Context {
+        entry: Option<String>,
+        stage: &'static str,
+        source: Box<LatError>,
+    },
+}
+
+impl LatError {
+    /// Wraps `self` with the entry and stage it happened in.
+    pub fn context(self, entry: Option<&str>, stage: &'static str) -> Self {
+        LatError::Context {
+            entry: entry.map(str::to_string),
+            stage,
+            source: Box::new(self),
+        }
+    }
+
+    /// The error beneath any [`LatError::Context`] wrapping, for matching on its kind.
+    pub fn root_cause(&self) -> &LatError {
+        match self {
+            LatError::Context { source, .. } => source.root_cause(),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for LatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LatError::CorruptArchive(msg) => write!(f, "Corrupt archive: {}", msg),
+            LatError::EntryNotFound(name) => write!(f, "Entry not found: {}", name),
+            LatError::UnsupportedFormat(msg) => write!(f, "Unsupported: {}", msg),
+            LatError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            LatError::Backend(msg) => write!(f, "{}", msg),
+            LatError::Timeout(limit) => write!(f, "Operation timed out after {:?}", limit),
+            LatError::WrongPassword => write!(f, "Wrong password"),
+            LatError::Io(e) => write!(f, "I/O error: {}", e),
+            LatError::Context {
+                entry: Some(entry),
+                stage,
+                source,
+            } => write!(f, "'{}' during {}: {}", entry, stage, source),
+            LatError::Context {
+                entry: None,
+                stage,
+                source,
+            } => write!(f, "during {}: {}", stage, source),
+        }
+    }
+}
+
+impl std::error::Error for LatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LatError::Io(e) => Some(e),
+            LatError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LatError {
+    fn from(e: std::io::Error) -> Self {
+        LatError::Io(e)
+    }
+}
@@ -0,0 +1,170 @@
+//! Multi-entry framing for single-stream codecs (bzip2, gzip, ...), which compress one
+//! stream and have no notion of files.
+//!
+//! The entries are packed into one stream before compression:
+//!
+//! ```text
+//! magic "LATI" | version u8 | entry_count u32 | (name_len u16 | name | size u64)* | data*
+//! ```
+//!
+//! All integers little-endian; the entries' data follows the index back to back. A
+//! decompressed stream without the magic came from another tool and is one unnamed file.
+
+use crate::{ArchiveEntry, LatError};
+use std::io::{Cursor, ErrorKind, Read};
+
+const MAGIC: &[u8; 4] = b"LATI";
+const VERSION: u8 = 1;
+
+/// Packs `entries` into one stream, index first.
+pub fn pack(entries: &[ArchiveEntry]) -> Result<Vec<u8>, LatError> {
+    let index_len: usize = entries.iter().map(|e| 10 + e.name.len()).sum();
+    let data_len: usize = entries.iter().map(|e| e.data.len()).sum();
+    let mut out = Vec::with_capacity(9 + index_len + data_len);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    let count = u32::try_from(entries.len())
+        .map_err(|_| LatError::InvalidInput("too many entries".to_string()))?;
+    out.extend_from_slice(&count.to_le_bytes());
+    for entry in entries {
+        let name_len = u16::try_from(entry.name.len())
+            .map_err(|_| LatError::InvalidInput(format!("entry name too long: {}", entry.name)))?;
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(&(entry.data.len() as u64).to_le_bytes());
+    }
+    for entry in entries {
+        out.extend_from_slice(&entry.data);
+    }
+    Ok(out)
+}
+
+/// Whether `stream` starts with a [`pack`]ed index.
+pub fn is_packed(stream: &[u8]) -> bool {
+    stream.starts_with(MAGIC)
+}
+
+/// Splits a [`pack`]ed stream back into entries. A stream without the index becomes a
+/// single entry named `fallback_name`.
+pub fn unpack(stream: Vec<u8>, fallback_name: &str) -> Result<Vec<ArchiveEntry>, LatError> {
+    let mut reader = Cursor::new(stream.as_slice());
+    let Some(index) = read_index(&mut reader)? else {
+        return Ok(vec![
+            ArchiveEntry::builder(fallback_name.to_string())
+                .data(stream)
+                .build(),
+        ]);
+    };
+
+    let mut pos = reader.position() as usize;
+    let mut entries = Vec::with_capacity(index.len());
+    for (name, size) in index {
+        let data = usize::try_from(size)
+            .ok()
+            .and_then(|size| pos.checked_add(size))
+            .and_then(|end| stream.get(pos..end))
+            .ok_or_else(|| corrupt("truncated"))?;
+        pos += data.len();
+        entries.push(ArchiveEntry::builder(name).data(data.to_vec()).build());
+    }
+    if pos != stream.len() {
+        return Err(corrupt("trailing data"));
+    }
+    Ok(entries)
+}
+
+/// Reads a [`pack`]ed index (each entry's name and size) from the start of `reader`,
+/// leaving it at the first entry's data, or returns `None` for a stream without the
+/// index. Lets formats with random access find one entry without reading the others.
+pub fn read_index(reader: &mut impl Read) -> Result<Option<Vec<(String, u64)>>, LatError> {
+    let mut magic = [0; 4];
+    match reader.read_exact(&mut magic) {
+        Ok(()) if &magic == MAGIC => {}
+        Ok(()) => return Ok(None),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut take = |len: usize| -> Result<Vec<u8>, LatError> {
+        let mut bytes = vec![0; len];
+        reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => corrupt("truncated"),
+            _ => e.into(),
+        })?;
+        Ok(bytes)
+    };
+
+    let version = take(1)?[0];
+    if version != VERSION {
+        return Err(LatError::UnsupportedFormat(format!(
+            "entry index version {}",
+            version
+        )));
+    }
+    let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let mut index = Vec::new();
+    for _ in 0..count {
+        let name_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        let name =
+            String::from_utf8(take(name_len)?).map_err(|_| corrupt("entry name is not UTF-8"))?;
+        let size = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        index.push((name, size));
+    }
+    Ok(Some(index))
+}
+
+/// How many entries [`unpack`] would give for the stream read from `reader`, reading no
+/// further than the index: the index's count, or 1 for a stream without one.
+pub fn entry_count(reader: &mut impl Read) -> Result<usize, LatError> {
+    Ok(read_index(reader)?.map_or(1, |index| index.len()))
+}
+
+fn corrupt(what: &str) -> LatError {
+    LatError::CorruptArchive(format!("entry index: {}", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_round_trip() {
+        let entries = vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"alpha".to_vec())
+                .build(),
+            ArchiveEntry::builder("dir/empty").build(),
+        ];
+        let packed = pack(&entries).unwrap();
+        assert_eq!(&packed[..9], b"LATI\x01\x02\x00\x00\x00");
+
+        let unpacked = unpack(packed.clone(), "data").unwrap();
+        assert_eq!(unpacked.len(), 2);
+        assert_eq!(unpacked[0].name, "a.txt");
+        assert_eq!(unpacked[0].data, b"alpha");
+        assert!(unpacked[1].data.is_empty());
+
+        let raw = unpack(b"plain bytes".to_vec(), "data").unwrap();
+        assert_eq!(
+            (raw[0].name.as_str(), raw[0].data.as_slice()),
+            ("data", &b"plain bytes"[..])
+        );
+
+        assert!(matches!(
+            unpack(packed[..packed.len() - 1].to_vec(), "data"),
+            Err(LatError::CorruptArchive(_))
+        ));
+
+        let mut reader = &packed[..];
+        let index = read_index(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            index,
+            [("a.txt".to_string(), 5), ("dir/empty".to_string(), 0)]
+        );
+        assert_eq!(reader, b"alpha");
+        assert_eq!(read_index(&mut &b"raw"[..]).unwrap(), None);
+
+        assert_eq!(entry_count(&mut &packed[..]).unwrap(), 2);
+        assert_eq!(entry_count(&mut &b"raw"[..]).unwrap(), 1);
+        assert_eq!(entry_count(&mut &b""[..]).unwrap(), 1);
+    }
+}
@@ -0,0 +1,103 @@
+//! Capping how many GPU operations run at once when one accelerator is shared by many
+//! threads.
+
+use std::sync::{Condvar, Mutex};
+
+/// Lets at most `max` operations run concurrently; further callers block until one
+/// finishes. Keeps a shared accelerator from being flooded with buffers it can't hold.
+#[derive(Debug)]
+pub struct InFlightLimit {
+    max: usize,
+    in_flight: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl InFlightLimit {
+    /// A limit of `max` concurrent operations (at least 1).
+    pub fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            in_flight: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Runs `op` once a slot is free. The slot is released when `op` returns or panics.
+    pub fn run<T>(&self, op: impl FnOnce() -> T) -> T {
+        let _slot = self.acquire();
+        op()
+    }
+
+    fn acquire(&self) -> Slot<'_> {
+        let mut in_flight = self
+            .freed
+            .wait_while(self.in_flight.lock().unwrap(), |n| *n >= self.max)
+            .unwrap();
+        *in_flight += 1;
+        Slot { limit: self }
+    }
+}
+
+struct Slot<'a> {
+    limit: &'a InFlightLimit,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        // Recover from poisoning so a panicking operation still frees its slot.
+        let mut in_flight = self
+            .limit
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *in_flight -= 1;
+        self.limit.freed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_in_flight_never_exceeds_limit() {
+        let limit = Arc::new(InFlightLimit::new(3));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..12)
+            .map(|_| {
+                let (limit, running, peak) = (limit.clone(), running.clone(), peak.clone());
+                thread::spawn(move || {
+                    limit.run(|| {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(10));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    })
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert_eq!(*limit.in_flight.lock().unwrap(), 0);
+
+        // A panicking operation gives its slot back.
+        let single = InFlightLimit::new(0);
+        assert_eq!(single.max(), 1);
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            single.run(|| panic!("kernel failed"))
+        }));
+        assert!(caught.is_err());
+        assert_eq!(single.run(|| 7), 7);
+    }
+}
@@ -1,16 +1,786 @@
+pub mod batch;
+pub mod bench;
+mod builder;
+pub mod chunking;
+pub mod cpu;
+mod encrypted;
+pub mod entropy;
+mod error;
+pub mod index;
+pub mod inflight;
+pub mod logistic;
+pub mod manifest;
+pub mod mmap;
+pub mod nested;
+pub mod path;
+pub mod prefetch;
+pub mod profile;
+pub mod progress;
+pub mod sample;
+pub mod signing;
+pub mod store;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(test)]
+mod test_util;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod throttle;
+mod untrusted;
+
+pub use builder::{CompressorBuilder, CompressorConfig, FormatFactory};
+pub use encrypted::EncryptedCompressor;
+pub use error::LatError;
+pub use untrusted::try_decompress;
+
+use path::{NameLimits, OnDuplicate};
+use sample::EntrySample;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// One file, directory or link in an archive. Outside this crate, build one with
+/// [`ArchiveEntry::builder`]; the struct is `#[non_exhaustive]` so new metadata fields
+/// don't break callers.
+#[derive(Default)]
+#[non_exhaustive]
 pub struct ArchiveEntry {
     pub name: String,
     pub data: Vec<u8>,
+    /// Directories carry no data; symbolic links carry their target path. Formats without
+    /// directory records store them as empty files.
+    pub kind: EntryKind,
+    /// Owner to restore on Unix. Formats without a place for it drop it.
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Unix permission bits (e.g. `0o644`) to restore. Formats without a place for them
+    /// drop them.
+    pub mode: Option<u32>,
+    /// Extended attributes (name, value), e.g. `user.comment`. Formats without a place for
+    /// them drop them.
+    pub xattrs: Vec<(String, Vec<u8>)>,
+    /// Whether the data is text, for formats that flag it (ZIP, whose readers may convert
+    /// line endings of text members). `None` lets the format decide.
+    pub is_text: Option<bool>,
+}
+
+impl ArchiveEntry {
+    /// Starts an entry called `name`; every other field keeps its default until set.
+    pub fn builder(name: impl Into<String>) -> ArchiveEntryBuilder {
+        ArchiveEntryBuilder {
+            entry: ArchiveEntry {
+                name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Chainable construction of an [`ArchiveEntry`], from [`ArchiveEntry::builder`].
+#[must_use]
+pub struct ArchiveEntryBuilder {
+    entry: ArchiveEntry,
+}
+
+impl ArchiveEntryBuilder {
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.entry.data = data.into();
+        self
+    }
+
+    pub fn kind(mut self, kind: EntryKind) -> Self {
+        self.entry.kind = kind;
+        self
+    }
+
+    pub fn uid(mut self, uid: impl Into<Option<u32>>) -> Self {
+        self.entry.uid = uid.into();
+        self
+    }
+
+    pub fn gid(mut self, gid: impl Into<Option<u32>>) -> Self {
+        self.entry.gid = gid.into();
+        self
+    }
+
+    pub fn mode(mut self, mode: impl Into<Option<u32>>) -> Self {
+        self.entry.mode = mode.into();
+        self
+    }
+
+    /// Replaces all extended attributes.
+    pub fn xattrs(mut self, xattrs: Vec<(String, Vec<u8>)>) -> Self {
+        self.entry.xattrs = xattrs;
+        self
+    }
+
+    /// Adds one extended attribute.
+    pub fn xattr(mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> Self {
+        self.entry.xattrs.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn is_text(mut self, is_text: impl Into<Option<bool>>) -> Self {
+        self.entry.is_text = is_text.into();
+        self
+    }
+
+    pub fn build(self) -> ArchiveEntry {
+        self.entry
+    }
+}
+
+/// Whether an [`ArchiveEntry`] is a file, a directory or a symbolic link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryKind {
+    #[default]
+    File,
+    Directory,
+    /// `data` is the link's target as stored in the archive (UTF-8, usually relative to
+    /// the link's own directory). Only ZIP keeps links; other formats store the target
+    /// as file contents.
+    Symlink,
+}
+
+/// Checks that every entry can be written to an archive.
+///
+/// Names must contain something other than whitespace; ZIP and 7z would otherwise produce
+/// an entry that other tools treat as corrupt. Names must also be within the default
+/// [`NameLimits`]. Zero-length data is fine; data on a directory is not.
+pub fn validate_entries(entries: &[ArchiveEntry]) -> Result<(), LatError> {
+    validate_entries_with(entries, &NameLimits::default())
+}
+
+/// [`validate_entries`] with caller-chosen name limits.
+pub fn validate_entries_with(
+    entries: &[ArchiveEntry],
+    limits: &NameLimits,
+) -> Result<(), LatError> {
+    if let Some(index) = entries.iter().position(|e| e.name.trim().is_empty()) {
+        return Err(LatError::CorruptArchive(format!(
+            "entry #{} has an empty name",
+            index
+        )));
+    }
+    if let Some(entry) = entries
+        .iter()
+        .find(|e| e.kind == EntryKind::Directory && !e.data.is_empty())
+    {
+        return Err(LatError::InvalidInput(format!(
+            "directory {} has data",
+            entry.name
+        )));
+    }
+    limits.check_entries(entries)
+}
+
+/// Reads all of `reader` (e.g. stdin) into one entry called `name`, so piped data can be
+/// archived under a name of the caller's choosing. The name is checked like any other
+/// entry's by [`validate_entries`].
+pub fn entry_from_reader(name: &str, mut reader: impl Read) -> Result<ArchiveEntry, LatError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let entry = ArchiveEntry::builder(name.to_string()).data(data).build();
+    validate_entries(std::slice::from_ref(&entry))?;
+    Ok(entry)
+}
+
+/// Header-level facts about an archive, gathered without decompressing any entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveInfo {
+    pub format: &'static str,
+    /// Version of the format/tool that wrote the archive, e.g. "2.0" for ZIP.
+    pub creator_version: Option<String>,
+    /// Host system recorded by the writer, when the format stores one.
+    pub host_os: Option<String>,
+    pub entry_count: usize,
+    pub is_encrypted: bool,
+}
+
+/// Which format a [`Compressor`] reads and writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZ,
+    Lat,
+    Paqg,
+    Bzip2,
+    Gzip,
+    Tar,
+    Zstd,
+    Xz,
+    /// Reported by this crate's test doubles, so they don't pose as a real format. Not in
+    /// [`ALL`](Self::ALL).
+    #[cfg(test)]
+    Test,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 9] = [
+        Self::Zip,
+        Self::SevenZ,
+        Self::Lat,
+        Self::Paqg,
+        Self::Bzip2,
+        Self::Gzip,
+        Self::Tar,
+        Self::Zstd,
+        Self::Xz,
+    ];
+
+    /// File extension for archives of this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::SevenZ => "7z",
+            Self::Lat => "lat",
+            Self::Paqg => "paq",
+            Self::Bzip2 => "bz2",
+            Self::Gzip => "gz",
+            Self::Tar => "tar",
+            Self::Zstd => "zst",
+            Self::Xz => "xz",
+            #[cfg(test)]
+            Self::Test => "test",
+        }
+    }
+
+    /// Name to show users, e.g. in a format picker.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Self::Zip => "Zip",
+            Self::SevenZ => "7z",
+            Self::Lat => ".lat",
+            Self::Paqg => "PAQG",
+            Self::Bzip2 => "bzip2",
+            Self::Gzip => "gzip",
+            Self::Tar => "tar",
+            Self::Zstd => "Zstandard",
+            Self::Xz => "xz",
+            #[cfg(test)]
+            Self::Test => "Test",
+        }
+    }
+
+    /// Whether archives of this format keep entry names. bzip2, gzip and zstd keep them in
+    /// the [`index`] framing; a bare xz stream holds unnamed data.
+    pub fn stores_entry_names(self) -> bool {
+        !matches!(self, Self::Xz)
+    }
+
+    /// Whether the format compresses one stream rather than a file tree, so a single file
+    /// compressed on its own gives the familiar `a.txt.gz` that other tools open.
+    pub fn is_single_stream(self) -> bool {
+        matches!(self, Self::Bzip2 | Self::Gzip | Self::Zstd | Self::Xz)
+    }
+
+    /// The format whose [`extension`](Self::extension) is `ext`, ignoring ASCII case.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(ext))
+    }
+}
+
+/// Size report for one archive member.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryStat {
+    pub name: String,
+    pub uncompressed: u64,
+    /// Bytes the member occupies in the archive, excluding headers.
+    pub compressed: u64,
+    /// `compressed / uncompressed`; 1.0 for empty members. Values near or above 1.0 mark
+    /// data that is already compressed and would be better stored.
+    pub ratio: f64,
+    pub method_used: String,
+}
+
+impl EntryStat {
+    pub fn new(name: &str, uncompressed: u64, compressed: u64, method_used: &str) -> Self {
+        let ratio = if uncompressed == 0 {
+            1.0
+        } else {
+            compressed as f64 / uncompressed as f64
+        };
+        Self {
+            name: name.to_string(),
+            uncompressed,
+            compressed,
+            ratio,
+            method_used: method_used.to_string(),
+        }
+    }
+}
+
+/// One member as read from an archive's headers, returned by [`Compressor::list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+}
+
+/// How many entries [`Compressor::list_with_progress`] reads between progress calls.
+pub const LIST_PROGRESS_INTERVAL: usize = 1000;
+
+/// Collects a listing for [`Compressor::list_with_progress`], calling `on_progress` with
+/// the count so far every [`LIST_PROGRESS_INTERVAL`] entries and once more with the total.
+pub fn collect_listing(
+    entries: impl IntoIterator<Item = Result<ListedEntry, LatError>>,
+    on_progress: &mut dyn FnMut(usize),
+) -> Result<Vec<ListedEntry>, LatError> {
+    let entries = entries.into_iter();
+    let mut listed = Vec::with_capacity(entries.size_hint().0);
+    for entry in entries {
+        listed.push(entry?);
+        if listed.len() % LIST_PROGRESS_INTERVAL == 0 {
+            on_progress(listed.len());
+        }
+    }
+    on_progress(listed.len());
+    Ok(listed)
+}
+
+/// Archive bytes plus size statistics, returned by [`Compressor::compress_detailed`].
+#[derive(Debug, Clone)]
+pub struct CompressionResult {
+    pub data: Vec<u8>,
+    pub uncompressed_size: u64,
+    /// Per-member sizes; empty for codecs that can't attribute output to members.
+    pub per_entry: Vec<EntryStat>,
+    /// Sources left out because they couldn't be read. Compressors only see the entries
+    /// they are given, so this is filled in by whoever read the sources.
+    pub warnings: Vec<EntryWarning>,
+    /// Bytes the codec's model or deduplication index reached; 0 for codecs without one.
+    pub context_memory: usize,
+}
+
+/// A source that was skipped while collecting entries, e.g. one deleted between listing
+/// and reading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryWarning {
+    pub name: String,
+    pub reason: String,
+}
+
+impl CompressionResult {
+    /// Archive size divided by total input size (1.0 for empty input).
+    pub fn ratio(&self) -> f64 {
+        if self.uncompressed_size == 0 {
+            1.0
+        } else {
+            self.data.len() as f64 / self.uncompressed_size as f64
+        }
+    }
+}
+
+/// What [`Compressor::quick_verify`] looked at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Entries whose data was decoded and checked.
+    pub entries_checked: usize,
+    pub entries_total: usize,
+}
+
+/// Order in which `decompress` returns entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryOrder {
+    /// Physical order in the archive, which matches the order entries were written in.
+    #[default]
+    Archive,
+    /// Lexicographic order by entry name (stable for duplicate names).
+    Name,
 }
 
+impl EntryOrder {
+    pub fn apply(self, entries: &mut [ArchiveEntry]) {
+        if self == EntryOrder::Name {
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+}
+
+/// Called with the format and the number of bytes dropped under [`TrailingData::Warn`].
+pub type TrailingHandler = Arc<dyn Fn(ArchiveFormat, usize) + Send + Sync>;
+
+/// What `decompress` does with bytes after an archive's declared end, such as an
+/// accidental concatenation or the leftovers of a partial overwrite.
+#[derive(Clone, Default)]
+pub enum TrailingData {
+    /// Decode the archive and drop the extra bytes.
+    #[default]
+    Ignore,
+    /// Decode the archive, drop the extra bytes and report how many there were to the
+    /// handler, e.g. to show a warning.
+    Warn(TrailingHandler),
+    /// Refuse the archive with [`LatError::CorruptArchive`].
+    Error,
+}
+
+impl TrailingData {
+    /// [`TrailingData::Warn`] with `handler`.
+    pub fn warn(handler: impl Fn(ArchiveFormat, usize) + Send + Sync + 'static) -> Self {
+        TrailingData::Warn(Arc::new(handler))
+    }
+
+    /// Applies the policy to `trailing` bytes found after a `format` archive.
+    pub fn check(&self, format: ArchiveFormat, trailing: usize) -> Result<(), LatError> {
+        if trailing == 0 {
+            return Ok(());
+        }
+        match self {
+            TrailingData::Ignore => Ok(()),
+            TrailingData::Warn(handler) => {
+                handler(format, trailing);
+                Ok(())
+            }
+            TrailingData::Error => Err(LatError::CorruptArchive(format!(
+                "{} unexpected bytes after the end of the {} archive",
+                trailing,
+                format.display_name()
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for TrailingData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrailingData::Ignore => f.write_str("Ignore"),
+            TrailingData::Warn(_) => f.write_str("Warn(..)"),
+            TrailingData::Error => f.write_str("Error"),
+        }
+    }
+}
+
+/// Implementations hold only their configuration; everything a call needs is kept local
+/// to that call, so one configured compressor can be reused for any number of calls.
 pub trait Compressor {
-    fn compress(&self, entries: &[ArchiveEntry], password: Option<&str>)
-    -> Result<Vec<u8>, String>;
+    /// Builds an archive of `entries`. An empty list gives a valid archive that
+    /// decompresses to no entries, or [`LatError::UnsupportedFormat`] for formats that
+    /// can't represent one.
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError>;
+    /// Extracts all entries, in physical archive order unless the codec is configured
+    /// with a different [`EntryOrder`].
     fn decompress(
         &self,
         archive: &[u8],
         password: Option<&str>,
-    ) -> Result<Vec<ArchiveEntry>, String>;
+    ) -> Result<Vec<ArchiveEntry>, LatError>;
+    /// The format this compressor reads and writes.
+    fn format(&self) -> ArchiveFormat;
+
+    /// Whether the format can protect archives with a password.
+    fn supports_password(&self) -> bool {
+        false
+    }
+
+    /// Rejects a password for formats that can't honor it, rather than silently dropping it.
+    /// Implementations call this at the start of `compress`/`decompress`.
+    fn check_password(&self, password: Option<&str>) -> Result<(), LatError> {
+        if password.is_some() && !self.supports_password() {
+            return Err(LatError::UnsupportedFormat(
+                "this format does not support password protection".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Like `compress`, but also reports sizes. Codecs that can attribute output to
+    /// individual members fill in `per_entry`; the default leaves it empty.
+    fn compress_detailed(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<CompressionResult, LatError> {
+        let data = self.compress(entries, password)?;
+        Ok(CompressionResult {
+            data,
+            uncompressed_size: entries.iter().map(|e| e.data.len() as u64).sum(),
+            per_entry: Vec::new(),
+            warnings: Vec::new(),
+            context_memory: 0,
+        })
+    }
+
+    /// Compresses `entries`, giving up with [`LatError::Timeout`] once `deadline` passes.
+    ///
+    /// The default implementation only checks before and after the whole call; codecs that
+    /// work entry-by-entry or in batches should override it and check at each boundary. Any
+    /// partial output is discarded on timeout.
+    fn compress_with_deadline(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<Vec<u8>, LatError> {
+        deadline.check()?;
+        let output = self.compress(entries, password)?;
+        deadline.check()?;
+        Ok(output)
+    }
+
+    /// Extracts entries one at a time, so callers can act on (and report) each before the
+    /// next is decoded. The default decompresses everything up front; formats that can
+    /// decode incrementally override it, possibly yielding entries in a different order
+    /// than `decompress`.
+    fn decompress_iter<'a>(
+        &'a self,
+        archive: &'a [u8],
+        password: Option<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<ArchiveEntry, LatError>> + 'a>, LatError> {
+        let entries = self.decompress(archive, password)?;
+        Ok(Box::new(entries.into_iter().map(Ok)))
+    }
+
+    /// Extracts only the entries whose names match the glob `pattern` (see [`glob_matcher`]).
+    ///
+    /// The default implementation decompresses everything and filters; formats with
+    /// per-entry access override it to skip non-matching payloads entirely.
+    fn decompress_matching(
+        &self,
+        archive: &[u8],
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let matcher = glob_matcher(pattern)?;
+        let mut entries = self.decompress(archive, password)?;
+        entries.retain(|entry| matcher.is_match(&entry.name));
+        Ok(entries)
+    }
+
+    /// [`decompress_matching`](Self::decompress_matching) for an archive in a
+    /// [`store::BlockStore`], such as an object fetched by byte range.
+    ///
+    /// The default implementation reads the whole store; formats with an index override it
+    /// to fetch only the index and the matching entries.
+    fn decompress_matching_from(
+        &self,
+        store: &dyn store::BlockStore,
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let archive = store::read_all(store)?;
+        self.decompress_matching(&archive, pattern, password)
+    }
+
+    /// Extracts all entries into a map from name to data, for callers that look entries up
+    /// by name. Entries sharing a name are resolved per `on_duplicate` (see
+    /// [`path::resolve_duplicates`]); renamed copies are keyed by their new names. Names
+    /// beyond the default [`NameLimits`] fail with [`LatError::CorruptArchive`].
+    fn decompress_to_map(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        on_duplicate: OnDuplicate,
+    ) -> Result<HashMap<String, Vec<u8>>, LatError> {
+        let entries = self.decompress(archive, password)?;
+        NameLimits::default().check_entries(&entries)?;
+        let entries = path::resolve_duplicates(entries, on_duplicate)?;
+        Ok(entries.into_iter().map(|e| (e.name, e.data)).collect())
+    }
+
+    /// Reads archive metadata from headers only.
+    fn archive_info(&self, _archive: &[u8]) -> Result<ArchiveInfo, LatError> {
+        Err(LatError::UnsupportedFormat(
+            "archive info is not available for this format".to_string(),
+        ))
+    }
+
+    /// Checks that `archive` decodes cleanly, relying on the format's own CRCs or hashes.
+    /// The default decompresses everything and discards the result.
+    fn verify(&self, archive: &[u8], password: Option<&str>) -> Result<(), LatError> {
+        self.decompress(archive, password).map(|_| ())
+    }
+
+    /// A faster, probabilistic [`verify`](Self::verify): checks the archive's headers, then
+    /// decodes and checks only the entries `sample` picks. Damage in an unsampled entry
+    /// goes unnoticed, so use `verify` when certainty matters.
+    ///
+    /// The default can't read entries individually and checks all of them; formats with
+    /// per-entry access override it.
+    fn quick_verify(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        _sample: &EntrySample,
+    ) -> Result<VerifyReport, LatError> {
+        let total = self.decompress(archive, password)?.len();
+        Ok(VerifyReport {
+            entries_checked: total,
+            entries_total: total,
+        })
+    }
+
+    /// Lists the archive's members without keeping their contents.
+    fn list(&self, archive: &[u8], password: Option<&str>) -> Result<Vec<ListedEntry>, LatError> {
+        self.list_with_progress(archive, password, &mut |_| {})
+    }
+
+    /// [`list`](Self::list), calling `on_progress` with the number of entries read so far
+    /// as it goes (see [`collect_listing`]), so a listing of a huge archive can show
+    /// progress. The default decompresses everything first; formats with a central index
+    /// override it to read headers only.
+    fn list_with_progress(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<Vec<ListedEntry>, LatError> {
+        let entries = self.decompress(archive, password)?;
+        collect_listing(
+            entries.into_iter().map(|entry| {
+                Ok(ListedEntry {
+                    size: entry.data.len() as u64,
+                    name: entry.name,
+                    kind: entry.kind,
+                })
+            }),
+            on_progress,
+        )
+    }
+
+    /// Recreates the archive's folder tree below `dest` (every directory entry and every
+    /// file's parent) without writing any files, and returns the directories, parents
+    /// first. Names are sanitized like extracted files, and a name reaching outside
+    /// `dest` fails the whole call before anything is created.
+    fn extract_dirs_only(
+        &self,
+        archive: &[u8],
+        dest: &Path,
+        password: Option<&str>,
+    ) -> Result<Vec<PathBuf>, LatError> {
+        let tree = path::directory_tree(&self.list(archive, password)?)?;
+        let mut created = Vec::with_capacity(tree.len());
+        for dir in tree {
+            let name = path::sanitize_for_filesystem(&dir, path::SanitizeMode::Replace)?;
+            let target = dest.join(name.as_ref());
+            std::fs::create_dir_all(&target)?;
+            created.push(target);
+        }
+        Ok(created)
+    }
+
+    /// Compresses `entries` into the file at `path`, replacing it.
+    ///
+    /// With `verify_after_write` the file is synced, read back and checked with
+    /// [`verify_written`] before this returns, so a write the disk or controller mangled
+    /// fails here instead of at restore time. That costs a full read of the file plus a
+    /// [`verify`](Self::verify), which roughly doubles the time for fast codecs. The read
+    /// may be served from the OS cache, so it proves what reached the cache, not the
+    /// platters.
+    fn compress_to_file(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        path: &Path,
+        verify_after_write: bool,
+    ) -> Result<(), LatError> {
+        let archive = self.compress(entries, password)?;
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.write_all(&archive)?;
+        file.sync_all()?;
+        if verify_after_write {
+            verify_written(self, &archive, password, &mut file)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a just-written archive back from the start of `written` and checks it against
+/// `archive`, the bytes that were meant to be written, then runs
+/// [`Compressor::verify`] on it. Any difference or verification failure is
+/// [`LatError::CorruptArchive`].
+pub fn verify_written<C, S>(
+    compressor: &C,
+    archive: &[u8],
+    password: Option<&str>,
+    written: &mut S,
+) -> Result<(), LatError>
+where
+    C: Compressor + ?Sized,
+    S: Read + Seek,
+{
+    written.seek(SeekFrom::Start(0))?;
+    let mut read_back = Vec::with_capacity(archive.len());
+    written.read_to_end(&mut read_back)?;
+    if read_back != archive {
+        let at = read_back
+            .iter()
+            .zip(archive)
+            .position(|(a, b)| a != b)
+            .unwrap_or(read_back.len().min(archive.len()));
+        return Err(LatError::CorruptArchive(format!(
+            "archive read back after writing differs at byte {}",
+            at
+        )));
+    }
+    compressor
+        .verify(&read_back, password)
+        .map_err(|e| LatError::CorruptArchive(format!("verification after write failed: {}", e)))
+}
+
+/// Compiles an entry-name glob. `*` and `?` stop at `/`, while `**` spans directories,
+/// so `*.txt` only matches top-level files and `**/*.txt` matches at any depth.
+pub fn glob_matcher(pattern: &str) -> Result<globset::GlobMatcher, LatError> {
+    globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| LatError::InvalidInput(e.to_string()))
+}
+
+/// Entries declaring more than this are always read in chunks of this size, whatever
+/// chunk size the caller asked for, so a lying header can't force one huge allocation.
+const MAX_UPFRONT_READ: usize = 64 << 20;
+
+/// Reads exactly `size` bytes of entry data from `reader`.
+///
+/// Without a `chunk_size` (or when the entry fits in one chunk) this is a single
+/// `read_exact` into a pre-sized buffer. Otherwise the data is read through a fixed
+/// `chunk_size` scratch buffer and appended as it arrives, so a large (or lying) declared
+/// size never turns into one giant up-front allocation. Sizes above 64 MiB are read in
+/// chunks even without a `chunk_size`.
+pub fn read_entry_data<R: Read + ?Sized>(
+    reader: &mut R,
+    size: usize,
+    chunk_size: Option<usize>,
+) -> std::io::Result<Vec<u8>> {
+    let chunk_size = match chunk_size {
+        Some(chunk) if chunk > 0 && chunk < size => chunk,
+        _ if size > MAX_UPFRONT_READ => MAX_UPFRONT_READ,
+        _ => {
+            let mut buf = vec![0u8; size];
+            reader.read_exact(&mut buf)?;
+            return Ok(buf);
+        }
+    };
+
+    let mut buf = Vec::with_capacity(chunk_size);
+    let mut chunk = vec![0u8; chunk_size];
+    while buf.len() < size {
+        let want = chunk_size.min(size - buf.len());
+        let n = reader.read(&mut chunk[..want])?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(buf)
 }
 
 pub enum GpuBackend {
@@ -19,7 +789,20 @@ pub enum GpuBackend {
     None,
 }
 
-pub trait GpuAccelerator {
+/// Per-entry checksum for formats that let us choose one. ZIP and 7z always use their own
+/// CRC32 and ignore the choice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumAlgo {
+    /// No checksums: corruption goes unnoticed unless decoding itself fails.
+    None,
+    #[default]
+    Crc32,
+    /// Slower, but stronger against accidental corruption. Anyone who can edit the archive
+    /// can recompute it too; use [`signing`] to detect tampering.
+    Blake3,
+}
+
+pub trait GpuAccelerator: Send + Sync {
     fn name(&self) -> &str;
     fn run_kernel(&self, name: &str, data: &mut [u8]) -> Result<(), String>;
     /// Mixes probabilities from multiple models.
@@ -33,32 +816,236 @@ pub trait GpuAccelerator {
         weights: &[f32],
         num_bits: usize,
     ) -> Result<Vec<f32>, String>;
+
+    /// AES-256-GCM encrypts `data`, returning the ciphertext followed by the 16-byte tag.
+    ///
+    /// The output must match [`crypto::aes_gcm_encrypt`] byte for byte. The default
+    /// encrypts the bulk with [`aes_ctr_apply`](Self::aes_ctr_apply) and computes the
+    /// serial GHASH on the CPU (see [`crypto::aes_gcm_encrypt_with`]), so a backend with a
+    /// CTR kernel only overrides this to authenticate on the device as well.
+    fn aes_gcm_encrypt(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8; 12],
+        aad: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        crypto::aes_gcm_encrypt_with(key, nonce, aad, data, |counter, buf| {
+            self.aes_ctr_apply(key, counter, buf)
+        })
+    }
+
+    /// XORs the AES-256-CTR keystream starting at counter block `counter` into `data`,
+    /// incrementing the block's low 32 bits big-endian (wrapping) as GCM does.
+    ///
+    /// Every block is independent, so backends run one thread per counter block; the result
+    /// must match [`crypto::aes_ctr_apply`] byte for byte. The default is that CPU reference.
+    fn aes_ctr_apply(
+        &self,
+        key: &[u8; 32],
+        counter: &[u8; 16],
+        data: &mut [u8],
+    ) -> Result<(), String> {
+        crypto::aes_ctr_apply(key, counter, data);
+        Ok(())
+    }
+
+    /// Occurrences of each byte value in `data`, e.g. to estimate its entropy.
+    ///
+    /// Backends implement this as per-block shared-memory histograms summed into one; the
+    /// counts must match [`entropy::byte_histogram`]. The default is that CPU reference.
+    fn byte_histogram(&self, data: &[u8]) -> Result<[u64; 256], String> {
+        Ok(entropy::byte_histogram(data))
+    }
+
+    /// Stage timings accumulated so far, for backends built with profiling support. The
+    /// default (no profiling) returns `None`.
+    fn profile(&self) -> Option<profile::GpuProfile> {
+        None
+    }
 }
 
 pub struct CompressionOptions {
     pub level: u32,
     pub backend: GpuBackend,
     pub password: Option<String>,
+    /// Upper bound on how long a compression may run.
+    pub timeout: Option<Duration>,
+    /// Worker threads a codec may use; `Some(1)` disables parallel coding.
+    pub threads: Option<usize>,
+    /// Budget in bytes for the buffers a codec has in flight; codecs that batch work
+    /// shrink batches to fit. The input itself is held in memory regardless.
+    pub memory_limit: Option<u64>,
+    pub checksum: ChecksumAlgo,
+}
+
+impl CompressionOptions {
+    /// Starts the clock for a compression using these options.
+    pub fn deadline(&self) -> Deadline {
+        match self.timeout {
+            Some(timeout) => Deadline::after(timeout),
+            None => Deadline::none(),
+        }
+    }
+}
+
+/// A point in time after which a long-running operation should stop.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    expires: Option<(Instant, Duration)>,
+}
+
+impl Deadline {
+    pub fn none() -> Self {
+        Self { expires: None }
+    }
+
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            expires: Some((Instant::now() + timeout, timeout)),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        matches!(self.expires, Some((at, _)) if Instant::now() >= at)
+    }
+
+    /// Returns [`LatError::Timeout`] if the deadline has passed.
+    pub fn check(&self) -> Result<(), LatError> {
+        match self.expires {
+            Some((at, timeout)) if Instant::now() >= at => Err(LatError::Timeout(timeout)),
+            _ => Ok(()),
+        }
+    }
 }
 
 pub mod crypto {
-    use aes_gcm::aead::{Aead, AeadInPlace};
+    use aes_gcm::aead::{Aead, AeadInPlace, Payload};
+    use aes_gcm::aes::cipher::BlockEncrypt;
+    use aes_gcm::aes::{Aes256, Block};
     use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+    use ghash::GHash;
+    use ghash::universal_hash::UniversalHash;
     use pbkdf2::pbkdf2_hmac_array;
     use rand::Rng;
     use sha2::Sha256;
 
+    use super::{GpuAccelerator, LatError};
+
+    /// Inputs at least this large are encrypted on the GPU when an accelerator is given.
+    pub const GPU_ENCRYPT_THRESHOLD: usize = 16 * 1024 * 1024;
+
     pub fn encrypt(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+        encrypt_with_aad(data, password, b"")
+    }
+
+    pub fn decrypt(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+        decrypt_with_aad(data, password, b"")
+    }
+
+    /// Encrypts `data`, authenticating `aad` alongside the ciphertext.
+    ///
+    /// The associated data is not stored in the output; callers derive it from context
+    /// (e.g. the archive magic and version) and must pass the same bytes to
+    /// [`decrypt_with_aad`], otherwise authentication fails.
+    pub fn encrypt_with_aad(data: &[u8], password: &str, aad: &[u8]) -> Result<Vec<u8>, String> {
+        encrypt_accelerated(data, password, aad, None)
+    }
+
+    /// Like [`encrypt_with_aad`], but offloads bulk encryption to `accelerator` for inputs of
+    /// at least [`GPU_ENCRYPT_THRESHOLD`] bytes. Falls back to the CPU if the GPU path fails;
+    /// the output format is identical either way.
+    pub fn encrypt_accelerated(
+        data: &[u8],
+        password: &str,
+        aad: &[u8],
+        accelerator: Option<&dyn GpuAccelerator>,
+    ) -> Result<Vec<u8>, String> {
+        seal(data, password, aad, None, accelerator)
+    }
+
+    /// Where the 96-bit GCM nonce of an [`encrypt_with_nonce`] call comes from. The nonce
+    /// is stored in the output either way, so decryption works the same for both.
+    ///
+    /// A nonce must never repeat under one key. Random nonces collide with probability
+    /// about n² / 2⁹⁷ after n messages, roughly 2⁻³³ at 2³² messages, which is why NIST
+    /// SP 800-38D caps random nonces at 2³² messages per key. A counter can't collide until
+    /// its 64 bits wrap, provided each `base` is only ever used by one writer.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum NonceStrategy {
+        Random,
+        /// `base` followed by `counter` as big-endian; the counter advances once per call.
+        Counter {
+            base: [u8; 4],
+            counter: u64,
+        },
+    }
+
+    impl NonceStrategy {
+        /// The nonce for the next message, or `None` to draw a random one.
+        fn take(&mut self) -> Result<Option<[u8; 12]>, String> {
+            let NonceStrategy::Counter { base, counter } = self else {
+                return Ok(None);
+            };
+            let mut nonce = [0u8; 12];
+            nonce[..4].copy_from_slice(base);
+            nonce[4..].copy_from_slice(&counter.to_be_bytes());
+            // `decrypt` rejects an all-zero nonce as a sign of a zeroed file.
+            if nonce == [0u8; 12] {
+                return Err("Counter nonce is all zero; use a non-zero base or counter".to_string());
+            }
+            *counter = counter
+                .checked_add(1)
+                .ok_or_else(|| "Nonce counter exhausted".to_string())?;
+            Ok(Some(nonce))
+        }
+    }
+
+    /// Like [`encrypt_with_aad`], taking the nonce from `nonces` (which a counter strategy
+    /// advances), for callers that need deterministic, non-repeating nonces.
+    pub fn encrypt_with_nonce(
+        data: &[u8],
+        password: &str,
+        aad: &[u8],
+        nonces: &mut NonceStrategy,
+    ) -> Result<Vec<u8>, String> {
+        let nonce = nonces.take()?;
+        seal(data, password, aad, nonce, None)
+    }
+
+    /// Shared body of the encrypt functions; `nonce` of `None` means a random one.
+    fn seal(
+        data: &[u8],
+        password: &str,
+        aad: &[u8],
+        nonce: Option<[u8; 12]>,
+        accelerator: Option<&dyn GpuAccelerator>,
+    ) -> Result<Vec<u8>, String> {
         let mut rng = rand::thread_rng();
 
         // Bolt ⚡ Optimization: Generate salt and nonce on the stack in one go.
         // This avoids zero-initializing the heap-allocated result buffer before filling it,
         // and also simplifies key derivation by using the stack-allocated salt directly.
-        let salt_nonce: [u8; 28] = rng.r#gen();
+        let mut salt_nonce: [u8; 28] = rng.r#gen();
+        if let Some(nonce) = nonce {
+            salt_nonce[16..].copy_from_slice(&nonce);
+        }
         let salt = &salt_nonce[..16];
         let nonce = &salt_nonce[16..28];
 
         let key = pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt, 100_000);
+
+        if let Some(accel) = accelerator.filter(|_| data.len() >= GPU_ENCRYPT_THRESHOLD) {
+            let mut gpu_nonce = [0u8; 12];
+            gpu_nonce.copy_from_slice(nonce);
+            if let Ok(sealed) = accel.aes_gcm_encrypt(&key, &gpu_nonce, aad, data) {
+                let mut result = Vec::with_capacity(28 + sealed.len());
+                result.extend_from_slice(&salt_nonce);
+                result.extend_from_slice(&sealed);
+                return Ok(result);
+            }
+        }
+
         let cipher = Aes256Gcm::new(&key.into());
 
         // Bolt ⚡ Optimization: Pre-allocate result buffer and extend it with salt_nonce and data.
@@ -72,7 +1059,7 @@ pub mod crypto {
         // buffer while the rest of the buffer remains owned by the Vec.
         let (_, ciphertext) = result.split_at_mut(28);
         let tag = cipher
-            .encrypt_in_place_detached(Nonce::from_slice(nonce), b"", ciphertext)
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), aad, ciphertext)
             .map_err(|e| e.to_string())?;
 
         // Append the authentication tag. Capacity is guaranteed to be sufficient.
@@ -80,7 +1067,189 @@ pub mod crypto {
         Ok(result)
     }
 
-    pub fn decrypt(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    /// CPU reference AES-256-GCM: returns `ciphertext || tag` for a raw key and nonce.
+    pub fn aes_gcm_encrypt(
+        key: &[u8; 32],
+        nonce: &[u8; 12],
+        aad: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let cipher = Aes256Gcm::new(key.into());
+        cipher
+            .encrypt(Nonce::from_slice(nonce), Payload { msg: data, aad })
+            .map_err(|e| e.to_string())
+    }
+
+    /// GCM counter block `n` for a 96-bit nonce: the nonce followed by `n` big-endian.
+    /// Block 1 masks the tag; the keystream starts at block 2.
+    fn gcm_counter(nonce: &[u8; 12], n: u32) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(nonce);
+        block[12..].copy_from_slice(&n.to_be_bytes());
+        block
+    }
+
+    /// CPU reference AES-256-CTR: XORs the keystream starting at `counter` into `data`,
+    /// incrementing the low 32 bits big-endian per block (see
+    /// [`GpuAccelerator::aes_ctr_apply`]).
+    pub fn aes_ctr_apply(key: &[u8; 32], counter: &[u8; 16], data: &mut [u8]) {
+        let cipher = Aes256::new(key.into());
+        let start = u32::from_be_bytes([counter[12], counter[13], counter[14], counter[15]]);
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let mut block = Block::clone_from_slice(counter);
+            block[12..].copy_from_slice(&start.wrapping_add(i as u32).to_be_bytes());
+            cipher.encrypt_block(&mut block);
+            for (byte, key_byte) in chunk.iter_mut().zip(block.iter()) {
+                *byte ^= key_byte;
+            }
+        }
+    }
+
+    /// AES-256-GCM with the CTR encryption delegated to `ctr`, which is called once with
+    /// the first keystream counter block and the plaintext to encrypt in place. Only that
+    /// step is parallel; GHASH over the ciphertext is serial and runs here. Returns
+    /// `ciphertext || tag`, identical to [`aes_gcm_encrypt`] when `ctr` matches
+    /// [`aes_ctr_apply`].
+    pub fn aes_gcm_encrypt_with(
+        key: &[u8; 32],
+        nonce: &[u8; 12],
+        aad: &[u8],
+        data: &[u8],
+        ctr: impl FnOnce(&[u8; 16], &mut [u8]) -> Result<(), String>,
+    ) -> Result<Vec<u8>, String> {
+        let cipher = Aes256::new(key.into());
+        let mut sealed = Vec::with_capacity(data.len() + TAG_LEN);
+        sealed.extend_from_slice(data);
+        ctr(&gcm_counter(nonce, 2), &mut sealed)?;
+        let tag = gcm_tag(&cipher, nonce, aad, &sealed);
+        sealed.extend_from_slice(&tag);
+        Ok(sealed)
+    }
+
+    /// The GCM tag of `ciphertext` and `aad`: GHASH over both, masked with counter block 1.
+    /// Computing it needs no decryption, so checking a tag produces no plaintext.
+    fn gcm_tag(cipher: &Aes256, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Block {
+        // The hash key is the encryption of the zero block.
+        let mut hash_key = Block::default();
+        cipher.encrypt_block(&mut hash_key);
+        let mut ghash = GHash::new(&hash_key);
+        ghash.update_padded(aad);
+        ghash.update_padded(ciphertext);
+        let mut lengths = Block::default();
+        lengths[..8].copy_from_slice(&(aad.len() as u64 * 8).to_be_bytes());
+        lengths[8..].copy_from_slice(&(ciphertext.len() as u64 * 8).to_be_bytes());
+        ghash.update(&[lengths]);
+        let mut tag = ghash.finalize();
+
+        let mut mask = Block::clone_from_slice(&gcm_counter(nonce, 1));
+        cipher.encrypt_block(&mut mask);
+        for (byte, mask_byte) in tag.iter_mut().zip(mask.iter()) {
+            *byte ^= mask_byte;
+        }
+        tag
+    }
+
+    /// Whether `sealed` (`ciphertext || tag`) carries the right tag, compared in constant
+    /// time.
+    fn tag_matches(cipher: &Aes256, nonce: &[u8; 12], aad: &[u8], sealed: &[u8]) -> bool {
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let expected = gcm_tag(cipher, nonce, aad, ciphertext);
+        expected
+            .iter()
+            .zip(tag)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+
+    /// Checks the password and integrity of [`encrypt`] or [`encrypt_stream`] output
+    /// without decrypting it: every tag (one per chunk for a stream) is recomputed from the
+    /// ciphertext, so no plaintext is produced.
+    ///
+    /// Broken framing, or a stream where some chunks fail and others pass, is
+    /// [`LatError::CorruptArchive`]. When every tag of a multi-chunk stream fails the key
+    /// is taken to be wrong, [`LatError::WrongPassword`]. A single tag (one-shot output, a
+    /// one-chunk stream) can't tell a wrong password from damage, so its failure is a
+    /// [`LatError::CorruptArchive`] naming both causes.
+    pub fn authenticate(data: &[u8], password: &str) -> Result<(), LatError> {
+        if data.starts_with(&STREAM_MAGIC) {
+            match check_stream_header(data) {
+                Ok((chunk_size, chunk_count)) => {
+                    return authenticate_stream(data, password, chunk_size, chunk_count as usize);
+                }
+                // See `decrypt_auto`: such data may still be one-shot output.
+                Err(stream_err) => {
+                    return authenticate_one_shot(data, password)
+                        .map_err(|_| LatError::CorruptArchive(stream_err));
+                }
+            }
+        }
+        authenticate_one_shot(data, password)
+    }
+
+    fn authenticate_one_shot(data: &[u8], password: &str) -> Result<(), LatError> {
+        if data.len() < 44 {
+            return Err(LatError::CorruptArchive(
+                "Invalid encrypted data: too short".to_string(),
+            ));
+        }
+        let salt = &data[..16];
+        let nonce: [u8; 12] = data[16..28].try_into().unwrap();
+        if salt == [0u8; 16] || nonce == [0u8; 12] {
+            return Err(LatError::CorruptArchive(
+                "Invalid encrypted data: possible zeroed or corrupted file".to_string(),
+            ));
+        }
+        let key = pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt, 100_000);
+        let cipher = Aes256::new(&key.into());
+        if !tag_matches(&cipher, &nonce, b"", &data[28..]) {
+            return Err(lone_tag_failed());
+        }
+        Ok(())
+    }
+
+    /// The one failure a single tag can report: it doesn't say whether the key or the
+    /// data was wrong.
+    fn lone_tag_failed() -> LatError {
+        LatError::CorruptArchive(
+            "wrong password or damaged archive: authentication failed".to_string(),
+        )
+    }
+
+    fn authenticate_stream(
+        data: &[u8],
+        password: &str,
+        chunk_size: usize,
+        chunk_count: usize,
+    ) -> Result<(), LatError> {
+        let header = &data[..STREAM_HEADER_LEN];
+        let (salt, prefix) = (&header[5..21], &header[21..28]);
+        let key = pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt, 100_000);
+        let cipher = Aes256::new(&key.into());
+
+        let failed: Vec<usize> = data[STREAM_HEADER_LEN..]
+            .chunks(chunk_size + TAG_LEN)
+            .enumerate()
+            .filter(|&(index, sealed)| {
+                let nonce = stream_nonce(prefix, index as u32, index + 1 == chunk_count);
+                !tag_matches(&cipher, &nonce, header, sealed)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        match failed.first() {
+            None => Ok(()),
+            Some(_) if chunk_count == 1 => Err(lone_tag_failed()),
+            Some(_) if failed.len() == chunk_count => Err(LatError::WrongPassword),
+            Some(first) => Err(LatError::CorruptArchive(format!(
+                "Chunk {} failed authentication ({} of {} chunks damaged)",
+                first,
+                failed.len(),
+                chunk_count
+            ))),
+        }
+    }
+
+    /// Decrypts data produced by [`encrypt_with_aad`] with the same associated data.
+    pub fn decrypt_with_aad(data: &[u8], password: &str, aad: &[u8]) -> Result<Vec<u8>, String> {
         // Bolt ⚡ Optimization: Fail fast if data is too short to contain salt, nonce, and tag.
         // 16 (salt) + 12 (nonce) + 16 (tag) = 44 bytes
         if data.len() < 44 {
@@ -107,16 +1276,619 @@ pub mod crypto {
         // cipher.decrypt() reads directly from the ciphertext slice and writes to a new
         // plaintext Vec, saving the overhead of manually copying ciphertext into a buffer.
         let plaintext = cipher
-            .decrypt(Nonce::from_slice(nonce), ciphertext_and_tag)
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext_and_tag,
+                    aad,
+                },
+            )
             .map_err(|e| e.to_string())?;
 
         Ok(plaintext)
     }
+
+    /// Recommended plaintext chunk size for [`encrypt_stream`].
+    pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Leads every [`encrypt_stream`] output, so it is never mistaken for one-shot
+    /// [`encrypt`] output, which starts straight with a random salt.
+    pub const STREAM_MAGIC: [u8; 4] = *b"LATs";
+    const STREAM_VERSION: u8 = 1;
+
+    // magic (4) | version (1) | salt (16) | nonce prefix (7) | chunk size (u32 LE) |
+    // chunk count (u64 LE)
+    // Like every integer this crate serializes, the header fields are little-endian on all
+    // hosts; only the nonce's chunk index is big-endian, and it is never parsed back.
+    const STREAM_HEADER_LEN: usize = 40;
+    const TAG_LEN: usize = 16;
+
+    /// Per-chunk nonce: random prefix, big-endian chunk index and a final-chunk flag, so
+    /// chunks can't be reordered, and dropping the tail is detected even without the count.
+    fn stream_nonce(prefix: &[u8], index: u32, last: bool) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..7].copy_from_slice(prefix);
+        nonce[7..11].copy_from_slice(&index.to_be_bytes());
+        nonce[11] = last as u8;
+        nonce
+    }
+
+    /// Encrypts `data` as independently authenticated chunks of `chunk_size` bytes.
+    ///
+    /// Each chunk is sealed under the same key with its own nonce (see [`stream_nonce`]),
+    /// and the header, including the chunk count, is authenticated as associated data of
+    /// every chunk. Chunks can therefore be decrypted in parallel by [`decrypt_stream`].
+    pub fn encrypt_stream(
+        data: &[u8],
+        password: &str,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, String> {
+        let chunk_size = chunk_size.clamp(1, u32::MAX as usize);
+        // Empty input still gets one (empty) final chunk so the final flag is present.
+        let chunk_count = data.len().div_ceil(chunk_size).max(1);
+        if chunk_count > u32::MAX as usize + 1 {
+            return Err("Input too large for streaming encryption".to_string());
+        }
+
+        let mut rng = rand::thread_rng();
+        let salt_prefix: [u8; 23] = rng.r#gen();
+        let key = pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), &salt_prefix[..16], 100_000);
+        let cipher = Aes256Gcm::new(&key.into());
+
+        let mut result = Vec::with_capacity(STREAM_HEADER_LEN + data.len() + chunk_count * TAG_LEN);
+        result.extend_from_slice(&STREAM_MAGIC);
+        result.push(STREAM_VERSION);
+        result.extend_from_slice(&salt_prefix);
+        result.extend_from_slice(&(chunk_size as u32).to_le_bytes());
+        result.extend_from_slice(&(chunk_count as u64).to_le_bytes());
+        let header = result.clone();
+
+        for index in 0..chunk_count {
+            let start = index * chunk_size;
+            let chunk = &data[start.min(data.len())..(start + chunk_size).min(data.len())];
+            let nonce = stream_nonce(&salt_prefix[16..], index as u32, index + 1 == chunk_count);
+            let offset = result.len();
+            result.extend_from_slice(chunk);
+            let tag = cipher
+                .encrypt_in_place_detached(
+                    Nonce::from_slice(&nonce),
+                    &header,
+                    &mut result[offset..],
+                )
+                .map_err(|e| e.to_string())?;
+            result.extend_from_slice(tag.as_slice());
+        }
+        Ok(result)
+    }
+
+    /// Decrypts the output of [`encrypt_stream`], spreading chunks across all cores.
+    pub fn decrypt_stream(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        decrypt_stream_with_threads(data, password, threads)
+    }
+
+    /// Checks the magic, version and framing of a stream, returning its chunk size and
+    /// chunk count.
+    fn check_stream_header(data: &[u8]) -> Result<(usize, u64), String> {
+        if !data.starts_with(&STREAM_MAGIC) {
+            return Err(if STREAM_MAGIC.starts_with(data) {
+                "Invalid encrypted stream: truncated header".to_string()
+            } else {
+                "Invalid encrypted stream: no stream magic (one-shot encrypted data?)".to_string()
+            });
+        }
+        match data.get(4) {
+            Some(&STREAM_VERSION) => {}
+            Some(version) => {
+                return Err(format!("Unsupported encrypted stream version {}", version));
+            }
+            None => return Err("Invalid encrypted stream: truncated header".to_string()),
+        }
+        if data.len() < STREAM_HEADER_LEN {
+            return Err("Invalid encrypted stream: truncated header".to_string());
+        }
+        let chunk_size = u32::from_le_bytes(data[28..32].try_into().unwrap()) as usize;
+        let chunk_count = u64::from_le_bytes(data[32..40].try_into().unwrap());
+
+        if chunk_size == 0 || chunk_count == 0 || chunk_count > u32::MAX as u64 + 1 {
+            return Err("Invalid encrypted stream: bad header".to_string());
+        }
+        // The body must hold exactly `chunk_count` sealed chunks (all full but the last);
+        // anything else means chunks were dropped or appended. The count itself is
+        // authenticated by every chunk's tag.
+        let body_len = (data.len() - STREAM_HEADER_LEN) as u64;
+        let sealed_size = (chunk_size + TAG_LEN) as u64;
+        // Both fields come from the header; a count and size near their limits overflow.
+        let min_len = chunk_count
+            .checked_sub(1)
+            .and_then(|full| full.checked_mul(sealed_size))
+            .and_then(|len| len.checked_add(TAG_LEN as u64));
+        let max_len = chunk_count.checked_mul(sealed_size);
+        let (Some(min_len), Some(max_len)) = (min_len, max_len) else {
+            return Err("Invalid encrypted stream: bad header".to_string());
+        };
+        if !(min_len..=max_len).contains(&body_len) {
+            return Err("Invalid encrypted stream: chunk count mismatch (truncated?)".to_string());
+        }
+        Ok((chunk_size, chunk_count))
+    }
+
+    /// Decrypts the output of either [`encrypt`] or [`encrypt_stream`], told apart by
+    /// [`STREAM_MAGIC`]. `source` is read to the end first; pass `&data[..]` for bytes
+    /// already in memory.
+    pub fn decrypt_auto(mut source: impl std::io::Read, password: &str) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        source.read_to_end(&mut data).map_err(|e| e.to_string())?;
+        if !data.starts_with(&STREAM_MAGIC) {
+            if STREAM_MAGIC.starts_with(&data) {
+                return Err("Encrypted data too short to identify".to_string());
+            }
+            return decrypt(&data, password);
+        }
+        match check_stream_header(&data) {
+            Ok(_) => decrypt_stream(&data, password),
+            // One-shot data starts with the magic once per 2^32 random salts; only such
+            // data can fail the stream framing yet still decrypt.
+            Err(stream_err) => decrypt(&data, password).map_err(|_| stream_err),
+        }
+    }
+
+    /// [`decrypt_stream`] with an explicit worker count; output is identical for any count.
+    pub fn decrypt_stream_with_threads(
+        data: &[u8],
+        password: &str,
+        threads: usize,
+    ) -> Result<Vec<u8>, String> {
+        let (chunk_size, chunk_count) = check_stream_header(data)?;
+        let header = &data[..STREAM_HEADER_LEN];
+        let (salt, prefix) = (&header[5..21], &header[21..28]);
+        let body = &data[STREAM_HEADER_LEN..];
+        let sealed_size = chunk_size + TAG_LEN;
+
+        let key = pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt, 100_000);
+        let cipher = Aes256Gcm::new(&key.into());
+        let chunk_count = chunk_count as usize;
+
+        let decrypt_chunk = |index: usize| -> Result<Vec<u8>, String> {
+            let start = index * sealed_size;
+            let sealed = &body[start..(start + sealed_size).min(body.len())];
+            let nonce = stream_nonce(prefix, index as u32, index + 1 == chunk_count);
+            cipher
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: sealed,
+                        aad: header,
+                    },
+                )
+                .map_err(|_| format!("Chunk {} failed authentication", index))
+        };
+
+        // Workers take contiguous runs of chunks so the results concatenate in order.
+        let per_worker = chunk_count.div_ceil(threads.max(1));
+        let parts: Vec<Result<Vec<u8>, String>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..chunk_count)
+                .step_by(per_worker)
+                .map(|first| {
+                    let decrypt_chunk = &decrypt_chunk;
+                    scope.spawn(move || {
+                        let end = (first + per_worker).min(chunk_count);
+                        // Sized from the body, not the unauthenticated chunk size, so a
+                        // forged header can't request a huge buffer.
+                        let covered = (end - first).saturating_mul(sealed_size);
+                        let mut out = Vec::with_capacity(covered.min(body.len()));
+                        for index in first..end {
+                            out.extend_from_slice(&decrypt_chunk(index)?);
+                        }
+                        Ok(out)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| {
+                    h.join()
+                        .unwrap_or_else(|_| Err("Decryption worker panicked".to_string()))
+                })
+                .collect()
+        });
+
+        let mut plaintext = Vec::with_capacity(body.len());
+        for part in parts {
+            plaintext.extend_from_slice(&part?);
+        }
+        Ok(plaintext)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::crypto;
+    use super::{
+        ArchiveEntry, ArchiveFormat, ChecksumAlgo, CompressionOptions, Compressor, Deadline,
+        EncryptedCompressor, EntryKind, GpuAccelerator, GpuBackend, LatError, crypto,
+        read_entry_data, validate_entries, validate_entries_with, verify_written,
+    };
+    use crate::path::{NameLimits, OnDuplicate};
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    /// Accelerator that uses the default (CPU reference) kernels and counts AES calls.
+    #[derive(Default)]
+    struct CountingAccelerator {
+        aes_calls: AtomicUsize,
+    }
+
+    impl GpuAccelerator for CountingAccelerator {
+        fn name(&self) -> &str {
+            "Counting"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn mix_probabilities(
+            &self,
+            _model_probs: &[f32],
+            _weights: &[f32],
+            num_bits: usize,
+        ) -> Result<Vec<f32>, String> {
+            Ok(vec![0.5; num_bits])
+        }
+
+        fn aes_gcm_encrypt(
+            &self,
+            key: &[u8; 32],
+            nonce: &[u8; 12],
+            aad: &[u8],
+            data: &[u8],
+        ) -> Result<Vec<u8>, String> {
+            self.aes_calls.fetch_add(1, Ordering::SeqCst);
+            crypto::aes_gcm_encrypt(key, nonce, aad, data)
+        }
+    }
+
+    /// Compressor that spends a fixed amount of time on every entry.
+    struct SlowCompressor {
+        per_entry: Duration,
+    }
+
+    impl Compressor for SlowCompressor {
+        fn compress(
+            &self,
+            entries: &[ArchiveEntry],
+            _password: Option<&str>,
+        ) -> Result<Vec<u8>, LatError> {
+            let mut out = Vec::new();
+            for entry in entries {
+                std::thread::sleep(self.per_entry);
+                out.extend_from_slice(&entry.data);
+            }
+            Ok(out)
+        }
+
+        fn decompress(
+            &self,
+            _archive: &[u8],
+            _password: Option<&str>,
+        ) -> Result<Vec<ArchiveEntry>, LatError> {
+            Ok(Vec::new())
+        }
+
+        fn format(&self) -> ArchiveFormat {
+            ArchiveFormat::Test
+        }
+
+        fn compress_with_deadline(
+            &self,
+            entries: &[ArchiveEntry],
+            _password: Option<&str>,
+            deadline: Deadline,
+        ) -> Result<Vec<u8>, LatError> {
+            let mut out = Vec::new();
+            for entry in entries {
+                deadline.check()?;
+                std::thread::sleep(self.per_entry);
+                out.extend_from_slice(&entry.data);
+            }
+            Ok(out)
+        }
+    }
+
+    #[test]
+    fn test_compression_timeout() {
+        let compressor = SlowCompressor {
+            per_entry: Duration::from_millis(20),
+        };
+        let entries: Vec<ArchiveEntry> = (0..100)
+            .map(|i| {
+                ArchiveEntry::builder(format!("{}.txt", i))
+                    .data(vec![0; 16])
+                    .build()
+            })
+            .collect();
+        let options = CompressionOptions {
+            level: 0,
+            backend: GpuBackend::None,
+            password: None,
+            timeout: Some(Duration::from_millis(50)),
+            threads: None,
+            memory_limit: None,
+            checksum: ChecksumAlgo::default(),
+        };
+
+        let start = Instant::now();
+        let result = compressor.compress_with_deadline(&entries, None, options.deadline());
+
+        assert!(matches!(result, Err(LatError::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let unbounded = compressor.compress_with_deadline(&entries[..2], None, Deadline::none());
+        assert_eq!(unbounded.expect("Compression failed").len(), 32);
+    }
+
+    /// Compressor whose archives are entry names separated by newlines; each entry's data
+    /// is its name.
+    struct NameListCompressor;
+
+    impl Compressor for NameListCompressor {
+        fn compress(
+            &self,
+            entries: &[ArchiveEntry],
+            _password: Option<&str>,
+        ) -> Result<Vec<u8>, LatError> {
+            let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+            Ok(names.join("\n").into_bytes())
+        }
+
+        fn decompress(
+            &self,
+            archive: &[u8],
+            _password: Option<&str>,
+        ) -> Result<Vec<ArchiveEntry>, LatError> {
+            Ok(String::from_utf8_lossy(archive)
+                .lines()
+                .map(|name| {
+                    ArchiveEntry::builder(name.to_string())
+                        .data(name.as_bytes().to_vec())
+                        .build()
+                })
+                .collect())
+        }
+
+        fn format(&self) -> ArchiveFormat {
+            ArchiveFormat::Test
+        }
+    }
+
+    /// Sink that flips one bit of the byte written at `fault_at`, like a flaky controller.
+    struct FaultySink {
+        inner: std::io::Cursor<Vec<u8>>,
+        fault_at: u64,
+    }
+
+    impl Write for FaultySink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let mut data = buf.to_vec();
+            let start = self.inner.position();
+            let faulty = self.fault_at.checked_sub(start).map(|i| i as usize);
+            if let Some(byte) = faulty.and_then(|i| data.get_mut(i)) {
+                *byte ^= 0x10;
+            }
+            self.inner.write(&data)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for FaultySink {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for FaultySink {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_verify_after_write() {
+        let entries: Vec<ArchiveEntry> = ["a.txt", "b.txt"]
+            .iter()
+            .map(|name| ArchiveEntry::builder(name.to_string()).build())
+            .collect();
+        let path = std::env::temp_dir().join(format!("lat-verify-{}", std::process::id()));
+        NameListCompressor
+            .compress_to_file(&entries, None, &path, true)
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"a.txt\nb.txt");
+        std::fs::remove_file(&path).unwrap();
+
+        let archive = NameListCompressor.compress(&entries, None).unwrap();
+        let mut faulty = FaultySink {
+            inner: std::io::Cursor::new(Vec::new()),
+            fault_at: 3,
+        };
+        faulty.write_all(&archive).unwrap();
+        assert!(matches!(
+            verify_written(&NameListCompressor, &archive, None, &mut faulty),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("byte 3")
+        ));
+        let mut clean = std::io::Cursor::new(archive.clone());
+        assert!(verify_written(&NameListCompressor, &archive, None, &mut clean).is_ok());
+    }
+
+    #[test]
+    fn test_decompress_to_map() {
+        let map = NameListCompressor
+            .decompress_to_map(b"a.txt\ndir/b.txt\nc", None, OnDuplicate::Error)
+            .expect("Extraction failed");
+        let mut keys: Vec<&str> = map.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["a.txt", "c", "dir/b.txt"]);
+        assert_eq!(map["dir/b.txt"], b"dir/b.txt");
+
+        let duplicated = b"a.txt\nb\na.txt";
+        assert!(matches!(
+            NameListCompressor.decompress_to_map(duplicated, None, OnDuplicate::Error),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("a.txt")
+        ));
+        let renamed = NameListCompressor
+            .decompress_to_map(duplicated, None, OnDuplicate::Rename)
+            .unwrap();
+        assert_eq!(renamed.len(), 3);
+        assert!(renamed.contains_key("a (1).txt"));
+    }
+
+    #[test]
+    fn test_entry_builder_defaults_unset_fields() {
+        let entry = ArchiveEntry::builder("bin/run.sh")
+            .data(b"#!/bin/sh".to_vec())
+            .mode(0o755)
+            .xattr("user.note", b"x".to_vec())
+            .build();
+        assert_eq!(entry.name, "bin/run.sh");
+        assert_eq!(entry.data, b"#!/bin/sh");
+        assert_eq!(entry.mode, Some(0o755));
+        assert_eq!(entry.xattrs, [("user.note".to_string(), b"x".to_vec())]);
+        assert_eq!(entry.kind, EntryKind::File);
+        assert_eq!((entry.uid, entry.gid), (None, None));
+
+        let dir = ArchiveEntry::builder("docs/")
+            .kind(EntryKind::Directory)
+            .build();
+        assert!(dir.data.is_empty());
+        assert_eq!(dir.mode, None);
+        assert!(dir.xattrs.is_empty());
+    }
+
+    #[test]
+    fn test_list_with_progress_counts_up_to_total() {
+        let names: Vec<String> = (0..2500).map(|i| format!("dir/{}.txt", i)).collect();
+        let archive = names.join("\n");
+        let mut counts = Vec::new();
+        let listed = NameListCompressor
+            .list_with_progress(archive.as_bytes(), None, &mut |n| counts.push(n))
+            .expect("Listing failed");
+        assert_eq!(listed.len(), 2500);
+        assert_eq!(counts, [1000, 2000, 2500]);
+        assert_eq!(
+            NameListCompressor.list(archive.as_bytes(), None).unwrap(),
+            listed
+        );
+    }
+
+    #[test]
+    fn test_extract_dirs_only_writes_no_files() {
+        let dest = std::env::temp_dir().join(format!("lat-dirs-only-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dest);
+        let archive = b"a/b/c.txt\na/empty/\ne/f/g/h.bin\ntop.txt";
+        let created = NameListCompressor
+            .extract_dirs_only(archive, &dest, None)
+            .expect("Extraction failed");
+        let expected = ["a", "a/b", "a/empty", "e", "e/f", "e/f/g"];
+        assert_eq!(created, expected.map(|dir| dest.join(dir)).to_vec());
+
+        fn walk(dir: &Path, dirs: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) {
+            for item in std::fs::read_dir(dir).unwrap() {
+                let path = item.unwrap().path();
+                if path.is_dir() {
+                    walk(&path, dirs, files);
+                    dirs.push(path);
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        let (mut dirs, mut files) = (Vec::new(), Vec::new());
+        walk(&dest, &mut dirs, &mut files);
+        dirs.sort();
+        assert_eq!(dirs, created);
+        assert!(files.is_empty(), "{:?}", files);
+        std::fs::remove_dir_all(&dest).unwrap();
+
+        assert!(matches!(
+            NameListCompressor.extract_dirs_only(b"ok/x\n../escape/y", &dest, None),
+            Err(LatError::InvalidInput(_))
+        ));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_context_forms_a_breadcrumb_trail() {
+        let err = LatError::Backend("cipher unavailable".to_string())
+            .context(Some("foo/bar.bin"), "encryption");
+        assert_eq!(
+            err.to_string(),
+            "'foo/bar.bin' during encryption: cipher unavailable"
+        );
+        assert!(matches!(err.root_cause(), LatError::Backend(_)));
+
+        // Outer layers add their own breadcrumb without hiding the inner one.
+        let outer = err.context(None, "compression");
+        assert_eq!(
+            outer.to_string(),
+            "during compression: 'foo/bar.bin' during encryption: cipher unavailable"
+        );
+        let source = std::error::Error::source(&outer).unwrap();
+        assert!(source.to_string().starts_with("'foo/bar.bin'"));
+        assert!(matches!(outer.root_cause(), LatError::Backend(_)));
+    }
+
+    #[test]
+    fn test_name_limits_on_compress_and_extract() {
+        let long = ArchiveEntry::builder("n".repeat(5000)).build();
+        assert!(matches!(
+            validate_entries(&[long]),
+            Err(LatError::CorruptArchive(_))
+        ));
+
+        let deep = vec!["d"; 300].join("/");
+        assert!(matches!(
+            NameListCompressor.decompress_to_map(deep.as_bytes(), None, OnDuplicate::Error),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("300 components")
+        ));
+
+        let shallow = ArchiveEntry::builder("a/b/c").build();
+        let limits = NameLimits::new().with_max_components(2);
+        assert!(validate_entries(std::slice::from_ref(&shallow)).is_ok());
+        assert!(validate_entries_with(&[shallow], &limits).is_err());
+    }
+
+    /// Reader that records the largest buffer it was asked to fill.
+    struct MaxReadProbe<'a> {
+        data: &'a [u8],
+        max_request: usize,
+    }
+
+    impl Read for MaxReadProbe<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.max_request = self.max_request.max(buf.len());
+            self.data.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_read_entry_data_chunked() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 253) as u8).collect();
+
+        let mut probe = MaxReadProbe {
+            data: &data,
+            max_request: 0,
+        };
+        let read = read_entry_data(&mut probe, data.len(), Some(4096)).expect("Read failed");
+        assert_eq!(data, read);
+        assert!(probe.max_request <= 4096);
+
+        let mut short = &data[..10];
+        assert!(read_entry_data(&mut short, 20, Some(4)).is_err());
+    }
 
     #[test]
     fn test_encryption_decryption() {
@@ -140,4 +1912,271 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_verify_encrypted_without_decrypting() {
+        // Three chunks, so damage to one is told apart from a wrong key.
+        let data = vec![7u8; 10_000];
+        let sealed = crypto::encrypt_stream(&data, "pw", 4096).unwrap();
+        crypto::authenticate(&sealed, "pw").unwrap();
+        assert!(matches!(
+            crypto::authenticate(&sealed, "nope"),
+            Err(LatError::WrongPassword)
+        ));
+        let mut flipped = sealed.clone();
+        flipped[100] ^= 1;
+        assert!(matches!(
+            crypto::authenticate(&flipped, "pw"),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("Chunk 0")
+        ));
+
+        let compressor = EncryptedCompressor::new(SlowCompressor {
+            per_entry: Duration::ZERO,
+        });
+        let entries = vec![ArchiveEntry::builder("a.txt").data(data).build()];
+        let archive = compressor.compress(&entries, Some("pw")).unwrap();
+        compressor.verify(&archive, Some("pw")).unwrap();
+        // The compressor seals with a single tag, which fails the same way for a wrong
+        // password and for damage; the error says so instead of guessing.
+        let mut flipped = archive.clone();
+        flipped[60] ^= 1;
+        for (archive, password) in [(&archive, "nope"), (&flipped, "pw")] {
+            assert!(matches!(
+                compressor.verify(archive, Some(password)),
+                Err(LatError::CorruptArchive(msg)) if msg.contains("wrong password or damaged")
+            ));
+        }
+        let mut truncated = archive.clone();
+        truncated.truncate(40);
+        assert!(matches!(
+            compressor.verify(&truncated, Some("pw")),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_accelerated_encryption_threshold() {
+        let accel = CountingAccelerator::default();
+        let password = "gpu_password";
+
+        let small = vec![7u8; 1024];
+        let encrypted = crypto::encrypt_accelerated(&small, password, b"", Some(&accel))
+            .expect("Encryption failed");
+        assert_eq!(accel.aes_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(crypto::decrypt(&encrypted, password).unwrap(), small);
+
+        let large = vec![9u8; crypto::GPU_ENCRYPT_THRESHOLD];
+        let encrypted = crypto::encrypt_accelerated(&large, password, b"", Some(&accel))
+            .expect("Encryption failed");
+        assert_eq!(accel.aes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(crypto::decrypt(&encrypted, password).unwrap(), large);
+    }
+
+    #[test]
+    fn test_split_aes_gcm_matches_one_shot() {
+        let key = [0x5au8; 32];
+        let nonce = [0xa5u8; 12];
+        for len in [0, 1, 15, 16, 17, 4096 + 7] {
+            let data: Vec<u8> = (0..len as u32).map(|i| (i * 7 % 256) as u8).collect();
+            for aad in [&b""[..], b"header", &[3u8; 40]] {
+                let split = crypto::aes_gcm_encrypt_with(&key, &nonce, aad, &data, |c, buf| {
+                    crypto::aes_ctr_apply(&key, c, buf);
+                    Ok(())
+                })
+                .unwrap();
+                let one_shot = crypto::aes_gcm_encrypt(&key, &nonce, aad, &data).unwrap();
+                assert_eq!(split, one_shot, "len {} aad {:?}", len, aad);
+            }
+        }
+
+        // Accelerators without their own AES inherit the split path.
+        let data = vec![1u8; 1000];
+        assert_eq!(
+            crate::cpu::CpuAccelerator::new().aes_gcm_encrypt(&key, &nonce, b"x", &data),
+            crypto::aes_gcm_encrypt(&key, &nonce, b"x", &data)
+        );
+    }
+
+    #[test]
+    fn test_stream_parallel_decryption() {
+        let password = "stream_password";
+        let data: Vec<u8> = (0..1_000_003u32).map(|i| (i * 13 % 256) as u8).collect();
+        let encrypted = crypto::encrypt_stream(&data, password, 4096).expect("Encryption failed");
+
+        let sequential = crypto::decrypt_stream_with_threads(&encrypted, password, 1)
+            .expect("Sequential decryption failed");
+        let parallel = crypto::decrypt_stream_with_threads(&encrypted, password, 8)
+            .expect("Parallel decryption failed");
+        assert_eq!(sequential, data);
+        assert_eq!(parallel, sequential);
+
+        let empty = crypto::encrypt_stream(b"", password, 4096).unwrap();
+        assert!(crypto::decrypt_stream(&empty, password).unwrap().is_empty());
+        assert!(crypto::decrypt_stream(&encrypted, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_stream_header_is_little_endian() {
+        // 3 chunks of 0x0102 bytes; the header must spell both fields little-endian
+        // regardless of the host, or streams wouldn't move between machines.
+        let encrypted = crypto::encrypt_stream(&[7u8; 0x0300], "pw", 0x0102).unwrap();
+        assert_eq!(encrypted[..5], *b"LATs\x01");
+        assert_eq!(encrypted[28..32], [0x02, 0x01, 0, 0]);
+        assert_eq!(encrypted[32..40], [3, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            crypto::decrypt_stream(&encrypted, "pw").unwrap(),
+            [7u8; 0x0300]
+        );
+    }
+
+    #[test]
+    fn test_decrypt_auto_routes_by_magic() {
+        let password = "auto_password";
+        let data = b"route me".repeat(1000);
+        let one_shot = crypto::encrypt(&data, password).unwrap();
+        let streamed = crypto::encrypt_stream(&data, password, 1000).unwrap();
+        assert_eq!(crypto::decrypt_auto(&one_shot[..], password).unwrap(), data);
+        assert_eq!(crypto::decrypt_auto(&streamed[..], password).unwrap(), data);
+        assert!(crypto::decrypt_auto(&streamed[..], "wrong").is_err());
+
+        // Each decryptor names the other layout instead of failing obscurely.
+        let err = crypto::decrypt_stream(&one_shot, password).unwrap_err();
+        assert!(err.contains("no stream magic"), "{}", err);
+
+        let err = crypto::decrypt_auto(&streamed[..3], password).unwrap_err();
+        assert!(err.contains("too short"), "{}", err);
+        let err = crypto::decrypt_auto(&streamed[..20], password).unwrap_err();
+        assert!(err.contains("truncated header"), "{}", err);
+        let err = crypto::decrypt_auto(&streamed[..streamed.len() - 1], password).unwrap_err();
+        assert!(err.contains("truncated"), "{}", err);
+        let mut future = streamed.clone();
+        future[4] = 2;
+        let err = crypto::decrypt_auto(&future[..], password).unwrap_err();
+        assert!(err.contains("version 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_stream_detects_dropped_and_swapped_chunks() {
+        let password = "stream_password";
+        let data = vec![0x5Au8; 10 * 1000];
+        let encrypted = crypto::encrypt_stream(&data, password, 1000).unwrap();
+        let (header, body) = encrypted.split_at(40);
+        let sealed = 1000 + 16;
+
+        // Drop chunk 4.
+        let mut dropped = header.to_vec();
+        dropped.extend_from_slice(&body[..4 * sealed]);
+        dropped.extend_from_slice(&body[5 * sealed..]);
+        assert!(crypto::decrypt_stream(&dropped, password).is_err());
+
+        // Also rewriting the count in the header breaks every chunk's authentication.
+        dropped[32..40].copy_from_slice(&9u64.to_le_bytes());
+        assert!(crypto::decrypt_stream(&dropped, password).is_err());
+
+        // Swap chunks 2 and 3.
+        let mut swapped = encrypted.clone();
+        let (a, b) = (40 + 2 * sealed, 40 + 3 * sealed);
+        let chunk2 = encrypted[a..b].to_vec();
+        swapped.copy_within(b..b + sealed, a);
+        swapped[b..b + sealed].copy_from_slice(&chunk2);
+        assert!(crypto::decrypt_stream(&swapped, password).is_err());
+    }
+
+    #[test]
+    fn test_stream_header_sizes_cannot_overflow() {
+        // The largest chunk size and count multiply past u64.
+        let mut encrypted = crypto::encrypt_stream(&[1u8; 100], "pw", 64).unwrap();
+        encrypted[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        encrypted[32..40].copy_from_slice(&(u32::MAX as u64 + 1).to_le_bytes());
+        let err = crypto::decrypt_stream(&encrypted, "pw").unwrap_err();
+        assert!(err.contains("bad header"), "{}", err);
+        assert!(crypto::authenticate(&encrypted, "pw").is_err());
+    }
+
+    #[test]
+    fn test_counter_nonces_are_distinct_and_decrypt() {
+        let mut nonces = crypto::NonceStrategy::Counter {
+            base: *b"lat0",
+            counter: 41,
+        };
+        let sealed: Vec<Vec<u8>> = (0..3)
+            .map(|i| {
+                crypto::encrypt_with_nonce(
+                    format!("message {}", i).as_bytes(),
+                    "pw",
+                    b"",
+                    &mut nonces,
+                )
+                .unwrap()
+            })
+            .collect();
+        for (i, data) in sealed.iter().enumerate() {
+            assert_eq!(&data[16..20], b"lat0");
+            assert_eq!(data[16..28][4..], (41 + i as u64).to_be_bytes());
+            assert_eq!(
+                crypto::decrypt(data, "pw").unwrap(),
+                format!("message {}", i).as_bytes()
+            );
+        }
+        assert_eq!(
+            nonces,
+            crypto::NonceStrategy::Counter {
+                base: *b"lat0",
+                counter: 44
+            }
+        );
+
+        let mut zero = crypto::NonceStrategy::Counter {
+            base: [0; 4],
+            counter: 0,
+        };
+        assert!(crypto::encrypt_with_nonce(b"x", "pw", b"", &mut zero).is_err());
+        let mut random = crypto::NonceStrategy::Random;
+        let a = crypto::encrypt_with_nonce(b"x", "pw", b"", &mut random).unwrap();
+        let b = crypto::encrypt_with_nonce(b"x", "pw", b"", &mut random).unwrap();
+        assert_ne!(a[16..28], b[16..28]);
+    }
+
+    #[test]
+    fn test_aad_binding() {
+        let password = "aad_password";
+        let data = b"Bound to its header";
+
+        let encrypted =
+            crypto::encrypt_with_aad(data, password, b"LAT\x01").expect("Encryption failed");
+
+        let decrypted =
+            crypto::decrypt_with_aad(&encrypted, password, b"LAT\x01").expect("Decryption failed");
+        assert_eq!(data.as_slice(), decrypted.as_slice());
+
+        assert!(crypto::decrypt_with_aad(&encrypted, password, b"LAT\x02").is_err());
+        assert!(crypto::decrypt(&encrypted, password).is_err());
+    }
+
+    #[test]
+    fn test_archive_format_extensions() {
+        let extensions: Vec<&str> = ArchiveFormat::ALL.iter().map(|f| f.extension()).collect();
+        assert_eq!(
+            extensions,
+            ["zip", "7z", "lat", "paq", "bz2", "gz", "tar", "zst", "xz"]
+        );
+        for format in ArchiveFormat::ALL {
+            assert_eq!(
+                ArchiveFormat::from_extension(format.extension()),
+                Some(format)
+            );
+        }
+        assert_eq!(
+            ArchiveFormat::from_extension("7Z"),
+            Some(ArchiveFormat::SevenZ)
+        );
+        assert_eq!(ArchiveFormat::from_extension("rar"), None);
+        assert_eq!(ArchiveFormat::SevenZ.display_name(), "7z");
+        assert!(ArchiveFormat::Zip.stores_entry_names());
+        assert!(ArchiveFormat::Gzip.stores_entry_names());
+        assert!(!ArchiveFormat::Xz.stores_entry_names());
+
+        let encrypted = EncryptedCompressor::new(NameListCompressor);
+        assert_eq!(encrypted.format(), ArchiveFormat::Test);
+    }
 }
@@ -0,0 +1,121 @@
+//! The logistic functions of context mixing, exact and as lookup tables.
+//!
+//! Mixing calls [`stretch`] (the logit) once per model and [`squash`] (the sigmoid) once
+//! per coded bit, so `ln`/`exp` dominate its cost. A [`LogisticTable`] samples both
+//! functions once and interpolates between samples, as PAQ-family coders do. The table is
+//! deterministic, so an encoder and decoder using tables of the same size agree bit for
+//! bit; its values can also be uploaded for a GPU kernel to sample.
+
+/// Probabilities are clamped this far from 0 and 1 before stretching, so the logit stays
+/// finite.
+pub const PROB_EPSILON: f32 = 1.0 / 4096.0;
+
+/// [`squash`] is sampled over `-SQUASH_LIMIT..=SQUASH_LIMIT`; beyond that it is within
+/// 1e-5 of 0 or 1, which no coder distinguishes.
+pub const SQUASH_LIMIT: f32 = 12.0;
+
+/// Samples per table: stretch errs by under 0.06 (the worst of it next to the clamped
+/// ends) and squash by under 1e-5.
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// `ln(p / (1 - p))`, with `p` clamped to `PROB_EPSILON..=1 - PROB_EPSILON`.
+pub fn stretch(p: f32) -> f32 {
+    let p = p.clamp(PROB_EPSILON, 1.0 - PROB_EPSILON);
+    (p / (1.0 - p)).ln()
+}
+
+/// `1 / (1 + e^-x)`, the inverse of [`stretch`].
+pub fn squash(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// [`stretch`] and [`squash`] sampled at `size + 1` evenly spaced points of their domains
+/// (`0..=1` and `-SQUASH_LIMIT..=SQUASH_LIMIT`) and linearly interpolated in between.
+/// Larger tables are more accurate and take more cache.
+#[derive(Debug, Clone)]
+pub struct LogisticTable {
+    stretch: Vec<f32>,
+    squash: Vec<f32>,
+}
+
+impl Default for LogisticTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_SIZE)
+    }
+}
+
+impl LogisticTable {
+    /// A table with `size` intervals per function (at least 1).
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        let step = 2.0 * SQUASH_LIMIT / size as f32;
+        Self {
+            stretch: (0..=size)
+                .map(|i| stretch(i as f32 / size as f32))
+                .collect(),
+            squash: (0..=size)
+                .map(|i| squash(i as f32 * step - SQUASH_LIMIT))
+                .collect(),
+        }
+    }
+
+    /// Intervals per function.
+    pub fn size(&self) -> usize {
+        self.stretch.len() - 1
+    }
+
+    /// Table lookup of [`stretch`].
+    pub fn stretch(&self, p: f32) -> f32 {
+        interpolate(&self.stretch, p.clamp(0.0, 1.0))
+    }
+
+    /// Table lookup of [`squash`].
+    pub fn squash(&self, x: f32) -> f32 {
+        let x = x.clamp(-SQUASH_LIMIT, SQUASH_LIMIT);
+        interpolate(&self.squash, (x + SQUASH_LIMIT) / (2.0 * SQUASH_LIMIT))
+    }
+
+    /// The stretch samples, for p = `i / size()`.
+    pub fn stretch_values(&self) -> &[f32] {
+        &self.stretch
+    }
+
+    /// The squash samples, for x = `-SQUASH_LIMIT + i * 2 * SQUASH_LIMIT / size()`.
+    pub fn squash_values(&self) -> &[f32] {
+        &self.squash
+    }
+}
+
+/// Linear interpolation in `samples` at `t` in `0..=1`.
+fn interpolate(samples: &[f32], t: f32) -> f32 {
+    let pos = t * (samples.len() - 1) as f32;
+    let i = (pos as usize).min(samples.len() - 2);
+    let frac = pos - i as f32;
+    samples[i] + (samples[i + 1] - samples[i]) * frac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_tracks_exact_functions() {
+        let fine = LogisticTable::default();
+        let coarse = LogisticTable::new(64);
+        assert_eq!(fine.size(), DEFAULT_TABLE_SIZE);
+        assert_eq!(fine.stretch_values().len(), DEFAULT_TABLE_SIZE + 1);
+
+        let (mut fine_err, mut coarse_err) = (0.0f32, 0.0f32);
+        for i in 0..=10_000 {
+            let p = i as f32 / 10_000.0;
+            fine_err = fine_err.max((fine.stretch(p) - stretch(p)).abs());
+            coarse_err = coarse_err.max((coarse.stretch(p) - stretch(p)).abs());
+            let x = (i as f32 / 10_000.0 - 0.5) * 30.0;
+            assert!((fine.squash(x) - squash(x)).abs() < 1e-5, "squash({})", x);
+        }
+        assert!(fine_err < 0.06, "stretch error {}", fine_err);
+        assert!(coarse_err > fine_err);
+        assert_eq!(fine.stretch(0.0), stretch(0.0));
+        assert_eq!(fine.stretch(1.0), stretch(1.0));
+    }
+}
@@ -0,0 +1,133 @@
+//! Machine-readable listing of what went into an archive, for tooling and CI.
+
+use crate::{ArchiveEntry, CompressionResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One archived member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub size: u64,
+    /// `None` when the codec can't attribute output to members (see
+    /// [`CompressionResult::per_entry`]).
+    pub compressed_size: Option<u64>,
+    pub method: Option<String>,
+    /// Seconds since the Unix epoch. Archive entries don't carry it, so callers that know
+    /// the source file fill it in.
+    pub mtime: Option<u64>,
+    /// Unix mode bits, filled in the same way as `mtime`.
+    pub permissions: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Lowercase hex SHA-256 of the uncompressed data.
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub archive_size: u64,
+    pub uncompressed_size: u64,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Describes `entries` as they were compressed into `result`.
+    pub fn new(result: &CompressionResult, entries: &[ArchiveEntry]) -> Self {
+        let stats: HashMap<&str, _> = result
+            .per_entry
+            .iter()
+            .map(|stat| (stat.name.as_str(), stat))
+            .collect();
+        let entries = entries
+            .iter()
+            .map(|entry| {
+                let stat = stats.get(entry.name.as_str());
+                ManifestEntry {
+                    name: entry.name.clone(),
+                    size: entry.data.len() as u64,
+                    compressed_size: stat.map(|s| s.compressed),
+                    method: stat.map(|s| s.method_used.clone()),
+                    mtime: None,
+                    permissions: None,
+                    uid: entry.uid,
+                    gid: entry.gid,
+                    sha256: hex(&Sha256::digest(&entry.data)),
+                }
+            })
+            .collect();
+        Self {
+            archive_size: result.data.len() as u64,
+            uncompressed_size: result.uncompressed_size,
+            entries,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only strings, integers and options: serialization can't fail.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Writes the manifest as JSON to `path`, usually next to the archive.
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntryStat;
+
+    #[test]
+    fn test_manifest_json_round_trip() {
+        let entries = vec![
+            ArchiveEntry::builder("dir/\"quoted\".txt")
+                .data(b"abc".to_vec())
+                .uid(1000)
+                .build(),
+            ArchiveEntry::builder("empty").build(),
+        ];
+        let result = CompressionResult {
+            data: vec![0; 40],
+            uncompressed_size: 3,
+            per_entry: vec![EntryStat::new("dir/\"quoted\".txt", 3, 5, "Stored")],
+            warnings: Vec::new(),
+            context_memory: 0,
+        };
+        let mut manifest = Manifest::new(&result, &entries);
+        manifest.entries[0].mtime = Some(1_700_000_000);
+        manifest.entries[0].permissions = Some(0o644);
+
+        let parsed: Manifest = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert_eq!(parsed, manifest);
+        assert_eq!(parsed.archive_size, 40);
+        let first = &parsed.entries[0];
+        assert_eq!(first.name, entries[0].name);
+        assert_eq!((first.size, first.compressed_size), (3, Some(5)));
+        assert_eq!(first.method.as_deref(), Some("Stored"));
+        assert_eq!(first.uid, Some(1000));
+        assert_eq!(
+            first.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(parsed.entries[1].compressed_size, None);
+
+        let empty = CompressionResult {
+            data: Vec::new(),
+            uncompressed_size: 0,
+            per_entry: Vec::new(),
+            warnings: Vec::new(),
+            context_memory: 0,
+        };
+        let json = Manifest::new(&empty, &[]).to_json();
+        let parsed: Manifest = serde_json::from_str(&json).unwrap();
+        assert!(parsed.entries.is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Memory-mapped reading of large input files.
+//!
+//! Reading a multi-gigabyte file into a `Vec<u8>` allocates and copies all of it up
+//! front. A [`MappedInput`] maps files at or above a threshold instead, so the OS pages
+//! data in as a codec walks it; smaller files are read normally, where a mapping's setup
+//! cost outweighs the copy.
+//!
+//! A mapping reflects the file as it is on disk, not as it was when it was opened: if
+//! another process truncates the file while it is mapped, touching the lost pages kills
+//! the process with `SIGBUS` (or raises an access violation on Windows), which can't be
+//! recovered from safely. [`MappedInput::check_unchanged`] detects files that were
+//! modified while in use, after the fact, so the result can be discarded.
+
+use crate::LatError;
+use memmap2::Mmap;
+use std::fs::{File, Metadata};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Files at or above this size are mapped by [`MappedInput::open`].
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+enum Data {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+/// The contents of an input file, either mapped or read into memory depending on its
+/// size. Derefs to the file's bytes.
+pub struct MappedInput {
+    data: Data,
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl MappedInput {
+    /// Opens `path`, mapping it if it is at least [`MMAP_THRESHOLD`] bytes.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LatError> {
+        Self::open_with_threshold(path, MMAP_THRESHOLD)
+    }
+
+    /// Opens `path`, mapping it if it is at least `threshold` bytes. Empty files are
+    /// always read, since they can't be mapped.
+    pub fn open_with_threshold(path: impl AsRef<Path>, threshold: u64) -> Result<Self, LatError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let len = metadata.len();
+        let data = if len > 0 && len >= threshold {
+            // SAFETY: the mapping is read-only and private to this value. Changes made to
+            // the file by other processes while it is mapped are the hazard described in
+            // the module docs, not undefined behavior this crate can rule out.
+            Data::Mapped(unsafe { Mmap::map(&file)? })
+        } else {
+            let mut data = Vec::with_capacity(len as usize);
+            std::io::Read::read_to_end(&mut &file, &mut data)?;
+            Data::Read(data)
+        };
+        Ok(Self {
+            data,
+            path: path.to_path_buf(),
+            len,
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Whether the file is mapped rather than read into memory.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.data, Data::Mapped(_))
+    }
+
+    /// Fails if the file's size or modification time differ from when it was opened, in
+    /// which case the bytes seen through a mapping may be a mix of old and new contents.
+    pub fn check_unchanged(&self) -> Result<(), LatError> {
+        let metadata = std::fs::metadata(&self.path)?;
+        if !self.matches(&metadata) {
+            return Err(LatError::InvalidInput(format!(
+                "{} changed while it was being read",
+                self.path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn matches(&self, metadata: &Metadata) -> bool {
+        metadata.len() == self.len && metadata.modified().ok() == self.modified
+    }
+
+    /// The file's bytes as an owned buffer. Copies out of the mapping if there is one,
+    /// failing if the file changed meanwhile.
+    pub fn into_vec(self) -> Result<Vec<u8>, LatError> {
+        match self.data {
+            Data::Read(data) => Ok(data),
+            Data::Mapped(ref map) => {
+                let data = map.to_vec();
+                self.check_unchanged()?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+impl Deref for MappedInput {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.data {
+            Data::Mapped(map) => map,
+            Data::Read(data) => data,
+        }
+    }
+}
+
+impl AsRef<[u8]> for MappedInput {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mapping_matches_the_file_and_detects_changes() {
+        let path = std::env::temp_dir().join(format!("lat-mmap-{}", std::process::id()));
+        let data: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let mapped = MappedInput::open_with_threshold(&path, 0).unwrap();
+        assert!(mapped.is_mapped());
+        assert_eq!(&*mapped, &data[..]);
+        mapped.check_unchanged().unwrap();
+        drop(mapped);
+
+        let read = MappedInput::open(&path).unwrap();
+        assert!(!read.is_mapped());
+        std::fs::write(&path, b"shorter").unwrap();
+        assert!(read.check_unchanged().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! Extracting archives stored inside archives, with a bound on how deep that goes.
+//!
+//! Recursive "extract everything" tools can be looped forever (or exhausted) by an
+//! archive that nests copies of itself. [`DepthGuard`] counts the levels and refuses to
+//! go past a limit.
+
+use crate::{ArchiveEntry, Compressor, LatError};
+
+/// How many archive levels an extraction has entered. The outermost archive is level 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthGuard {
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Default for DepthGuard {
+    /// Only the outermost archive: no automatic recursion.
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl DepthGuard {
+    /// A guard for the outermost archive that allows `max_depth` levels in total.
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            depth: 1,
+            max_depth: max_depth.max(1),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// The guard for an archive found inside the current one, or
+    /// [`LatError::CorruptArchive`] if that would exceed the limit.
+    pub fn descend(&self) -> Result<Self, LatError> {
+        if self.depth >= self.max_depth {
+            return Err(LatError::CorruptArchive(format!(
+                "archives nested more than {} levels deep",
+                self.max_depth
+            )));
+        }
+        Ok(Self {
+            depth: self.depth + 1,
+            max_depth: self.max_depth,
+        })
+    }
+}
+
+/// Decompresses `archive` and, recursively, every entry that `compressor` recognizes as
+/// an archive of its own format (its [`archive_info`](Compressor::archive_info)
+/// succeeds), up to `max_depth` levels in total. Nested entries are named
+/// `outer-entry/inner-entry`; the archive entries themselves are replaced by their
+/// contents.
+///
+/// An archive found at the last allowed level fails the whole extraction with
+/// [`LatError::CorruptArchive`], so with a `max_depth` of 1 any nested archive is refused.
+/// Formats without `archive_info` are never recursed into.
+pub fn decompress_nested(
+    compressor: &dyn Compressor,
+    archive: &[u8],
+    password: Option<&str>,
+    max_depth: usize,
+) -> Result<Vec<ArchiveEntry>, LatError> {
+    let mut out = Vec::new();
+    expand(
+        compressor,
+        archive,
+        password,
+        DepthGuard::new(max_depth),
+        "",
+        &mut out,
+    )?;
+    Ok(out)
+}
+
+fn expand(
+    compressor: &dyn Compressor,
+    archive: &[u8],
+    password: Option<&str>,
+    guard: DepthGuard,
+    prefix: &str,
+    out: &mut Vec<ArchiveEntry>,
+) -> Result<(), LatError> {
+    for mut entry in compressor.decompress(archive, password)? {
+        entry.name = format!("{}{}", prefix, entry.name);
+        if entry.data.is_empty() || compressor.archive_info(&entry.data).is_err() {
+            out.push(entry);
+            continue;
+        }
+        let prefix = format!("{}/", entry.name);
+        expand(
+            compressor,
+            &entry.data,
+            password,
+            guard.descend()?,
+            &prefix,
+            out,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::IndexCompressor;
+
+    fn wrap(name: &str, data: Vec<u8>) -> Vec<u8> {
+        let entry = ArchiveEntry::builder(name.to_string()).data(data).build();
+        IndexCompressor.compress(&[entry], None).unwrap()
+    }
+
+    #[test]
+    fn test_nested_depth_limit() {
+        // outer -> middle.idx -> inner.idx -> deep.txt: three archive levels.
+        let inner = wrap("deep.txt", b"bottom".to_vec());
+        let middle = wrap("inner.idx", inner);
+        let outer = wrap("middle.idx", middle);
+
+        assert!(matches!(
+            decompress_nested(&IndexCompressor, &outer, None, 2),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("2 levels")
+        ));
+        assert!(decompress_nested(&IndexCompressor, &outer, None, 1).is_err());
+
+        let entries = decompress_nested(&IndexCompressor, &outer, None, 3).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "middle.idx/inner.idx/deep.txt");
+        assert_eq!(entries[0].data, b"bottom");
+
+        let guard = DepthGuard::default();
+        assert_eq!((guard.depth(), guard.max_depth()), (1, 1));
+        assert!(guard.descend().is_err());
+        assert_eq!(DepthGuard::new(2).descend().unwrap().depth(), 2);
+    }
+}
@@ -0,0 +1,465 @@
+//! Mapping archive entry names onto host filesystem paths.
+
+use crate::{ArchiveEntry, EntryKind, LatError, ListedEntry};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use unicode_normalization::{UnicodeNormalization, is_nfc};
+
+/// What to do with characters the host filesystem can't store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SanitizeMode {
+    /// Replace illegal characters with `_` and rename reserved components.
+    #[default]
+    Replace,
+    /// Reject names that would need any change.
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    Windows,
+    Unix,
+}
+
+impl Platform {
+    fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Unix
+        }
+    }
+}
+
+/// Makes an entry name (with `/` separators) safe to join onto an extraction directory on
+/// this platform. Names that are already valid are returned borrowed.
+///
+/// NUL can't be represented on any supported filesystem and is always rejected.
+pub fn sanitize_for_filesystem(name: &str, mode: SanitizeMode) -> Result<Cow<'_, str>, LatError> {
+    sanitize_for(name, mode, Platform::current())
+}
+
+fn is_illegal(c: char, platform: Platform) -> bool {
+    match platform {
+        Platform::Windows => {
+            matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*' | '\\') || c.is_ascii_control()
+        }
+        Platform::Unix => false,
+    }
+}
+
+/// Device names Windows reserves in every directory, with or without an extension.
+fn is_reserved_windows_name(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or_default();
+    let upper = stem.trim_end().to_ascii_uppercase();
+    match upper.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            (upper.starts_with("COM") || upper.starts_with("LPT"))
+                && upper.len() == 4
+                && matches!(upper.as_bytes()[3], b'1'..=b'9')
+        }
+    }
+}
+
+fn sanitize_for(
+    name: &str,
+    mode: SanitizeMode,
+    platform: Platform,
+) -> Result<Cow<'_, str>, LatError> {
+    if name.contains('\0') {
+        return Err(LatError::InvalidInput(format!(
+            "entry name {:?} contains a NUL byte",
+            name
+        )));
+    }
+
+    let needs_change = |component: &str| {
+        platform == Platform::Windows
+            && (component.chars().any(|c| is_illegal(c, platform))
+                || is_reserved_windows_name(component)
+                || (component.ends_with(['.', ' ']) && component != "." && component != ".."))
+    };
+    if !name.split('/').any(needs_change) {
+        return Ok(Cow::Borrowed(name));
+    }
+    if mode == SanitizeMode::Strict {
+        return Err(LatError::InvalidInput(format!(
+            "entry name {:?} is not a valid path on this platform",
+            name
+        )));
+    }
+
+    let components: Vec<String> = name
+        .split('/')
+        .map(|component| {
+            if !needs_change(component) {
+                return component.to_string();
+            }
+            let mut fixed: String = component
+                .chars()
+                .map(|c| if is_illegal(c, platform) { '_' } else { c })
+                .collect();
+            // Windows silently strips trailing dots and spaces, which would merge names.
+            if fixed.ends_with(['.', ' ']) {
+                fixed.pop();
+                fixed.push('_');
+            }
+            if is_reserved_windows_name(&fixed) {
+                fixed.insert(0, '_');
+            }
+            fixed
+        })
+        .collect();
+    Ok(Cow::Owned(components.join("/")))
+}
+
+/// Every directory `entries` implies: directory entries (including names ending in `/`)
+/// and the parents of every entry, as relative `/`-separated paths. Sorted, so parents
+/// come before their children. Empty and `.` components are dropped; `..` would leave
+/// the extraction directory and is rejected.
+pub fn directory_tree(entries: &[ListedEntry]) -> Result<BTreeSet<String>, LatError> {
+    let mut dirs = BTreeSet::new();
+    for entry in entries {
+        let mut components: Vec<&str> = entry
+            .name
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect();
+        if components.contains(&"..") {
+            return Err(LatError::InvalidInput(format!(
+                "entry name {:?} points outside the destination",
+                entry.name
+            )));
+        }
+        if entry.kind != EntryKind::Directory && !entry.name.ends_with('/') {
+            components.pop();
+        }
+        for depth in 1..=components.len() {
+            dirs.insert(components[..depth].join("/"));
+        }
+    }
+    Ok(dirs)
+}
+
+/// Bounds on entry names, checked when writing and when extracting. Absurdly long names
+/// or deeply nested paths exhaust memory or trip filesystem limits well before they
+/// describe a real file tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameLimits {
+    /// Longest allowed name, in bytes.
+    pub max_name_len: usize,
+    /// Most `/`-separated components a name may have.
+    pub max_components: usize,
+}
+
+impl Default for NameLimits {
+    fn default() -> Self {
+        Self {
+            max_name_len: 4096,
+            max_components: 256,
+        }
+    }
+}
+
+impl NameLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_name_len(mut self, bytes: usize) -> Self {
+        self.max_name_len = bytes;
+        self
+    }
+
+    pub fn with_max_components(mut self, components: usize) -> Self {
+        self.max_components = components;
+        self
+    }
+
+    /// Returns [`LatError::CorruptArchive`] if `name` exceeds either limit.
+    pub fn check(&self, name: &str) -> Result<(), LatError> {
+        if name.len() > self.max_name_len {
+            return Err(LatError::CorruptArchive(format!(
+                "entry name is {} bytes, more than the limit of {}",
+                name.len(),
+                self.max_name_len
+            )));
+        }
+        let components = name.split('/').filter(|c| !c.is_empty()).count();
+        if components > self.max_components {
+            return Err(LatError::CorruptArchive(format!(
+                "entry path has {} components, more than the limit of {}",
+                components, self.max_components
+            )));
+        }
+        Ok(())
+    }
+
+    /// [`check`](Self::check)s every entry's name.
+    pub fn check_entries(&self, entries: &[ArchiveEntry]) -> Result<(), LatError> {
+        entries.iter().try_for_each(|e| self.check(&e.name))
+    }
+}
+
+/// Returns `name` in Unicode NFC. macOS stores file names decomposed (NFD), so the same
+/// name can reach an archive in either form; names already in NFC are returned borrowed.
+pub fn normalize_nfc(name: &str) -> Cow<'_, str> {
+    if is_nfc(name) {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(name.nfc().collect())
+    }
+}
+
+/// Rewrites every entry name to NFC (see [`normalize_nfc`]), so names round-trip to the
+/// same file on every platform. Run it before [`resolve_duplicates`] to catch entries
+/// whose names differ only in normalization form.
+pub fn normalize_entry_names(entries: &mut [ArchiveEntry]) {
+    for entry in entries {
+        if let Cow::Owned(name) = normalize_nfc(&entry.name) {
+            entry.name = name;
+        }
+    }
+}
+
+/// What to do when an archive holds several entries with the same name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// Keep the last entry, as sequential extraction would.
+    Overwrite,
+    /// Keep the first entry.
+    Skip,
+    /// Keep all, renaming later ones to `name (1).ext`, `name (2).ext`, ...
+    Rename,
+    /// Refuse to extract anything.
+    #[default]
+    Error,
+}
+
+/// Inserts ` (n)` before the extension of the last path component.
+fn numbered_name(name: &str, n: usize) -> String {
+    let file_start = name.rfind('/').map_or(0, |i| i + 1);
+    match name[file_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = file_start + dot;
+            format!("{} ({}){}", &name[..dot], n, &name[dot..])
+        }
+        _ => format!("{} ({})", name, n),
+    }
+}
+
+/// Applies `policy` to entries sharing a name, preserving the order of those kept.
+pub fn resolve_duplicates(
+    entries: Vec<ArchiveEntry>,
+    policy: OnDuplicate,
+) -> Result<Vec<ArchiveEntry>, LatError> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for entry in &entries {
+        *counts.entry(entry.name.as_str()).or_default() += 1;
+    }
+    if counts.values().all(|&count| count == 1) {
+        return Ok(entries);
+    }
+
+    match policy {
+        OnDuplicate::Error => {
+            let mut duplicates: Vec<&str> = counts
+                .iter()
+                .filter(|(_, count)| **count > 1)
+                .map(|(name, _)| *name)
+                .collect();
+            duplicates.sort_unstable();
+            Err(LatError::CorruptArchive(format!(
+                "archive contains duplicate entries: {}",
+                duplicates.join(", ")
+            )))
+        }
+        OnDuplicate::Skip => {
+            let mut seen = HashSet::new();
+            Ok(entries
+                .into_iter()
+                .filter(|entry| seen.insert(entry.name.clone()))
+                .collect())
+        }
+        OnDuplicate::Overwrite => {
+            let mut remaining: HashMap<String, usize> = counts
+                .into_iter()
+                .map(|(name, count)| (name.to_string(), count))
+                .collect();
+            Ok(entries
+                .into_iter()
+                .filter(|entry| {
+                    let left = remaining.get_mut(&entry.name).expect("counted above");
+                    *left -= 1;
+                    *left == 0
+                })
+                .collect())
+        }
+        OnDuplicate::Rename => {
+            let mut taken: HashSet<String> = entries.iter().map(|e| e.name.clone()).collect();
+            let mut seen = HashSet::new();
+            Ok(entries
+                .into_iter()
+                .map(|mut entry| {
+                    if !seen.insert(entry.name.clone()) {
+                        let renamed = (1..)
+                            .map(|n| numbered_name(&entry.name, n))
+                            .find(|candidate| !taken.contains(candidate))
+                            .expect("unbounded search");
+                        taken.insert(renamed.clone());
+                        entry.name = renamed;
+                    }
+                    entry
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_illegal_characters_are_remapped() {
+        let fixed = sanitize_for(
+            "notes/10:30 what?.txt",
+            SanitizeMode::Replace,
+            Platform::Windows,
+        )
+        .unwrap();
+        assert_eq!(fixed, "notes/10_30 what_.txt");
+
+        assert_eq!(
+            sanitize_for("dev/CON.txt", SanitizeMode::Replace, Platform::Windows).unwrap(),
+            "dev/_CON.txt"
+        );
+        assert_eq!(
+            sanitize_for("trailing./x", SanitizeMode::Replace, Platform::Windows).unwrap(),
+            "trailing_/x"
+        );
+        assert!(matches!(
+            sanitize_for("a:b", SanitizeMode::Strict, Platform::Windows),
+            Err(LatError::InvalidInput(_))
+        ));
+
+        // The same names are fine elsewhere and are passed through untouched.
+        assert!(matches!(
+            sanitize_for("10:30 what?.txt", SanitizeMode::Strict, Platform::Unix),
+            Ok(Cow::Borrowed(_))
+        ));
+        assert!(matches!(
+            sanitize_for("dir/ok.txt", SanitizeMode::Replace, Platform::Windows),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_nul_is_rejected() {
+        for platform in [Platform::Windows, Platform::Unix] {
+            for mode in [SanitizeMode::Replace, SanitizeMode::Strict] {
+                assert!(matches!(
+                    sanitize_for("bad\0name", mode, platform),
+                    Err(LatError::InvalidInput(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_name_limits() {
+        let limits = NameLimits::default();
+        assert!(limits.check(&"a".repeat(4096)).is_ok());
+        assert!(matches!(
+            limits.check(&"a".repeat(4097)),
+            Err(LatError::CorruptArchive(_))
+        ));
+
+        let deep = vec!["d"; 257].join("/");
+        assert!(matches!(
+            limits.check(&deep),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("257 components")
+        ));
+        assert!(limits.check(&deep[2..]).is_ok());
+        // Empty components from doubled or trailing slashes don't count.
+        assert!(limits.with_max_components(2).check("a//b/").is_ok());
+        assert!(limits.with_max_name_len(3).check("abcd").is_err());
+    }
+
+    fn same_name_entries() -> Vec<ArchiveEntry> {
+        ["docs/a.txt", "b", "docs/a.txt"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(vec![i as u8])
+                    .build()
+            })
+            .collect()
+    }
+
+    fn summary(entries: &[ArchiveEntry]) -> Vec<(&str, u8)> {
+        entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.data[0]))
+            .collect()
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let kept = resolve_duplicates(same_name_entries(), OnDuplicate::Overwrite).unwrap();
+        assert_eq!(summary(&kept), [("b", 1), ("docs/a.txt", 2)]);
+
+        let kept = resolve_duplicates(same_name_entries(), OnDuplicate::Skip).unwrap();
+        assert_eq!(summary(&kept), [("docs/a.txt", 0), ("b", 1)]);
+
+        let kept = resolve_duplicates(same_name_entries(), OnDuplicate::Rename).unwrap();
+        assert_eq!(
+            summary(&kept),
+            [("docs/a.txt", 0), ("b", 1), ("docs/a (1).txt", 2)]
+        );
+
+        assert!(matches!(
+            resolve_duplicates(same_name_entries(), OnDuplicate::default()),
+            Err(LatError::CorruptArchive(msg)) if msg.contains("docs/a.txt")
+        ));
+    }
+
+    #[test]
+    fn test_nfc_normalization_merges_spellings() {
+        let nfc = "caf\u{e9}.txt";
+        let nfd = "cafe\u{301}.txt";
+        assert!(matches!(normalize_nfc(nfc), Cow::Borrowed(_)));
+        assert_eq!(normalize_nfc(nfd), nfc);
+
+        let entries = || -> Vec<ArchiveEntry> {
+            [nfc, nfd]
+                .iter()
+                .map(|name| ArchiveEntry::builder(name.to_string()).build())
+                .collect()
+        };
+        // Byte-different names are distinct entries unless normalized.
+        assert!(resolve_duplicates(entries(), OnDuplicate::Error).is_ok());
+
+        let mut normalized = entries();
+        normalize_entry_names(&mut normalized);
+        assert_eq!(normalized[1].name, nfc);
+        assert!(matches!(
+            resolve_duplicates(normalized, OnDuplicate::Error),
+            Err(LatError::CorruptArchive(msg)) if msg.contains(nfc)
+        ));
+    }
+
+    #[test]
+    fn test_rename_avoids_existing_names() {
+        let entries = ["x", "x (1)", "x"]
+            .iter()
+            .map(|name| ArchiveEntry::builder(name.to_string()).build())
+            .collect();
+        let renamed = resolve_duplicates(entries, OnDuplicate::Rename).unwrap();
+        let names: Vec<&str> = renamed.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["x", "x (1)", "x (2)"]);
+        assert_eq!(numbered_name(".hidden", 1), ".hidden (1)");
+    }
+}
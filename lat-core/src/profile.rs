@@ -0,0 +1,55 @@
+//! Per-stage timing of GPU work.
+
+use std::time::Duration;
+
+/// Phase of a GPU operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuStage {
+    /// Host-to-device copies of the inputs.
+    Upload,
+    /// Kernel or compute-shader execution.
+    Dispatch,
+    /// Device-to-host copies of the results.
+    Readback,
+}
+
+/// Time spent in each [`GpuStage`], accumulated over every operation since the profile
+/// was last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuProfile {
+    pub upload: Duration,
+    pub dispatch: Duration,
+    pub readback: Duration,
+}
+
+impl GpuProfile {
+    pub fn record(&mut self, stage: GpuStage, elapsed: Duration) {
+        match stage {
+            GpuStage::Upload => self.upload += elapsed,
+            GpuStage::Dispatch => self.dispatch += elapsed,
+            GpuStage::Readback => self.readback += elapsed,
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.upload + self.dispatch + self.readback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_accumulates_per_stage() {
+        let mut profile = GpuProfile::default();
+        profile.record(GpuStage::Upload, Duration::from_millis(2));
+        profile.record(GpuStage::Dispatch, Duration::from_millis(5));
+        profile.record(GpuStage::Upload, Duration::from_millis(1));
+        profile.record(GpuStage::Readback, Duration::from_millis(4));
+
+        assert_eq!(profile.upload, Duration::from_millis(3));
+        assert_eq!(profile.dispatch, Duration::from_millis(5));
+        assert_eq!(profile.total(), Duration::from_millis(12));
+    }
+}
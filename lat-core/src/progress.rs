@@ -0,0 +1,168 @@
+//! Progress reporting and remaining-time estimation for long-running operations.
+
+use std::time::{Duration, Instant};
+
+/// Snapshot of how far an operation has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressEvent {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub at: Instant,
+}
+
+/// Turns a stream of [`ProgressEvent`]s into a throughput and remaining-time estimate.
+///
+/// Throughput is an exponential moving average of the per-event rate, so a single slow or
+/// fast interval nudges the estimate instead of making it jump.
+#[derive(Debug, Clone)]
+pub struct EtaEstimator {
+    alpha: f64,
+    rate: Option<f64>,
+    last: Option<(u64, Instant)>,
+    remaining_bytes: u64,
+}
+
+impl Default for EtaEstimator {
+    fn default() -> Self {
+        Self::new(0.3)
+    }
+}
+
+impl EtaEstimator {
+    /// `alpha` is the weight of the newest sample, from 0 (never adapt) to 1 (no smoothing).
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            rate: None,
+            last: None,
+            remaining_bytes: 0,
+        }
+    }
+
+    pub fn update(&mut self, event: &ProgressEvent) {
+        self.remaining_bytes = event.bytes_total.saturating_sub(event.bytes_done);
+        if let Some((done, at)) = self.last {
+            let elapsed = event.at.saturating_duration_since(at).as_secs_f64();
+            if elapsed > 0.0 {
+                let sample = event.bytes_done.saturating_sub(done) as f64 / elapsed;
+                self.rate = match self.rate {
+                    Some(rate) => Some(self.alpha * sample + (1.0 - self.alpha) * rate),
+                    // Don't seed the average with a stalled start; it would take many
+                    // samples to climb out of zero once data starts flowing.
+                    None if sample > 0.0 => Some(sample),
+                    None => None,
+                };
+            } else {
+                return;
+            }
+        }
+        self.last = Some((event.bytes_done, event.at));
+    }
+
+    /// Smoothed throughput in bytes per second, once at least one interval has been seen.
+    pub fn throughput(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// Estimated time to completion; `None` while no progress has been measured.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.last.is_some() && self.remaining_bytes == 0 {
+            return Some(Duration::ZERO);
+        }
+        match self.rate {
+            Some(rate) if rate > 0.0 => {
+                Some(Duration::from_secs_f64(self.remaining_bytes as f64 / rate))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Formats an estimate for a status bar, e.g. "~2m30s remaining".
+pub fn format_remaining(remaining: Option<Duration>) -> String {
+    let Some(remaining) = remaining else {
+        return "Estimating time remaining...".to_string();
+    };
+    let secs = remaining.as_secs_f64().round() as u64;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("~{}h{:02}m remaining", h, m)
+    } else if m > 0 {
+        format!("~{}m{:02}s remaining", m, s)
+    } else {
+        format!("~{}s remaining", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(start: Instant, millis: u64, done: u64, total: u64) -> ProgressEvent {
+        ProgressEvent {
+            bytes_done: done,
+            bytes_total: total,
+            at: start + Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_constant_rate_gives_stable_eta() {
+        // 1 MB/s towards 100 MB, reported every 500ms.
+        let start = Instant::now();
+        let total = 100_000_000;
+        let mut eta = EtaEstimator::default();
+        let mut previous: Option<Duration> = None;
+        for step in 0..=40u64 {
+            eta.update(&event(start, step * 500, step * 500_000, total));
+            let Some(remaining) = eta.remaining() else {
+                assert_eq!(step, 0);
+                continue;
+            };
+            let expected = (total - step * 500_000) as f64 / 1_000_000.0;
+            assert!((remaining.as_secs_f64() - expected).abs() < 0.01);
+            if let Some(previous) = previous {
+                assert!(remaining < previous);
+            }
+            previous = Some(remaining);
+        }
+        assert!((eta.throughput().unwrap() - 1_000_000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_zero_rate_start() {
+        let start = Instant::now();
+        let mut eta = EtaEstimator::default();
+        assert_eq!(eta.remaining(), None);
+
+        eta.update(&event(start, 0, 0, 1000));
+        eta.update(&event(start, 1000, 0, 1000));
+        eta.update(&event(start, 1000, 0, 1000));
+        assert_eq!(eta.throughput(), None);
+        assert_eq!(eta.remaining(), None);
+
+        eta.update(&event(start, 2000, 100, 1000));
+        let remaining = eta.remaining().expect("rate known after progress");
+        assert!((remaining.as_secs_f64() - 9.0).abs() < 0.01);
+
+        eta.update(&event(start, 3000, 1000, 1000));
+        assert_eq!(eta.remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(None), "Estimating time remaining...");
+        assert_eq!(
+            format_remaining(Some(Duration::from_secs(150))),
+            "~2m30s remaining"
+        );
+        assert_eq!(
+            format_remaining(Some(Duration::from_millis(44_600))),
+            "~45s remaining"
+        );
+        assert_eq!(
+            format_remaining(Some(Duration::from_secs(3900))),
+            "~1h05m remaining"
+        );
+    }
+}
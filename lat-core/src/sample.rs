@@ -0,0 +1,79 @@
+//! Choosing a subset of entries for checks that trade certainty for speed.
+
+/// Which entries a sampled check looks at: `fraction` of them, rounded up so a non-zero
+/// fraction always covers at least one entry, chosen by a seeded shuffle. The same seed
+/// and entry count always pick the same entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntrySample {
+    fraction: f64,
+    seed: u64,
+}
+
+impl EntrySample {
+    /// Samples `fraction` (0.0-1.0) of the entries, with a random seed.
+    pub fn new(fraction: f64) -> Self {
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+        Self {
+            fraction,
+            seed: rand::random(),
+        }
+    }
+
+    /// Fixes the seed, e.g. to reproduce a run.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    /// Indices out of `count` entries to check, in ascending order.
+    pub fn pick(&self, count: usize) -> Vec<usize> {
+        let wanted = ((count as f64 * self.fraction).ceil() as usize).min(count);
+        let mut indices: Vec<usize> = (0..count).collect();
+        let mut state = self.seed;
+        // Partial Fisher-Yates: the first `wanted` slots end up a uniform sample.
+        for i in 0..wanted {
+            let j = i + (splitmix64(&mut state) % (count - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        indices.truncate(wanted);
+        indices.sort_unstable();
+        indices
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_is_seeded_and_sized() {
+        let sample = EntrySample::new(0.25).with_seed(7);
+        let picked = sample.pick(10);
+        assert_eq!(picked.len(), 3);
+        assert!(picked.windows(2).all(|w| w[0] < w[1]));
+        assert!(picked.iter().all(|&i| i < 10));
+        assert_eq!(picked, sample.pick(10));
+
+        assert_eq!(EntrySample::new(0.01).pick(5).len(), 1);
+        assert_eq!(EntrySample::new(2.0).pick(4), [0, 1, 2, 3]);
+        assert!(EntrySample::new(0.0).pick(4).is_empty());
+        assert!(EntrySample::new(f64::NAN).pick(4).is_empty());
+        assert!(EntrySample::new(0.5).pick(0).is_empty());
+    }
+}
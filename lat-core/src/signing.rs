@@ -0,0 +1,73 @@
+//! Detached Ed25519 signatures over finished archives.
+//!
+//! Independent of password encryption: the signature covers the archive bytes exactly as
+//! written, encrypted or not, and is meant to be shipped next to it as a `.sig` file.
+
+use crate::LatError;
+use ed25519_dalek::{Signer, Verifier};
+use std::path::{Path, PathBuf};
+
+pub use ed25519_dalek::{SIGNATURE_LENGTH, Signature, SigningKey, VerifyingKey};
+
+pub fn sign_archive(data: &[u8], signing_key: &SigningKey) -> Signature {
+    signing_key.sign(data)
+}
+
+pub fn verify_signature(
+    data: &[u8],
+    signature: &Signature,
+    public_key: &VerifyingKey,
+) -> Result<(), LatError> {
+    public_key
+        .verify(data, signature)
+        .map_err(|_| LatError::CorruptArchive("signature verification failed".to_string()))
+}
+
+/// Where the detached signature for `archive` lives: the archive path with `.sig` appended.
+pub fn signature_path(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Parses the raw 64 bytes of a `.sig` file.
+pub fn signature_from_bytes(bytes: &[u8]) -> Result<Signature, LatError> {
+    Signature::from_slice(bytes).map_err(|_| {
+        LatError::InvalidInput(format!(
+            "signature must be {} bytes, got {}",
+            SIGNATURE_LENGTH,
+            bytes.len()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let archive = b"PK\x03\x04 pretend archive bytes".to_vec();
+        let signature = sign_archive(&archive, &key);
+
+        let detached = signature_from_bytes(&signature.to_bytes()).unwrap();
+        assert!(verify_signature(&archive, &detached, &key.verifying_key()).is_ok());
+
+        let mut tampered = archive.clone();
+        tampered[5] ^= 1;
+        assert!(matches!(
+            verify_signature(&tampered, &signature, &key.verifying_key()),
+            Err(LatError::CorruptArchive(_))
+        ));
+
+        let other = SigningKey::from_bytes(&[8u8; 32]).verifying_key();
+        assert!(verify_signature(&archive, &signature, &other).is_err());
+
+        assert!(signature_from_bytes(&[0u8; 10]).is_err());
+        assert_eq!(
+            signature_path(Path::new("out/archive.lat")),
+            Path::new("out/archive.lat.sig")
+        );
+    }
+}
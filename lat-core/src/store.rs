@@ -0,0 +1,234 @@
+//! Random-access storage to read archives from: memory, or objects fetched by byte range
+//! (HTTP `Range` requests, S3 ranged `GetObject`, a custom store).
+//!
+//! Formats with an index (ZIP's central directory, the `.lat` entry table, the seekable
+//! zstd seek table) read only that index and the entries asked for, through
+//! [`Compressor::decompress_matching_from`](crate::Compressor::decompress_matching_from).
+//! [`StoreReader`] adapts a store to `Read + Seek` for the format readers.
+
+use crate::LatError;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Bytes a [`StoreReader`] fetches per request unless configured otherwise.
+pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+/// A byte sequence that can be read at any offset, such as an object in remote storage.
+pub trait BlockStore {
+    /// `len` bytes starting at `offset`; fewer only where the store ends.
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, LatError>;
+
+    /// Total size in bytes.
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl BlockStore for [u8] {
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, LatError> {
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(self.len());
+        let end = start.saturating_add(len).min(self.len());
+        Ok(self[start..end].to_vec())
+    }
+
+    fn len(&self) -> u64 {
+        <[u8]>::len(self) as u64
+    }
+}
+
+impl BlockStore for Vec<u8> {
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, LatError> {
+        self.as_slice().read_at(offset, len)
+    }
+
+    fn len(&self) -> u64 {
+        Vec::len(self) as u64
+    }
+}
+
+impl<S: BlockStore + ?Sized> BlockStore for &S {
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, LatError> {
+        (**self).read_at(offset, len)
+    }
+
+    fn len(&self) -> u64 {
+        (**self).len()
+    }
+}
+
+/// Wraps a store and counts what is fetched through it, e.g. to see how much of a remote
+/// archive an extraction reads.
+pub struct CountingStore<S> {
+    inner: S,
+    fetched: AtomicU64,
+    requests: AtomicUsize,
+}
+
+impl<S: BlockStore> CountingStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            fetched: AtomicU64::new(0),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Bytes returned by `read_at` so far.
+    pub fn fetched(&self) -> u64 {
+        self.fetched.load(Ordering::Relaxed)
+    }
+
+    /// Calls to `read_at` so far.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: BlockStore> BlockStore for CountingStore<S> {
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, LatError> {
+        let data = self.inner.read_at(offset, len)?;
+        self.fetched.fetch_add(data.len() as u64, Ordering::Relaxed);
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        Ok(data)
+    }
+
+    fn len(&self) -> u64 {
+        self.inner.len()
+    }
+}
+
+/// Reads the whole store, for formats that can't read selectively.
+pub fn read_all(store: &dyn BlockStore) -> Result<Vec<u8>, LatError> {
+    let len = usize::try_from(store.len())
+        .map_err(|_| LatError::InvalidInput("archive too large for memory".to_string()))?;
+    store.read_at(0, len)
+}
+
+/// `Read + Seek` over a [`BlockStore`]. Small reads are served from whole aligned blocks,
+/// fetched one request at a time with the most recent one kept, so parsing headers
+/// doesn't cost a request per field; reads of at least a block go to the store directly.
+pub struct StoreReader<S> {
+    store: S,
+    pos: u64,
+    block_size: usize,
+    cached: Option<(u64, Vec<u8>)>,
+}
+
+impl<S: BlockStore> StoreReader<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            pos: 0,
+            block_size: DEFAULT_BLOCK_SIZE,
+            cached: None,
+        }
+    }
+
+    /// Sets how many bytes a small read fetches (at least 1). Larger blocks mean fewer
+    /// requests and more bytes fetched that may go unused.
+    pub fn with_block_size(mut self, bytes: usize) -> Self {
+        self.block_size = bytes.max(1);
+        self.cached = None;
+        self
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    fn block(&mut self, index: u64) -> io::Result<&[u8]> {
+        if self.cached.as_ref().map(|(cached, _)| *cached) != Some(index) {
+            let data = self
+                .store
+                .read_at(index * self.block_size as u64, self.block_size)
+                .map_err(io::Error::other)?;
+            self.cached = Some((index, data));
+        }
+        Ok(&self.cached.as_ref().unwrap().1)
+    }
+}
+
+impl<S: BlockStore> Read for StoreReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.store.len() {
+            return Ok(0);
+        }
+        let n = if buf.len() >= self.block_size {
+            let data = self
+                .store
+                .read_at(self.pos, buf.len())
+                .map_err(io::Error::other)?;
+            buf[..data.len()].copy_from_slice(&data);
+            data.len()
+        } else {
+            let block_size = self.block_size as u64;
+            let from = (self.pos % block_size) as usize;
+            let data = self
+                .block(self.pos / block_size)?
+                .get(from..)
+                .unwrap_or_default();
+            let n = buf.len().min(data.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            n
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: BlockStore> Seek for StoreReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.store.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the store",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_fetches_blocks_on_demand() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let store = CountingStore::new(data.clone());
+        let mut reader = StoreReader::new(&store).with_block_size(1024);
+
+        // Small reads within one block cost one request.
+        let mut field = [0u8; 4];
+        reader.seek(SeekFrom::End(-10)).unwrap();
+        reader.read_exact(&mut field).unwrap();
+        reader.read_exact(&mut field).unwrap();
+        assert_eq!(field, data[99_994..99_998]);
+        assert_eq!(store.requests(), 1);
+
+        // A large read goes straight to the store.
+        let mut big = vec![0u8; 5000];
+        reader.seek(SeekFrom::Start(50_000)).unwrap();
+        reader.read_exact(&mut big).unwrap();
+        assert_eq!(big, data[50_000..55_000]);
+        assert_eq!(store.fetched(), 100_000 % 1024 + 5000);
+
+        let mut rest = Vec::new();
+        reader.seek(SeekFrom::Start(99_990)).unwrap();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, data[99_990..]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Async streaming of archive entries for servers (`async` feature).
+
+use crate::{ArchiveEntry, Compressor, LatError};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Streams the entries produced by the iterator `open` returns.
+///
+/// `open` and the iteration run on tokio's blocking pool, so decompression never stalls
+/// the async runtime. At most `buffer` entries wait in the stream; once it is full the
+/// producer blocks until the consumer catches up, so a slow consumer holds back
+/// decompression instead of the whole archive piling up in memory. Iteration stops after
+/// the first error (which is yielded) or when the stream is dropped.
+///
+/// Must be called from within a tokio runtime.
+pub fn entry_stream<F, I>(open: F, buffer: usize) -> ReceiverStream<Result<ArchiveEntry, LatError>>
+where
+    F: FnOnce() -> Result<I, LatError> + Send + 'static,
+    I: Iterator<Item = Result<ArchiveEntry, LatError>>,
+{
+    let (tx, rx) = mpsc::channel(buffer.max(1));
+    tokio::task::spawn_blocking(move || {
+        let entries = match open() {
+            Ok(entries) => entries,
+            Err(e) => {
+                let _ = tx.blocking_send(Err(e));
+                return;
+            }
+        };
+        for entry in entries {
+            let failed = entry.is_err();
+            // A send error means the consumer went away.
+            if tx.blocking_send(entry).is_err() || failed {
+                break;
+            }
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+/// Streams every entry of `archive` through [`Compressor::decompress`].
+///
+/// Codecs decompress whole archives, so this bounds buffering between producer and
+/// consumer but not the decompression itself. Formats with per-entry readers (such as
+/// `.lat`) can stream lazily by handing their iterator to [`entry_stream`].
+pub fn decompress_stream<C>(
+    compressor: C,
+    archive: Vec<u8>,
+    password: Option<String>,
+    buffer: usize,
+) -> ReceiverStream<Result<ArchiveEntry, LatError>>
+where
+    C: Compressor + Send + 'static,
+{
+    entry_stream(
+        move || {
+            compressor
+                .decompress(&archive, password.as_deref())
+                .map(|entries| entries.into_iter().map(Ok))
+        },
+        buffer,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_slow_consumer_bounds_buffering() {
+        const BUFFER: usize = 4;
+        let produced = Arc::new(AtomicUsize::new(0));
+        let counter = produced.clone();
+        let mut stream = entry_stream(
+            move || {
+                Ok((0..50).map(move |i| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(ArchiveEntry::builder(format!("{:02}", i))
+                        .data(vec![0; 64 * 1024])
+                        .build())
+                }))
+            },
+            BUFFER,
+        );
+
+        let mut consumed = 0;
+        while let Some(entry) = stream.next().await {
+            assert_eq!(
+                entry.expect("Entry failed").name,
+                format!("{:02}", consumed)
+            );
+            consumed += 1;
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            // Queued entries plus the one the blocked producer is holding.
+            assert!(produced.load(Ordering::SeqCst) <= consumed + BUFFER + 1);
+        }
+        assert_eq!(consumed, 50);
+    }
+
+    #[tokio::test]
+    async fn test_open_error_is_yielded() {
+        let mut stream = entry_stream(
+            || -> Result<std::iter::Empty<_>, _> {
+                Err(LatError::CorruptArchive("bad header".to_string()))
+            },
+            1,
+        );
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(LatError::CorruptArchive(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,33 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use crate::{ArchiveEntry, ArchiveFormat, ArchiveInfo, Compressor, LatError, index};
+
+/// The entry index from [`crate::index`] as an uncompressed archive format.
+pub(crate) struct IndexCompressor;
+
+impl Compressor for IndexCompressor {
+    fn compress(&self, entries: &[ArchiveEntry], _: Option<&str>) -> Result<Vec<u8>, LatError> {
+        index::pack(entries)
+    }
+
+    fn decompress(&self, archive: &[u8], _: Option<&str>) -> Result<Vec<ArchiveEntry>, LatError> {
+        index::unpack(archive.to_vec(), "data")
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Test
+    }
+
+    fn archive_info(&self, archive: &[u8]) -> Result<ArchiveInfo, LatError> {
+        if !index::is_packed(archive) {
+            return Err(LatError::CorruptArchive("not an index".to_string()));
+        }
+        Ok(ArchiveInfo {
+            format: "index",
+            creator_version: None,
+            host_os: None,
+            entry_count: self.decompress(archive, None)?.len(),
+            is_encrypted: false,
+        })
+    }
+}
@@ -0,0 +1,50 @@
+//! Helpers shared by the test suites of this crate and the format crates.
+//!
+//! Compiled for this crate's own tests and, in other crates, through the `testing` feature,
+//! which they enable from `[dev-dependencies]` only.
+
+/// `len` bytes of 64-bit xorshift output: incompressible, but the same for the same `seed`.
+pub fn noise(len: usize, mut seed: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        })
+        .collect()
+}
+
+/// Seekable sink that discards bytes and only tracks how long the output would be.
+#[derive(Debug, Default)]
+pub struct CountingWriter {
+    position: u64,
+    /// Length of the output so far: the furthest position written to.
+    pub len: u64,
+}
+
+impl std::io::Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.position += buf.len() as u64;
+        self.len = self.len.max(self.position);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for CountingWriter {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            std::io::SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+        };
+        self.position = target.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek out of range")
+        })?;
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,68 @@
+//! Spacing out GPU dispatches so a long run leaves the GPU to other work in between.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Keeps at least `gap` between the end of one dispatch and the start of the next,
+/// sleeping the calling thread as needed. Without a gap dispatches run at full speed.
+///
+/// Trades throughput for responsiveness on shared machines, where back-to-back batches
+/// would otherwise starve the desktop compositor.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    gap: Option<Duration>,
+    last_end: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    pub fn new(gap: Option<Duration>) -> Self {
+        Self {
+            gap,
+            last_end: Mutex::new(None),
+        }
+    }
+
+    pub fn gap(&self) -> Option<Duration> {
+        self.gap
+    }
+
+    /// Runs one dispatch, first waiting out whatever is left of the gap since the last.
+    pub fn run<T>(&self, dispatch: impl FnOnce() -> T) -> T {
+        let Some(gap) = self.gap else {
+            return dispatch();
+        };
+        // Held across the dispatch so concurrent callers are spaced out too.
+        let mut last_end = self.last_end.lock().unwrap();
+        if let Some(end) = *last_end {
+            std::thread::sleep(gap.saturating_sub(end.elapsed()));
+        }
+        let out = dispatch();
+        *last_end = Some(Instant::now());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_spaces_dispatches() {
+        let gap = Duration::from_millis(20);
+        let throttle = Throttle::new(Some(gap));
+        let start = Instant::now();
+        for _ in 0..4 {
+            throttle.run(|| ());
+        }
+        // Three gaps between four dispatches; only a lower bound, so slow CI can't fail it.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= gap * 3, "{:?}", elapsed);
+
+        let unthrottled = Throttle::new(None);
+        let start = Instant::now();
+        for _ in 0..4 {
+            unthrottled.run(|| ());
+        }
+        assert!(start.elapsed() < gap);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::path::NameLimits;
+use crate::{ArchiveEntry, CompressorConfig, FormatFactory, LatError};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Decompresses `data` as `format` for input that may be arbitrary bytes, such as a fuzz
+/// target's. Every failure is an `Err`: a decoder panic becomes
+/// [`LatError::CorruptArchive`], and entry names are held to the default
+/// [`NameLimits`].
+///
+/// Decoders are meant to reject malformed input without panicking, and fuzzing should
+/// treat a `"decoder panicked"` error as a bug. Catching only works with unwinding
+/// panics; under `panic = "abort"` a panic still aborts the process.
+pub fn try_decompress(
+    data: &[u8],
+    format: &dyn FormatFactory,
+) -> Result<Vec<ArchiveEntry>, LatError> {
+    let compressor = format.build(&CompressorConfig::default())?;
+    let entries = panic::catch_unwind(AssertUnwindSafe(|| compressor.decompress(data, None)))
+        .unwrap_or_else(|payload| {
+            Err(LatError::CorruptArchive(format!(
+                "decoder panicked: {}",
+                panic_message(payload.as_ref())
+            )))
+        })?;
+    NameLimits::default().check_entries(&entries)?;
+    Ok(entries)
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArchiveFormat, Compressor};
+
+    /// Indexes into the archive by its first byte, like a decoder trusting a length.
+    struct Trusting;
+
+    impl Compressor for Trusting {
+        fn compress(&self, _: &[ArchiveEntry], _: Option<&str>) -> Result<Vec<u8>, LatError> {
+            Ok(Vec::new())
+        }
+
+        fn decompress(
+            &self,
+            archive: &[u8],
+            _: Option<&str>,
+        ) -> Result<Vec<ArchiveEntry>, LatError> {
+            let len = archive[0] as usize;
+            Ok(vec![
+                ArchiveEntry::builder("x")
+                    .data(archive[1..1 + len].to_vec())
+                    .build(),
+            ])
+        }
+
+        fn format(&self) -> ArchiveFormat {
+            ArchiveFormat::Test
+        }
+    }
+
+    impl FormatFactory for Trusting {
+        fn build(&self, _: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+            Ok(Box::new(Trusting))
+        }
+    }
+
+    #[test]
+    fn test_panics_become_corrupt_archive() {
+        assert_eq!(
+            try_decompress(&[2, 7, 8], &Trusting).unwrap()[0].data,
+            [7, 8]
+        );
+        for malformed in [&[][..], &[200, 1], &[255]] {
+            assert!(matches!(
+                try_decompress(malformed, &Trusting),
+                Err(LatError::CorruptArchive(msg)) if msg.starts_with("decoder panicked")
+            ));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{entry_kind, is_empty_archive, map_read_err, read_member};
+use lat_core::{ArchiveEntry, LatError};
+use sevenz_rust::{Archive, BlockDecoder, Password, SevenZArchiveEntry};
+use std::collections::VecDeque;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Entries of a 7z archive read from a seekable source, decoded one block at a time.
+///
+/// 7z compresses entries in blocks (folders) that can only be decoded from the start, so
+/// each block is decoded whole when the iterator reaches it and its entries are handed
+/// out before the next block is read. Memory use is therefore bounded by the largest
+/// block rather than the archive: one entry for archives we write (every entry gets its
+/// own block), possibly all of them for a solid archive from another tool. A limit set
+/// with [`SevenZCompressor::with_max_block_size`](crate::SevenZCompressor::with_max_block_size)
+/// turns an oversized block into an error before it is decoded.
+///
+/// Entries come in block order, followed by entries without data (empty files and
+/// directories). After an error the iterator ends.
+pub struct SevenZEntries<R> {
+    source: R,
+    archive: Archive,
+    password: Password,
+    read_chunk_size: Option<usize>,
+    max_block_size: Option<u64>,
+    next_block: usize,
+    pending: VecDeque<ArchiveEntry>,
+    empties_done: bool,
+    failed: bool,
+}
+
+impl<R: Read + Seek> SevenZEntries<R> {
+    pub(crate) fn open(
+        mut source: R,
+        password: Option<&str>,
+        read_chunk_size: Option<usize>,
+        max_block_size: Option<u64>,
+    ) -> Result<Self, LatError> {
+        let len = source.seek(SeekFrom::End(0))?;
+        source.seek(SeekFrom::Start(0))?;
+        let password: Password = password.map(|p| p.into()).unwrap_or_default();
+        let archive = if is_empty_archive(&mut source, len)? {
+            Archive::default()
+        } else {
+            Archive::read(&mut source, len, password.as_slice()).map_err(map_read_err)?
+        };
+        Ok(Self {
+            source,
+            archive,
+            password,
+            read_chunk_size,
+            max_block_size,
+            next_block: 0,
+            pending: VecDeque::new(),
+            empties_done: false,
+            failed: false,
+        })
+    }
+
+    /// Number of entries in the archive, including ones without data.
+    pub fn entry_count(&self) -> usize {
+        self.archive.files.len()
+    }
+
+    /// Header records of all entries, in archive order.
+    pub(crate) fn files(&self) -> &[SevenZArchiveEntry] {
+        &self.archive.files
+    }
+
+    /// Decodes block `index` into `pending`.
+    fn decode_block(&mut self, index: usize) -> Result<(), LatError> {
+        let decoder = BlockDecoder::new(
+            index,
+            &self.archive,
+            self.password.as_slice(),
+            &mut self.source,
+        );
+        let (chunk_size, max_block_size) = (self.read_chunk_size, self.max_block_size);
+        let pending = &mut self.pending;
+        let mut block_size = 0u64;
+        let mut failure = None;
+        let result = decoder.for_each_entries(&mut |file, reader| {
+            // Declared sizes are checked before reading, so an oversized block fails
+            // before its data is allocated.
+            block_size = block_size.saturating_add(file.size());
+            if let Some(limit) = max_block_size.filter(|&limit| block_size > limit) {
+                failure = Some(LatError::CorruptArchive(format!(
+                    "7z block {} holds more than {} bytes",
+                    index, limit
+                )));
+                return Ok(false);
+            }
+            // Bolt ⚡ Optimization: Use read_exact into a pre-resized buffer instead of
+            // std::io::copy to avoid redundant reallocations and EOF checks.
+            // Entries above the configured chunk size are appended incrementally.
+            match read_member(reader, file, chunk_size) {
+                Ok(data) => {
+                    pending.push_back(
+                        ArchiveEntry::builder(file.name().to_string())
+                            .data(data)
+                            .kind(entry_kind(file))
+                            .build(),
+                    );
+                    Ok(true)
+                }
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        });
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        result.map_err(map_read_err)?;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Iterator for SevenZEntries<R> {
+    type Item = Result<ArchiveEntry, LatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(Ok(entry));
+            }
+            if self.failed {
+                return None;
+            }
+            if self.next_block < self.archive.folders.len() {
+                let index = self.next_block;
+                self.next_block += 1;
+                if let Err(e) = self.decode_block(index) {
+                    self.failed = true;
+                    self.pending.clear();
+                    return Some(Err(e));
+                }
+                continue;
+            }
+            if self.empties_done {
+                return None;
+            }
+            self.empties_done = true;
+            self.pending.extend(
+                self.archive
+                    .files
+                    .iter()
+                    .filter(|file| !file.has_stream)
+                    .map(|file| {
+                        ArchiveEntry::builder(file.name().to_string())
+                            .kind(entry_kind(file))
+                            .build()
+                    }),
+            );
+        }
+    }
+}
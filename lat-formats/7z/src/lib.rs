@@ -1,89 +1,566 @@
-use lat_core::{ArchiveEntry, Compressor};
-use sevenz_rust::{SevenZArchiveEntry, SevenZReader, SevenZWriter};
-use std::io::Cursor;
+use lat_core::{
+    collect_listing, ArchiveEntry, ArchiveFormat, ArchiveInfo, CompressionResult, Compressor,
+    CompressorConfig, EntryKind, EntryOrder, EntryStat, FormatFactory, LatError, ListedEntry,
+    TrailingData,
+};
+use sevenz_rust::{
+    AesEncoderOptions, SevenZArchiveEntry, SevenZMethod, SevenZMethodConfiguration, SevenZReader,
+    SevenZWriter,
+};
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::rc::Rc;
 
-pub struct SevenZCompressor;
+mod entries;
 
-impl Compressor for SevenZCompressor {
-    fn compress(
+pub use entries::SevenZEntries;
+
+/// A 7z archive with no entries: the signature header alone, with an empty next header,
+/// as 7-Zip writes it. Written and recognized here rather than left to the library, so
+/// an empty entry list always round-trips.
+const EMPTY_ARCHIVE: [u8; 32] = [
+    b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C, 0, 4,
+    // CRC32 of the 20 zero bytes that follow: next header offset, size and CRC.
+    0x8D, 0x9B, 0xD5, 0x0F, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// Whether the `len`-byte `source` is [`EMPTY_ARCHIVE`]. Leaves `source` at the start.
+fn is_empty_archive<R: Read + Seek>(source: &mut R, len: u64) -> std::io::Result<bool> {
+    if len != EMPTY_ARCHIVE.len() as u64 {
+        return Ok(false);
+    }
+    let mut header = [0u8; 32];
+    source.seek(SeekFrom::Start(0))?;
+    source.read_exact(&mut header)?;
+    source.seek(SeekFrom::Start(0))?;
+    Ok(header == EMPTY_ARCHIVE)
+}
+
+/// How many bytes of the `len`-byte `source` follow the end of the archive its signature
+/// header declares (the header itself, then the packed streams up to the next header).
+/// Leaves `source` at the start.
+fn trailing_len<R: Read + Seek>(source: &mut R, len: u64) -> std::io::Result<u64> {
+    let mut header = [0u8; 32];
+    source.seek(SeekFrom::Start(0))?;
+    let read = source.read_exact(&mut header);
+    source.seek(SeekFrom::Start(0))?;
+    if read.is_err() || header[..6] != EMPTY_ARCHIVE[..6] {
+        // Not an archive at all; the reader reports that itself.
+        return Ok(0);
+    }
+    let field = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+    let end = 32u64.saturating_add(field(12)).saturating_add(field(20));
+    Ok(len.saturating_sub(end))
+}
+
+#[derive(Clone, Default)]
+pub struct SevenZCompressor {
+    method: Option<SevenZMethodConfiguration>,
+    read_chunk_size: Option<usize>,
+    entry_order: EntryOrder,
+    store_threshold: u64,
+    max_block_size: Option<u64>,
+    trailing_data: TrailingData,
+}
+
+/// Builds [`SevenZCompressor`]s for [`lat_core::CompressorBuilder`].
+///
+/// Understands `level` (LZMA2 preset 0-9) and `method` ("lzma2", "lzma", "copy").
+/// 7z archives carry no timestamps here, so output is always deterministic, and entries
+/// are always checked with 7z's own CRC32 whatever `checksum` asks for.
+pub struct SevenZFormat;
+
+impl FormatFactory for SevenZFormat {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        let method = match (config.method.as_deref(), config.level) {
+            (None | Some("lzma2"), Some(level)) => {
+                Some(sevenz_rust::lzma::LZMA2Options::with_preset(level.min(9)).into())
+            }
+            (None, None) => None,
+            (Some("lzma2"), None) => Some(SevenZMethodConfiguration::new(SevenZMethod::LZMA2)),
+            (Some("lzma"), _) => Some(SevenZMethodConfiguration::new(SevenZMethod::LZMA)),
+            (Some("copy"), _) => Some(SevenZMethodConfiguration::new(SevenZMethod::COPY)),
+            (Some(other), _) => {
+                return Err(LatError::InvalidInput(format!(
+                    "unknown 7z method '{}'",
+                    other
+                )));
+            }
+        };
+        let mut compressor =
+            SevenZCompressor::new().with_trailing_data(config.trailing_data.clone());
+        compressor.method = method;
+        Ok(Box::new(compressor))
+    }
+}
+
+impl SevenZCompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the LZMA2 preset (0-9) used for content streams.
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.method = Some(sevenz_rust::lzma::LZMA2Options::with_preset(level.min(9)).into());
+        self
+    }
+
+    /// Reads entries larger than `chunk_size` incrementally during `decompress` instead of
+    /// allocating their full declared size up front.
+    pub fn with_read_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.read_chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Sets the order `decompress` returns entries in (archive order by default).
+    pub fn with_entry_order(mut self, order: EntryOrder) -> Self {
+        self.entry_order = order;
+        self
+    }
+
+    /// Writes content streams with the copy method when the total input is below `bytes`,
+    /// skipping LZMA setup for archives too small to benefit; `0` (the default) disables this.
+    pub fn with_store_threshold(mut self, bytes: u64) -> Self {
+        self.store_threshold = bytes;
+        self
+    }
+
+    /// Refuses to decode a block whose entries declare more than `bytes` in total. Blocks
+    /// are decoded whole (see [`SevenZEntries`]), so this bounds the memory reading one
+    /// solid archive can take.
+    pub fn with_max_block_size(mut self, bytes: u64) -> Self {
+        self.max_block_size = Some(bytes);
+        self
+    }
+
+    /// Sets what reading does with bytes after the end of the archive (ignored by default).
+    pub fn with_trailing_data(mut self, policy: TrailingData) -> Self {
+        self.trailing_data = policy;
+        self
+    }
+
+    /// Applies the trailing-data policy to the `len`-byte `source`.
+    fn check_trailing<R: Read + Seek>(&self, source: &mut R, len: u64) -> Result<(), LatError> {
+        let trailing = trailing_len(source, len)?;
+        self.trailing_data
+            .check(ArchiveFormat::SevenZ, trailing as usize)
+    }
+
+    /// Streams entries from any seekable source (typically a `File`), holding at most one
+    /// block's entries in memory at a time. Entries come in block order, not archive order.
+    pub fn entries_reader<R: Read + Seek>(
+        &self,
+        mut reader: R,
+        password: Option<&str>,
+    ) -> Result<SevenZEntries<R>, LatError> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        self.check_trailing(&mut reader, len)?;
+        SevenZEntries::open(reader, password, self.read_chunk_size, self.max_block_size)
+    }
+
+    /// Like `decompress`, but reads from any seekable source (typically a `File`) instead
+    /// of requiring the whole archive in memory.
+    pub fn decompress_reader<R: Read + Seek>(
+        &self,
+        reader: R,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let stream = self.entries_reader(reader, password)?;
+
+        // Blocks are read in folder order with stream-less entries last, so remember each
+        // name's header position to restore the physical archive order.
+        let mut positions: HashMap<String, VecDeque<usize>> = HashMap::new();
+        for (index, file) in stream.files().iter().enumerate() {
+            positions
+                .entry(file.name().to_string())
+                .or_default()
+                .push_back(index);
+        }
+
+        // Bolt ⚡ Optimization: Pre-allocate the entries vector.
+        let mut entries = Vec::with_capacity(stream.entry_count());
+        for entry in stream {
+            let entry = entry?;
+            let position = positions
+                .get_mut(&entry.name)
+                .and_then(|queue| queue.pop_front())
+                .unwrap_or(usize::MAX);
+            entries.push((position, entry));
+        }
+
+        entries.sort_by_key(|(position, _)| *position);
+        let mut entries: Vec<ArchiveEntry> = entries.into_iter().map(|(_, e)| e).collect();
+        self.entry_order.apply(&mut entries);
+        Ok(entries)
+    }
+
+    /// Writes the archive straight into `writer` (a file, memory map, hashing writer, ...)
+    /// instead of an intermediate buffer, and hands the writer back when done. 7z
+    /// backpatches its start header, hence the `Seek` bound.
+    pub fn compress_into<W: Write + Seek>(
         &self,
         entries: &[ArchiveEntry],
-        _password: Option<&str>,
-    ) -> Result<Vec<u8>, String> {
-        // Bolt ⚡ Optimization: Pre-allocate output buffer.
-        // 7z compression is very effective, so uncompressed size is a safe upper bound.
-        let total_uncompressed_size: usize = entries.iter().map(|e| e.data.len()).sum();
-        let mut buf = Vec::with_capacity(total_uncompressed_size);
+        password: Option<&str>,
+        writer: W,
+    ) -> Result<W, LatError> {
+        self.write_archive(entries, password, writer)
+            .map(|(writer, _)| writer)
+    }
 
-        let mut writer = SevenZWriter::new(Cursor::new(&mut buf)).map_err(|e| e.to_string())?;
+    fn write_archive<W: Write + Seek>(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        mut writer: W,
+    ) -> Result<(W, Vec<EntryStat>), LatError> {
+        lat_core::validate_entries(entries)?;
+        // There is no header to encrypt, so a password changes nothing here.
+        if entries.is_empty() {
+            writer.write_all(&EMPTY_ARCHIVE)?;
+            return Ok((writer, Vec::new()));
+        }
+
+        let position = Rc::new(Cell::new(writer.stream_position()?));
+        let sink = PositionTracker {
+            inner: writer,
+            position: position.clone(),
+        };
+        let mut writer = SevenZWriter::new(sink).map_err(map_write_err)?;
+        let total: u64 = entries.iter().map(|e| e.data.len() as u64).sum();
+        let method = if total < self.store_threshold {
+            SevenZMethodConfiguration::new(SevenZMethod::COPY)
+        } else {
+            self.method
+                .clone()
+                .unwrap_or_else(|| SevenZMethodConfiguration::new(SevenZMethod::LZMA2))
+        };
+        let method_used = match password {
+            Some(_) => format!("AES256+{}", method.method.name()),
+            None => method.method.name().to_string(),
+        };
+        let methods = match password {
+            Some(password) => vec![AesEncoderOptions::new(password.into()).into(), method],
+            None => vec![method],
+        };
+        writer.set_content_methods(methods);
+        let mut per_entry = Vec::with_capacity(entries.len());
         for entry in entries {
             let mut sz_entry = SevenZArchiveEntry::default();
             sz_entry.name = entry.name.clone();
-            sz_entry.has_stream = true;
             sz_entry.size = entry.data.len() as u64;
+            if entry.kind == EntryKind::Directory {
+                sz_entry.is_directory = true;
+                sz_entry.has_windows_attributes = true;
+                sz_entry.windows_attributes = FILE_ATTRIBUTE_DIRECTORY;
+            }
+            // Directories and zero-length files are recorded as entries without a stream.
+            sz_entry.has_stream = entry.kind == EntryKind::File && !entry.data.is_empty();
+            let stream = sz_entry.has_stream.then(|| Cursor::new(&entry.data));
 
+            let start = position.get();
             writer
-                .push_archive_entry(sz_entry, Some(Cursor::new(&entry.data)))
-                .map_err(|e| e.to_string())?;
+                .push_archive_entry(sz_entry, stream)
+                .map_err(|e| map_write_err(e).context(Some(&entry.name), "compression"))?;
+            per_entry.push(EntryStat::new(
+                &entry.name,
+                entry.data.len() as u64,
+                position.get() - start,
+                &method_used,
+            ));
         }
-        writer.finish().map_err(|e| e.to_string())?;
-        Ok(buf)
+        let sink = writer
+            .finish()
+            .map_err(|e| LatError::Backend(format!("7z: {}", e)))?;
+        Ok((sink.inner, per_entry))
+    }
+}
+
+/// Mirrors the sink's position into a shared cell so the bytes each
+/// `push_archive_entry` call writes (one packed stream per entry) can be measured while
+/// `SevenZWriter` owns the sink.
+struct PositionTracker<W> {
+    inner: W,
+    position: Rc<Cell<u64>>,
+}
+
+impl<W: Write> Write for PositionTracker<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.position.set(self.position.get() + written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for PositionTracker<W> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.position.set(position);
+        Ok(position)
+    }
+}
+
+fn map_write_err(e: sevenz_rust::Error) -> LatError {
+    LatError::Backend(format!("7z: {}", e))
+}
+
+fn map_read_err(e: sevenz_rust::Error) -> LatError {
+    match e {
+        // 7z's AES has no password check; sevenz-rust reports a failed decode of an
+        // encrypted block this way.
+        sevenz_rust::Error::MaybeBadPassword(_) => LatError::WrongPassword,
+        e => LatError::CorruptArchive(e.to_string()),
+    }
+}
+
+/// Windows attribute 7-Zip sets on directory entries, alongside the directory flag.
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+
+fn entry_kind(file: &SevenZArchiveEntry) -> EntryKind {
+    if file.is_directory() {
+        EntryKind::Directory
+    } else {
+        EntryKind::File
+    }
+}
+
+/// Reads one member's data. Entries without a stream (or with a declared size of zero) are
+/// returned empty without touching `reader`; a stream that ends before the declared size is
+/// reported as corruption of that entry rather than a bare I/O error.
+fn read_member<R: Read + ?Sized>(
+    reader: &mut R,
+    file: &SevenZArchiveEntry,
+    chunk_size: Option<usize>,
+) -> Result<Vec<u8>, LatError> {
+    if !file.has_stream || file.size() == 0 {
+        return Ok(Vec::new());
+    }
+    let size = usize::try_from(file.size()).map_err(|_| {
+        LatError::CorruptArchive(format!("{}: declared size too large", file.name()))
+    })?;
+    lat_core::read_entry_data(reader, size, chunk_size).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => LatError::CorruptArchive(format!(
+            "{}: data ends before its declared {} bytes",
+            file.name(),
+            size
+        )),
+        _ => LatError::CorruptArchive(format!("{}: {}", file.name(), e)),
+    })
+}
+
+impl Compressor for SevenZCompressor {
+    fn supports_password(&self) -> bool {
+        true
+    }
+
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        self.compress_detailed(entries, password)
+            .map(|result| result.data)
+    }
+
+    fn compress_detailed(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<CompressionResult, LatError> {
+        // Bolt ⚡ Optimization: Pre-allocate output buffer.
+        // 7z compression is very effective, so uncompressed size is a safe upper bound.
+        let total_uncompressed_size: usize = entries.iter().map(|e| e.data.len()).sum();
+        let buf = Vec::with_capacity(total_uncompressed_size);
+
+        let (sink, per_entry) = self.write_archive(entries, password, Cursor::new(buf))?;
+        Ok(CompressionResult {
+            data: sink.into_inner(),
+            uncompressed_size: total_uncompressed_size as u64,
+            per_entry,
+            warnings: Vec::new(),
+            context_memory: 0,
+        })
     }
 
     fn decompress(
         &self,
         archive_data: &[u8],
-        _password: Option<&str>,
-    ) -> Result<Vec<ArchiveEntry>, String> {
-        let password = _password.map(|p| p.into()).unwrap_or_default();
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.decompress_reader(Cursor::new(archive_data), password)
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::SevenZ
+    }
+
+    /// Decodes one block at a time (see [`SevenZEntries`]), in block order.
+    fn decompress_iter<'a>(
+        &'a self,
+        archive_data: &'a [u8],
+        password: Option<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<ArchiveEntry, LatError>> + 'a>, LatError> {
+        Ok(Box::new(
+            self.entries_reader(Cursor::new(archive_data), password)?,
+        ))
+    }
+
+    fn decompress_matching(
+        &self,
+        archive_data: &[u8],
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let matcher = lat_core::glob_matcher(pattern)?;
+        if archive_data == EMPTY_ARCHIVE {
+            return Ok(Vec::new());
+        }
+        self.check_trailing(&mut Cursor::new(archive_data), archive_data.len() as u64)?;
+        let password = password.map(|p| p.into()).unwrap_or_default();
         let mut reader = SevenZReader::new(
             Cursor::new(archive_data),
             archive_data.len() as u64,
             password,
         )
-        .map_err(|e| e.to_string())?;
+        .map_err(map_read_err)?;
 
-        // Bolt ⚡ Optimization: Pre-allocate the entries vector.
-        let mut entries = Vec::with_capacity(reader.archive().files.len());
-
-        reader
-            .for_each_entries(|file, reader| {
-                // Bolt ⚡ Optimization: Use read_exact into a pre-resized buffer instead of
-                // std::io::copy to avoid redundant reallocations and EOF checks.
-                let size = file.size() as usize;
-                let mut buf = vec![0u8; size];
-                std::io::Read::read_exact(reader, &mut buf)
-                    .map_err(|e| std::io::Error::other(e.to_string()))?;
-
-                entries.push(ArchiveEntry {
-                    name: file.name().to_string(),
-                    data: buf,
-                });
-                Ok(true)
-            })
-            .map_err(|e| e.to_string())?;
+        let mut entries = Vec::new();
+        let mut failure = None;
+        let result = reader.for_each_entries(|file, reader| {
+            if !matcher.is_match(file.name()) {
+                // Solid blocks still have to be decoded past this entry, but the
+                // payload is streamed to a sink instead of being buffered.
+                std::io::copy(reader, &mut std::io::sink())?;
+                return Ok(true);
+            }
+            match read_member(reader, file, None) {
+                Ok(buf) => entries.push(
+                    ArchiveEntry::builder(file.name().to_string())
+                        .data(buf)
+                        .kind(entry_kind(file))
+                        .build(),
+                ),
+                Err(e) => {
+                    failure = Some(e);
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        });
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        result.map_err(map_read_err)?;
 
+        self.entry_order.apply(&mut entries);
         Ok(entries)
     }
+
+    /// Reads only the header database; no block is decoded.
+    fn list_with_progress(
+        &self,
+        archive_data: &[u8],
+        password: Option<&str>,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<Vec<ListedEntry>, LatError> {
+        if archive_data == EMPTY_ARCHIVE {
+            on_progress(0);
+            return Ok(Vec::new());
+        }
+        self.check_trailing(&mut Cursor::new(archive_data), archive_data.len() as u64)?;
+        let password = password.map(|p| p.into()).unwrap_or_default();
+        let reader = SevenZReader::new(
+            Cursor::new(archive_data),
+            archive_data.len() as u64,
+            password,
+        )
+        .map_err(map_read_err)?;
+        let entries = reader.archive().files.iter().map(|file| {
+            Ok(ListedEntry {
+                name: file.name().to_string(),
+                kind: entry_kind(file),
+                size: file.size(),
+            })
+        });
+        collect_listing(entries, on_progress)
+    }
+
+    fn archive_info(&self, archive_data: &[u8]) -> Result<ArchiveInfo, LatError> {
+        // Signature header: 6-byte magic followed by the major/minor format version.
+        if archive_data.len() < 8 || archive_data[..6] != [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C] {
+            return Err(LatError::CorruptArchive("missing 7z signature".to_string()));
+        }
+        let creator_version = Some(format!("{}.{}", archive_data[6], archive_data[7]));
+        if archive_data == EMPTY_ARCHIVE {
+            return Ok(ArchiveInfo {
+                format: "7z",
+                creator_version,
+                host_os: None,
+                entry_count: 0,
+                is_encrypted: false,
+            });
+        }
+
+        let reader = match SevenZReader::new(
+            Cursor::new(archive_data),
+            archive_data.len() as u64,
+            Default::default(),
+        ) {
+            Ok(reader) => reader,
+            // Encrypted headers can't be listed without the password.
+            Err(sevenz_rust::Error::PasswordRequired) => {
+                return Ok(ArchiveInfo {
+                    format: "7z",
+                    creator_version,
+                    host_os: None,
+                    entry_count: 0,
+                    is_encrypted: true,
+                });
+            }
+            Err(e) => return Err(map_read_err(e)),
+        };
+
+        let archive = reader.archive();
+        let is_encrypted = archive.folders.iter().any(|folder| {
+            folder
+                .coders
+                .iter()
+                .any(|coder| coder.decompression_method_id() == SevenZMethod::AES256SHA256.id())
+        });
+
+        Ok(ArchiveInfo {
+            format: "7z",
+            creator_version,
+            // 7z does not record the host system.
+            host_os: None,
+            entry_count: archive.files.len(),
+            is_encrypted,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lat_core::testing::CountingWriter;
     use lat_core::ArchiveEntry;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn test_7z_compress_decompress() {
-        let compressor = SevenZCompressor;
+        let compressor = SevenZCompressor::new();
         let entries = vec![
-            ArchiveEntry {
-                name: "test1.txt".to_string(),
-                data: b"Hello 7z world".to_vec(),
-            },
-            ArchiveEntry {
-                name: "folder/test2.txt".to_string(),
-                data: b"More 7z data".to_vec(),
-            },
+            ArchiveEntry::builder("test1.txt")
+                .data(b"Hello 7z world".to_vec())
+                .build(),
+            ArchiveEntry::builder("folder/test2.txt")
+                .data(b"More 7z data".to_vec())
+                .build(),
         ];
 
         let compressed = compressor
@@ -99,4 +576,533 @@ mod tests {
         assert_eq!(entries[1].name, decompressed[1].name);
         assert_eq!(entries[1].data, decompressed[1].data);
     }
+
+    #[test]
+    fn test_7z_archive_info() {
+        let compressor = SevenZCompressor::new();
+        let entries = vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"alpha".to_vec())
+                .build(),
+            ArchiveEntry::builder("b.txt")
+                .data(b"beta".to_vec())
+                .build(),
+        ];
+
+        let compressed = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let info = compressor.archive_info(&compressed).expect("Info failed");
+
+        assert_eq!(info.format, "7z");
+        assert_eq!(info.creator_version.as_deref(), Some("0.4"));
+        assert_eq!(info.host_os, None);
+        assert_eq!(info.entry_count, 2);
+        assert!(!info.is_encrypted);
+    }
+
+    /// In-memory sink that fails once `capacity` bytes have been written, like a full disk.
+    struct FullDisk {
+        inner: Cursor<Vec<u8>>,
+        capacity: u64,
+    }
+
+    impl std::io::Write for FullDisk {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.inner.position() + buf.len() as u64 > self.capacity {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl std::io::Seek for FullDisk {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_7z_errors_name_the_failing_entry() {
+        let entries: Vec<ArchiveEntry> = ["a.bin", "b.bin", "foo/bar.bin", "d.bin"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(vec![7; 4096])
+                    .build()
+            })
+            .collect();
+        let compressor = SevenZCompressor::new().with_store_threshold(u64::MAX);
+        // Each member is its own encrypted stream; room for the first two only.
+        let disk = FullDisk {
+            inner: Cursor::new(Vec::new()),
+            capacity: 32 + 2 * 4096 + 512,
+        };
+
+        let err = compressor
+            .compress_into(&entries, Some("pw"), disk)
+            .err()
+            .expect("the third member should not fit");
+        assert!(matches!(
+            &err,
+            LatError::Context { entry: Some(entry), stage: "compression", .. }
+                if entry == "foo/bar.bin"
+        ));
+    }
+
+    #[test]
+    fn test_7z_entry_order() {
+        let names = ["b.txt", "empty", "a.txt", "c/d.txt"];
+        let entries: Vec<ArchiveEntry> = names
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(if *name == "empty" {
+                        Vec::new()
+                    } else {
+                        name.as_bytes().to_vec()
+                    })
+                    .build()
+            })
+            .collect();
+        let compressed = SevenZCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+
+        let physical = SevenZCompressor::new()
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+        let physical_names: Vec<&str> = physical.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(physical_names, names);
+
+        let sorted = SevenZCompressor::new()
+            .with_entry_order(EntryOrder::Name)
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+        let sorted_names: Vec<&str> = sorted.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(sorted_names, ["a.txt", "b.txt", "c/d.txt", "empty"]);
+
+        let matched = SevenZCompressor::new()
+            .with_entry_order(EntryOrder::Name)
+            .decompress_matching(&compressed, "**/*.txt", None)
+            .expect("Selective extraction failed");
+        let matched_names: Vec<&str> = matched.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(matched_names, ["a.txt", "b.txt", "c/d.txt"]);
+    }
+
+    #[test]
+    fn test_7z_decompress_matching() {
+        let entries: Vec<ArchiveEntry> = ["a.log", "dir/b.log", "c.txt", "dir/d.bin"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(name.repeat(50).into_bytes())
+                    .build()
+            })
+            .collect();
+        let compressed = SevenZCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+
+        let logs = SevenZCompressor::new()
+            .decompress_matching(&compressed, "**/*.log", None)
+            .expect("Selective extraction failed");
+        let names: Vec<&str> = logs.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.log", "dir/b.log"]);
+        assert_eq!(logs[0].data, entries[0].data);
+    }
+
+    #[test]
+    fn test_7z_empty_file_and_empty_name() {
+        let compressor = SevenZCompressor::new();
+        let entries = vec![
+            ArchiveEntry::builder("empty.txt").build(),
+            ArchiveEntry::builder("full.txt")
+                .data(b"full".to_vec())
+                .build(),
+        ];
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let decompressed = compressor.decompress(&compressed, None).unwrap();
+        assert_eq!(decompressed[0].name, "empty.txt");
+        assert!(decompressed[0].data.is_empty());
+        assert_eq!(decompressed[1].data, b"full");
+
+        let entries = vec![ArchiveEntry::builder(" ").data(b"data".to_vec()).build()];
+        assert!(matches!(
+            compressor.compress(&entries, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_7z_directory_round_trips() {
+        let compressor = SevenZCompressor::new();
+        let entries = vec![
+            ArchiveEntry::builder("docs")
+                .kind(EntryKind::Directory)
+                .build(),
+            ArchiveEntry::builder("docs/readme.txt")
+                .data(b"read me".to_vec())
+                .build(),
+        ];
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let decompressed = compressor.decompress(&compressed, None).unwrap();
+        assert_eq!(decompressed.len(), 2);
+        assert_eq!(decompressed[0].name, "docs");
+        assert_eq!(decompressed[0].kind, EntryKind::Directory);
+        assert!(decompressed[0].data.is_empty());
+        assert_eq!(decompressed[1].kind, EntryKind::File);
+        assert_eq!(decompressed[1].data, b"read me");
+        let listed = compressor.list(&compressed, None).unwrap();
+        assert_eq!(listed[0].kind, EntryKind::Directory);
+        assert_eq!(
+            (listed[1].name.as_str(), listed[1].size),
+            ("docs/readme.txt", 7)
+        );
+
+        // Streaming yields entries without data after the blocks.
+        let streamed: Vec<EntryKind> = compressor
+            .decompress_iter(&compressed, None)
+            .unwrap()
+            .map(|entry| entry.unwrap().kind)
+            .collect();
+        assert_eq!(streamed, [EntryKind::File, EntryKind::Directory]);
+
+        let with_data = vec![ArchiveEntry::builder("docs")
+            .data(b"not allowed".to_vec())
+            .kind(EntryKind::Directory)
+            .build()];
+        assert!(matches!(
+            compressor.compress(&with_data, None),
+            Err(LatError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_7z_empty_archive_round_trips() {
+        let compressor = SevenZCompressor::new();
+        let archive = compressor.compress(&[], None).unwrap();
+        assert_eq!(archive, EMPTY_ARCHIVE);
+        assert!(compressor.decompress(&archive, None).unwrap().is_empty());
+        assert!(compressor
+            .decompress_matching(&archive, "*", None)
+            .unwrap()
+            .is_empty());
+        assert_eq!(compressor.archive_info(&archive).unwrap().entry_count, 0);
+    }
+
+    #[test]
+    fn test_7z_truncated_archive() {
+        let entries = vec![ArchiveEntry::builder("payload.bin")
+            .data((0..50_000u32).map(|i| (i * 7 % 251) as u8).collect())
+            .build()];
+        let compressed = SevenZCompressor::new().compress(&entries, None).unwrap();
+
+        let truncated = &compressed[..compressed.len() / 2];
+        assert!(matches!(
+            SevenZCompressor::new().decompress(truncated, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_7z_short_member_names_entry() {
+        let mut file = SevenZArchiveEntry::default();
+        file.name = "short.bin".to_string();
+        file.has_stream = true;
+        file.size = 100;
+
+        let mut short = Cursor::new(vec![0u8; 40]);
+        match read_member(&mut short, &file, None) {
+            Err(LatError::CorruptArchive(msg)) => assert!(msg.contains("short.bin")),
+            other => panic!("expected CorruptArchive, got {:?}", other.map(|d| d.len())),
+        }
+    }
+
+    #[test]
+    fn test_7z_zero_size_member_is_not_read() {
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("must not be read"))
+            }
+        }
+
+        let mut file = SevenZArchiveEntry::default();
+        file.name = "empty".to_string();
+        file.has_stream = false;
+        assert!(read_member(&mut FailingReader, &file, None)
+            .unwrap()
+            .is_empty());
+
+        file.has_stream = true;
+        file.size = 0;
+        assert!(read_member(&mut FailingReader, &file, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_7z_password_round_trip() {
+        let compressor = SevenZCompressor::new();
+        let entries = vec![ArchiveEntry::builder("secret.txt")
+            .data(b"7z protects this".to_vec())
+            .build()];
+
+        assert!(compressor.supports_password());
+        let compressed = compressor
+            .compress(&entries, Some("hunter2"))
+            .expect("Compression failed");
+        let decompressed = compressor
+            .decompress(&compressed, Some("hunter2"))
+            .expect("Decompression failed");
+        assert_eq!(entries[0].data, decompressed[0].data);
+
+        assert!(compressor.decompress(&compressed, Some("wrong")).is_err());
+    }
+
+    #[test]
+    fn test_7z_decompress_chunked() {
+        let payload: Vec<u8> = (0..1_000_000u32).map(|i| (i * 31 % 256) as u8).collect();
+        let entries = vec![ArchiveEntry::builder("large.bin")
+            .data(payload.clone())
+            .build()];
+
+        let compressed = SevenZCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let decompressed = SevenZCompressor::new()
+            .with_read_chunk_size(1024)
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+
+        assert_eq!(payload, decompressed[0].data);
+    }
+
+    #[test]
+    fn test_7z_compress_into_custom_sink() {
+        let entries = vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"streamed straight to the sink ".repeat(300))
+                .build(),
+            ArchiveEntry::builder("dir/b.txt")
+                .data(b"second member".to_vec())
+                .build(),
+        ];
+        let compressor = SevenZCompressor::new();
+
+        let sink = compressor
+            .compress_into(&entries, None, CountingWriter::default())
+            .expect("Compression failed");
+        let buffered = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        assert_eq!(sink.len, buffered.len() as u64);
+    }
+
+    #[test]
+    fn test_7z_builder_options() {
+        let text = b"seven zip builder test line ".repeat(4000);
+        let entries = vec![ArchiveEntry::builder("lines.txt")
+            .data(text.clone())
+            .build()];
+
+        let copy = lat_core::CompressorBuilder::new(&SevenZFormat)
+            .method("copy")
+            .build()
+            .expect("Build failed");
+        let best = lat_core::CompressorBuilder::new(&SevenZFormat)
+            .level(9)
+            .password("pw")
+            .build()
+            .expect("Build failed");
+
+        let stored = copy.compress(&entries, None).unwrap();
+        assert!(stored.len() > text.len());
+        // The builder's password is applied without passing it per call.
+        let packed = best.compress(&entries, None).unwrap();
+        assert!(packed.len() < stored.len());
+        assert_eq!(best.decompress(&packed, None).unwrap()[0].data, text);
+        assert!(SevenZCompressor::new().decompress(&packed, None).is_err());
+
+        assert!(lat_core::CompressorBuilder::new(&SevenZFormat)
+            .method("ppmd")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_7z_store_threshold() {
+        let entries = vec![ArchiveEntry::builder("tiny.txt")
+            .data(b"aaaaaaaaaa".to_vec())
+            .build()];
+        let compressor = SevenZCompressor::new().with_store_threshold(64);
+
+        let result = compressor.compress_detailed(&entries, None).unwrap();
+        assert_eq!(result.per_entry[0].method_used, SevenZMethod::COPY.name());
+        assert_eq!(result.per_entry[0].compressed, 10);
+        let decompressed = compressor.decompress(&result.data, None).unwrap();
+        assert_eq!(decompressed[0].data, entries[0].data);
+
+        let result = SevenZCompressor::new()
+            .with_store_threshold(10)
+            .compress_detailed(&entries, None)
+            .unwrap();
+        assert_eq!(result.per_entry[0].method_used, SevenZMethod::LZMA2.name());
+    }
+
+    #[test]
+    fn test_7z_decompress_from_file() {
+        let entries: Vec<ArchiveEntry> = ["a.txt", "dir/b.bin"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(name.repeat(200).into_bytes())
+                    .build()
+            })
+            .collect();
+        let compressor = SevenZCompressor::new();
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let path = std::env::temp_dir().join(format!("lat-7z-reader-{}.7z", std::process::id()));
+        std::fs::write(&path, &compressed).unwrap();
+
+        let from_file = compressor
+            .decompress_reader(std::fs::File::open(&path).unwrap(), None)
+            .expect("Decompression failed");
+        let from_slice = compressor.decompress(&compressed, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_file.len(), from_slice.len());
+        for (a, b) in from_file.iter().zip(&from_slice) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.data, b.data);
+        }
+    }
+
+    #[test]
+    fn test_7z_streams_entries_from_file() {
+        let entries: Vec<ArchiveEntry> = ["first", "second", "empty", "third"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(if *name == "empty" {
+                        Vec::new()
+                    } else {
+                        name.repeat(1000).into_bytes()
+                    })
+                    .build()
+            })
+            .collect();
+        let compressor = SevenZCompressor::new();
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let path = std::env::temp_dir().join(format!("lat-7z-stream-{}.7z", std::process::id()));
+        std::fs::write(&path, &compressed).unwrap();
+
+        let stream = compressor
+            .entries_reader(std::fs::File::open(&path).unwrap(), None)
+            .unwrap();
+        assert_eq!(stream.entry_count(), 4);
+        let streamed: Vec<ArchiveEntry> = stream.map(Result::unwrap).collect();
+        // One block per entry with data, then the empty entry.
+        let names: Vec<&str> = streamed.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["first", "second", "third", "empty"]);
+        let in_memory = compressor.decompress(&compressed, None).unwrap();
+        for entry in &streamed {
+            let same = in_memory.iter().find(|e| e.name == entry.name).unwrap();
+            assert_eq!(entry.data, same.data);
+        }
+
+        // Each block holds one 5-6 KB entry, so a 4 KB bound stops at the first.
+        let mut bounded = SevenZCompressor::new()
+            .with_max_block_size(4096)
+            .entries_reader(std::fs::File::open(&path).unwrap(), None)
+            .unwrap();
+        assert!(matches!(
+            bounded.next(),
+            Some(Err(LatError::CorruptArchive(_)))
+        ));
+        assert!(bounded.next().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_7z_malformed_input_returns_err() {
+        // Signature, version, then a start header pointing far past the end.
+        let mut bad_header = b"7z\xBC\xAF\x27\x1C\x00\x04".to_vec();
+        bad_header.extend_from_slice(&[0; 4]);
+        bad_header.extend_from_slice(&[0xff; 16]);
+        for malformed in [&b""[..], b"7z", &[0xA5; 64], &bad_header] {
+            assert!(lat_core::try_decompress(malformed, &SevenZFormat).is_err());
+        }
+
+        let entries = vec![ArchiveEntry::builder("a.txt")
+            .data(b"fuzz me ".repeat(64))
+            .build()];
+        let archive = SevenZCompressor::new().compress(&entries, None).unwrap();
+        for len in 0..archive.len() {
+            assert!(
+                lat_core::try_decompress(&archive[..len], &SevenZFormat).is_err(),
+                "prefix of {} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_7z_trailing_data_policy() {
+        let entries = vec![ArchiveEntry::builder("a.txt")
+            .data(b"hello".repeat(50))
+            .build()];
+        let clean = SevenZCompressor::new().compress(&entries, None).unwrap();
+        assert_eq!(
+            trailing_len(&mut Cursor::new(&clean), clean.len() as u64).unwrap(),
+            0
+        );
+        let mut archive = clean;
+        archive.extend_from_slice(b"garbage after the end");
+
+        let reported = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&reported);
+        let warn = TrailingData::warn(move |_, bytes| {
+            counter.fetch_add(bytes, Ordering::SeqCst);
+        });
+        for policy in [TrailingData::Ignore, warn] {
+            let compressor = SevenZCompressor::new().with_trailing_data(policy);
+            assert_eq!(
+                compressor.decompress(&archive, None).unwrap()[0].data,
+                entries[0].data
+            );
+            assert_eq!(
+                compressor
+                    .decompress_matching(&archive, "*.txt", None)
+                    .unwrap()
+                    .len(),
+                1
+            );
+        }
+        // Warn reports the extra bytes on each call, Ignore doesn't.
+        assert_eq!(
+            reported.load(Ordering::SeqCst),
+            2 * b"garbage after the end".len()
+        );
+        let strict = SevenZCompressor::new().with_trailing_data(TrailingData::Error);
+        assert!(matches!(
+            strict.decompress(&archive, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+        assert!(matches!(
+            strict.decompress_matching(&archive, "*", None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_7z_reports_format() {
+        let compressor: Box<dyn Compressor> = Box::new(SevenZCompressor::new());
+        assert_eq!(compressor.format(), ArchiveFormat::SevenZ);
+        assert_eq!(compressor.format().extension(), "7z");
+    }
 }
@@ -0,0 +1,77 @@
+//! Every format crate behind one dependency, each behind its own cargo feature.
+//!
+//! [`formats`] and [`factory`] only know the formats that were compiled in, so front ends
+//! built with fewer features simply offer fewer formats.
+
+use lat_core::{ArchiveFormat, FormatFactory};
+
+#[cfg(feature = "sevenz")]
+pub use lat_7z as sevenz;
+#[cfg(feature = "bzip2")]
+pub use lat_bzip2 as bzip2;
+#[cfg(feature = "lat")]
+pub use lat_format as lat;
+#[cfg(feature = "gzip")]
+pub use lat_gzip as gzip;
+#[cfg(feature = "paqg")]
+pub use lat_paqg as paqg;
+#[cfg(feature = "zip")]
+pub use lat_zip as zip;
+#[cfg(feature = "zstd")]
+pub use lat_zstd as zstd;
+
+static FORMATS: &[(ArchiveFormat, &dyn FormatFactory)] = &[
+    #[cfg(feature = "zip")]
+    (ArchiveFormat::Zip, &lat_zip::ZipFormat),
+    #[cfg(feature = "sevenz")]
+    (ArchiveFormat::SevenZ, &lat_7z::SevenZFormat),
+    #[cfg(feature = "lat")]
+    (ArchiveFormat::Lat, &lat_format::LatFormat),
+    #[cfg(feature = "paqg")]
+    (ArchiveFormat::Paqg, &lat_paqg::PaqgFormat),
+    #[cfg(feature = "bzip2")]
+    (ArchiveFormat::Bzip2, &lat_bzip2::Bzip2Format),
+    #[cfg(feature = "gzip")]
+    (ArchiveFormat::Gzip, &lat_gzip::GzipFormat),
+    #[cfg(feature = "zstd")]
+    (ArchiveFormat::Zstd, &lat_zstd::ZstdFormat),
+];
+
+/// The compiled-in formats and their factories.
+pub fn formats() -> &'static [(ArchiveFormat, &'static dyn FormatFactory)] {
+    FORMATS
+}
+
+/// Factory for `format`, or `None` if its feature is disabled or no crate implements it.
+pub fn factory(format: ArchiveFormat) -> Option<&'static dyn FormatFactory> {
+    FORMATS
+        .iter()
+        .find(|(compiled, _)| *compiled == format)
+        .map(|(_, factory)| *factory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run with e.g. `--no-default-features --features zip` to check a minimal build.
+    #[test]
+    fn test_registry_lists_only_enabled_formats() {
+        let enabled = [
+            (ArchiveFormat::Zip, cfg!(feature = "zip")),
+            (ArchiveFormat::SevenZ, cfg!(feature = "sevenz")),
+            (ArchiveFormat::Lat, cfg!(feature = "lat")),
+            (ArchiveFormat::Paqg, cfg!(feature = "paqg")),
+            (ArchiveFormat::Bzip2, cfg!(feature = "bzip2")),
+            (ArchiveFormat::Gzip, cfg!(feature = "gzip")),
+            (ArchiveFormat::Tar, false),
+            (ArchiveFormat::Zstd, cfg!(feature = "zstd")),
+            (ArchiveFormat::Xz, false),
+        ];
+        for (format, on) in enabled {
+            assert_eq!(factory(format).is_some(), on, "{:?}", format);
+        }
+        let count = enabled.iter().filter(|(_, on)| *on).count();
+        assert_eq!(formats().len(), count);
+    }
+}
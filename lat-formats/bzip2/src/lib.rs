@@ -0,0 +1,233 @@
+use bzip2::bufread::BzDecoder;
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use lat_core::{
+    ArchiveEntry, ArchiveFormat, ArchiveInfo, Compressor, CompressorConfig, FormatFactory,
+    LatError, TrailingData,
+};
+use std::io::{Read, Write};
+
+/// Entry name given to a plain `.bz2` stream written by another tool.
+const RAW_STREAM_NAME: &str = "data";
+
+/// bzip2 as a standalone format. bzip2 compresses a single stream, so entries are packed
+/// with [`lat_core::index`] first; a plain `.bz2` from the `bzip2` tool decompresses to
+/// one entry named `data`.
+#[derive(Clone)]
+pub struct Bzip2Compressor {
+    level: u32,
+    trailing_data: TrailingData,
+}
+
+impl Default for Bzip2Compressor {
+    fn default() -> Self {
+        Self {
+            // Same default as the `bzip2` tool (900k blocks).
+            level: 9,
+            trailing_data: TrailingData::default(),
+        }
+    }
+}
+
+/// Builds [`Bzip2Compressor`]s for [`lat_core::CompressorBuilder`]. Understands `level`
+/// (1-9, the block size in 100k units); `threads` and `method` have no effect.
+pub struct Bzip2Format;
+
+impl FormatFactory for Bzip2Format {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        let mut compressor =
+            Bzip2Compressor::new().with_trailing_data(config.trailing_data.clone());
+        if let Some(level) = config.level {
+            compressor = compressor.with_level(level);
+        }
+        Ok(Box::new(compressor))
+    }
+}
+
+impl Bzip2Compressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the block size in 100k units, clamped to 1-9.
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level.clamp(1, 9);
+        self
+    }
+
+    /// Sets what `decompress` does with bytes after the end of the stream (ignored by
+    /// default).
+    pub fn with_trailing_data(mut self, policy: TrailingData) -> Self {
+        self.trailing_data = policy;
+        self
+    }
+}
+
+fn corrupt(e: std::io::Error) -> LatError {
+    LatError::CorruptArchive(format!("bzip2: {}", e))
+}
+
+impl Compressor for Bzip2Compressor {
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        self.check_password(password)?;
+        lat_core::validate_entries(entries)?;
+        let packed = lat_core::index::pack(entries)?;
+
+        let mut encoder = BzEncoder::new(
+            Vec::with_capacity(packed.len() / 2),
+            Compression::new(self.level),
+        );
+        encoder.write_all(&packed)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn decompress(
+        &self,
+        archive_data: &[u8],
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.check_password(password)?;
+        let mut stream = Vec::new();
+        // The bufread decoder stops at the end of the stream and leaves the rest unread.
+        let mut decoder = BzDecoder::new(archive_data);
+        decoder.read_to_end(&mut stream).map_err(corrupt)?;
+        self.trailing_data
+            .check(ArchiveFormat::Bzip2, decoder.into_inner().len())?;
+        lat_core::index::unpack(stream, RAW_STREAM_NAME)
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Bzip2
+    }
+
+    fn archive_info(&self, archive_data: &[u8]) -> Result<ArchiveInfo, LatError> {
+        // Stream header: "BZh" plus the block size digit.
+        if archive_data.len() < 4 || &archive_data[..3] != b"BZh" {
+            return Err(LatError::CorruptArchive(
+                "missing bzip2 signature".to_string(),
+            ));
+        }
+        // The entry index opens the stream, so only that much is decoded.
+        let mut decoder = BzDecoder::new(archive_data);
+        let entry_count = lat_core::index::entry_count(&mut decoder).map_err(|e| match e {
+            LatError::Io(e) => corrupt(e),
+            e => e,
+        })?;
+        Ok(ArchiveInfo {
+            format: "bzip2",
+            creator_version: None,
+            host_os: None,
+            entry_count,
+            is_encrypted: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn entries() -> Vec<ArchiveEntry> {
+        vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"bzip2 likes repetition ".repeat(400))
+                .build(),
+            ArchiveEntry::builder("dir/b.bin")
+                .data((0..5000u32).map(|i| (i * 7 % 251) as u8).collect())
+                .build(),
+            ArchiveEntry::builder("empty").build(),
+        ]
+    }
+
+    #[test]
+    fn test_bzip2_levels_round_trip() {
+        let entries = entries();
+        for level in [1, 9] {
+            let compressor = Bzip2Compressor::new().with_level(level);
+            let compressed = compressor.compress(&entries, None).unwrap();
+            assert_eq!(&compressed[..4], format!("BZh{}", level).as_bytes());
+
+            let decompressed = compressor.decompress(&compressed, None).unwrap();
+            assert_eq!(decompressed.len(), entries.len());
+            for (a, b) in entries.iter().zip(&decompressed) {
+                assert_eq!(a.name, b.name);
+                assert_eq!(a.data, b.data);
+            }
+        }
+        assert_eq!(Bzip2Compressor::new().with_level(42).level, 9);
+        assert_eq!(Bzip2Compressor::new().with_level(0).level, 1);
+    }
+
+    #[test]
+    fn test_bzip2_reads_cli_output() {
+        // Written by `bzip2 -9` from a one-line text file.
+        let fixture = include_bytes!("../fixtures/hello.txt.bz2");
+        let entries = Bzip2Compressor::new().decompress(fixture, None).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "data");
+        assert_eq!(
+            entries[0].data,
+            b"Hello from the bzip2 command line tool.\n"
+        );
+    }
+
+    #[test]
+    fn test_bzip2_malformed_stream_is_corrupt() {
+        let mut compressed = Bzip2Compressor::new().compress(&entries(), None).unwrap();
+        let middle = compressed.len() / 2;
+        compressed[middle] ^= 0xff;
+        assert!(matches!(
+            Bzip2Compressor::new().decompress(&compressed, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+        assert!(matches!(
+            Bzip2Compressor::new().decompress(b"not bzip2 at all", None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_bzip2_trailing_data_policy() {
+        let mut compressed = Bzip2Compressor::new().compress(&entries(), None).unwrap();
+        compressed.extend_from_slice(b"junk");
+        let reported = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&reported);
+        let warn = TrailingData::warn(move |_, bytes| {
+            counter.fetch_add(bytes, Ordering::SeqCst);
+        });
+        for policy in [TrailingData::Ignore, warn] {
+            let entries = Bzip2Compressor::new()
+                .with_trailing_data(policy)
+                .decompress(&compressed, None)
+                .unwrap();
+            assert_eq!(entries.len(), 3);
+        }
+        assert_eq!(reported.load(Ordering::SeqCst), b"junk".len());
+        assert!(matches!(
+            Bzip2Compressor::new()
+                .with_trailing_data(TrailingData::Error)
+                .decompress(&compressed, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_bzip2_rejects_password() {
+        assert!(Bzip2Compressor::new()
+            .compress(&entries(), Some("pw"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_bzip2_reports_format() {
+        let compressor: Box<dyn Compressor> = Box::new(Bzip2Compressor::new());
+        assert_eq!(compressor.format(), ArchiveFormat::Bzip2);
+        assert_eq!(compressor.format().extension(), "bz2");
+    }
+}
@@ -0,0 +1,215 @@
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use lat_core::{
+    ArchiveEntry, ArchiveFormat, ArchiveInfo, Compressor, CompressorConfig, FormatFactory,
+    LatError, TrailingData,
+};
+use std::io::{Read, Write};
+
+/// Entry name given to a plain `.gz` stream written by another tool.
+const RAW_STREAM_NAME: &str = "data";
+
+/// gzip as a standalone format. Entries are packed with [`lat_core::index`] first; a
+/// plain `.gz` from `gzip` decompresses to one entry named `data`. Concatenated members
+/// (`cat a.gz b.gz`, `bgzip`) decompress to their combined output, as with `gzip -d`.
+#[derive(Clone)]
+pub struct GzipCompressor {
+    level: u32,
+    trailing_data: TrailingData,
+}
+
+impl Default for GzipCompressor {
+    fn default() -> Self {
+        Self {
+            // Same default as the `gzip` tool.
+            level: 6,
+            trailing_data: TrailingData::default(),
+        }
+    }
+}
+
+/// Builds [`GzipCompressor`]s for [`lat_core::CompressorBuilder`]. Understands `level`
+/// (0-9); `threads` and `method` have no effect.
+pub struct GzipFormat;
+
+impl FormatFactory for GzipFormat {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        let mut compressor = GzipCompressor::new().with_trailing_data(config.trailing_data.clone());
+        if let Some(level) = config.level {
+            compressor = compressor.with_level(level);
+        }
+        Ok(Box::new(compressor))
+    }
+}
+
+impl GzipCompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the deflate level, clamped to 0-9.
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Sets what `decompress` does with bytes after the last member that don't start
+    /// another one (ignored by default).
+    pub fn with_trailing_data(mut self, policy: TrailingData) -> Self {
+        self.trailing_data = policy;
+        self
+    }
+}
+
+fn corrupt(e: std::io::Error) -> LatError {
+    LatError::CorruptArchive(format!("gzip: {}", e))
+}
+
+/// Decompresses every gzip member in `data` into one stream. Bytes left after a member
+/// that don't start with the gzip magic are handed to `trailing`.
+fn read_members(data: &[u8], trailing: &TrailingData) -> Result<Vec<u8>, LatError> {
+    let mut stream = Vec::new();
+    let mut rest = data;
+    loop {
+        // The bufread decoder consumes exactly one member from the slice, so whatever it
+        // hands back is the start of the next one. A single-member file takes one pass.
+        let mut decoder = GzDecoder::new(rest);
+        decoder.read_to_end(&mut stream).map_err(corrupt)?;
+        rest = decoder.into_inner();
+        if !rest.starts_with(&[0x1f, 0x8b]) {
+            trailing.check(ArchiveFormat::Gzip, rest.len())?;
+            return Ok(stream);
+        }
+    }
+}
+
+impl Compressor for GzipCompressor {
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        self.check_password(password)?;
+        lat_core::validate_entries(entries)?;
+        let packed = lat_core::index::pack(entries)?;
+
+        let mut encoder = GzEncoder::new(
+            Vec::with_capacity(packed.len() / 2),
+            Compression::new(self.level),
+        );
+        encoder.write_all(&packed)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn decompress(
+        &self,
+        archive_data: &[u8],
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.check_password(password)?;
+        lat_core::index::unpack(
+            read_members(archive_data, &self.trailing_data)?,
+            RAW_STREAM_NAME,
+        )
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Gzip
+    }
+
+    fn archive_info(&self, archive_data: &[u8]) -> Result<ArchiveInfo, LatError> {
+        // Member header: ID1 ID2 and the deflate method byte.
+        if archive_data.len() < 3 || archive_data[..3] != [0x1f, 0x8b, 0x08] {
+            return Err(LatError::CorruptArchive(
+                "missing gzip signature".to_string(),
+            ));
+        }
+        // The entry index opens the stream, so only that much is decoded.
+        let mut decoder = MultiGzDecoder::new(archive_data);
+        let entry_count = lat_core::index::entry_count(&mut decoder).map_err(|e| match e {
+            LatError::Io(e) => corrupt(e),
+            e => e,
+        })?;
+        Ok(ArchiveInfo {
+            format: "gzip",
+            creator_version: None,
+            host_os: None,
+            entry_count,
+            is_encrypted: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<ArchiveEntry> {
+        vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"gzip likes repetition ".repeat(400))
+                .build(),
+            ArchiveEntry::builder("empty").build(),
+        ]
+    }
+
+    fn member(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let entries = entries();
+        let compressor = GzipCompressor::new().with_level(9);
+        let compressed = compressor.compress(&entries, None).unwrap();
+        assert_eq!(compressor.archive_info(&compressed).unwrap().entry_count, 2);
+
+        let decompressed = compressor.decompress(&compressed, None).unwrap();
+        assert_eq!(decompressed.len(), entries.len());
+        for (a, b) in entries.iter().zip(&decompressed) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.data, b.data);
+        }
+        assert_eq!(GzipCompressor::new().with_level(42).level, 9);
+    }
+
+    #[test]
+    fn test_gzip_reads_concatenated_members() {
+        // What `cat first.gz second.gz` produces.
+        let mut concatenated = member(b"first member, ");
+        concatenated.extend(member(b"second member\n"));
+
+        let entries = GzipCompressor::new()
+            .decompress(&concatenated, None)
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "data");
+        assert_eq!(entries[0].data, b"first member, second member\n");
+
+        // Trailing bytes that aren't a member follow the trailing-data policy.
+        concatenated.extend_from_slice(b"junk");
+        assert_eq!(
+            GzipCompressor::new()
+                .decompress(&concatenated, None)
+                .unwrap()[0]
+                .data,
+            b"first member, second member\n"
+        );
+        assert!(matches!(
+            GzipCompressor::new()
+                .with_trailing_data(TrailingData::Error)
+                .decompress(&concatenated, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_gzip_reports_format() {
+        let compressor: Box<dyn Compressor> = Box::new(GzipCompressor::new());
+        assert_eq!(compressor.format(), ArchiveFormat::Gzip);
+        assert_eq!(compressor.format().extension(), "gz");
+    }
+}
@@ -0,0 +1,78 @@
+//! Checksums stored in `.lat` archives.
+
+use lat_core::{ChecksumAlgo, LatError};
+
+/// Checksum algorithm for entry and container digests, stored as one byte in the header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumKind {
+    /// No checksums; also what archives before version 3 have.
+    None,
+    /// 4-byte CRC32: catches accidental corruption at negligible cost.
+    #[default]
+    Crc32,
+    /// 32-byte BLAKE3: stronger against accidental corruption. It is unkeyed, so it doesn't
+    /// detect tampering; sign the archive with `lat_core::signing` for that.
+    Blake3,
+}
+
+impl ChecksumKind {
+    pub fn id(self) -> u8 {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Crc32 => 1,
+            ChecksumKind::Blake3 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, LatError> {
+        match id {
+            0 => Ok(ChecksumKind::None),
+            1 => Ok(ChecksumKind::Crc32),
+            2 => Ok(ChecksumKind::Blake3),
+            other => Err(LatError::UnsupportedFormat(format!(
+                "unknown .lat checksum {}",
+                other
+            ))),
+        }
+    }
+
+    /// Digest length in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Crc32 => 4,
+            ChecksumKind::Blake3 => 32,
+        }
+    }
+
+    /// Digest of the concatenation of `parts`.
+    pub fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            ChecksumKind::None => Vec::new(),
+            ChecksumKind::Crc32 => {
+                let mut hasher = crc32fast::Hasher::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                hasher.finalize().to_le_bytes().to_vec()
+            }
+            ChecksumKind::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                hasher.finalize().as_bytes().to_vec()
+            }
+        }
+    }
+}
+
+impl From<ChecksumAlgo> for ChecksumKind {
+    fn from(algo: ChecksumAlgo) -> Self {
+        match algo {
+            ChecksumAlgo::None => ChecksumKind::None,
+            ChecksumAlgo::Crc32 => ChecksumKind::Crc32,
+            ChecksumAlgo::Blake3 => ChecksumKind::Blake3,
+        }
+    }
+}
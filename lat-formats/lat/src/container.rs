@@ -0,0 +1,360 @@
+//! On-disk layout of `.lat` archives.
+//!
+//! ```text
+//! magic "LAT\0" | version: u8 | coder: u8 | checksum: u8 | entry_count: u32 | shard_count: u32
+//! container digest
+//! entry table:  { name_len: u32, name, extent_count: u32,
+//!                 { offset: u64, size: u64 } * extent_count, entry digest } * entry_count
+//! shard table:  { raw_len: u64, stored_len: u64 } * shard_count
+//! shard payloads, back to back
+//! ```
+//!
+//! Extent offsets index into the concatenation of all decoded shards; an entry's data is
+//! its extents joined in order. Without deduplication every entry has a single extent,
+//! with it several entries may reference the same chunk. All integers are little-endian.
+//!
+//! `coder` is the [`CoderKind`] id every shard was entropy coded with, except shards whose
+//! stored length equals their raw length: those are kept verbatim. Version 1 archives have
+//! no coder byte and store all shards verbatim.
+//!
+//! `checksum` is the [`ChecksumKind`] id of both digests, whose length it determines. An
+//! entry digest covers the entry's data; the container digest covers every byte of the
+//! archive except the digest itself. Archives before version 3 have no checksum byte and
+//! no digests.
+//!
+//! A split archive is a set of ordinary `.lat` archives (one per part, each holding whole
+//! entries) plus a manifest recording which part holds which entry:
+//!
+//! ```text
+//! magic "LATM" | manifest version: u8 | part_count: u32
+//! { entry_count: u32, { name_len: u32, name } * entry_count } * part_count
+//! ```
+
+use crate::checksum::ChecksumKind;
+use crate::entropy::CoderKind;
+use lat_core::LatError;
+use std::io::{self, Read};
+
+pub const MAGIC: [u8; 4] = *b"LAT\0";
+pub const VERSION: u8 = 3;
+pub const MANIFEST_MAGIC: [u8; 4] = *b"LATM";
+pub const MANIFEST_VERSION: u8 = 1;
+
+/// Size of the fixed header fields (magic, version, coder, checksum and the two counts).
+/// The container digest follows them.
+pub const HEADER_FIXED_LEN: usize = 15;
+pub const SHARD_RECORD_LEN: usize = 16;
+
+/// Size of an entry record with a single extent.
+pub fn single_extent_record_len(name: &str, checksum: ChecksumKind) -> usize {
+    4 + name.len() + 4 + 16 + checksum.digest_len()
+}
+
+/// Container digest of a complete archive, skipping the digest field itself.
+pub fn container_digest(archive: &[u8], checksum: ChecksumKind) -> Vec<u8> {
+    let field_end = (HEADER_FIXED_LEN + checksum.digest_len()).min(archive.len());
+    checksum.digest(&[
+        &archive[..HEADER_FIXED_LEN.min(archive.len())],
+        &archive[field_end..],
+    ])
+}
+
+pub struct Extent {
+    pub offset: u64,
+    pub size: u64,
+}
+
+pub struct EntryRecord {
+    pub name: String,
+    pub extents: Vec<Extent>,
+    /// Digest of the entry data; empty when the archive has no checksums.
+    pub digest: Vec<u8>,
+}
+
+pub struct ShardRecord {
+    pub raw_len: u64,
+    pub stored_len: u64,
+}
+
+pub struct Header {
+    pub coder: CoderKind,
+    pub checksum: ChecksumKind,
+    /// Stored container digest. [`Header::write`] writes zeros here; the finished archive
+    /// is patched with [`container_digest`].
+    pub container_digest: Vec<u8>,
+    pub entries: Vec<EntryRecord>,
+    pub shards: Vec<ShardRecord>,
+}
+
+impl Header {
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+        out.push(self.coder.id());
+        out.push(self.checksum.id());
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.shards.len() as u32).to_le_bytes());
+        out.resize(out.len() + self.checksum.digest_len(), 0);
+        for entry in &self.entries {
+            out.extend_from_slice(&(entry.name.len() as u32).to_le_bytes());
+            out.extend_from_slice(entry.name.as_bytes());
+            out.extend_from_slice(&(entry.extents.len() as u32).to_le_bytes());
+            for extent in &entry.extents {
+                out.extend_from_slice(&extent.offset.to_le_bytes());
+                out.extend_from_slice(&extent.size.to_le_bytes());
+            }
+            out.extend_from_slice(&entry.digest);
+        }
+        for shard in &self.shards {
+            out.extend_from_slice(&shard.raw_len.to_le_bytes());
+            out.extend_from_slice(&shard.stored_len.to_le_bytes());
+        }
+    }
+
+    /// Parses the header, returning it along with the offset of the first shard payload.
+    pub fn read(data: &[u8]) -> Result<(Self, usize), LatError> {
+        Self::read_from(data, data.len())
+    }
+
+    /// Parses the header from the start of `reader`, consuming nothing past it. `size`
+    /// is the archive length; it bounds allocations made on the strength of stored counts.
+    pub fn read_from<R: Read>(reader: R, size: usize) -> Result<(Self, usize), LatError> {
+        let mut cursor = ByteCursor::new(reader);
+        if cursor.array()? != MAGIC {
+            return Err(LatError::CorruptArchive("not a .lat archive".to_string()));
+        }
+        let (coder, checksum) = match cursor.array::<1>()?[0] {
+            1 => (CoderKind::Stored, ChecksumKind::None),
+            2 => (
+                CoderKind::from_id(cursor.array::<1>()?[0])?,
+                ChecksumKind::None,
+            ),
+            VERSION => {
+                let [coder, checksum] = cursor.array()?;
+                (CoderKind::from_id(coder)?, ChecksumKind::from_id(checksum)?)
+            }
+            version => {
+                return Err(LatError::UnsupportedFormat(format!(
+                    ".lat version {} is not supported",
+                    version
+                )));
+            }
+        };
+        let entry_count = cursor.u32()? as usize;
+        let shard_count = cursor.u32()? as usize;
+        let container_digest = cursor.take(checksum.digest_len())?;
+
+        // Every record takes at least 8 bytes, so this bounds allocations by input size.
+        let mut entries = Vec::with_capacity(entry_count.min(size / 8));
+        for _ in 0..entry_count {
+            let name_len = cursor.u32()? as usize;
+            let name = String::from_utf8(cursor.take(name_len)?)
+                .map_err(|_| LatError::CorruptArchive("entry name is not UTF-8".to_string()))?;
+            let extent_count = cursor.u32()? as usize;
+            let mut extents = Vec::with_capacity(extent_count.min(size / 16));
+            for _ in 0..extent_count {
+                extents.push(Extent {
+                    offset: cursor.u64()?,
+                    size: cursor.u64()?,
+                });
+            }
+            let digest = cursor.take(checksum.digest_len())?;
+            entries.push(EntryRecord {
+                name,
+                extents,
+                digest,
+            });
+        }
+
+        let mut shards = Vec::with_capacity(shard_count.min(size / 16));
+        for _ in 0..shard_count {
+            shards.push(ShardRecord {
+                raw_len: cursor.u64()?,
+                stored_len: cursor.u64()?,
+            });
+        }
+
+        Ok((
+            Self {
+                coder,
+                checksum,
+                container_digest,
+                entries,
+                shards,
+            },
+            cursor.pos,
+        ))
+    }
+}
+
+/// Entry names held by each part of a split archive, in part order.
+pub struct Manifest {
+    pub parts: Vec<Vec<String>>,
+}
+
+impl Manifest {
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MANIFEST_MAGIC);
+        out.push(MANIFEST_VERSION);
+        out.extend_from_slice(&(self.parts.len() as u32).to_le_bytes());
+        for names in &self.parts {
+            out.extend_from_slice(&(names.len() as u32).to_le_bytes());
+            for name in names {
+                out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                out.extend_from_slice(name.as_bytes());
+            }
+        }
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, LatError> {
+        let mut cursor = ByteCursor::new(data);
+        if cursor.array()? != MANIFEST_MAGIC {
+            return Err(LatError::CorruptArchive(
+                "not a .lat split manifest".to_string(),
+            ));
+        }
+        let version = cursor.array::<1>()?[0];
+        if version != MANIFEST_VERSION {
+            return Err(LatError::UnsupportedFormat(format!(
+                ".lat manifest version {} is not supported",
+                version
+            )));
+        }
+        let part_count = cursor.u32()? as usize;
+        let mut parts = Vec::with_capacity(part_count.min(data.len() / 4));
+        for _ in 0..part_count {
+            let entry_count = cursor.u32()? as usize;
+            let mut names = Vec::with_capacity(entry_count.min(data.len() / 4));
+            for _ in 0..entry_count {
+                let name_len = cursor.u32()? as usize;
+                let name = String::from_utf8(cursor.take(name_len)?)
+                    .map_err(|_| LatError::CorruptArchive("entry name is not UTF-8".to_string()))?;
+                names.push(name);
+            }
+            parts.push(names);
+        }
+        Ok(Self { parts })
+    }
+}
+
+/// Reads header fields from a byte source, counting how much was consumed.
+struct ByteCursor<R> {
+    reader: R,
+    pos: usize,
+}
+
+impl<R: Read> ByteCursor<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<Vec<u8>, LatError> {
+        // Read through `take` so a lying length can't force a huge allocation up front.
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(LatError::CorruptArchive(
+                "truncated .lat header".to_string(),
+            ));
+        }
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], LatError> {
+        let mut buf = [0u8; N];
+        self.reader
+            .read_exact(&mut buf)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => {
+                    LatError::CorruptArchive("truncated .lat header".to_string())
+                }
+                _ => LatError::Io(e),
+            })?;
+        self.pos += N;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> Result<u32, LatError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, LatError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header spelled out byte by byte, so the layout (and its little-endian integers)
+    /// doesn't depend on the byte order of the machine running the test.
+    fn golden_header() -> Vec<u8> {
+        let mut bytes = b"LAT\0".to_vec();
+        bytes.extend_from_slice(&[3, 1, 1]); // version, coder (rANS), checksum (CRC32)
+        bytes.extend_from_slice(&[1, 0, 0, 0]); // entry_count
+        bytes.extend_from_slice(&[1, 0, 0, 0]); // shard_count
+        bytes.extend_from_slice(&[0; 4]); // container digest placeholder
+        bytes.extend_from_slice(&[2, 0, 0, 0]); // name_len
+        bytes.extend_from_slice(b"ab");
+        bytes.extend_from_slice(&[1, 0, 0, 0]); // extent_count
+        bytes.extend_from_slice(&[8, 7, 6, 5, 4, 3, 2, 1]); // offset
+        bytes.extend_from_slice(&[0x10, 0, 0, 0, 0, 0, 0, 0]); // size
+        bytes.extend_from_slice(&[0xAA, 0xBB, 0xCC, 0xDD]); // entry digest
+        bytes.extend_from_slice(&[0x0B, 0x0A, 0, 0, 0, 0, 0, 0]); // raw_len
+        bytes.extend_from_slice(&[0x0C, 0, 0, 0, 0, 0, 0, 0]); // stored_len
+        bytes
+    }
+
+    #[test]
+    fn test_header_is_little_endian() {
+        let header = Header {
+            coder: CoderKind::Rans,
+            checksum: ChecksumKind::Crc32,
+            container_digest: Vec::new(),
+            entries: vec![EntryRecord {
+                name: "ab".to_string(),
+                extents: vec![Extent {
+                    offset: 0x0102_0304_0506_0708,
+                    size: 0x10,
+                }],
+                digest: vec![0xAA, 0xBB, 0xCC, 0xDD],
+            }],
+            shards: vec![ShardRecord {
+                raw_len: 0x0A0B,
+                stored_len: 0x0C,
+            }],
+        };
+        let mut written = Vec::new();
+        header.write(&mut written);
+        assert_eq!(written, golden_header());
+
+        let (parsed, payload_start) = Header::read(&golden_header()).unwrap();
+        assert_eq!(payload_start, golden_header().len());
+        assert_eq!(parsed.entries[0].extents[0].offset, 0x0102_0304_0506_0708);
+        assert_eq!(parsed.entries[0].extents[0].size, 0x10);
+        assert_eq!(parsed.shards[0].raw_len, 0x0A0B);
+        assert_eq!(parsed.shards[0].stored_len, 0x0C);
+    }
+
+    #[test]
+    fn test_version_1_header_still_parses() {
+        let mut bytes = b"LAT\0".to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0]);
+        bytes.extend_from_slice(&[1, 0, 0, 0, b'x', 1, 0, 0, 0]);
+        bytes.extend_from_slice(&[0; 8]);
+        bytes.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+
+        let (parsed, _) = Header::read(&bytes).unwrap();
+        assert_eq!(parsed.coder, CoderKind::Stored);
+        assert_eq!(parsed.checksum, ChecksumKind::None);
+        assert_eq!(parsed.entries[0].name, "x");
+        assert_eq!(parsed.entries[0].extents[0].size, 3);
+        assert_eq!(parsed.shards[0].raw_len, 3);
+    }
+}
@@ -0,0 +1,492 @@
+//! Entropy coders for `.lat` shards.
+//!
+//! Each shard is coded independently with the coder recorded in the container header.
+//! Coded shards start with the model (frequency table or code lengths) they were coded
+//! with, so decoding needs nothing but the shard and its raw length.
+
+use lat_core::LatError;
+use lat_core::entropy::byte_histogram;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+pub trait EntropyCoder: Send + Sync {
+    fn encode(&self, data: &[u8]) -> Vec<u8>;
+    /// Decodes exactly `raw_len` bytes.
+    fn decode(&self, data: &[u8], raw_len: usize) -> Result<Vec<u8>, LatError>;
+}
+
+/// Entropy coder choice, stored as one byte in the container header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoderKind {
+    /// No entropy coding; also what version 1 archives used.
+    Stored,
+    /// Static order-0 rANS: best ratio of the three, table-driven decode.
+    #[default]
+    Rans,
+    /// Static canonical Huffman: simplest, whole bits per symbol.
+    Huffman,
+}
+
+impl CoderKind {
+    pub fn id(self) -> u8 {
+        match self {
+            CoderKind::Stored => 0,
+            CoderKind::Rans => 1,
+            CoderKind::Huffman => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, LatError> {
+        match id {
+            0 => Ok(CoderKind::Stored),
+            1 => Ok(CoderKind::Rans),
+            2 => Ok(CoderKind::Huffman),
+            other => Err(LatError::UnsupportedFormat(format!(
+                "unknown .lat entropy coder {}",
+                other
+            ))),
+        }
+    }
+
+    /// Parses an option value such as "rans" or "huffman".
+    pub fn from_name(name: &str) -> Result<Self, LatError> {
+        match name.to_ascii_lowercase().as_str() {
+            "stored" | "none" => Ok(CoderKind::Stored),
+            "rans" => Ok(CoderKind::Rans),
+            "huffman" => Ok(CoderKind::Huffman),
+            other => Err(LatError::InvalidInput(format!(
+                "unknown .lat entropy coder '{}'",
+                other
+            ))),
+        }
+    }
+
+    pub fn coder(self) -> &'static dyn EntropyCoder {
+        match self {
+            CoderKind::Stored => &Stored,
+            CoderKind::Rans => &Rans,
+            CoderKind::Huffman => &Huffman,
+        }
+    }
+}
+
+/// Most output reserved up front from a length in the shard table. Past this the buffer
+/// grows as symbols actually decode, so a corrupt table can't force a huge allocation.
+const MAX_PREALLOC: usize = 64 << 20;
+
+fn truncated() -> LatError {
+    LatError::CorruptArchive("truncated entropy-coded shard".to_string())
+}
+
+pub struct Stored;
+
+impl EntropyCoder for Stored {
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        data.to_vec()
+    }
+
+    fn decode(&self, data: &[u8], raw_len: usize) -> Result<Vec<u8>, LatError> {
+        if data.len() != raw_len {
+            return Err(LatError::CorruptArchive(
+                "shard length does not match shard table".to_string(),
+            ));
+        }
+        Ok(data.to_vec())
+    }
+}
+
+const PROB_BITS: u32 = 12;
+const PROB_SCALE: u32 = 1 << PROB_BITS;
+/// Lower bound of the normalized rANS state; renormalization keeps it in [L, 256 * L).
+const RANS_L: u32 = 1 << 23;
+/// Most bytes a rANS shard decodes to per stored byte. A symbol holding the whole range
+/// costs no bits at all, so without this bound a few bytes could claim any length; the
+/// encoder pads shards that would expand further.
+const MAX_RANS_EXPANSION: usize = 4096;
+
+/// Byte-wise rANS with a static order-0 model.
+///
+/// Layout: `symbol_count: u16 | { symbol: u8, freq: u16 } * symbol_count | final state:
+/// u32 | renormalization bytes | zero padding`. Frequencies sum to 4096. The padding
+/// brings the shard to at least 1/[`MAX_RANS_EXPANSION`] of its decoded length.
+pub struct Rans;
+
+/// Scales symbol counts to frequencies summing to [`PROB_SCALE`], keeping every present
+/// symbol at frequency 1 or more.
+fn normalize(counts: &[u64; 256]) -> [u32; 256] {
+    let total: u64 = counts.iter().sum();
+    let mut freqs = [0u32; 256];
+    if total == 0 {
+        return freqs;
+    }
+    for (freq, &count) in freqs.iter_mut().zip(counts) {
+        if count > 0 {
+            *freq = ((count * PROB_SCALE as u64 / total) as u32).max(1);
+        }
+    }
+    let mut sum: u32 = freqs.iter().sum();
+    while sum > PROB_SCALE {
+        // Take from the largest frequency; it loses the least relative precision.
+        let largest = (0..256).max_by_key(|&s| freqs[s]).unwrap();
+        freqs[largest] -= 1;
+        sum -= 1;
+    }
+    if sum < PROB_SCALE {
+        let largest = (0..256).max_by_key(|&s| freqs[s]).unwrap();
+        freqs[largest] += PROB_SCALE - sum;
+    }
+    freqs
+}
+
+impl EntropyCoder for Rans {
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let freqs = normalize(&byte_histogram(data));
+        let mut starts = [0u32; 256];
+        let mut running = 0;
+        for (start, &freq) in starts.iter_mut().zip(&freqs) {
+            *start = running;
+            running += freq;
+        }
+
+        let mut out = Vec::with_capacity(data.len() / 2 + 1024);
+        let present: Vec<usize> = (0..256).filter(|&s| freqs[s] > 0).collect();
+        out.extend_from_slice(&(present.len() as u16).to_le_bytes());
+        for &symbol in &present {
+            out.push(symbol as u8);
+            out.extend_from_slice(&(freqs[symbol] as u16).to_le_bytes());
+        }
+        if data.is_empty() {
+            return out;
+        }
+
+        // rANS is last-in first-out: encode backwards so the decoder runs forwards. The
+        // renormalization bytes come out in reverse order too and are flipped at the end.
+        let mut state = RANS_L;
+        let mut emitted = Vec::with_capacity(data.len());
+        for &byte in data.iter().rev() {
+            let (freq, start) = (freqs[byte as usize], starts[byte as usize]);
+            let state_max = ((RANS_L >> PROB_BITS) << 8) * freq;
+            while state >= state_max {
+                emitted.push(state as u8);
+                state >>= 8;
+            }
+            state = ((state / freq) << PROB_BITS) + (state % freq) + start;
+        }
+        out.extend_from_slice(&state.to_le_bytes());
+        out.extend(emitted.iter().rev());
+        out.resize(out.len().max(data.len().div_ceil(MAX_RANS_EXPANSION)), 0);
+        out
+    }
+
+    fn decode(&self, data: &[u8], raw_len: usize) -> Result<Vec<u8>, LatError> {
+        if raw_len.div_ceil(MAX_RANS_EXPANSION) > data.len() {
+            return Err(LatError::CorruptArchive(format!(
+                "rANS shard of {} bytes claims {} decoded bytes",
+                data.len(),
+                raw_len
+            )));
+        }
+        let mut pos = 0;
+        let mut take = |len: usize| -> Result<&[u8], LatError> {
+            let bytes = data.get(pos..pos + len).ok_or_else(truncated)?;
+            pos += len;
+            Ok(bytes)
+        };
+
+        let symbol_count = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        let mut freqs = [0u32; 256];
+        for _ in 0..symbol_count {
+            let record = take(3)?;
+            freqs[record[0] as usize] = u16::from_le_bytes([record[1], record[2]]) as u32;
+        }
+        if raw_len == 0 {
+            return Ok(Vec::new());
+        }
+        if freqs.iter().sum::<u32>() != PROB_SCALE {
+            return Err(LatError::CorruptArchive(
+                "rANS frequency table does not sum to 4096".to_string(),
+            ));
+        }
+
+        let mut starts = [0u32; 256];
+        let mut slots = vec![0u8; PROB_SCALE as usize];
+        let mut running = 0;
+        for symbol in 0..256 {
+            starts[symbol] = running;
+            let end = running + freqs[symbol];
+            slots[running as usize..end as usize].fill(symbol as u8);
+            running = end;
+        }
+
+        let mut state = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let mut bytes = data[pos..].iter();
+        let mut out = Vec::with_capacity(raw_len.min(MAX_PREALLOC));
+        for _ in 0..raw_len {
+            let slot = state & (PROB_SCALE - 1);
+            let symbol = slots[slot as usize];
+            let (freq, start) = (freqs[symbol as usize], starts[symbol as usize]);
+            state = freq * (state >> PROB_BITS) + slot - start;
+            while state < RANS_L {
+                state = (state << 8) | *bytes.next().ok_or_else(truncated)? as u32;
+            }
+            out.push(symbol);
+        }
+        Ok(out)
+    }
+}
+
+const MAX_CODE_LEN: u8 = 15;
+
+/// Static canonical Huffman.
+///
+/// Layout: 128 bytes of code lengths (two 4-bit lengths per byte, symbol 2k in the low
+/// nibble), then the codes packed MSB-first.
+pub struct Huffman;
+
+/// Code lengths from a Huffman tree over `counts`, limited to [`MAX_CODE_LEN`] by
+/// flattening the counts and rebuilding until the tree is shallow enough.
+fn code_lengths(counts: &[u64; 256]) -> [u8; 256] {
+    let mut counts = *counts;
+    loop {
+        let mut lengths = [0u8; 256];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::new();
+        // Node i < 256 is a leaf, later nodes are internal; parents[i] links upwards.
+        let mut parents: Vec<usize> = vec![usize::MAX; 256];
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count > 0 {
+                heap.push(Reverse((count, symbol)));
+            }
+        }
+        match heap.len() {
+            0 => return lengths,
+            1 => {
+                let Reverse((_, symbol)) = heap.pop().unwrap();
+                lengths[symbol] = 1;
+                return lengths;
+            }
+            _ => {}
+        }
+        while heap.len() > 1 {
+            let Reverse((a_count, a)) = heap.pop().unwrap();
+            let Reverse((b_count, b)) = heap.pop().unwrap();
+            let node = parents.len();
+            parents.push(usize::MAX);
+            parents[a] = node;
+            parents[b] = node;
+            heap.push(Reverse((a_count + b_count, node)));
+        }
+
+        let mut too_long = false;
+        for symbol in 0..256 {
+            if counts[symbol] == 0 {
+                continue;
+            }
+            let (mut depth, mut node) = (0u32, symbol);
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            too_long |= depth > MAX_CODE_LEN as u32;
+            lengths[symbol] = depth.min(255) as u8;
+        }
+        if !too_long {
+            return lengths;
+        }
+        for count in counts.iter_mut().filter(|c| **c > 0) {
+            *count = (*count >> 1).max(1);
+        }
+    }
+}
+
+/// Number of codes of each length (index 0 unused).
+fn length_counts(lengths: &[u8; 256]) -> [u16; MAX_CODE_LEN as usize + 1] {
+    let mut counts = [0u16; MAX_CODE_LEN as usize + 1];
+    for &len in lengths.iter().filter(|&&len| len > 0) {
+        counts[len as usize] += 1;
+    }
+    counts
+}
+
+impl EntropyCoder for Huffman {
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let lengths = code_lengths(&byte_histogram(data));
+        let mut out = Vec::with_capacity(128 + data.len());
+        for pair in lengths.chunks(2) {
+            out.push(pair[0] | (pair[1] << 4));
+        }
+
+        // Canonical codes: shorter codes first, ties broken by symbol value.
+        let counts = length_counts(&lengths);
+        let mut next_code = [0u32; MAX_CODE_LEN as usize + 2];
+        let mut code = 0u32;
+        for len in 1..=MAX_CODE_LEN as usize {
+            code = (code + counts[len - 1] as u32) << 1;
+            next_code[len] = code;
+        }
+        let mut codes = [0u32; 256];
+        for symbol in 0..256 {
+            let len = lengths[symbol] as usize;
+            if len > 0 {
+                codes[symbol] = next_code[len];
+                next_code[len] += 1;
+            }
+        }
+
+        let (mut acc, mut bits) = (0u64, 0u32);
+        for &byte in data {
+            let len = lengths[byte as usize] as u32;
+            acc = (acc << len) | codes[byte as usize] as u64;
+            bits += len;
+            while bits >= 8 {
+                bits -= 8;
+                out.push((acc >> bits) as u8);
+            }
+        }
+        if bits > 0 {
+            out.push((acc << (8 - bits)) as u8);
+        }
+        out
+    }
+
+    fn decode(&self, data: &[u8], raw_len: usize) -> Result<Vec<u8>, LatError> {
+        let table = data.get(..128).ok_or_else(truncated)?;
+        // Every code is at least one bit long.
+        if raw_len / 8 > data.len() - 128 {
+            return Err(truncated());
+        }
+        let mut lengths = [0u8; 256];
+        for (i, &byte) in table.iter().enumerate() {
+            lengths[2 * i] = byte & 0x0F;
+            lengths[2 * i + 1] = byte >> 4;
+        }
+        let counts = length_counts(&lengths);
+        // Symbols ordered by (length, value), matching the canonical code assignment.
+        let mut symbols: Vec<u8> = (0..=255u8).filter(|&s| lengths[s as usize] > 0).collect();
+        symbols.sort_by_key(|&s| lengths[s as usize]);
+
+        let mut bits = data[128..]
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+        let mut out = Vec::with_capacity(raw_len.min(MAX_PREALLOC));
+        while out.len() < raw_len {
+            // Walk lengths upwards; at each length the valid codes form one contiguous
+            // range starting at `first`.
+            let (mut code, mut first, mut index) = (0i32, 0i32, 0usize);
+            let mut symbol = None;
+            for &count in &counts[1..] {
+                code |= bits.next().ok_or_else(truncated)? as i32;
+                let count = count as i32;
+                if code - count < first {
+                    symbol = symbols.get(index + (code - first) as usize).copied();
+                    break;
+                }
+                index += count as usize;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+            out.push(symbol.ok_or_else(|| {
+                LatError::CorruptArchive("invalid Huffman code in shard".to_string())
+            })?);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lat_core::testing::noise;
+
+    fn samples() -> Vec<Vec<u8>> {
+        let noise = noise(50_000, 0x1234_5678);
+        // Geometric-ish distribution that drives some Huffman codes past 15 bits.
+        let skewed: Vec<u8> = (0..200_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 8).leading_zeros() as u8 * 7)
+            .collect();
+        vec![
+            Vec::new(),
+            vec![42],
+            vec![7; 10_000],
+            b"abracadabra".repeat(500),
+            noise,
+            skewed,
+        ]
+    }
+
+    #[test]
+    fn test_coders_round_trip() {
+        for kind in [CoderKind::Stored, CoderKind::Rans, CoderKind::Huffman] {
+            let coder = kind.coder();
+            for data in samples() {
+                let encoded = coder.encode(&data);
+                let decoded = coder.decode(&encoded, data.len()).expect("Decode failed");
+                assert_eq!(decoded, data, "{:?} on {} bytes", kind, data.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_coders_compress_skewed_data() {
+        let text = b"abracadabra".repeat(500);
+        for kind in [CoderKind::Rans, CoderKind::Huffman] {
+            assert!(kind.coder().encode(&text).len() < text.len() / 2);
+        }
+    }
+
+    #[test]
+    fn test_rans_table_is_little_endian() {
+        // One symbol (5) holding the whole 4096 range, then the final state (RANS_L).
+        let encoded = Rans.encode(&[5, 5]);
+        assert_eq!(encoded, [1, 0, 5, 0x00, 0x10, 0x00, 0x00, 0x80, 0x00]);
+    }
+
+    #[test]
+    fn test_rans_rejects_implausible_lengths() {
+        // One symbol costs nothing per byte, so a long run is padded to the bound.
+        let run = vec![9u8; 1 << 20];
+        let encoded = Rans.encode(&run);
+        assert_eq!(encoded.len(), run.len() / MAX_RANS_EXPANSION);
+        assert_eq!(Rans.decode(&encoded, run.len()).unwrap(), run);
+
+        // Without the padding the same table would claim any length.
+        let tiny = Rans.encode(&[9, 9]);
+        assert!(matches!(
+            Rans.decode(&tiny, usize::MAX),
+            Err(LatError::CorruptArchive(_))
+        ));
+        assert!(Rans.decode(&encoded, run.len() * 2).is_err());
+    }
+
+    #[test]
+    fn test_huffman_lengths_are_limited() {
+        let mut counts = [0u64; 256];
+        // Fibonacci counts produce the deepest possible tree.
+        let (mut a, mut b) = (1u64, 1u64);
+        for count in counts.iter_mut().take(40) {
+            *count = a;
+            (a, b) = (b, a + b);
+        }
+        let lengths = code_lengths(&counts);
+        assert!(lengths.iter().all(|&len| len <= MAX_CODE_LEN));
+        // Kraft inequality holds with equality for a complete prefix code.
+        let kraft: f64 = lengths
+            .iter()
+            .filter(|&&len| len > 0)
+            .map(|&len| 0.5f64.powi(len as i32))
+            .sum();
+        assert!((kraft - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_truncated_shard_is_corrupt() {
+        let data = b"abracadabra".repeat(100);
+        for kind in [CoderKind::Rans, CoderKind::Huffman] {
+            let encoded = kind.coder().encode(&data);
+            let cut = &encoded[..encoded.len() / 2];
+            assert!(matches!(
+                kind.coder().decode(cut, data.len()),
+                Err(LatError::CorruptArchive(_))
+            ));
+        }
+    }
+}
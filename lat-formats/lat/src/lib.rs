@@ -1,13 +1,434 @@
-use lat_core::{ArchiveEntry, Compressor, GpuAccelerator};
+mod checksum;
+mod container;
+mod entropy;
+mod reader;
+
+pub use checksum::ChecksumKind;
+pub use entropy::{CoderKind, EntropyCoder};
+pub use reader::LatArchiveReader;
+
+use container::{EntryRecord, Extent, Header, Manifest, ShardRecord};
+use lat_core::chunking::Chunker;
+use lat_core::store::{BlockStore, StoreReader};
+use lat_core::{
+    ArchiveEntry, ArchiveFormat, CompressionResult, Compressor, CompressorConfig, Deadline,
+    FormatFactory, GpuAccelerator, LatError, TrailingData,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct LatCompressor {
     accelerator: Option<Arc<dyn GpuAccelerator>>,
+    shards: usize,
+    dedup: Option<Chunker>,
+    context_memory_limit: Option<usize>,
+    coder: CoderKind,
+    checksum: ChecksumKind,
+    skip_verify: bool,
+    threads: Option<usize>,
+    memory_limit: Option<u64>,
+    min_ratio_abort: Option<f32>,
+    trailing_data: TrailingData,
 }
 
+/// Bytes from the start of the stream coded to estimate whether the rest is compressible.
+const RATIO_SAMPLE_LEN: usize = 64 * 1024;
+
+/// Estimated memory per chunk in the deduplication index: the chunk's key and offset plus
+/// the hash table's own overhead.
+const INDEX_ENTRY_BYTES: usize = 32;
+
 impl LatCompressor {
     pub fn new(accelerator: Option<Arc<dyn GpuAccelerator>>) -> Self {
-        Self { accelerator }
+        Self {
+            accelerator,
+            shards: 1,
+            dedup: None,
+            context_memory_limit: None,
+            coder: CoderKind::default(),
+            checksum: ChecksumKind::default(),
+            skip_verify: false,
+            threads: None,
+            memory_limit: None,
+            min_ratio_abort: None,
+            trailing_data: TrailingData::default(),
+        }
+    }
+
+    /// Enables deduplication: entries are split into content-defined chunks of roughly
+    /// `avg_chunk_size` bytes and each distinct chunk is stored once. Smaller chunks find
+    /// more duplicates at the cost of a larger entry table.
+    pub fn with_dedup(mut self, avg_chunk_size: usize) -> Self {
+        self.dedup = Some(Chunker::new(avg_chunk_size));
+        self
+    }
+
+    /// Caps the memory of the deduplication index, the hash table of chunks seen so far.
+    /// Once it is full, new chunks are still stored but no longer indexed, so later
+    /// copies of them are stored again: the archive grows, but round-trips the same.
+    /// `compress_detailed` reports the size the index reached as `context_memory`.
+    pub fn with_context_memory_limit(mut self, bytes: usize) -> Self {
+        self.context_memory_limit = Some(bytes);
+        self
+    }
+
+    /// Caps how many shards are encoded at once (by default all of them). `1` encodes
+    /// shards one after another on the calling thread.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    /// Bounds the memory held by shards being encoded. Each shard in flight needs about
+    /// twice its size (input copy plus output), so a tight limit encodes fewer shards at
+    /// once; the archive is identical either way. At least one shard is always in flight.
+    /// The concatenated input stream and the encoded shards waiting to be written are held
+    /// in full and aren't counted against the limit.
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Number of shards of `shard_len` bytes to encode concurrently.
+    fn batch_size(&self, shard_len: usize) -> usize {
+        let by_memory = self.memory_limit.map_or(usize::MAX, |limit| {
+            usize::try_from(limit / (2 * shard_len as u64)).unwrap_or(usize::MAX)
+        });
+        self.threads.unwrap_or(self.shards).min(by_memory).max(1)
+    }
+
+    /// Selects the entropy coder for new archives. The choice is recorded in the header,
+    /// so decompression doesn't need to be told.
+    pub fn with_coder(mut self, coder: CoderKind) -> Self {
+        self.coder = coder;
+        self
+    }
+
+    /// Selects the checksum stored for each entry and for the whole archive.
+    pub fn with_checksum(mut self, checksum: ChecksumKind) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Codes a sample of the input first and stores the whole archive uncompressed when the
+    /// sample's compressed/uncompressed ratio (as in [`lat_core::EntryStat`]) is above
+    /// `ratio`, instead of spending a full pass on data that won't shrink.
+    pub fn with_min_ratio_abort(mut self, ratio: f32) -> Self {
+        self.min_ratio_abort = Some(ratio);
+        self
+    }
+
+    /// Coder to use for `stream`: the configured one, or `Stored` if a sample of the
+    /// stream compresses worse than the `min_ratio_abort` threshold.
+    fn pick_coder(&self, stream: &[u8]) -> CoderKind {
+        let Some(threshold) = self.min_ratio_abort else {
+            return self.coder;
+        };
+        let sample = &stream[..stream.len().min(RATIO_SAMPLE_LEN)];
+        if self.coder == CoderKind::Stored || sample.is_empty() {
+            return self.coder;
+        }
+        let ratio = self.coder.coder().encode(sample).len() as f32 / sample.len() as f32;
+        if ratio > threshold {
+            return CoderKind::Stored;
+        }
+        self.coder
+    }
+
+    /// Skips checksum verification on decompression, for speed when the archive is known
+    /// to be intact.
+    pub fn with_skip_verify(mut self, skip: bool) -> Self {
+        self.skip_verify = skip;
+        self
+    }
+
+    /// Sets what `decompress` does with bytes after the last shard (ignored by default).
+    pub fn with_trailing_data(mut self, policy: TrailingData) -> Self {
+        self.trailing_data = policy;
+        self
+    }
+
+    /// Compresses `entries` into parts of at most `part_budget` bytes, never splitting an
+    /// entry. Each part is a complete `.lat` archive, so a file can be extracted from its
+    /// part alone (see [`find_part`]). An entry too large for the budget gets a part of
+    /// its own, which will exceed it.
+    pub fn compress_split(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        part_budget: usize,
+    ) -> Result<SplitArchive, LatError> {
+        lat_core::validate_entries(entries)?;
+        let base_len = container::HEADER_FIXED_LEN
+            + self.checksum.digest_len()
+            + self.shards * container::SHARD_RECORD_LEN;
+
+        let mut groups: Vec<&[ArchiveEntry]> = Vec::new();
+        let (mut start, mut part_len) = (0, base_len);
+        for (i, entry) in entries.iter().enumerate() {
+            let len =
+                container::single_extent_record_len(&entry.name, self.checksum) + entry.data.len();
+            if i > start && part_len + len > part_budget {
+                groups.push(&entries[start..i]);
+                (start, part_len) = (i, base_len);
+            }
+            part_len += len;
+        }
+        if start < entries.len() || entries.is_empty() {
+            groups.push(&entries[start..]);
+        }
+
+        let parts = groups
+            .iter()
+            .map(|group| self.compress(group, password))
+            .collect::<Result<Vec<_>, _>>()?;
+        let manifest = Manifest {
+            parts: groups
+                .iter()
+                .map(|group| group.iter().map(|e| e.name.clone()).collect())
+                .collect(),
+        };
+        let mut manifest_bytes = Vec::new();
+        manifest.write(&mut manifest_bytes);
+        Ok(SplitArchive {
+            manifest: manifest_bytes,
+            parts,
+        })
+    }
+
+    /// Splits the input into `shards` independently coded segments that are dispatched to
+    /// the GPU concurrently. Each shard starts with fresh models, so more shards trade a
+    /// little ratio for throughput.
+    pub fn with_shards(mut self, shards: usize) -> Self {
+        self.shards = shards.max(1);
+        self
+    }
+}
+
+/// Output of [`LatCompressor::compress_split`]: self-contained part archives plus a
+/// manifest of which part holds which entry.
+pub struct SplitArchive {
+    pub manifest: Vec<u8>,
+    pub parts: Vec<Vec<u8>>,
+}
+
+/// Returns the index of the part holding `name` according to a split `manifest`.
+pub fn find_part(manifest: &[u8], name: &str) -> Result<usize, LatError> {
+    Manifest::read(manifest)?
+        .parts
+        .iter()
+        .position(|names| names.iter().any(|n| n == name))
+        .ok_or_else(|| LatError::EntryNotFound(name.to_string()))
+}
+
+/// Builds [`LatCompressor`]s for [`lat_core::CompressorBuilder`]. `threads` sets the
+/// shard count, `memory_limit` bounds the shards encoded at once, `method` selects the
+/// entropy coder ("rans", "huffman" or "stored") and `checksum` the digest recorded in
+/// the header. The accelerator is required at compression time.
+pub struct LatFormat;
+
+impl FormatFactory for LatFormat {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        let mut compressor =
+            LatCompressor::new(config.accelerator.clone()).with_shards(config.threads.unwrap_or(1));
+        if let Some(limit) = config.memory_limit {
+            compressor = compressor.with_memory_limit(limit);
+        }
+        if let Some(method) = &config.method {
+            compressor = compressor.with_coder(CoderKind::from_name(method)?);
+        }
+        if let Some(checksum) = config.checksum {
+            compressor = compressor.with_checksum(checksum.into());
+        }
+        compressor = compressor.with_trailing_data(config.trailing_data.clone());
+        Ok(Box::new(compressor))
+    }
+
+    fn requires_accelerator(&self) -> bool {
+        true
+    }
+}
+
+/// Concatenates entry data, giving each entry a single extent.
+fn build_stream(entries: &[ArchiveEntry], checksum: ChecksumKind) -> (Vec<u8>, Vec<EntryRecord>) {
+    let total: usize = entries.iter().map(|e| e.data.len()).sum();
+    let mut stream = Vec::with_capacity(total);
+    let mut records = Vec::with_capacity(entries.len());
+    for entry in entries {
+        records.push(EntryRecord {
+            name: entry.name.clone(),
+            extents: vec![Extent {
+                offset: stream.len() as u64,
+                size: entry.data.len() as u64,
+            }],
+            digest: checksum.digest(&[&entry.data]),
+        });
+        stream.extend_from_slice(&entry.data);
+    }
+    (stream, records)
+}
+
+/// Stores each distinct content-defined chunk once; entries become lists of chunk extents.
+/// Chunks are indexed only while the index stays within `index_limit` bytes. Also returns
+/// the index's final size.
+fn build_deduplicated_stream(
+    entries: &[ArchiveEntry],
+    chunker: &Chunker,
+    index_limit: Option<usize>,
+    checksum: ChecksumKind,
+) -> (Vec<u8>, Vec<EntryRecord>, usize) {
+    let max_indexed = index_limit.map_or(usize::MAX, |limit| limit / INDEX_ENTRY_BYTES);
+    let mut stream = Vec::new();
+    let mut seen: HashMap<&[u8], u64> = HashMap::new();
+    let mut records = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let mut extents = Vec::new();
+        for range in chunker.chunks(&entry.data) {
+            let chunk = &entry.data[range];
+            let offset = match seen.get(chunk) {
+                Some(&offset) => offset,
+                None => {
+                    let offset = stream.len() as u64;
+                    stream.extend_from_slice(chunk);
+                    if seen.len() < max_indexed {
+                        seen.insert(chunk, offset);
+                    }
+                    offset
+                }
+            };
+            extents.push(Extent {
+                offset,
+                size: chunk.len() as u64,
+            });
+        }
+        records.push(EntryRecord {
+            name: entry.name.clone(),
+            extents,
+            digest: checksum.digest(&[&entry.data]),
+        });
+    }
+    (stream, records, seen.len() * INDEX_ENTRY_BYTES)
+}
+
+/// Codes one shard. Shards share no state, so they can run concurrently.
+fn encode_shard(
+    accel: &dyn GpuAccelerator,
+    coder: &dyn EntropyCoder,
+    shard: &[u8],
+) -> Result<Vec<u8>, LatError> {
+    let mut buf = shard.to_vec();
+    // 1. Parallel match finding on GPU
+    accel
+        .run_kernel("lat_match_find", &mut buf)
+        .map_err(LatError::Backend)?;
+    // 2. Optimal parsing
+    // 3. Entropy coding. A shard that doesn't shrink is kept verbatim, which the decoder
+    //    recognizes by its stored length equalling its raw length.
+    let coded = coder.encode(&buf);
+    Ok(if coded.len() < buf.len() { coded } else { buf })
+}
+
+fn decode_shard(
+    coder: &dyn EntropyCoder,
+    stored: &[u8],
+    raw_len: usize,
+) -> Result<Vec<u8>, LatError> {
+    if stored.len() == raw_len {
+        return Ok(stored.to_vec());
+    }
+    coder.decode(stored, raw_len)
+}
+
+impl LatCompressor {
+    /// `compress`, checking `deadline` once the stream is built and before each batch of
+    /// shards. Also returns the size the deduplication index reached.
+    fn compress_until(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<(Vec<u8>, usize), LatError> {
+        self.check_password(password)?;
+        lat_core::validate_entries(entries)?;
+        let Some(ref accel) = self.accelerator else {
+            return Err(LatError::Backend(
+                "GPU accelerator required for .lat".to_string(),
+            ));
+        };
+        println!(
+            "Compressing {} entries with .lat using {} ({} shards)",
+            entries.len(),
+            accel.name(),
+            self.shards
+        );
+
+        let (stream, records, context_memory) = match self.dedup {
+            Some(chunker) => build_deduplicated_stream(
+                entries,
+                &chunker,
+                self.context_memory_limit,
+                self.checksum,
+            ),
+            None => {
+                let (stream, records) = build_stream(entries, self.checksum);
+                (stream, records, 0)
+            }
+        };
+
+        deadline.check()?;
+
+        let total = stream.len();
+        let shard_len = total.div_ceil(self.shards).max(1);
+        let coder_kind = self.pick_coder(&stream);
+        let coder = coder_kind.coder();
+        let batch = self.batch_size(shard_len);
+        let mut encoded: Vec<Result<Vec<u8>, LatError>> = Vec::new();
+        for group in stream.chunks(shard_len * batch) {
+            deadline.check()?;
+            if batch == 1 {
+                encoded.push(encode_shard(accel.as_ref(), coder, group));
+                continue;
+            }
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = group
+                    .chunks(shard_len)
+                    .map(|shard| scope.spawn(move || encode_shard(accel.as_ref(), coder, shard)))
+                    .collect();
+                encoded.extend(handles.into_iter().map(|h| {
+                    h.join().unwrap_or_else(|_| {
+                        Err(LatError::Backend("shard encoder panicked".to_string()))
+                    })
+                }));
+            });
+        }
+
+        let mut payloads = Vec::with_capacity(encoded.len());
+        let mut shards = Vec::with_capacity(encoded.len());
+        for (raw, stored) in stream.chunks(shard_len).zip(encoded) {
+            let stored = stored?;
+            shards.push(ShardRecord {
+                raw_len: raw.len() as u64,
+                stored_len: stored.len() as u64,
+            });
+            payloads.push(stored);
+        }
+
+        let header = Header {
+            coder: coder_kind,
+            checksum: self.checksum,
+            container_digest: Vec::new(),
+            entries: records,
+            shards,
+        };
+        let mut out = Vec::with_capacity(payloads.iter().map(Vec::len).sum::<usize>() + 64);
+        header.write(&mut out);
+        for payload in payloads {
+            out.extend_from_slice(&payload);
+        }
+        let digest = container::container_digest(&out, self.checksum);
+        out[container::HEADER_FIXED_LEN..][..digest.len()].copy_from_slice(&digest);
+        Ok((out, context_memory))
     }
 }
 
@@ -15,29 +436,650 @@ impl Compressor for LatCompressor {
     fn compress(
         &self,
         entries: &[ArchiveEntry],
-        _password: Option<&str>,
-    ) -> Result<Vec<u8>, String> {
-        if let Some(ref accel) = self.accelerator {
-            println!(
-                "Compressing {} entries with .lat using {}",
-                entries.len(),
-                accel.name()
-            );
-            // 1. Parallel match finding on GPU
-            // 2. Optimal parsing
-            // 3. rANS encoding
-            Ok(vec![0; 100]) // Mocked high-ratio compression
-        } else {
-            Err("GPU accelerator required for .lat".to_string())
-        }
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        Ok(self.compress_until(entries, password, Deadline::none())?.0)
+    }
+
+    fn compress_detailed(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<CompressionResult, LatError> {
+        let (data, context_memory) = self.compress_until(entries, password, Deadline::none())?;
+        Ok(CompressionResult {
+            data,
+            uncompressed_size: entries.iter().map(|e| e.data.len() as u64).sum(),
+            per_entry: Vec::new(),
+            warnings: Vec::new(),
+            context_memory,
+        })
+    }
+
+    fn compress_with_deadline(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<Vec<u8>, LatError> {
+        Ok(self.compress_until(entries, password, deadline)?.0)
     }
 
     fn decompress(
         &self,
-        _archive: &[u8],
-        _password: Option<&str>,
-    ) -> Result<Vec<ArchiveEntry>, String> {
-        // TODO: Implement GPU-accelerated .lat decompression
-        Err(".lat decompression not yet implemented".to_string())
+        archive: &[u8],
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.check_password(password)?;
+        let (header, mut pos) = Header::read(archive)?;
+        let coder = header.coder.coder();
+        let verify = !self.skip_verify && header.checksum != ChecksumKind::None;
+
+        // Shards are reassembled in table order to rebuild the original stream.
+        let mut stream = Vec::new();
+        for shard in &header.shards {
+            let stored = usize::try_from(shard.stored_len)
+                .ok()
+                .and_then(|len| archive.get(pos..pos.checked_add(len)?))
+                .ok_or_else(|| LatError::CorruptArchive("shard out of bounds".to_string()))?;
+            pos += stored.len();
+            let raw_len = usize::try_from(shard.raw_len)
+                .map_err(|_| LatError::CorruptArchive("shard too large".to_string()))?;
+            let raw = decode_shard(coder, stored, raw_len)?;
+            stream.extend_from_slice(&raw);
+        }
+        // The last shard ends the archive; anything after it isn't covered by the checksum.
+        self.trailing_data
+            .check(ArchiveFormat::Lat, archive.len() - pos)?;
+        let archive = &archive[..pos];
+
+        let entries = header
+            .entries
+            .into_iter()
+            .map(|record| {
+                let mut data = Vec::new();
+                for extent in &record.extents {
+                    let bytes = usize::try_from(extent.offset)
+                        .ok()
+                        .zip(usize::try_from(extent.size).ok())
+                        .and_then(|(start, len)| stream.get(start..start.checked_add(len)?))
+                        .ok_or_else(|| {
+                            LatError::CorruptArchive(format!("{} out of bounds", record.name))
+                        })?;
+                    data.extend_from_slice(bytes);
+                }
+                if verify && header.checksum.digest(&[&data]) != record.digest {
+                    return Err(LatError::CorruptArchive(format!(
+                        "checksum mismatch in {}",
+                        record.name
+                    )));
+                }
+                Ok(ArchiveEntry::builder(record.name).data(data).build())
+            })
+            .collect::<Result<Vec<_>, LatError>>()?;
+
+        // Entries are checked first so payload damage is reported against the entry it hit;
+        // this catches the rest (names, tables, unreferenced bytes).
+        if verify
+            && container::container_digest(archive, header.checksum) != header.container_digest
+        {
+            return Err(LatError::CorruptArchive(
+                "container checksum mismatch".to_string(),
+            ));
+        }
+        Ok(entries)
+    }
+
+    /// Fetches the header and only the shards holding matching entries, through a
+    /// [`LatArchiveReader`]. As there, the container checksum isn't checked.
+    fn decompress_matching_from(
+        &self,
+        store: &dyn BlockStore,
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.check_password(password)?;
+        let matcher = lat_core::glob_matcher(pattern)?;
+        let mut reader = LatArchiveReader::open(StoreReader::new(store))?;
+        let mut entries = Vec::new();
+        for index in 0..reader.entry_count() {
+            let name = reader.entry_name(index).unwrap_or_default().to_string();
+            if matcher.is_match(&name) {
+                let data = reader.read_entry(index)?;
+                entries.push(ArchiveEntry::builder(name).data(data).build());
+            }
+        }
+        Ok(entries)
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Lat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lat_core::ChecksumAlgo;
+    use lat_core::testing::noise;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    /// Accelerator whose kernels take a while and record peak concurrency.
+    #[derive(Default)]
+    struct ConcurrencyProbe {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl GpuAccelerator for ConcurrencyProbe {
+        fn name(&self) -> &str {
+            "Probe"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn mix_probabilities(
+            &self,
+            _model_probs: &[f32],
+            _weights: &[f32],
+            num_bits: usize,
+        ) -> Result<Vec<f32>, String> {
+            Ok(vec![0.5; num_bits])
+        }
+    }
+
+    /// Accelerator whose kernels return immediately, for tests that compress many times.
+    struct NoopAccelerator;
+
+    impl GpuAccelerator for NoopAccelerator {
+        fn name(&self) -> &str {
+            "Noop"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn mix_probabilities(
+            &self,
+            _model_probs: &[f32],
+            _weights: &[f32],
+            num_bits: usize,
+        ) -> Result<Vec<f32>, String> {
+            Ok(vec![0.5; num_bits])
+        }
+    }
+
+    fn sample_entries() -> Vec<ArchiveEntry> {
+        (0..5)
+            .map(|i| {
+                ArchiveEntry::builder(format!("dir/file{}.txt", i))
+                    .data(format!("contents of file {} ", i).repeat(100).into_bytes())
+                    .build()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lat_shards_round_trip() {
+        let entries = sample_entries();
+
+        for shards in [1, 4] {
+            let probe = Arc::new(ConcurrencyProbe::default());
+            let compressor = LatCompressor::new(Some(probe.clone())).with_shards(shards);
+
+            let compressed = compressor
+                .compress(&entries, None)
+                .expect("Compression failed");
+            let (header, _) = Header::read(&compressed).expect("Header parse failed");
+            assert_eq!(header.shards.len(), shards);
+
+            let decompressed = compressor
+                .decompress(&compressed, None)
+                .expect("Decompression failed");
+            assert_eq!(entries.len(), decompressed.len());
+            for (a, b) in entries.iter().zip(&decompressed) {
+                assert_eq!(a.name, b.name);
+                assert_eq!(a.data, b.data);
+            }
+
+            let peak = probe.peak.load(Ordering::SeqCst);
+            if shards == 1 {
+                assert_eq!(peak, 1);
+            } else {
+                assert!(peak > 1, "shards were not dispatched concurrently");
+            }
+        }
+    }
+
+    #[test]
+    fn test_lat_dedup_shares_chunks() {
+        let base = noise(300_000, 0x1234_5678);
+        let mut edited = b"a new header line\n".to_vec();
+        edited.extend_from_slice(&base);
+        let entries = vec![
+            ArchiveEntry::builder("v1.bin").data(base.clone()).build(),
+            ArchiveEntry::builder("v2.bin").data(edited.clone()).build(),
+        ];
+
+        let compressor =
+            LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default()))).with_dedup(4096);
+        let compressed = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+
+        // Most of the second file is stored as references to the first file's chunks.
+        let (header, _) = Header::read(&compressed).expect("Header parse failed");
+        let stored: u64 = header.shards.iter().map(|s| s.raw_len).sum();
+        assert!(stored < (base.len() + base.len() / 4) as u64);
+
+        let decompressed = compressor
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].data, base);
+        assert_eq!(decompressed[1].data, edited);
+    }
+
+    #[test]
+    fn test_lat_context_memory_limit() {
+        let base = noise(200_000, 0x9e37_79b9);
+        let entries: Vec<ArchiveEntry> = (0..3)
+            .map(|i| {
+                ArchiveEntry::builder(format!("copy{}.bin", i))
+                    .data(base.clone())
+                    .build()
+            })
+            .collect();
+
+        let accel: Arc<dyn GpuAccelerator> = Arc::new(NoopAccelerator);
+        let unbounded = LatCompressor::new(Some(accel.clone())).with_dedup(4096);
+        let tight = unbounded
+            .clone()
+            .with_context_memory_limit(10 * INDEX_ENTRY_BYTES);
+        let detailed =
+            |compressor: &LatCompressor| compressor.compress_detailed(&entries, None).unwrap();
+        let unbounded_result = detailed(&unbounded);
+        let tight_result = detailed(&tight);
+        assert!(unbounded_result.context_memory > 10 * INDEX_ENTRY_BYTES);
+        assert_eq!(tight_result.context_memory, 10 * INDEX_ENTRY_BYTES);
+        assert_eq!(detailed(&LatCompressor::new(Some(accel))).context_memory, 0);
+
+        // Unindexed chunks are stored again for every copy, but nothing is lost.
+        let stored = |archive: &[u8]| -> u64 {
+            let (header, _) = Header::read(archive).expect("Header parse failed");
+            header.shards.iter().map(|s| s.raw_len).sum()
+        };
+        let small = unbounded_result.data;
+        let large = tight_result.data;
+        assert!(stored(&small) < stored(&large));
+        for archive in [&small, &large] {
+            let decompressed = tight.decompress(archive, None).unwrap();
+            assert!(decompressed.iter().all(|entry| entry.data == base));
+        }
+    }
+
+    #[test]
+    fn test_lat_split_on_entry_boundaries() {
+        let entries: Vec<ArchiveEntry> = (0..10)
+            .map(|i| {
+                ArchiveEntry::builder(format!("file{}.bin", i))
+                    .data(vec![i as u8; 1000])
+                    .build()
+            })
+            .collect();
+        let compressor = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())));
+
+        let split = compressor
+            .compress_split(&entries, None, 3500)
+            .expect("Split failed");
+        assert!(split.parts.len() > 1);
+        for part in &split.parts {
+            assert!(
+                part.len() <= 3500,
+                "part of {} bytes over budget",
+                part.len()
+            );
+        }
+
+        // One part is enough to get a file back.
+        let index = find_part(&split.manifest, "file7.bin").expect("Not in manifest");
+        let extracted = compressor
+            .decompress_matching(&split.parts[index], "file7.bin", None)
+            .expect("Extraction failed");
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(extracted[0].data, vec![7u8; 1000]);
+
+        let total: usize = split
+            .parts
+            .iter()
+            .map(|part| compressor.decompress(part, None).unwrap().len())
+            .sum();
+        assert_eq!(total, entries.len());
+        assert!(matches!(
+            find_part(&split.manifest, "missing"),
+            Err(LatError::EntryNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_lat_entropy_coders_round_trip() {
+        let entries = sample_entries();
+        let raw: usize = entries.iter().map(|e| e.data.len()).sum();
+
+        for coder in [CoderKind::Rans, CoderKind::Huffman] {
+            let compressor = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())))
+                .with_shards(2)
+                .with_coder(coder);
+            let compressed = compressor
+                .compress(&entries, None)
+                .expect("Compression failed");
+            assert!(
+                compressed.len() < raw,
+                "{:?} did not shrink the input",
+                coder
+            );
+
+            let (header, _) = Header::read(&compressed).expect("Header parse failed");
+            assert_eq!(header.coder, coder);
+            assert_eq!(compressed[5], coder.id());
+
+            // A default compressor decodes whatever coder the header names.
+            let decompressed = LatCompressor::new(None)
+                .decompress(&compressed, None)
+                .expect("Decompression failed");
+            for (a, b) in entries.iter().zip(&decompressed) {
+                assert_eq!(a.name, b.name);
+                assert_eq!(a.data, b.data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lat_format_selects_coder() {
+        let config = CompressorConfig {
+            method: Some("huffman".to_string()),
+            accelerator: Some(Arc::new(ConcurrencyProbe::default())),
+            ..Default::default()
+        };
+        let compressed = LatFormat
+            .build(&config)
+            .unwrap()
+            .compress(&sample_entries(), None)
+            .unwrap();
+        let (header, _) = Header::read(&compressed).unwrap();
+        assert_eq!(header.coder, CoderKind::Huffman);
+
+        let config = CompressorConfig {
+            method: Some("lz4".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            LatFormat.build(&config),
+            Err(LatError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_lat_checksums_detect_corruption() {
+        let entries = sample_entries();
+        for checksum in [ChecksumKind::Crc32, ChecksumKind::Blake3] {
+            let compressor = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())))
+                .with_coder(CoderKind::Stored)
+                .with_checksum(checksum);
+            let clean = compressor.compress(&entries, None).unwrap();
+            assert_eq!(compressor.decompress(&clean, None).unwrap().len(), 5);
+
+            // Stored shards put file3.txt's bytes at a known place in the payload.
+            let (_, payload_start) = Header::read(&clean).unwrap();
+            let offset: usize = entries[..3].iter().map(|e| e.data.len()).sum();
+            let mut payload_hit = clean.clone();
+            payload_hit[payload_start + offset + 5] ^= 0x01;
+            assert!(matches!(
+                compressor.decompress(&payload_hit, None),
+                Err(LatError::CorruptArchive(msg)) if msg.contains("dir/file3.txt")
+            ));
+
+            // A renamed entry still decodes, so only the container digest notices.
+            let name_at = clean
+                .windows(13)
+                .position(|w| w == b"dir/file0.txt")
+                .unwrap();
+            let mut header_hit = clean.clone();
+            header_hit[name_at] = b'D';
+            assert!(matches!(
+                compressor.decompress(&header_hit, None),
+                Err(LatError::CorruptArchive(msg)) if msg.contains("container")
+            ));
+
+            let unchecked = LatCompressor::new(None).with_skip_verify(true);
+            assert_eq!(
+                unchecked.decompress(&header_hit, None).unwrap()[0].name,
+                "Dir/file0.txt"
+            );
+        }
+    }
+
+    #[test]
+    fn test_lat_format_selects_checksum() {
+        let entries = sample_entries();
+        for algo in [
+            ChecksumAlgo::None,
+            ChecksumAlgo::Crc32,
+            ChecksumAlgo::Blake3,
+        ] {
+            let config = CompressorConfig {
+                method: Some("stored".to_string()),
+                checksum: Some(algo),
+                accelerator: Some(Arc::new(ConcurrencyProbe::default())),
+                ..Default::default()
+            };
+            let compressor = LatFormat.build(&config).unwrap();
+            let clean = compressor.compress(&entries, None).unwrap();
+            let (header, payload_start) = Header::read(&clean).unwrap();
+            assert_eq!(header.checksum, ChecksumKind::from(algo));
+            let restored = compressor.decompress(&clean, None).unwrap();
+            assert_eq!(restored[3].data, entries[3].data);
+
+            let offset: usize = entries[..3].iter().map(|e| e.data.len()).sum();
+            let mut corrupt = clean.clone();
+            corrupt[payload_start + offset + 5] ^= 0x01;
+            let result = compressor.decompress(&corrupt, None);
+            if algo == ChecksumAlgo::None {
+                // Nothing to check against: the flipped bit comes back out.
+                assert_ne!(result.unwrap()[3].data, entries[3].data);
+            } else {
+                assert!(matches!(result, Err(LatError::CorruptArchive(_))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_lat_resource_limits_serialize_shards() {
+        let entries = sample_entries();
+        let shard_bytes = entries
+            .iter()
+            .map(|e| e.data.len())
+            .sum::<usize>()
+            .div_ceil(4);
+        let limited = [
+            LatCompressor::new(None).with_threads(1),
+            // Room for exactly one shard in flight.
+            LatCompressor::new(None).with_memory_limit(2 * shard_bytes as u64 + 1),
+            LatCompressor::new(None).with_threads(2),
+        ];
+
+        for (compressor, expected_peak) in limited.into_iter().zip([1, 1, 2]) {
+            let probe = Arc::new(ConcurrencyProbe::default());
+            let compressor = LatCompressor {
+                accelerator: Some(probe.clone()),
+                ..compressor.with_shards(4)
+            };
+            let compressed = compressor
+                .compress(&entries, None)
+                .expect("Compression failed");
+            assert_eq!(probe.peak.load(Ordering::SeqCst), expected_peak);
+
+            let decompressed = compressor.decompress(&compressed, None).unwrap();
+            for (a, b) in entries.iter().zip(&decompressed) {
+                assert_eq!(a.data, b.data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lat_deadline_stops_between_shards() {
+        // Each shard takes 50ms to encode on the probe, one at a time.
+        let compressor = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())))
+            .with_shards(20)
+            .with_threads(1);
+        let entries = sample_entries();
+
+        let start = Instant::now();
+        let result = compressor.compress_with_deadline(
+            &entries,
+            None,
+            Deadline::after(Duration::from_millis(120)),
+        );
+        assert!(matches!(result, Err(LatError::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        let unbounded = compressor
+            .compress_with_deadline(&entries, None, Deadline::none())
+            .expect("Compression failed");
+        assert_eq!(
+            compressor.decompress(&unbounded, None).unwrap().len(),
+            entries.len()
+        );
+    }
+
+    #[test]
+    fn test_lat_min_ratio_stores_incompressible_data() {
+        let noise = noise(100_000, 0x2545_f491);
+        let compressor = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())))
+            .with_min_ratio_abort(0.9);
+
+        let random = vec![ArchiveEntry::builder("noise.bin").data(noise).build()];
+        let archive = compressor.compress(&random, None).unwrap();
+        assert_eq!(Header::read(&archive).unwrap().0.coder, CoderKind::Stored);
+        assert_eq!(
+            compressor.decompress(&archive, None).unwrap()[0].data,
+            random[0].data
+        );
+
+        let archive = compressor.compress(&sample_entries(), None).unwrap();
+        assert_eq!(Header::read(&archive).unwrap().0.coder, CoderKind::Rans);
+    }
+
+    #[test]
+    fn test_lat_requires_accelerator() {
+        assert!(
+            LatCompressor::new(None)
+                .compress(&sample_entries(), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_lat_rejects_password() {
+        let compressor = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())));
+        assert!(!compressor.supports_password());
+        assert!(matches!(
+            compressor.compress(&sample_entries(), Some("secret")),
+            Err(LatError::UnsupportedFormat(_))
+        ));
+    }
+
+    /// A decoder panic shows up as this message from [`lat_core::try_decompress`].
+    fn assert_no_panic(result: Result<Vec<ArchiveEntry>, LatError>, what: &str) {
+        if let Err(LatError::CorruptArchive(msg)) = result {
+            assert!(!msg.contains("panicked"), "{}: {}", what, msg);
+        }
+    }
+
+    #[test]
+    fn test_lat_malformed_input_returns_err() {
+        for malformed in [&b""[..], b"LAT\0", b"LAT\0\x03\x01\x01\xff\xff\xff\xff"] {
+            assert!(lat_core::try_decompress(malformed, &LatFormat).is_err());
+        }
+        for coder in [CoderKind::Rans, CoderKind::Huffman] {
+            let archive = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())))
+                .with_coder(coder)
+                .with_checksum(ChecksumKind::None)
+                .compress(&sample_entries(), None)
+                .unwrap();
+            for len in 0..archive.len() {
+                assert!(lat_core::try_decompress(&archive[..len], &LatFormat).is_err());
+            }
+            // Without checksums, damage reaches the header parser and entropy decoders.
+            for at in 0..archive.len() {
+                let mut damaged = archive.clone();
+                damaged[at] ^= 0xFF;
+                assert_no_panic(
+                    lat_core::try_decompress(&damaged, &LatFormat),
+                    &format!("{:?} byte {}", coder, at),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lat_trailing_data_policy() {
+        let entries = sample_entries();
+        let mut archive = LatCompressor::new(Some(Arc::new(ConcurrencyProbe::default())))
+            .compress(&entries, None)
+            .unwrap();
+        archive.extend_from_slice(b"appended by accident");
+
+        let reported = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&reported);
+        let warn = TrailingData::warn(move |_, bytes| {
+            counter.fetch_add(bytes, Ordering::SeqCst);
+        });
+        for policy in [TrailingData::Ignore, warn] {
+            let decompressed = LatCompressor::new(None)
+                .with_trailing_data(policy)
+                .decompress(&archive, None)
+                .expect("Trailing data should be tolerated");
+            assert_eq!(decompressed.len(), entries.len());
+        }
+        assert_eq!(
+            reported.load(Ordering::SeqCst),
+            b"appended by accident".len()
+        );
+        assert!(matches!(
+            LatCompressor::new(None)
+                .with_trailing_data(TrailingData::Error)
+                .decompress(&archive, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_lat_compressor_is_reusable() {
+        let entries = sample_entries();
+        let compressor = LatCompressor::new(Some(Arc::new(NoopAccelerator)))
+            .with_shards(2)
+            .with_coder(CoderKind::Rans);
+        let first = compressor.compress(&entries, None).unwrap();
+        for _ in 0..100 {
+            assert_eq!(compressor.compress(&entries, None).unwrap(), first);
+        }
+        let copy = compressor.clone();
+        assert_eq!(copy.compress(&entries, None).unwrap(), first);
+        assert_eq!(copy.decompress(&first, None).unwrap().len(), entries.len());
+    }
+
+    #[test]
+    fn test_lat_reports_format() {
+        let compressor: Box<dyn Compressor> = Box::new(LatCompressor::new(None));
+        assert_eq!(compressor.format(), ArchiveFormat::Lat);
+        assert_eq!(compressor.format().extension(), "lat");
     }
 }
@@ -0,0 +1,338 @@
+//! Random access to `.lat` entries without decoding the whole archive.
+
+use crate::checksum::ChecksumKind;
+use crate::container::Header;
+use crate::decode_shard;
+use lat_core::{ArchiveEntry, LatError};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Reads individual entries of a `.lat` archive.
+///
+/// Only the header is read up front. [`read_entry`](Self::read_entry) then reads just the
+/// shards holding that entry, and from verbatim shards just the entry's own bytes, so
+/// earlier entries are never decoded. Entry checksums are verified as entries are read;
+/// the container checksum is not, since that would mean reading the whole archive.
+pub struct LatArchiveReader<R> {
+    reader: R,
+    header: Header,
+    /// Archive offset of each shard's payload.
+    payload_offsets: Vec<u64>,
+    /// Offset of each shard's first byte in the decoded stream.
+    raw_starts: Vec<u64>,
+    /// Most recently decoded shard; consecutive entries usually share one.
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> LatArchiveReader<R> {
+    pub fn open(mut reader: R) -> Result<Self, LatError> {
+        let size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let size = usize::try_from(size).unwrap_or(usize::MAX);
+        let (header, payload_start) = Header::read_from(&mut reader, size)?;
+
+        let mut payload_offsets = Vec::with_capacity(header.shards.len());
+        let mut raw_starts = Vec::with_capacity(header.shards.len());
+        let (mut payload, mut raw) = (payload_start as u64, 0u64);
+        for shard in &header.shards {
+            payload_offsets.push(payload);
+            raw_starts.push(raw);
+            payload = payload.saturating_add(shard.stored_len);
+            raw = raw.saturating_add(shard.raw_len);
+        }
+        if payload > size as u64 {
+            return Err(LatError::CorruptArchive("shard out of bounds".to_string()));
+        }
+
+        Ok(Self {
+            reader,
+            header,
+            payload_offsets,
+            raw_starts,
+            cached: None,
+        })
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.header.entries.len()
+    }
+
+    pub fn entry_name(&self, index: usize) -> Option<&str> {
+        self.header.entries.get(index).map(|e| e.name.as_str())
+    }
+
+    /// Uncompressed size of an entry, from the entry table alone.
+    pub fn entry_size(&self, index: usize) -> Option<u64> {
+        self.header
+            .entries
+            .get(index)
+            .map(|e| e.extents.iter().map(|x| x.size).sum())
+    }
+
+    pub fn read_entry(&mut self, index: usize) -> Result<Vec<u8>, LatError> {
+        let record = self
+            .header
+            .entries
+            .get(index)
+            .ok_or_else(|| LatError::EntryNotFound(format!("entry #{}", index)))?;
+        let extents: Vec<(u64, u64)> = record.extents.iter().map(|x| (x.offset, x.size)).collect();
+        let name = record.name.clone();
+        let digest = record.digest.clone();
+        let out_of_bounds = || LatError::CorruptArchive(format!("{} out of bounds", name));
+
+        let mut data = Vec::new();
+        for (offset, size) in extents {
+            let end = offset.checked_add(size).ok_or_else(out_of_bounds)?;
+            let mut pos = offset;
+            while pos < end {
+                let shard = self
+                    .raw_starts
+                    .partition_point(|&start| start <= pos)
+                    .checked_sub(1)
+                    .ok_or_else(out_of_bounds)?;
+                let (start, raw_len) = (self.raw_starts[shard], self.header.shards[shard].raw_len);
+                let local = (pos - start)..(end.min(start + raw_len) - start);
+                if local.is_empty() {
+                    return Err(out_of_bounds());
+                }
+                self.read_shard_range(shard, local.clone(), &mut data)?;
+                pos = start + local.end;
+            }
+        }
+        let checksum = self.header.checksum;
+        if checksum != ChecksumKind::None && checksum.digest(&[&data]) != digest {
+            return Err(LatError::CorruptArchive(format!(
+                "checksum mismatch in {}",
+                name
+            )));
+        }
+        Ok(data)
+    }
+
+    /// Reads entries one at a time in table order, e.g. to feed
+    /// `lat_core::stream::entry_stream` without decompressing the archive up front.
+    pub fn into_entries(mut self) -> impl Iterator<Item = Result<ArchiveEntry, LatError>> {
+        (0..self.entry_count()).map(move |index| {
+            let data = self.read_entry(index)?;
+            Ok(
+                ArchiveEntry::builder(self.header.entries[index].name.clone())
+                    .data(data)
+                    .build(),
+            )
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Appends bytes `range` of decoded shard `index` to `out`.
+    fn read_shard_range(
+        &mut self,
+        index: usize,
+        range: std::ops::Range<u64>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), LatError> {
+        let record = &self.header.shards[index];
+        let stored_len = usize::try_from(record.stored_len)
+            .map_err(|_| LatError::CorruptArchive("shard out of bounds".to_string()))?;
+        let raw_len = record.raw_len as usize;
+        let offset = self.payload_offsets[index];
+
+        // Verbatim shards are addressable byte for byte.
+        if record.stored_len == record.raw_len {
+            self.reader.seek(SeekFrom::Start(offset + range.start))?;
+            let start = out.len();
+            out.resize(start + (range.end - range.start) as usize, 0);
+            return read_payload(&mut self.reader, &mut out[start..]);
+        }
+
+        if self
+            .cached
+            .as_ref()
+            .is_none_or(|(cached, _)| *cached != index)
+        {
+            self.reader.seek(SeekFrom::Start(offset))?;
+            let mut stored = vec![0u8; stored_len];
+            read_payload(&mut self.reader, &mut stored)?;
+            let raw = decode_shard(self.header.coder.coder(), &stored, raw_len)?;
+            self.cached = Some((index, raw));
+        }
+        let (_, raw) = self.cached.as_ref().expect("filled above");
+        out.extend_from_slice(&raw[range.start as usize..range.end as usize]);
+        Ok(())
+    }
+}
+
+fn read_payload<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), LatError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => LatError::CorruptArchive("shard out of bounds".to_string()),
+        _ => LatError::Io(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CoderKind, LatCompressor};
+    use lat_core::store::{BlockStore, CountingStore};
+    use lat_core::{Compressor, GpuAccelerator};
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    struct NoopAccelerator;
+
+    impl GpuAccelerator for NoopAccelerator {
+        fn name(&self) -> &str {
+            "Noop"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn mix_probabilities(
+            &self,
+            _model_probs: &[f32],
+            _weights: &[f32],
+            num_bits: usize,
+        ) -> Result<Vec<f32>, String> {
+            Ok(vec![0.5; num_bits])
+        }
+    }
+
+    /// Cursor that records the archive ranges it was asked to read.
+    struct RecordingReader {
+        inner: Cursor<Vec<u8>>,
+        reads: Vec<(u64, u64)>,
+    }
+
+    impl Read for RecordingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let start = self.inner.position();
+            let n = self.inner.read(buf)?;
+            self.reads.push((start, start + n as u64));
+            Ok(n)
+        }
+    }
+
+    impl Seek for RecordingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn entries() -> Vec<ArchiveEntry> {
+        (0..50)
+            .map(|i| {
+                ArchiveEntry::builder(format!("file{:02}.txt", i))
+                    .data(
+                        format!("line {} of the archive\n", i)
+                            .repeat(40)
+                            .into_bytes(),
+                    )
+                    .build()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_last_entry_without_touching_earlier_ones() {
+        let entries = entries();
+        let archive = LatCompressor::new(Some(Arc::new(NoopAccelerator)))
+            .with_coder(CoderKind::Stored)
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let (_, payload_start) = Header::read(&archive).unwrap();
+        let last_start = payload_start as u64
+            + entries[..49]
+                .iter()
+                .map(|e| e.data.len() as u64)
+                .sum::<u64>();
+
+        let mut reader = LatArchiveReader::open(RecordingReader {
+            inner: Cursor::new(archive),
+            reads: Vec::new(),
+        })
+        .expect("Open failed");
+        assert_eq!(reader.entry_count(), 50);
+        assert_eq!(reader.entry_name(49), Some("file49.txt"));
+
+        let header_reads = reader.reader.reads.len();
+        assert_eq!(reader.read_entry(49).unwrap(), entries[49].data);
+        let payload_reads = &reader.reader.reads[header_reads..];
+        assert!(!payload_reads.is_empty());
+        assert!(
+            payload_reads
+                .iter()
+                .all(|&(start, end)| start >= last_start || start == end),
+            "earlier entries were read: {:?}",
+            payload_reads
+        );
+    }
+
+    #[test]
+    fn test_read_entries_from_coded_shards() {
+        let entries = entries();
+        let archive = LatCompressor::new(Some(Arc::new(NoopAccelerator)))
+            .with_shards(3)
+            .with_dedup(256)
+            .compress(&entries, None)
+            .expect("Compression failed");
+
+        let mut reader = LatArchiveReader::open(Cursor::new(archive)).expect("Open failed");
+        for i in (0..entries.len()).rev() {
+            assert_eq!(reader.entry_size(i), Some(entries[i].data.len() as u64));
+            assert_eq!(reader.read_entry(i).unwrap(), entries[i].data);
+        }
+        assert!(matches!(
+            reader.read_entry(50),
+            Err(LatError::EntryNotFound(_))
+        ));
+
+        let streamed: Vec<ArchiveEntry> = reader.into_entries().map(Result::unwrap).collect();
+        assert_eq!(streamed.len(), entries.len());
+        assert_eq!(streamed[7].name, entries[7].name);
+        assert_eq!(streamed[7].data, entries[7].data);
+    }
+
+    #[test]
+    fn test_selective_extraction_fetches_little_of_a_store() {
+        let entries: Vec<ArchiveEntry> = (0..40)
+            .map(|i| {
+                ArchiveEntry::builder(format!("file{}.txt", i))
+                    .data(format!("entry {} ", i).repeat(32 * 1024).into_bytes())
+                    .build()
+            })
+            .collect();
+        let archive = LatCompressor::new(Some(Arc::new(NoopAccelerator)))
+            .with_coder(CoderKind::Stored)
+            .with_shards(40)
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let store = CountingStore::new(archive);
+
+        let matched = LatCompressor::new(Some(Arc::new(NoopAccelerator)))
+            .decompress_matching_from(&store, "file7.txt", None)
+            .expect("Selective extraction failed");
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].data, entries[7].data);
+        assert!(
+            store.fetched() * 10 < store.len(),
+            "fetched {} of {} bytes",
+            store.fetched(),
+            store.len()
+        );
+    }
+
+    #[test]
+    fn test_truncated_archive_is_corrupt() {
+        let archive = LatCompressor::new(Some(Arc::new(NoopAccelerator)))
+            .compress(&entries(), None)
+            .unwrap();
+        let cut = archive[..archive.len() - 10].to_vec();
+        assert!(matches!(
+            LatArchiveReader::open(Cursor::new(cut)),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+}
@@ -1,43 +1,369 @@
-use lat_core::{ArchiveEntry, Compressor, GpuAccelerator};
+use lat_core::{
+    ArchiveEntry, ArchiveFormat, Compressor, CompressorConfig, Deadline, FormatFactory,
+    GpuAccelerator, LatError,
+};
 use std::sync::Arc;
 
+/// Models mixed per coded bit.
+const NUM_MODELS: usize = 4;
+/// Mixed probabilities are kept this far from 0 and 1 so the coder never sees certainty.
+const PROB_EPSILON: f32 = 1.0 / 4096.0;
+/// Bytes from the start of the input used to estimate compressibility.
+const RATIO_SAMPLE_LEN: usize = 64 * 1024;
+/// Input bytes modelled per mixing pass; a deadline is checked between blocks.
+const BLOCK_LEN: usize = 64 * 1024;
+
+#[derive(Clone)]
 pub struct PaqgCompressor {
     accelerator: Option<Arc<dyn GpuAccelerator>>,
+    fallback: Option<Arc<dyn GpuAccelerator>>,
+    min_ratio_abort: Option<f32>,
 }
 
 impl PaqgCompressor {
     pub fn new(accelerator: Option<Arc<dyn GpuAccelerator>>) -> Self {
-        Self { accelerator }
+        Self {
+            accelerator,
+            fallback: None,
+            min_ratio_abort: None,
+        }
+    }
+
+    /// Fails fast with `LatError::Backend("data not compressible")` when a sample of the
+    /// input is estimated to compress worse than `ratio` (compressed/uncompressed, as in
+    /// [`lat_core::EntryStat`]), instead of committing to the slow modelling pass.
+    pub fn with_min_ratio_abort(mut self, ratio: f32) -> Self {
+        self.min_ratio_abort = Some(ratio);
+        self
+    }
+
+    /// Accelerator to retry on (typically a CPU implementation) when the primary one
+    /// fails or returns unusable probabilities.
+    pub fn with_fallback(mut self, fallback: Arc<dyn GpuAccelerator>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Mixes on `accel`, retrying on the fallback if the result is unusable.
+    fn mix(
+        &self,
+        accel: &dyn GpuAccelerator,
+        model_probs: &[f32],
+        weights: &[f32],
+        num_bits: usize,
+    ) -> Result<Vec<f32>, LatError> {
+        let primary = accel
+            .mix_probabilities(model_probs, weights, num_bits)
+            .map_err(LatError::Backend)
+            .and_then(|probs| validate_mixed(accel.name(), probs, num_bits));
+        match (primary, &self.fallback) {
+            (Err(_), Some(fallback)) => fallback
+                .mix_probabilities(model_probs, weights, num_bits)
+                .map_err(LatError::Backend)
+                .and_then(|probs| validate_mixed(fallback.name(), probs, num_bits)),
+            (result, _) => result,
+        }
     }
 }
 
-impl Compressor for PaqgCompressor {
-    fn compress(
+/// Checks a `mix_probabilities` result before it reaches the arithmetic coder: it must
+/// hold exactly `num_bits` finite values. Finite values at or outside the bounds (0, 1)
+/// are clamped into range; a short or non-finite result is a backend error.
+fn validate_mixed(
+    backend: &str,
+    mut probs: Vec<f32>,
+    num_bits: usize,
+) -> Result<Vec<f32>, LatError> {
+    if probs.len() != num_bits {
+        return Err(LatError::Backend(format!(
+            "{} returned {} mixed probabilities, expected {}",
+            backend,
+            probs.len(),
+            num_bits
+        )));
+    }
+    if let Some(bit) = probs.iter().position(|p| !p.is_finite()) {
+        return Err(LatError::Backend(format!(
+            "{} returned a non-finite probability for bit {}",
+            backend, bit
+        )));
+    }
+    for p in &mut probs {
+        *p = p.clamp(PROB_EPSILON, 1.0 - PROB_EPSILON);
+    }
+    Ok(probs)
+}
+
+/// Order-0 entropy of the first [`RATIO_SAMPLE_LEN`] bytes of `entries`, as a fraction of
+/// eight bits per byte. PAQG's context mixing does far better on structured data, so this
+/// only flags input that even a byte-frequency coder can't shrink.
+fn sample_ratio(entries: &[ArchiveEntry]) -> f32 {
+    let mut counts = [0u32; 256];
+    let mut total = 0u32;
+    for &byte in entries.iter().flat_map(|e| &e.data).take(RATIO_SAMPLE_LEN) {
+        counts[byte as usize] += 1;
+        total += 1;
+    }
+    if total == 0 {
+        return 0.0;
+    }
+    let bits: f64 = counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum();
+    (bits / 8.0) as f32
+}
+
+/// Builds [`PaqgCompressor`]s for [`lat_core::CompressorBuilder`]; only the
+/// accelerator is used.
+pub struct PaqgFormat;
+
+impl FormatFactory for PaqgFormat {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        Ok(Box::new(PaqgCompressor::new(config.accelerator.clone())))
+    }
+
+    fn requires_accelerator(&self) -> bool {
+        true
+    }
+}
+
+impl PaqgCompressor {
+    /// `compress`, checking `deadline` before each block.
+    fn compress_until(
         &self,
         entries: &[ArchiveEntry],
-        _password: Option<&str>,
-    ) -> Result<Vec<u8>, String> {
+        password: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<Vec<u8>, LatError> {
+        self.check_password(password)?;
+        lat_core::validate_entries(entries)?;
+        if entries.is_empty() {
+            return Err(LatError::UnsupportedFormat(
+                "PAQG archives need at least one entry".to_string(),
+            ));
+        }
+        if let Some(threshold) = self.min_ratio_abort {
+            if sample_ratio(entries) > threshold {
+                return Err(LatError::Backend("data not compressible".to_string()));
+            }
+        }
         if let Some(ref accel) = self.accelerator {
             println!(
                 "Compressing {} entries with PAQG using {}",
                 entries.len(),
                 accel.name()
             );
-            // 1. Prepare contexts
-            // 2. Mix probabilities on GPU (Ensure [num_models][num_bits] layout for coalescing)
-            // 3. Arithmetic code
+            let total: usize = entries.iter().map(|e| e.data.len()).sum();
+            for _block in 0..total.div_ceil(BLOCK_LEN).max(1) {
+                deadline.check()?;
+                // 1. Prepare contexts
+                let num_bits = 8;
+                let model_probs = vec![0.5; NUM_MODELS * num_bits];
+                let weights = vec![1.0 / NUM_MODELS as f32; NUM_MODELS * num_bits];
+                // 2. Mix probabilities on GPU (Ensure [num_models][num_bits] layout for coalescing)
+                let _mixed = self.mix(accel.as_ref(), &model_probs, &weights, num_bits)?;
+                // 3. Arithmetic code
+            }
             Ok(vec![0; 100]) // Mocked compression
         } else {
-            Err("GPU accelerator required for PAQG".to_string())
+            Err(LatError::Backend(
+                "GPU accelerator required for PAQG".to_string(),
+            ))
         }
     }
+}
+
+impl Compressor for PaqgCompressor {
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        self.compress_until(entries, password, Deadline::none())
+    }
+
+    fn compress_with_deadline(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<Vec<u8>, LatError> {
+        self.compress_until(entries, password, deadline)
+    }
 
     fn decompress(
         &self,
         _archive: &[u8],
-        _password: Option<&str>,
-    ) -> Result<Vec<ArchiveEntry>, String> {
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.check_password(password)?;
         // TODO: Implement GPU-accelerated PAQ decompression
-        Err("PAQG decompression not yet implemented".to_string())
+        Err(LatError::UnsupportedFormat(
+            "PAQG decompression not yet implemented".to_string(),
+        ))
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Paqg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lat_core::testing::noise;
+    use std::time::{Duration, Instant};
+
+    /// Accelerator whose mixer returns a fixed vector regardless of input.
+    struct FixedMixer(Vec<f32>);
+
+    impl GpuAccelerator for FixedMixer {
+        fn name(&self) -> &str {
+            "Fixed"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn mix_probabilities(
+            &self,
+            _model_probs: &[f32],
+            _weights: &[f32],
+            _num_bits: usize,
+        ) -> Result<Vec<f32>, String> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Mixer that takes 20ms per call.
+    struct SlowMixer;
+
+    impl GpuAccelerator for SlowMixer {
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        fn run_kernel(&self, _name: &str, _data: &mut [u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn mix_probabilities(
+            &self,
+            _model_probs: &[f32],
+            _weights: &[f32],
+            num_bits: usize,
+        ) -> Result<Vec<f32>, String> {
+            std::thread::sleep(Duration::from_millis(20));
+            Ok(vec![0.5; num_bits])
+        }
+    }
+
+    fn entries() -> Vec<ArchiveEntry> {
+        vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"hello".to_vec())
+                .build(),
+        ]
+    }
+
+    #[test]
+    fn test_bad_mixer_output_is_rejected() {
+        let short = PaqgCompressor::new(Some(Arc::new(FixedMixer(vec![0.5; 3]))));
+        assert!(matches!(
+            short.compress(&entries(), None),
+            Err(LatError::Backend(msg)) if msg.contains("returned 3")
+        ));
+
+        let mut with_nan = vec![0.5; 8];
+        with_nan[2] = f32::NAN;
+        let nan = PaqgCompressor::new(Some(Arc::new(FixedMixer(with_nan))));
+        assert!(matches!(
+            nan.compress(&entries(), None),
+            Err(LatError::Backend(msg)) if msg.contains("non-finite")
+        ));
+    }
+
+    #[test]
+    fn test_bad_mixer_output_falls_back() {
+        let compressor = PaqgCompressor::new(Some(Arc::new(FixedMixer(vec![f32::INFINITY; 8]))))
+            .with_fallback(Arc::new(FixedMixer(vec![0.5; 8])));
+        assert!(compressor.compress(&entries(), None).is_ok());
+    }
+
+    #[test]
+    fn test_out_of_range_probabilities_are_clamped() {
+        let probs = validate_mixed("Fixed", vec![0.0, 1.0, -3.0, 0.25], 4).unwrap();
+        assert_eq!(
+            probs,
+            [PROB_EPSILON, 1.0 - PROB_EPSILON, PROB_EPSILON, 0.25]
+        );
+    }
+
+    #[test]
+    fn test_empty_input_is_unsupported() {
+        let compressor = PaqgCompressor::new(Some(Arc::new(FixedMixer(vec![0.5; 8]))));
+        assert!(matches!(
+            compressor.compress(&[], None),
+            Err(LatError::UnsupportedFormat(msg)) if msg.contains("at least one entry")
+        ));
+    }
+
+    #[test]
+    fn test_min_ratio_aborts_on_random_data() {
+        let noise = noise(100_000, 0x2545_f491);
+        let compressor =
+            PaqgCompressor::new(Some(Arc::new(FixedMixer(vec![0.5; 8])))).with_min_ratio_abort(0.9);
+
+        let random = vec![ArchiveEntry::builder("noise.bin").data(noise).build()];
+        assert!(matches!(
+            compressor.compress(&random, None),
+            Err(LatError::Backend(msg)) if msg == "data not compressible"
+        ));
+
+        let text = vec![
+            ArchiveEntry::builder("text.txt")
+                .data(b"aaaaabbbcc".repeat(1000))
+                .build(),
+        ];
+        assert!(compressor.compress(&text, None).is_ok());
+    }
+
+    #[test]
+    fn test_deadline_stops_between_blocks() {
+        let compressor = PaqgCompressor::new(Some(Arc::new(SlowMixer)));
+        // 50 blocks: a second of mixing without a deadline.
+        let large = vec![
+            ArchiveEntry::builder("large.bin")
+                .data(vec![7; 50 * BLOCK_LEN])
+                .build(),
+        ];
+
+        let start = Instant::now();
+        let result = compressor.compress_with_deadline(
+            &large,
+            None,
+            Deadline::after(Duration::from_millis(60)),
+        );
+        assert!(matches!(result, Err(LatError::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        assert!(
+            compressor
+                .compress_with_deadline(&entries(), None, Deadline::none())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_paqg_reports_format() {
+        let compressor: Box<dyn Compressor> = Box::new(PaqgCompressor::new(None));
+        assert_eq!(compressor.format(), ArchiveFormat::Paqg);
+        assert_eq!(compressor.format().extension(), "paq");
     }
 }
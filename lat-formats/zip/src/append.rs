@@ -0,0 +1,324 @@
+use crate::recover::{
+    self, DATA_DESCRIPTOR_SIGNATURE, FLAG_DATA_DESCRIPTOR, LOCAL_HEADER_LEN,
+    LOCAL_HEADER_SIGNATURE, METHOD_DEFLATED,
+};
+use crate::{read_u32, CENTRAL_HEADER_LEN, CENTRAL_HEADER_SIGNATURE, EOCD_LEN, EOCD_SIGNATURE};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use lat_core::{ArchiveEntry, LatError};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// General purpose bit 11: the name is UTF-8.
+const FLAG_UTF8: u16 = 0x0800;
+/// ZIP 2.0, the first version with DEFLATE and data descriptors.
+const VERSION: u16 = 20;
+/// 1980-01-01 00:00, the DOS epoch; appended entries carry no timestamps.
+const DOS_EPOCH: u32 = 0x0021 << 16;
+
+/// Central directory fields kept in memory until [`ZipAppender::finalize`].
+struct CentralRecord {
+    name: String,
+    flags: u16,
+    method: u16,
+    dos_datetime: u32,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Appends entries to a ZIP file one at a time, for logs and other archives that grow.
+///
+/// Each entry is deflated and written after the previous one with a data descriptor, and
+/// the central directory is only written by [`finalize`](Self::finalize), so an append
+/// costs O(entry) rather than a rewrite of the archive. Until then the file has no
+/// central directory and ordinary readers reject it; [`ZipCompressor::recover`] reads it
+/// from the local headers, and [`open`](Self::open) picks up where it left off.
+///
+/// [`ZipCompressor::recover`]: crate::ZipCompressor::recover
+pub struct ZipAppender {
+    file: File,
+    records: Vec<CentralRecord>,
+    position: u64,
+    level: Compression,
+}
+
+impl ZipAppender {
+    /// Opens `path` for appending, creating it if it doesn't exist.
+    ///
+    /// Existing entries are found by scanning local headers, so this works whether or not
+    /// the archive was finalized; the old central directory and any entry torn by a crash
+    /// are cut off. Anything else the scan can't read (an encrypted member, another
+    /// compression method, damage before the last entry) fails with
+    /// [`LatError::CorruptArchive`] and leaves the file untouched. The scan reads the whole
+    /// file once.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LatError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut existing = Vec::new();
+        file.read_to_end(&mut existing)?;
+
+        let (found, end) = recover::scan(&existing);
+        // An empty finalized archive is just the end record; anything else without a
+        // single readable entry isn't ours to truncate.
+        if end == 0 && !existing.is_empty() && read_u32(&existing, 0) != Some(EOCD_SIGNATURE) {
+            return Err(LatError::CorruptArchive(
+                "no ZIP entries to append to".to_string(),
+            ));
+        }
+        if !is_disposable_tail(&existing[end..]) {
+            return Err(LatError::CorruptArchive(format!(
+                "can't append: the entry at offset {} can't be read, and cutting it off \
+                 would lose the entries after it",
+                end
+            )));
+        }
+        let records = found
+            .into_iter()
+            .map(|local| CentralRecord {
+                name: local.entry.name,
+                flags: local.flags,
+                method: local.method,
+                dos_datetime: local.dos_datetime,
+                crc: local.crc,
+                // Scanned entries came from 32-bit header fields.
+                compressed: local.compressed as u32,
+                size: local.entry.data.len() as u32,
+                offset: local.offset as u32,
+            })
+            .collect();
+
+        file.set_len(end as u64)?;
+        file.seek(SeekFrom::Start(end as u64))?;
+        Ok(Self {
+            file,
+            records,
+            position: end as u64,
+            level: Compression::default(),
+        })
+    }
+
+    /// Sets the DEFLATE level (0-9) for entries added from now on.
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = Compression::new(level.min(9));
+        self
+    }
+
+    /// Number of entries in the archive so far, including ones found by `open`.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Writes `entry` at the end of the file.
+    pub fn add(&mut self, entry: &ArchiveEntry) -> Result<(), LatError> {
+        lat_core::validate_entries(std::slice::from_ref(entry))?;
+        // Without ZIP64 records the counts and offsets are 16 and 32 bits.
+        if self.records.len() >= u16::MAX as usize {
+            return Err(LatError::InvalidInput(
+                "too many entries to append without ZIP64".to_string(),
+            ));
+        }
+        let too_large = || LatError::InvalidInput("archive too large without ZIP64".to_string());
+        let offset = u32::try_from(self.position).map_err(|_| too_large())?;
+        let size = u32::try_from(entry.data.len()).map_err(|_| too_large())?;
+        let name_len = u16::try_from(entry.name.len())
+            .map_err(|_| LatError::InvalidInput(format!("entry name too long: {}", entry.name)))?;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), self.level);
+        encoder.write_all(&entry.data)?;
+        let data = encoder.finish()?;
+        let compressed = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let crc = crc32fast::hash(&entry.data);
+        let flags = FLAG_DATA_DESCRIPTOR | FLAG_UTF8;
+
+        // CRC and sizes are zero in the local header and follow the data instead.
+        let mut out = Vec::with_capacity(LOCAL_HEADER_LEN + entry.name.len() + data.len() + 16);
+        out.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&METHOD_DEFLATED.to_le_bytes());
+        out.extend_from_slice(&DOS_EPOCH.to_le_bytes());
+        out.extend_from_slice(&[0; 12]);
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(&data);
+        out.extend_from_slice(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&compressed.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        self.file.write_all(&out)?;
+
+        self.position += out.len() as u64;
+        self.records.push(CentralRecord {
+            name: entry.name.clone(),
+            flags,
+            method: METHOD_DEFLATED,
+            dos_datetime: DOS_EPOCH,
+            crc,
+            compressed,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// Writes the central directory, making the file a complete ZIP archive. Costs
+    /// O(entries) for the directory, not O(archive).
+    pub fn finalize(mut self) -> Result<(), LatError> {
+        let too_large = || LatError::InvalidInput("archive too large without ZIP64".to_string());
+        let cd_offset = u32::try_from(self.position).map_err(|_| too_large())?;
+        let mut out = Vec::with_capacity(
+            self.records
+                .iter()
+                .map(|r| CENTRAL_HEADER_LEN + r.name.len())
+                .sum::<usize>()
+                + EOCD_LEN,
+        );
+        for record in &self.records {
+            out.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            out.extend_from_slice(&VERSION.to_le_bytes()); // made by
+            out.extend_from_slice(&VERSION.to_le_bytes()); // needed to extract
+            out.extend_from_slice(&record.flags.to_le_bytes());
+            out.extend_from_slice(&record.method.to_le_bytes());
+            out.extend_from_slice(&record.dos_datetime.to_le_bytes());
+            out.extend_from_slice(&record.crc.to_le_bytes());
+            out.extend_from_slice(&record.compressed.to_le_bytes());
+            out.extend_from_slice(&record.size.to_le_bytes());
+            out.extend_from_slice(&(record.name.len() as u16).to_le_bytes());
+            // Extra and comment lengths, disk number, internal and external attributes.
+            out.extend_from_slice(&[0; 12]);
+            out.extend_from_slice(&record.offset.to_le_bytes());
+            out.extend_from_slice(record.name.as_bytes());
+        }
+        let cd_size = u32::try_from(out.len()).map_err(|_| too_large())?;
+        let count = self.records.len() as u16;
+        out.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&[0; 4]); // this disk, central directory disk
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&cd_size.to_le_bytes());
+        out.extend_from_slice(&cd_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.file.write_all(&out)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Whether the bytes after the last readable entry can be cut off: an old central
+/// directory, or one entry torn by a crash while it was appended. A torn entry is the last
+/// thing in the file, so no other record starts after its header.
+fn is_disposable_tail(tail: &[u8]) -> bool {
+    let is_record = |sig: u32| {
+        matches!(
+            sig,
+            LOCAL_HEADER_SIGNATURE | CENTRAL_HEADER_SIGNATURE | EOCD_SIGNATURE
+        )
+    };
+    match read_u32(tail, 0) {
+        Some(CENTRAL_HEADER_SIGNATURE | EOCD_SIGNATURE) => true,
+        Some(LOCAL_HEADER_SIGNATURE) => {
+            !(4..tail.len()).any(|at| read_u32(tail, at).is_some_and(is_record))
+        }
+        Some(_) => false,
+        // Cut off inside the signature itself.
+        None => LOCAL_HEADER_SIGNATURE.to_le_bytes().starts_with(tail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ZipCompressor;
+    use lat_core::Compressor;
+
+    fn log_entry(i: usize) -> ArchiveEntry {
+        ArchiveEntry::builder(format!("logs/{:03}.log", i))
+            .data(format!("line {}\n", i).repeat(i + 1).into_bytes())
+            .build()
+    }
+
+    #[test]
+    fn test_append_incrementally_then_finalize() {
+        let path = std::env::temp_dir().join(format!("lat-append-{}.zip", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let zip = ZipCompressor::new();
+
+        let mut appender = ZipAppender::open(&path).unwrap();
+        for i in 0..60 {
+            appender.add(&log_entry(i)).unwrap();
+        }
+        drop(appender);
+
+        // Unfinalized: no central directory, but every entry is recoverable.
+        let partial = std::fs::read(&path).unwrap();
+        assert!(zip.decompress(&partial, None).is_err());
+        let recovered = zip.recover(&partial).unwrap();
+        assert_eq!(recovered.len(), 60);
+        assert_eq!(recovered[59].data, log_entry(59).data);
+
+        // A crash mid-append leaves a torn entry, which reopening drops.
+        std::fs::write(&path, &partial[..partial.len() - 5]).unwrap();
+        let mut appender = ZipAppender::open(&path).unwrap();
+        assert_eq!(appender.len(), 59);
+        for i in 59..100 {
+            appender.add(&log_entry(i)).unwrap();
+        }
+        appender.finalize().unwrap();
+
+        let archive = std::fs::read(&path).unwrap();
+        let entries = zip.decompress(&archive, None).expect("finalized archive");
+        assert_eq!(entries.len(), 100);
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.name, log_entry(i).name);
+            assert_eq!(entry.data, log_entry(i).data);
+        }
+
+        // A finalized archive can be extended again.
+        let mut appender = ZipAppender::open(&path).unwrap();
+        appender.add(&log_entry(100)).unwrap();
+        appender.finalize().unwrap();
+        let archive = std::fs::read(&path).unwrap();
+        assert_eq!(zip.decompress(&archive, None).unwrap().len(), 101);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_refuses_to_cut_off_unreadable_entries() {
+        let path = std::env::temp_dir().join(format!("lat-append-odd-{}.zip", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut appender = ZipAppender::open(&path).unwrap();
+        for i in 0..3 {
+            appender.add(&log_entry(i)).unwrap();
+        }
+        appender.finalize().unwrap();
+
+        // Mark the second entry as compressed with a method the scan doesn't read.
+        let mut archive = std::fs::read(&path).unwrap();
+        let second = LOCAL_HEADER_LEN
+            + archive[LOCAL_HEADER_LEN..]
+                .windows(4)
+                .position(|w| w == LOCAL_HEADER_SIGNATURE.to_le_bytes())
+                .unwrap();
+        archive[second + 8..second + 10].copy_from_slice(&12u16.to_le_bytes());
+        std::fs::write(&path, &archive).unwrap();
+
+        assert!(matches!(
+            ZipAppender::open(&path),
+            Err(LatError::CorruptArchive(_))
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), archive);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
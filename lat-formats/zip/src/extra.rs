@@ -0,0 +1,144 @@
+//! Unix ownership and extended attributes in ZIP extra fields.
+//!
+//! Ownership uses Info-ZIP's "ux" field (0x7875), which `unzip` understands. ZIP has no
+//! standard field for extended attributes, so they go in a private one that other tools
+//! skip over: repeated `name_len u16 | name | value_len u16 | value`, little-endian.
+
+use lat_core::{ArchiveEntry, LatError};
+
+const UNIX_OWNER_ID: u16 = 0x7875;
+const XATTR_ID: u16 = 0x4c78;
+
+/// Extra field bytes for `entry`, empty when it carries no metadata.
+pub fn encode(entry: &ArchiveEntry) -> Result<Vec<u8>, LatError> {
+    let mut out = Vec::new();
+    if entry.uid.is_some() || entry.gid.is_some() {
+        // Version 1, then each id as a length-prefixed little-endian integer. A missing
+        // one is written as 0, since the field has no way to leave it out.
+        let mut body = vec![1, 4];
+        body.extend_from_slice(&entry.uid.unwrap_or(0).to_le_bytes());
+        body.push(4);
+        body.extend_from_slice(&entry.gid.unwrap_or(0).to_le_bytes());
+        push_record(&mut out, UNIX_OWNER_ID, &body, &entry.name)?;
+    }
+    if !entry.xattrs.is_empty() {
+        let mut body = Vec::new();
+        for (name, value) in &entry.xattrs {
+            for part in [name.as_bytes(), value.as_slice()] {
+                let len = u16::try_from(part.len()).map_err(|_| too_large(&entry.name))?;
+                body.extend_from_slice(&len.to_le_bytes());
+                body.extend_from_slice(part);
+            }
+        }
+        push_record(&mut out, XATTR_ID, &body, &entry.name)?;
+    }
+    if out.len() > u16::MAX as usize {
+        return Err(too_large(&entry.name));
+    }
+    Ok(out)
+}
+
+/// Fills `entry`'s metadata from an extra field. Unknown and malformed records are
+/// skipped, as other tools do.
+pub fn decode(mut extra: &[u8], entry: &mut ArchiveEntry) {
+    while extra.len() >= 4 {
+        let id = u16::from_le_bytes([extra[0], extra[1]]);
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        let Some(body) = extra.get(4..4 + len) else {
+            return;
+        };
+        match id {
+            UNIX_OWNER_ID => decode_owner(body, entry),
+            XATTR_ID => entry.xattrs = decode_xattrs(body).unwrap_or_default(),
+            _ => {}
+        }
+        extra = &extra[4 + len..];
+    }
+}
+
+fn push_record(out: &mut Vec<u8>, id: u16, body: &[u8], name: &str) -> Result<(), LatError> {
+    let len = u16::try_from(body.len()).map_err(|_| too_large(name))?;
+    out.extend_from_slice(&id.to_le_bytes());
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(body);
+    Ok(())
+}
+
+fn too_large(name: &str) -> LatError {
+    LatError::InvalidInput(format!(
+        "{}: metadata exceeds the 64 KiB ZIP extra field",
+        name
+    ))
+}
+
+/// Reads a little-endian id of `size` bytes; larger than 32 bits is rejected.
+fn read_id(body: &[u8], at: usize) -> Option<(u32, usize)> {
+    let size = *body.get(at)? as usize;
+    let bytes = body.get(at + 1..at + 1 + size).filter(|_| size <= 4)?;
+    let id = bytes
+        .iter()
+        .rev()
+        .fold(0u32, |acc, &b| (acc << 8) | b as u32);
+    Some((id, at + 1 + size))
+}
+
+fn decode_owner(body: &[u8], entry: &mut ArchiveEntry) {
+    if body.first() != Some(&1) {
+        return;
+    }
+    let Some((uid, next)) = read_id(body, 1) else {
+        return;
+    };
+    let Some((gid, _)) = read_id(body, next) else {
+        return;
+    };
+    entry.uid = Some(uid);
+    entry.gid = Some(gid);
+}
+
+fn decode_xattrs(mut body: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    let take = |body: &mut &[u8]| -> Option<Vec<u8>> {
+        let len = u16::from_le_bytes([*body.first()?, *body.get(1)?]) as usize;
+        let part = body.get(2..2 + len)?.to_vec();
+        *body = &body[2 + len..];
+        Some(part)
+    };
+    let mut xattrs = Vec::new();
+    while !body.is_empty() {
+        let name = String::from_utf8(take(&mut body)?).ok()?;
+        let value = take(&mut body)?;
+        xattrs.push((name, value));
+    }
+    Some(xattrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_round_trips_through_extra_field() {
+        let entry = ArchiveEntry::builder("a.txt")
+            .uid(1000)
+            .gid(100)
+            .xattrs(vec![("user.comment".to_string(), b"hello".to_vec())])
+            .build();
+        let extra = encode(&entry).unwrap();
+        // "ux" record: id, length 11, version 1, 4-byte uid, 4-byte gid.
+        assert_eq!(&extra[..7], &[0x75, 0x78, 11, 0, 1, 4, 0xe8]);
+
+        // Records from other tools (here Info-ZIP's "UT" timestamps) are skipped.
+        let mut foreign = vec![0x55, 0x54, 5, 0, 1, 0, 0, 0, 0];
+        foreign.extend_from_slice(&extra);
+        let mut decoded = ArchiveEntry::default();
+        decode(&foreign, &mut decoded);
+        assert_eq!((decoded.uid, decoded.gid), (Some(1000), Some(100)));
+        assert_eq!(decoded.xattrs, entry.xattrs);
+
+        assert!(encode(&ArchiveEntry::default()).unwrap().is_empty());
+        let huge = ArchiveEntry::builder("")
+            .xattr("user.big", vec![0; 70_000])
+            .build();
+        assert!(matches!(encode(&huge), Err(LatError::InvalidInput(_))));
+    }
+}
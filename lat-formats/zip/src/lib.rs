@@ -1,16 +1,689 @@
-use lat_core::{ArchiveEntry, Compressor};
-use std::io::{Cursor, Read, Write};
+mod append;
+mod extra;
+mod recover;
+mod text;
+
+pub use append::ZipAppender;
+
+use flate2::read::DeflateDecoder;
+use lat_core::sample::EntrySample;
+use lat_core::store::{BlockStore, StoreReader};
+use lat_core::{
+    collect_listing, ArchiveEntry, ArchiveFormat, ArchiveInfo, CompressionResult, Compressor,
+    CompressorConfig, EntryKind, EntryOrder, EntryStat, FormatFactory, LatError, ListedEntry,
+    TrailingData, VerifyReport,
+};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use zip::result::ZipError;
 use zip::write::FileOptions;
-use zip::{ZipArchive, ZipWriter};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Clone)]
+pub struct ZipCompressor {
+    method: CompressionMethod,
+    level: Option<i32>,
+    deterministic: bool,
+    read_chunk_size: Option<usize>,
+    entry_order: EntryOrder,
+    store_threshold: u64,
+    host: HostSystem,
+    trailing_data: TrailingData,
+    detect_text: bool,
+}
+
+/// The host system recorded in each member's "version made by" field, which tells readers
+/// how to interpret its external attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HostSystem {
+    /// Attributes hold Unix permissions, so they survive a round trip through `unzip`.
+    #[default]
+    Unix,
+    /// Attributes hold DOS flags; only the read-only bit is kept from a permission mode.
+    Fat,
+}
+
+const HOST_FAT: u8 = 0;
+const HOST_UNIX: u8 = 3;
+
+impl Default for ZipCompressor {
+    fn default() -> Self {
+        Self {
+            // Bolt ⚡ Optimization: Explicitly use DEFLATE for ZIP.
+            // While 'Stored' is faster, DEFLATE is the expected default for ZIP.
+            method: CompressionMethod::Deflated,
+            level: None,
+            deterministic: false,
+            read_chunk_size: None,
+            entry_order: EntryOrder::default(),
+            store_threshold: 0,
+            host: HostSystem::default(),
+            trailing_data: TrailingData::default(),
+            detect_text: true,
+        }
+    }
+}
+
+/// Builds [`ZipCompressor`]s for [`lat_core::CompressorBuilder`].
+///
+/// Understands `level`, `method` ("stored", "deflated", and "bzip2" and "zstd" with the
+/// crate features of the same names) and `deterministic`; `threads` has no effect on ZIP,
+/// and `checksum` is always ZIP's own CRC32.
+pub struct ZipFormat;
+
+impl FormatFactory for ZipFormat {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        let mut compressor = ZipCompressor::new()
+            .with_deterministic(config.deterministic)
+            .with_trailing_data(config.trailing_data.clone());
+        if let Some(method) = &config.method {
+            compressor = compressor.with_method(parse_method(method)?);
+        }
+        if let Some(level) = config.level {
+            compressor = compressor.with_level(level);
+        }
+        Ok(Box::new(compressor))
+    }
+}
+
+fn parse_method(method: &str) -> Result<CompressionMethod, LatError> {
+    match method.to_ascii_lowercase().as_str() {
+        "stored" | "store" => Ok(CompressionMethod::Stored),
+        "deflated" | "deflate" => Ok(CompressionMethod::Deflated),
+        #[cfg(feature = "bzip2")]
+        "bzip2" => Ok(CompressionMethod::Bzip2),
+        #[cfg(feature = "zstd")]
+        "zstd" => Ok(CompressionMethod::Zstd),
+        other => Err(LatError::InvalidInput(format!(
+            "unknown ZIP method '{}'",
+            other
+        ))),
+    }
+}
+
+impl ZipCompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_method(mut self, method: CompressionMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Sets the method-specific compression level (e.g. 0-9 for Deflate).
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = Some(level.min(i32::MAX as u32) as i32);
+        self
+    }
+
+    /// Writes a fixed modification time so identical input yields identical archives.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Reads entries larger than `chunk_size` incrementally during `decompress` instead of
+    /// allocating their full declared size up front.
+    pub fn with_read_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.read_chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Sets the order `decompress` returns entries in (archive order by default).
+    pub fn with_entry_order(mut self, order: EntryOrder) -> Self {
+        self.entry_order = order;
+        self
+    }
+
+    /// Stores every member uncompressed when the total input is below `bytes`. For tiny
+    /// archives the codec setup costs more than it saves; `0` (the default) disables this.
+    pub fn with_store_threshold(mut self, bytes: u64) -> Self {
+        self.store_threshold = bytes;
+        self
+    }
+
+    /// Sets the host system recorded for every member (Unix by default, as the `zip`
+    /// crate writes). Entry permissions are only stored under a Unix host.
+    pub fn with_host_system(mut self, host: HostSystem) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Sets what reading does with bytes after the end-of-central-directory record and its
+    /// comment (ignored by default).
+    pub fn with_trailing_data(mut self, policy: TrailingData) -> Self {
+        self.trailing_data = policy;
+        self
+    }
+
+    /// Sets whether files that look like text (valid UTF-8 without NUL bytes) get ZIP's
+    /// text flag, which `unzip -a` uses to convert line endings (on by default). An entry's
+    /// own [`ArchiveEntry::is_text`] takes precedence.
+    pub fn with_text_detection(mut self, detect: bool) -> Self {
+        self.detect_text = detect;
+        self
+    }
+
+    /// Writes the archive straight into `writer` (a file, memory map, hashing writer, ...)
+    /// instead of an intermediate buffer, and hands the writer back when done. ZIP
+    /// backpatches local headers with sizes and CRCs, hence the `Seek` bound.
+    pub fn compress_into<W: Write + Seek>(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+        mut writer: W,
+    ) -> Result<W, LatError> {
+        self.check_password(password)?;
+        lat_core::validate_entries(entries)?;
+
+        if self.host == HostSystem::Fat {
+            // The `zip` crate always records a Unix host, so rewrite the finished headers.
+            let mut archive = self
+                .write_entries(entries, Cursor::new(Vec::new()))?
+                .into_inner();
+            set_fat_host(&mut archive, entries)?;
+            writer.write_all(&archive)?;
+            return Ok(writer);
+        }
+        self.write_entries(entries, writer)
+    }
+
+    fn write_entries<W: Write + Seek>(
+        &self,
+        entries: &[ArchiveEntry],
+        writer: W,
+    ) -> Result<W, LatError> {
+        let total: u64 = entries.iter().map(|e| e.data.len() as u64).sum();
+        let (method, level) = if total < self.store_threshold {
+            (CompressionMethod::Stored, None)
+        } else {
+            (self.method, self.level)
+        };
+
+        let (tap, capture) = text::Tap::new(writer)?;
+        let mut writer = ZipWriter::new(tap);
+        let mut options = FileOptions::default()
+            .compression_method(method)
+            .compression_level(level);
+        if self.deterministic {
+            options = options.last_modified_time(zip::DateTime::default());
+        }
+
+        for entry in entries {
+            let options = match entry.mode {
+                Some(mode) if self.host == HostSystem::Unix => options.unix_permissions(mode),
+                _ => options,
+            };
+            write_entry(&mut writer, entry, options)
+                .map_err(|e| e.context(Some(&entry.name), "compression"))?;
+        }
+        let text: Vec<bool> = entries
+            .iter()
+            .map(|entry| {
+                entry.kind == EntryKind::File
+                    && entry
+                        .is_text
+                        .unwrap_or_else(|| self.detect_text && text::looks_like_text(&entry.data))
+            })
+            .collect();
+        capture.capture_from_here();
+        writer.finish().map_err(map_zip_err)?.finish(&text)
+    }
+
+    /// Like `decompress`, but reads from any seekable source (typically a `File`), so only
+    /// the central directory and member data are read instead of loading the whole archive.
+    pub fn decompress_reader<R: Read + Seek>(
+        &self,
+        mut reader: R,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let hosts = member_hosts(&mut reader);
+        let trailing = trailing_len(&mut reader);
+        let mut archive = ZipArchive::new(reader).map_err(map_zip_err)?;
+        self.trailing_data.check(ArchiveFormat::Zip, trailing)?;
+
+        // Pre-allocate the entries vector
+        let mut entries = Vec::with_capacity(archive.len());
+
+        for i in 0..archive.len() {
+            let mut file = open_member(&mut archive, i, password)?;
+
+            // Reads in chunks above the configured size, then once past the end for the CRC.
+            let buf = read_member(&mut file, self.read_chunk_size)?;
+
+            let mut entry = ArchiveEntry::builder(file.name().to_string())
+                .data(buf)
+                .kind(kind(&file, hosts.get(i).copied()))
+                .mode(permissions(&file, hosts.get(i).copied()))
+                .build();
+            extra::decode(file.extra_data(), &mut entry);
+            entries.push(entry);
+        }
+        self.entry_order.apply(&mut entries);
+        Ok(entries)
+    }
+
+    /// `decompress_matching` over any seekable source.
+    fn matching_from_reader<R: Read + Seek>(
+        &self,
+        mut reader: R,
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let matcher = lat_core::glob_matcher(pattern)?;
+        let hosts = member_hosts(&mut reader);
+        let trailing = trailing_len(&mut reader);
+        let mut archive = ZipArchive::new(reader).map_err(map_zip_err)?;
+        self.trailing_data.check(ArchiveFormat::Zip, trailing)?;
+        let mut entries = Vec::new();
+
+        for i in 0..archive.len() {
+            // Raw access only parses the headers, so skipped entries are never inflated.
+            let name = archive
+                .by_index_raw(i)
+                .map_err(map_zip_err)?
+                .name()
+                .to_string();
+            if !matcher.is_match(&name) {
+                continue;
+            }
+
+            let mut file = open_member(&mut archive, i, password)?;
+            let data = read_member(&mut file, self.read_chunk_size)?;
+            let mut entry = ArchiveEntry::builder(name)
+                .data(data)
+                .kind(kind(&file, hosts.get(i).copied()))
+                .mode(permissions(&file, hosts.get(i).copied()))
+                .build();
+            extra::decode(file.extra_data(), &mut entry);
+            entries.push(entry);
+        }
+        self.entry_order.apply(&mut entries);
+        Ok(entries)
+    }
+
+    /// Salvages entries from an archive without a usable central directory (an unfinalized
+    /// [`ZipAppender`], a truncated download) by walking the local file headers. Stops at
+    /// the first entry that is cut off or fails its CRC; encrypted entries and stored
+    /// entries with data descriptors can't be delimited and end the scan too.
+    pub fn recover(&self, archive: &[u8]) -> Result<Vec<ArchiveEntry>, LatError> {
+        let (found, _) = recover::scan(archive);
+        if found.is_empty() && !archive.is_empty() {
+            return Err(LatError::CorruptArchive(
+                "no recoverable ZIP entries".to_string(),
+            ));
+        }
+        let mut entries: Vec<ArchiveEntry> = found.into_iter().map(|local| local.entry).collect();
+        self.entry_order.apply(&mut entries);
+        Ok(entries)
+    }
+
+    /// Opens a single entry for streaming reads without buffering it in memory.
+    ///
+    /// Stored and Deflated members are decoded directly from `archive`, and the CRC is
+    /// checked once the reader reaches the end of the entry. Encrypted members (or other
+    /// compression methods) have no streaming path in the `zip` crate, so they are
+    /// decompressed up front and served from memory.
+    ///
+    /// Like `decompress`, this accepts a password for reading legacy ZipCrypto members
+    /// written by other tools; this crate cannot create encrypted ZIPs itself.
+    pub fn open_entry<'a>(
+        &'a self,
+        archive: &'a [u8],
+        name: &str,
+        password: Option<&str>,
+    ) -> Result<Box<dyn Read + 'a>, LatError> {
+        let mut zip = ZipArchive::new(Cursor::new(archive)).map_err(map_zip_err)?;
+        let lookup_err = |e: ZipError| match e {
+            ZipError::FileNotFound => LatError::EntryNotFound(name.to_string()),
+            e => map_zip_err(e),
+        };
+
+        if let Some(password) = password {
+            let mut file = zip
+                .by_name_decrypt(name, password.as_bytes())
+                .map_err(lookup_err)?
+                .map_err(|e| LatError::CorruptArchive(format!("{}: {}", name, e)))?;
+            let buf = read_member(&mut file, None)?;
+            return Ok(Box::new(Cursor::new(buf)));
+        }
+
+        let mut file = zip.by_name(name).map_err(lookup_err)?;
+        let start = usize::try_from(file.data_start()).ok();
+        let len = usize::try_from(file.compressed_size()).ok();
+        let raw = match (start, len) {
+            (Some(start), Some(len)) => start
+                .checked_add(len)
+                .and_then(|end| archive.get(start..end)),
+            _ => None,
+        }
+        .ok_or_else(|| LatError::CorruptArchive(format!("{}: data out of bounds", name)))?;
+
+        let expected_crc = file.crc32();
+        let inner: Box<dyn Read + 'a> = match file.compression() {
+            CompressionMethod::Stored => Box::new(raw),
+            CompressionMethod::Deflated => Box::new(DeflateDecoder::new(raw)),
+            _ => {
+                let buf = read_member(&mut file, None)?;
+                return Ok(Box::new(Cursor::new(buf)));
+            }
+        };
+
+        Ok(Box::new(CrcReader {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+            expected: expected_crc,
+        }))
+    }
+}
+
+/// Verifies the entry CRC once the wrapped reader is exhausted.
+struct CrcReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+    expected: u32,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 {
+            if !buf.is_empty() && self.hasher.clone().finalize() != self.expected {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Invalid checksum",
+                ));
+            }
+        } else {
+            self.hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const EOCD_LEN: usize = 22;
+const CENTRAL_HEADER_LEN: usize = 46;
+
+/// Writes one member: its header (with any extra fields) and its data.
+fn write_entry<W: Write + Seek>(
+    writer: &mut ZipWriter<W>,
+    entry: &ArchiveEntry,
+    options: FileOptions,
+) -> Result<(), LatError> {
+    if entry.kind == EntryKind::Symlink {
+        let target = std::str::from_utf8(&entry.data).map_err(|_| {
+            LatError::InvalidInput(format!("{}: link target is not UTF-8", entry.name))
+        })?;
+        return writer
+            .add_symlink(&entry.name, target, options)
+            .map_err(map_zip_err);
+    }
+    if entry.kind == EntryKind::Directory {
+        return writer
+            .add_directory(&entry.name, options)
+            .map_err(map_zip_err);
+    }
+    let extra = extra::encode(entry)?;
+    if extra.is_empty() {
+        writer
+            .start_file(&entry.name, options)
+            .map_err(map_zip_err)?;
+    } else {
+        writer
+            .start_file_with_extra_data(&entry.name, options)
+            .map_err(map_zip_err)?;
+        writer.write_all(&extra)?;
+        writer.end_extra_data().map_err(map_zip_err)?;
+    }
+    writer.write_all(&entry.data)?;
+    Ok(())
+}
+
+/// Uncompressed size from `file`'s header. It comes from the archive, so it is checked
+/// rather than cast.
+fn declared_size(file: &zip::read::ZipFile) -> Result<usize, LatError> {
+    usize::try_from(file.size())
+        .map_err(|_| LatError::CorruptArchive(format!("{}: declared size too large", file.name())))
+}
+
+/// Opens member `index`, decrypting it with `password` if it is a ZipCrypto member. The
+/// `zip` crate ignores the password for members that aren't encrypted.
+fn open_member<'a, R: Read + Seek>(
+    archive: &'a mut ZipArchive<R>,
+    index: usize,
+    password: Option<&str>,
+) -> Result<zip::read::ZipFile<'a>, LatError> {
+    match password {
+        Some(password) => archive
+            .by_index_decrypt(index, password.as_bytes())
+            .map_err(map_zip_err)?
+            .map_err(|_| LatError::WrongPassword),
+        None => archive.by_index(index).map_err(map_zip_err),
+    }
+}
+
+/// Reads `file`'s data, then reads once more past its declared size: the `zip` reader only
+/// compares the CRC on a read that finds the end, which reading exactly `size` bytes
+/// never does.
+fn read_member(
+    file: &mut zip::read::ZipFile,
+    chunk_size: Option<usize>,
+) -> Result<Vec<u8>, LatError> {
+    let size = declared_size(file)?;
+    let data = lat_core::read_entry_data(file, size, chunk_size)?;
+    match file.read(&mut [0u8; 1]) {
+        Ok(0) => Ok(data),
+        Ok(_) => Err(LatError::CorruptArchive(format!(
+            "{}: data runs past its declared size",
+            file.name()
+        ))),
+        Err(e) => Err(LatError::CorruptArchive(format!("{}: {}", file.name(), e))),
+    }
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Maps the upper byte of "version made by" to a host system name (APPNOTE 4.4.2).
+fn host_os_name(host: u8) -> String {
+    match host {
+        0 => "MS-DOS/FAT".to_string(),
+        3 => "Unix".to_string(),
+        7 => "Macintosh".to_string(),
+        10 => "Windows NTFS".to_string(),
+        14 => "VFAT".to_string(),
+        19 => "OS X".to_string(),
+        other => format!("Unknown ({})", other),
+    }
+}
+
+/// Position of the end-of-central-directory record in `tail`, the last bytes of an
+/// archive.
+fn find_eocd(tail: &[u8]) -> Result<usize, LatError> {
+    // The EOCD record sits at the end, followed by a comment of at most 64 KiB.
+    let search_start = tail.len().saturating_sub(EOCD_LEN + u16::MAX as usize);
+    (search_start..=tail.len().saturating_sub(EOCD_LEN))
+        .rev()
+        .find(|&i| read_u32(tail, i) == Some(EOCD_SIGNATURE))
+        .ok_or_else(|| LatError::CorruptArchive("end of central directory not found".to_string()))
+}
+
+/// Finds the end-of-central-directory record in `tail`, the last bytes of an archive, and
+/// returns the central directory's offset and size as recorded there.
+fn find_central_directory(tail: &[u8]) -> Result<(usize, usize), LatError> {
+    let corrupt = |msg: &str| LatError::CorruptArchive(msg.to_string());
+    let eocd = find_eocd(tail)?;
+
+    let cd_size = read_u32(tail, eocd + 12).ok_or_else(|| corrupt("truncated EOCD"))? as usize;
+    let cd_offset = read_u32(tail, eocd + 16).ok_or_else(|| corrupt("truncated EOCD"))? as usize;
+    Ok((cd_offset, cd_size))
+}
+
+/// Offsets of the file headers in central directory `cd`, each checked to have its fixed
+/// part in bounds.
+fn central_headers(cd: &[u8]) -> Result<Vec<usize>, LatError> {
+    let corrupt = |msg: &str| LatError::CorruptArchive(msg.to_string());
+    let mut headers = Vec::new();
+    let mut pos = 0;
+    while pos < cd.len() {
+        if read_u32(cd, pos) != Some(CENTRAL_HEADER_SIGNATURE) {
+            return Err(corrupt("bad central directory header"));
+        }
+        let header = cd
+            .get(pos..pos + CENTRAL_HEADER_LEN)
+            .ok_or_else(|| corrupt("truncated central directory header"))?;
+        let variable_len = read_u16(header, 28).unwrap_or_default() as usize
+            + read_u16(header, 30).unwrap_or_default() as usize
+            + read_u16(header, 32).unwrap_or_default() as usize;
+        headers.push(pos);
+        pos += CENTRAL_HEADER_LEN + variable_len;
+    }
+    Ok(headers)
+}
+
+/// The central directory of a complete in-memory archive.
+fn central_directory(archive: &[u8]) -> Result<std::ops::Range<usize>, LatError> {
+    let (cd_offset, cd_size) = find_central_directory(archive)?;
+    cd_offset
+        .checked_add(cd_size)
+        .filter(|&end| end <= archive.len())
+        .map(|end| cd_offset..end)
+        .ok_or_else(|| LatError::CorruptArchive("central directory out of bounds".to_string()))
+}
+
+/// Parses the end-of-central-directory record and central directory headers.
+fn parse_archive_info(archive: &[u8]) -> Result<ArchiveInfo, LatError> {
+    let cd = &archive[central_directory(archive)?];
+
+    let mut info = ArchiveInfo {
+        format: "zip",
+        creator_version: None,
+        host_os: None,
+        entry_count: 0,
+        is_encrypted: false,
+    };
+
+    for pos in central_headers(cd)? {
+        let made_by = read_u16(cd, pos + 4).unwrap_or_default();
+        let flags = read_u16(cd, pos + 8).unwrap_or_default();
+        if info.entry_count == 0 {
+            let spec = made_by & 0xff;
+            info.creator_version = Some(format!("{}.{}", spec / 10, spec % 10));
+            info.host_os = Some(host_os_name((made_by >> 8) as u8));
+        }
+        // General purpose bit 0 marks an encrypted entry.
+        info.is_encrypted |= flags & 1 != 0;
+        info.entry_count += 1;
+    }
+
+    Ok(info)
+}
+
+/// Reads the last bytes of `reader`, as much as can hold the EOCD record and its comment.
+fn read_tail<R: Read + Seek>(reader: &mut R) -> Result<(Vec<u8>, u64), LatError> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = len.min((EOCD_LEN + u16::MAX as usize) as u64);
+    let mut tail = vec![0; tail_len as usize];
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    reader.read_exact(&mut tail)?;
+    Ok((tail, len))
+}
+
+/// Bytes after the EOCD record and its comment. 0 when there is no EOCD record, which
+/// the `zip` reader reports on its own.
+fn trailing_len<R: Read + Seek>(reader: &mut R) -> usize {
+    let Ok((tail, _)) = read_tail(reader) else {
+        return 0;
+    };
+    let Ok(eocd) = find_eocd(&tail) else {
+        return 0;
+    };
+    let comment_len = read_u16(&tail, eocd + 20).unwrap_or_default() as usize;
+    tail.len().saturating_sub(eocd + EOCD_LEN + comment_len)
+}
+
+/// Host system of each member, in archive order. Empty when the central directory can't
+/// be read here (e.g. Zip64), in which case no member's permissions are trusted.
+fn member_hosts<R: Read + Seek>(reader: &mut R) -> Vec<u8> {
+    fn read<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>, LatError> {
+        let (tail, len) = read_tail(reader)?;
+        let (cd_offset, cd_size) = find_central_directory(&tail)?;
+        if cd_offset as u64 + cd_size as u64 > len {
+            return Err(LatError::CorruptArchive(
+                "central directory out of bounds".to_string(),
+            ));
+        }
+        let mut cd = vec![0; cd_size];
+        reader.seek(SeekFrom::Start(cd_offset as u64))?;
+        reader.read_exact(&mut cd)?;
+        Ok(central_headers(&cd)?
+            .into_iter()
+            .map(|pos| cd[pos + 5])
+            .collect())
+    }
+    read(reader).unwrap_or_default()
+}
+
+/// `file`'s permission bits when its `host` stores Unix modes. Other hosts keep DOS
+/// attributes there, which the `zip` crate would turn into made-up modes.
+fn permissions(file: &zip::read::ZipFile, host: Option<u8>) -> Option<u32> {
+    if host != Some(HOST_UNIX) {
+        return None;
+    }
+    file.unix_mode().map(|mode| mode & 0o777)
+}
+
+/// [`EntryKind::Directory`] for members named like `dir/`, [`EntryKind::Symlink`] for
+/// members a Unix host marked as links. Link bits only mean something under a Unix host,
+/// like the permissions next to them.
+fn kind(file: &zip::read::ZipFile, host: Option<u8>) -> EntryKind {
+    if file.is_dir() {
+        return EntryKind::Directory;
+    }
+    match file.unix_mode() {
+        Some(mode) if host == Some(HOST_UNIX) && mode & 0o170000 == 0o120000 => EntryKind::Symlink,
+        _ => EntryKind::default(),
+    }
+}
+
+/// Rewrites every central directory header of a finished archive to a FAT host, keeping
+/// only the read-only flag from each entry's mode as its DOS attributes.
+fn set_fat_host(archive: &mut [u8], entries: &[ArchiveEntry]) -> Result<(), LatError> {
+    let cd = central_directory(archive)?;
+    let headers = central_headers(&archive[cd.clone()])?;
+    for (pos, entry) in headers.into_iter().zip(entries) {
+        let header = &mut archive[cd.start + pos..cd.start + pos + CENTRAL_HEADER_LEN];
+        header[5] = HOST_FAT;
+        let read_only = entry.mode.is_some_and(|mode| mode & 0o200 == 0);
+        header[38..42].copy_from_slice(&u32::from(read_only).to_le_bytes());
+    }
+    Ok(())
+}
 
-pub struct ZipCompressor;
+fn map_zip_err(e: ZipError) -> LatError {
+    match e {
+        ZipError::Io(e) => LatError::Io(e),
+        ZipError::InvalidArchive(msg) => LatError::CorruptArchive(msg.to_string()),
+        ZipError::UnsupportedArchive(msg) => LatError::UnsupportedFormat(msg.to_string()),
+        ZipError::FileNotFound => LatError::EntryNotFound("file not found in archive".to_string()),
+    }
+}
 
 impl Compressor for ZipCompressor {
     fn compress(
         &self,
         entries: &[ArchiveEntry],
-        _password: Option<&str>,
-    ) -> Result<Vec<u8>, String> {
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
         // Bolt ⚡ Optimization: Pre-allocate buffer with an accurate estimate of both
         // uncompressed data AND ZIP metadata overhead (headers, central directory).
         // This prevents multiple expensive reallocations for archives with many small files.
@@ -23,72 +696,146 @@ impl Compressor for ZipCompressor {
         // Moving the constant 76 bytes per entry and 22 bytes EOCD outside the loop reduces arithmetic operations.
         let total_overhead = 22 + (76 * entries.len()) + (2 * total_name_len);
 
-        let mut buf = Vec::with_capacity(total_uncompressed_size + total_overhead);
-        {
-            let mut writer = ZipWriter::new(Cursor::new(&mut buf));
-            // Bolt ⚡ Optimization: Explicitly use DEFLATE for ZIP.
-            // While 'Stored' is faster, DEFLATE is the expected default for ZIP.
-            // Performance is already optimized by pre-calculating total capacity
-            // and using a single pass for metadata estimation.
-            let options =
-                FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
-
-            for entry in entries {
-                writer
-                    .start_file(&entry.name, options)
-                    .map_err(|e| e.to_string())?;
-                writer.write_all(&entry.data).map_err(|e| e.to_string())?;
+        // Performance is already optimized by pre-calculating total capacity
+        // and using a single pass for metadata estimation.
+        let buf = Vec::with_capacity(total_uncompressed_size + total_overhead);
+        let sink = self.compress_into(entries, password, Cursor::new(buf))?;
+        Ok(sink.into_inner())
+    }
+
+    fn compress_detailed(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<CompressionResult, LatError> {
+        let data = self.compress(entries, password)?;
+
+        // Sizes come from the central directory we just wrote; raw access skips inflating.
+        let per_entry = {
+            let mut archive = ZipArchive::new(Cursor::new(&data)).map_err(map_zip_err)?;
+            let mut stats = Vec::with_capacity(archive.len());
+            for i in 0..archive.len() {
+                let file = archive.by_index_raw(i).map_err(map_zip_err)?;
+                stats.push(EntryStat::new(
+                    file.name(),
+                    file.size(),
+                    file.compressed_size(),
+                    &format!("{:?}", file.compression()),
+                ));
             }
-            writer.finish().map_err(|e| e.to_string())?;
-        }
-        Ok(buf)
+            stats
+        };
+
+        Ok(CompressionResult {
+            uncompressed_size: entries.iter().map(|e| e.data.len() as u64).sum(),
+            data,
+            per_entry,
+            warnings: Vec::new(),
+            context_memory: 0,
+        })
     }
 
     fn decompress(
         &self,
         archive_data: &[u8],
-        _password: Option<&str>,
-    ) -> Result<Vec<ArchiveEntry>, String> {
-        let mut archive = ZipArchive::new(Cursor::new(archive_data)).map_err(|e| e.to_string())?;
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.decompress_reader(Cursor::new(archive_data), password)
+    }
 
-        // Pre-allocate the entries vector
-        let mut entries = Vec::with_capacity(archive.len());
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Zip
+    }
 
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+    fn decompress_matching(
+        &self,
+        archive_data: &[u8],
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.matching_from_reader(Cursor::new(archive_data), pattern, password)
+    }
 
-            // Bolt ⚡ Optimization: Use read_exact into a pre-resized buffer instead of
-            // read_to_end with capacity. This avoids redundant EOF checks and
-            // additional read syscalls since the file size is already known.
-            let mut buf = vec![0u8; file.size() as usize];
-            file.read_exact(&mut buf).map_err(|e| e.to_string())?;
+    /// Fetches the end of the archive, its central directory and the matching members.
+    fn decompress_matching_from(
+        &self,
+        store: &dyn BlockStore,
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.matching_from_reader(StoreReader::new(store), pattern, password)
+    }
 
-            entries.push(ArchiveEntry {
+    /// Reads only the central directory; no member is inflated, so no password is needed.
+    fn list_with_progress(
+        &self,
+        archive_data: &[u8],
+        _password: Option<&str>,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<Vec<ListedEntry>, LatError> {
+        let trailing = trailing_len(&mut Cursor::new(archive_data));
+        let mut archive = ZipArchive::new(Cursor::new(archive_data)).map_err(map_zip_err)?;
+        self.trailing_data.check(ArchiveFormat::Zip, trailing)?;
+        let entries = (0..archive.len()).map(|i| {
+            let file = archive.by_index_raw(i).map_err(map_zip_err)?;
+            Ok(ListedEntry {
                 name: file.name().to_string(),
-                data: buf,
-            });
+                kind: if file.is_dir() {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                },
+                size: file.size(),
+            })
+        });
+        collect_listing(entries, on_progress)
+    }
+
+    fn archive_info(&self, archive: &[u8]) -> Result<ArchiveInfo, LatError> {
+        parse_archive_info(archive)
+    }
+
+    /// Parses the central directory, then inflates each sampled member to a sink; the
+    /// `zip` reader checks the member's CRC when it reaches the end.
+    fn quick_verify(
+        &self,
+        archive: &[u8],
+        password: Option<&str>,
+        sample: &EntrySample,
+    ) -> Result<VerifyReport, LatError> {
+        let mut zip = ZipArchive::new(Cursor::new(archive)).map_err(map_zip_err)?;
+        let picked = sample.pick(zip.len());
+        for &index in &picked {
+            let mut file = open_member(&mut zip, index, password)?;
+            std::io::copy(&mut file, &mut std::io::sink())
+                .map_err(|e| LatError::CorruptArchive(format!("{}: {}", file.name(), e)))?;
         }
-        Ok(entries)
+        Ok(VerifyReport {
+            entries_checked: picked.len(),
+            entries_total: zip.len(),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lat_core::store::CountingStore;
+    use lat_core::testing::{noise, CountingWriter};
     use lat_core::ArchiveEntry;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn test_zip_compress_decompress() {
-        let compressor = ZipCompressor;
+        let compressor = ZipCompressor::new();
         let entries = vec![
-            ArchiveEntry {
-                name: "test1.txt".to_string(),
-                data: b"Hello world".to_vec(),
-            },
-            ArchiveEntry {
-                name: "folder/test2.txt".to_string(),
-                data: b"More data".to_vec(),
-            },
+            ArchiveEntry::builder("test1.txt")
+                .data(b"Hello world".to_vec())
+                .build(),
+            ArchiveEntry::builder("folder/test2.txt")
+                .data(b"More data".to_vec())
+                .build(),
         ];
 
         let compressed = compressor
@@ -104,4 +851,789 @@ mod tests {
         assert_eq!(entries[1].name, decompressed[1].name);
         assert_eq!(entries[1].data, decompressed[1].data);
     }
+
+    #[test]
+    fn test_zip_empty_archive_round_trips() {
+        let compressor = ZipCompressor::new();
+        let archive = compressor.compress(&[], None).unwrap();
+        assert!(compressor.decompress(&archive, None).unwrap().is_empty());
+        assert_eq!(compressor.archive_info(&archive).unwrap().entry_count, 0);
+    }
+
+    #[test]
+    fn test_zip_open_entry_streaming() {
+        let compressor = ZipCompressor::new();
+        let payload: Vec<u8> = (0..10_000u32)
+            .flat_map(|i| (i % 251).to_le_bytes())
+            .collect();
+        let entries = vec![
+            ArchiveEntry::builder("first.txt")
+                .data(b"Small entry".to_vec())
+                .build(),
+            ArchiveEntry::builder("big.bin")
+                .data(payload.clone())
+                .build(),
+        ];
+
+        let compressed = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let mut reader = compressor
+            .open_entry(&compressed, "big.bin", None)
+            .expect("Open failed");
+
+        let mut assembled = Vec::new();
+        let mut chunk = [0u8; 7];
+        loop {
+            let n = reader.read(&mut chunk).expect("Read failed");
+            if n == 0 {
+                break;
+            }
+            assembled.extend_from_slice(&chunk[..n]);
+        }
+        assert_eq!(payload, assembled);
+
+        assert!(matches!(
+            compressor.open_entry(&compressed, "missing.txt", None),
+            Err(LatError::EntryNotFound(_))
+        ));
+    }
+
+    /// Builds a one-entry stored ZIP whose headers claim it was made by `made_by`.
+    fn zip_fixture(made_by: u16) -> Vec<u8> {
+        let name = b"a.txt";
+        let data = b"hi";
+        let crc = crc32fast::hash(data);
+        let mut out = Vec::new();
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        out.extend_from_slice(&[0; 8]); // flags, method, time, date
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(name);
+        out.extend_from_slice(data);
+
+        let cd_offset = out.len();
+        out.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&made_by.to_le_bytes());
+        out.extend_from_slice(&20u16.to_le_bytes());
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&[0; 12]); // extra, comment, disk, internal/external attrs
+        out.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+        out.extend_from_slice(name);
+        let cd_size = out.len() - cd_offset;
+
+        out.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&(cd_size as u32).to_le_bytes());
+        out.extend_from_slice(&(cd_offset as u32).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn test_zip_archive_info() {
+        let compressor = ZipCompressor::new();
+
+        let unix = compressor
+            .archive_info(&zip_fixture(0x031e))
+            .expect("Info failed");
+        assert_eq!(unix.format, "zip");
+        assert_eq!(unix.host_os.as_deref(), Some("Unix"));
+        assert_eq!(unix.creator_version.as_deref(), Some("3.0"));
+        assert_eq!(unix.entry_count, 1);
+        assert!(!unix.is_encrypted);
+
+        let dos = compressor
+            .archive_info(&zip_fixture(0x0014))
+            .expect("Info failed");
+        assert_eq!(dos.host_os.as_deref(), Some("MS-DOS/FAT"));
+        assert_eq!(dos.creator_version.as_deref(), Some("2.0"));
+
+        // The fixture is a valid archive for the regular reader too.
+        let decompressed = compressor
+            .decompress(&zip_fixture(0x031e), None)
+            .expect("Decompression failed");
+        assert_eq!(b"hi".to_vec(), decompressed[0].data);
+
+        assert!(compressor.archive_info(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn test_zip_trailing_data_policy() {
+        let entries = vec![ArchiveEntry::builder("a.txt")
+            .data(b"kept".to_vec())
+            .build()];
+        let mut archive = ZipCompressor::new().compress(&entries, None).unwrap();
+        archive.extend_from_slice(b"garbage after the archive");
+
+        let reported = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&reported);
+        let warn = TrailingData::warn(move |_, bytes| {
+            counter.fetch_add(bytes, Ordering::SeqCst);
+        });
+        for policy in [TrailingData::Ignore, warn] {
+            let compressor = ZipCompressor::new().with_trailing_data(policy);
+            let decompressed = compressor.decompress(&archive, None).unwrap();
+            assert_eq!(decompressed[0].data, b"kept");
+            assert_eq!(
+                compressor
+                    .decompress_matching(&archive, "*", None)
+                    .unwrap()
+                    .len(),
+                1
+            );
+        }
+        // Warn reports the extra bytes on each call, Ignore doesn't.
+        assert_eq!(
+            reported.load(Ordering::SeqCst),
+            2 * b"garbage after the archive".len()
+        );
+        let strict = ZipCompressor::new().with_trailing_data(TrailingData::Error);
+        assert!(matches!(
+            strict.decompress(&archive, None),
+            Err(LatError::CorruptArchive(_))
+        ));
+        assert!(matches!(
+            strict.decompress_matching(&archive, "*", None),
+            Err(LatError::CorruptArchive(_))
+        ));
+        // Nothing is flagged on an archive without extra bytes.
+        let clean = ZipCompressor::new().compress(&entries, None).unwrap();
+        assert!(strict.decompress(&clean, None).is_ok());
+    }
+
+    #[test]
+    fn test_zip_list_reports_progress() {
+        let entries: Vec<ArchiveEntry> = (0..2500)
+            .map(|i| ArchiveEntry::builder(format!("dir/{}.txt", i)).build())
+            .collect();
+        let compressor = ZipCompressor::new();
+        let archive = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let mut counts = Vec::new();
+        let listed = compressor
+            .list_with_progress(&archive, None, &mut |n| counts.push(n))
+            .expect("Listing failed");
+        assert_eq!(listed.len(), 2500);
+        assert_eq!(listed[2499].name, "dir/2499.txt");
+        assert_eq!(counts, [1000, 2000, 2500]);
+    }
+
+    #[test]
+    fn test_zip_keeps_symlinks() {
+        let entries = vec![
+            ArchiveEntry::builder("target.txt")
+                .data(b"data".to_vec())
+                .build(),
+            ArchiveEntry::builder("link.txt")
+                .data(b"target.txt".to_vec())
+                .kind(EntryKind::Symlink)
+                .build(),
+        ];
+        let compressor = ZipCompressor::new().with_host_system(HostSystem::Unix);
+        let archive = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let decompressed = compressor
+            .decompress(&archive, None)
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].kind, EntryKind::File);
+        assert_eq!(decompressed[1].kind, EntryKind::Symlink);
+        assert_eq!(decompressed[1].data, b"target.txt");
+    }
+
+    #[test]
+    fn test_zip_keeps_directories() {
+        let entries = vec![
+            ArchiveEntry::builder("dir/")
+                .kind(EntryKind::Directory)
+                .build(),
+            ArchiveEntry::builder("dir/a.txt")
+                .data(b"data".to_vec())
+                .build(),
+        ];
+        let compressor = ZipCompressor::new();
+        let archive = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let decompressed = compressor
+            .decompress(&archive, None)
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].name, "dir/");
+        assert_eq!(decompressed[0].kind, EntryKind::Directory);
+        assert!(decompressed[0].data.is_empty());
+        assert_eq!(decompressed[1].kind, EntryKind::File);
+
+        let listed = compressor.list(&archive, None).expect("Listing failed");
+        assert_eq!(listed[0].kind, EntryKind::Directory);
+        assert_eq!(listed[1].kind, EntryKind::File);
+    }
+
+    #[test]
+    fn test_zip_flags_text_entries() {
+        let entries = vec![
+            ArchiveEntry::builder("notes.txt")
+                .data("line one\nline two ✓\n".as_bytes().to_vec())
+                .build(),
+            ArchiveEntry::builder("image.bin")
+                .data(vec![0x89, b'P', b'N', b'G', 0, 0, 0xff])
+                .build(),
+            ArchiveEntry::builder("data.csv")
+                .data(b"a,b\n1,2\n".to_vec())
+                .is_text(false)
+                .build(),
+        ];
+        let text_flags = |archive: &[u8]| -> Vec<u16> {
+            let cd = &archive[central_directory(archive).unwrap()];
+            central_headers(cd)
+                .unwrap()
+                .into_iter()
+                .map(|pos| read_u16(cd, pos + 36).unwrap())
+                .collect()
+        };
+
+        let archive = ZipCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+        assert_eq!(text_flags(&archive), [1, 0, 0]);
+        let decompressed = ZipCompressor::new()
+            .decompress(&archive, None)
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].data, entries[0].data);
+
+        let archive = ZipCompressor::new()
+            .with_host_system(HostSystem::Fat)
+            .with_text_detection(false)
+            .compress(&entries, None)
+            .expect("Compression failed");
+        assert_eq!(text_flags(&archive), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_zip_host_system_decides_permissions() {
+        let entries = vec![
+            ArchiveEntry::builder("run.sh")
+                .data(b"#!/bin/sh".to_vec())
+                .mode(0o750)
+                .build(),
+            ArchiveEntry::builder("notes.txt")
+                .data(b"read me".to_vec())
+                .mode(0o444)
+                .build(),
+        ];
+
+        let unix = ZipCompressor::new().with_host_system(HostSystem::Unix);
+        let archive = unix.compress(&entries, None).expect("Compression failed");
+        let info = unix.archive_info(&archive).expect("Info failed");
+        assert_eq!(info.host_os.as_deref(), Some("Unix"));
+        let decompressed = unix
+            .decompress(&archive, None)
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].mode, Some(0o750));
+        assert_eq!(decompressed[1].mode, Some(0o444));
+        let matched = unix
+            .decompress_matching(&archive, "*.sh", None)
+            .expect("Decompression failed");
+        assert_eq!(matched[0].mode, Some(0o750));
+
+        // Under a FAT host the attributes are DOS flags, so no mode is reported.
+        let fat = ZipCompressor::new().with_host_system(HostSystem::Fat);
+        let archive = fat.compress(&entries, None).expect("Compression failed");
+        let info = fat.archive_info(&archive).expect("Info failed");
+        assert_eq!(info.host_os.as_deref(), Some("MS-DOS/FAT"));
+        let decompressed = fat
+            .decompress(&archive, None)
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].data, b"#!/bin/sh");
+        assert!(decompressed.iter().all(|entry| entry.mode.is_none()));
+        let mut zip = ZipArchive::new(Cursor::new(&archive)).unwrap();
+        assert_eq!(zip.by_index(1).unwrap().unix_mode(), Some(0o100444));
+    }
+
+    #[test]
+    fn test_zip_entry_order() {
+        let names = ["b.txt", "a.txt", "c/d.txt", "empty"];
+        let entries: Vec<ArchiveEntry> = names
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(if *name == "empty" {
+                        Vec::new()
+                    } else {
+                        name.as_bytes().to_vec()
+                    })
+                    .build()
+            })
+            .collect();
+        let compressed = ZipCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+
+        let physical = ZipCompressor::new()
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+        let physical_names: Vec<&str> = physical.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(physical_names, names);
+
+        let sorted = ZipCompressor::new()
+            .with_entry_order(EntryOrder::Name)
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+        let sorted_names: Vec<&str> = sorted.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(sorted_names, ["a.txt", "b.txt", "c/d.txt", "empty"]);
+    }
+
+    #[test]
+    fn test_zip_decompress_matching() {
+        let entries: Vec<ArchiveEntry> = ["a.log", "dir/b.log", "c.txt", "dir/d.bin"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(name.repeat(50).into_bytes())
+                    .build()
+            })
+            .collect();
+        let mut compressed = ZipCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+
+        // Corrupt a non-matching payload: it must not be touched during selective extraction.
+        let offset = ZipArchive::new(Cursor::new(compressed.as_slice()))
+            .unwrap()
+            .by_name("c.txt")
+            .unwrap()
+            .data_start() as usize;
+        compressed[offset] ^= 0xff;
+        assert!(ZipCompressor::new().decompress(&compressed, None).is_err());
+
+        let logs = ZipCompressor::new()
+            .decompress_matching(&compressed, "**/*.log", None)
+            .expect("Selective extraction failed");
+        let names: Vec<&str> = logs.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.log", "dir/b.log"]);
+        assert_eq!(logs[1].data, entries[1].data);
+    }
+
+    #[test]
+    fn test_zip_selective_extraction_fetches_little_of_a_store() {
+        let entries: Vec<ArchiveEntry> = (0..400)
+            .map(|i| {
+                ArchiveEntry::builder(format!("file{}.txt", i))
+                    .data(format!("entry {} ", i).repeat(1024).into_bytes())
+                    .build()
+            })
+            .collect();
+        let compressor = ZipCompressor::new().with_method(CompressionMethod::Stored);
+        let store = CountingStore::new(compressor.compress(&entries, None).unwrap());
+
+        let matched = compressor
+            .decompress_matching_from(&store, "file7.txt", None)
+            .expect("Selective extraction failed");
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].data, entries[7].data);
+        // The tail, the central directory and one member; not the other 399 members.
+        assert!(
+            store.fetched() * 5 < store.len(),
+            "fetched {} of {} bytes",
+            store.fetched(),
+            store.len()
+        );
+    }
+
+    #[test]
+    fn test_zip_empty_file_and_empty_name() {
+        let compressor = ZipCompressor::new();
+        let entries = vec![ArchiveEntry::builder("empty.txt").build()];
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let decompressed = compressor.decompress(&compressed, None).unwrap();
+        assert_eq!(decompressed[0].name, "empty.txt");
+        assert!(decompressed[0].data.is_empty());
+
+        for name in ["", "   "] {
+            let entries = vec![ArchiveEntry::builder(name.to_string())
+                .data(b"data".to_vec())
+                .build()];
+            assert!(matches!(
+                compressor.compress(&entries, None),
+                Err(LatError::CorruptArchive(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_zip_per_entry_stats() {
+        let noise = noise(4096, 0x9e37_79b9);
+        let entries = vec![
+            ArchiveEntry::builder("text.txt")
+                .data(b"compress me please ".repeat(500))
+                .build(),
+            ArchiveEntry::builder("noise.bin").data(noise).build(),
+            ArchiveEntry::builder("empty").build(),
+        ];
+
+        let result = ZipCompressor::new()
+            .compress_detailed(&entries, None)
+            .expect("Compression failed");
+        assert_eq!(result.per_entry.len(), entries.len());
+        for (entry, stat) in entries.iter().zip(&result.per_entry) {
+            assert_eq!(stat.name, entry.name);
+            assert_eq!(stat.uncompressed, entry.data.len() as u64);
+            assert_eq!(stat.method_used, "Deflated");
+        }
+        assert!(result.per_entry[0].ratio < 0.1);
+        assert!(result.per_entry[1].ratio > 0.9);
+        assert_eq!(result.per_entry[2].ratio, 1.0);
+
+        // Whatever isn't member data is header overhead: local + central header per
+        // member (with the name twice) plus the end-of-central-directory record.
+        let payload: u64 = result.per_entry.iter().map(|s| s.compressed).sum();
+        let overhead = result.data.len() as u64 - payload;
+        let names: usize = entries.iter().map(|e| e.name.len()).sum();
+        assert!(overhead >= (22 + 76 * entries.len() + 2 * names) as u64);
+        assert!(overhead <= (22 + 128 * entries.len() + 2 * names) as u64);
+    }
+
+    #[test]
+    fn test_zip_compress_into_custom_sink() {
+        let entries = vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"streamed straight to the sink ".repeat(300))
+                .build(),
+            ArchiveEntry::builder("dir/b.txt")
+                .data(b"second member".to_vec())
+                .build(),
+        ];
+        let compressor = ZipCompressor::new();
+
+        let sink = compressor
+            .compress_into(&entries, None, CountingWriter::default())
+            .expect("Compression failed");
+        let buffered = compressor
+            .compress(&entries, None)
+            .expect("Compression failed");
+        assert_eq!(sink.len, buffered.len() as u64);
+    }
+
+    #[test]
+    fn test_zip_builder_options() {
+        let text = b"the quick brown fox jumps over the lazy dog ".repeat(2000);
+        let entries = vec![ArchiveEntry::builder("fox.txt").data(text.clone()).build()];
+        let build = |builder: lat_core::CompressorBuilder| builder.build().expect("Build failed");
+
+        let stored = build(lat_core::CompressorBuilder::new(&ZipFormat).method("stored"))
+            .compress(&entries, None)
+            .unwrap();
+        let fast = build(lat_core::CompressorBuilder::new(&ZipFormat).level(1))
+            .compress(&entries, None)
+            .unwrap();
+        let best = build(lat_core::CompressorBuilder::new(&ZipFormat).level(9))
+            .compress(&entries, None)
+            .unwrap();
+        assert!(stored.len() > text.len());
+        assert!(fast.len() < stored.len());
+        assert!(best.len() <= fast.len());
+
+        let deterministic = build(lat_core::CompressorBuilder::new(&ZipFormat).deterministic(true));
+        let first = deterministic.compress(&entries, None).unwrap();
+        assert_eq!(first, deterministic.compress(&entries, None).unwrap());
+        // The fixed time rather than the clock, which two quick runs could share anyway.
+        let mut archive = ZipArchive::new(Cursor::new(&first)).unwrap();
+        let written = archive.by_index(0).unwrap().last_modified();
+        assert_eq!(
+            (written.year(), written.month(), written.day()),
+            (1980, 1, 1)
+        );
+        assert_eq!(
+            (written.hour(), written.minute(), written.second()),
+            (0, 0, 0)
+        );
+
+        assert!(lat_core::CompressorBuilder::new(&ZipFormat)
+            .method("lzham")
+            .build()
+            .is_err());
+        assert!(lat_core::CompressorBuilder::new(&ZipFormat)
+            .password("pw")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_zip_errors_name_the_failing_entry() {
+        let mut entries: Vec<ArchiveEntry> = ["a.txt", "b.txt", "foo/bar.bin", "d.txt"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(b"data".to_vec())
+                    .build()
+            })
+            .collect();
+        // Too large for a ZIP extra field, so the third member's header can't be written.
+        entries[2]
+            .xattrs
+            .push(("user.big".to_string(), vec![0; 70_000]));
+
+        let err = ZipCompressor::new().compress(&entries, None).unwrap_err();
+        assert!(matches!(
+            &err,
+            LatError::Context { entry: Some(entry), stage: "compression", .. }
+                if entry == "foo/bar.bin"
+        ));
+        assert!(matches!(err.root_cause(), LatError::InvalidInput(_)));
+
+        // Sealing never starts, and the member's breadcrumb comes through the wrapper.
+        let encrypted = lat_core::EncryptedCompressor::new(ZipCompressor::new());
+        let err = encrypted.compress(&entries, Some("pw")).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("'foo/bar.bin' during compression: "));
+    }
+
+    #[test]
+    fn test_zip_wrapped_in_encryption() {
+        let entries = vec![ArchiveEntry::builder("private/notes.txt")
+            .data(b"zip, then encrypt".to_vec())
+            .build()];
+        let encrypted = lat_core::EncryptedCompressor::new(ZipCompressor::new());
+        assert!(encrypted.supports_password());
+
+        let archive = encrypted
+            .compress(&entries, Some("s3cret"))
+            .expect("Compression failed");
+        assert!(ZipCompressor::new().decompress(&archive, None).is_err());
+
+        let decompressed = encrypted
+            .decompress(&archive, Some("s3cret"))
+            .expect("Decompression failed");
+        assert_eq!(decompressed[0].name, "private/notes.txt");
+        assert_eq!(decompressed[0].data, entries[0].data);
+
+        assert!(encrypted.decompress(&archive, Some("wrong")).is_err());
+        assert!(encrypted.decompress(&archive, None).is_err());
+    }
+
+    /// One stored member, "secret.txt", encrypted with ZipCrypto under "hunter2" by
+    /// another tool.
+    const ZIPCRYPTO_ARCHIVE: [u8; 154] = [
+        0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0xb2,
+        0x60, 0x6d, 0x51, 0x24, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,
+        0x73, 0x65, 0x63, 0x72, 0x65, 0x74, 0x2e, 0x74, 0x78, 0x74, 0xfc, 0x16, 0x82, 0xea, 0x25,
+        0x72, 0xf4, 0x63, 0xb0, 0xb8, 0xd2, 0xb9, 0x9c, 0xfa, 0x85, 0xb5, 0xea, 0x17, 0x6a, 0xbb,
+        0x20, 0x44, 0xa3, 0x1e, 0xd3, 0x59, 0x84, 0xfa, 0x27, 0x57, 0x41, 0xe4, 0x1e, 0x71, 0x16,
+        0x8b, 0x50, 0x4b, 0x01, 0x02, 0x14, 0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x21, 0x00, 0xb2, 0x60, 0x6d, 0x51, 0x24, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x0a,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x73, 0x65, 0x63, 0x72, 0x65, 0x74, 0x2e, 0x74, 0x78, 0x74, 0x50, 0x4b, 0x05,
+        0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x38, 0x00, 0x00, 0x00, 0x4c, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_zip_rejects_password_on_compress_only() {
+        let compressor = ZipCompressor::new();
+        let entries = vec![ArchiveEntry::builder("secret.txt")
+            .data(b"top secret".to_vec())
+            .build()];
+
+        assert!(!compressor.supports_password());
+        assert!(matches!(
+            compressor.compress(&entries, Some("hunter2")),
+            Err(LatError::UnsupportedFormat(_))
+        ));
+
+        // Members that aren't encrypted read the same with or without a password.
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let decompressed = compressor.decompress(&compressed, Some("hunter2")).unwrap();
+        assert_eq!(decompressed[0].data, b"top secret");
+
+        let legacy = compressor
+            .decompress(&ZIPCRYPTO_ARCHIVE, Some("hunter2"))
+            .expect("ZipCrypto member should decrypt");
+        assert_eq!(legacy[0].name, "secret.txt");
+        assert_eq!(legacy[0].data, b"legacy zipcrypto member\n");
+        let matched = compressor
+            .decompress_matching(&ZIPCRYPTO_ARCHIVE, "*.txt", Some("hunter2"))
+            .unwrap();
+        assert_eq!(matched[0].data, legacy[0].data);
+        assert!(matches!(
+            compressor.decompress(&ZIPCRYPTO_ARCHIVE, Some("wrong")),
+            Err(LatError::WrongPassword)
+        ));
+        assert!(compressor.decompress(&ZIPCRYPTO_ARCHIVE, None).is_err());
+    }
+
+    #[test]
+    fn test_zip_decompress_chunked() {
+        let payload: Vec<u8> = (0..1_000_000u32).map(|i| (i * 31 % 256) as u8).collect();
+        let entries = vec![
+            ArchiveEntry::builder("small.txt")
+                .data(b"tiny".to_vec())
+                .build(),
+            ArchiveEntry::builder("large.bin")
+                .data(payload.clone())
+                .build(),
+        ];
+
+        let compressed = ZipCompressor::new()
+            .compress(&entries, None)
+            .expect("Compression failed");
+        let decompressed = ZipCompressor::new()
+            .with_read_chunk_size(1024)
+            .decompress(&compressed, None)
+            .expect("Decompression failed");
+
+        assert_eq!(b"tiny".to_vec(), decompressed[0].data);
+        assert_eq!(payload, decompressed[1].data);
+    }
+
+    #[test]
+    fn test_zip_store_threshold() {
+        let entries = vec![ArchiveEntry::builder("tiny.txt")
+            .data(b"aaaaaaaaaa".to_vec())
+            .build()];
+        let compressor = ZipCompressor::new().with_store_threshold(64);
+
+        let result = compressor.compress_detailed(&entries, None).unwrap();
+        assert_eq!(result.per_entry[0].method_used, "Stored");
+        assert_eq!(result.per_entry[0].compressed, 10);
+        let decompressed = compressor.decompress(&result.data, None).unwrap();
+        assert_eq!(decompressed[0].data, entries[0].data);
+
+        // At or above the threshold the configured method is used again.
+        let result = ZipCompressor::new()
+            .with_store_threshold(10)
+            .compress_detailed(&entries, None)
+            .unwrap();
+        assert_eq!(result.per_entry[0].method_used, "Deflated");
+    }
+
+    #[test]
+    fn test_zip_decompress_from_file() {
+        let entries: Vec<ArchiveEntry> = ["a.txt", "dir/b.bin", "empty"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(name.repeat(200).into_bytes())
+                    .build()
+            })
+            .collect();
+        let compressor = ZipCompressor::new();
+        let compressed = compressor.compress(&entries, None).unwrap();
+        let path = std::env::temp_dir().join(format!("lat-zip-reader-{}.zip", std::process::id()));
+        std::fs::write(&path, &compressed).unwrap();
+
+        let from_file = compressor
+            .decompress_reader(std::fs::File::open(&path).unwrap(), None)
+            .expect("Decompression failed");
+        let from_slice = compressor.decompress(&compressed, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_file.len(), from_slice.len());
+        for (a, b) in from_file.iter().zip(&from_slice) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.data, b.data);
+        }
+    }
+
+    #[test]
+    fn test_zip_malformed_input_returns_err() {
+        let mut eocd_overrun = EOCD_SIGNATURE.to_le_bytes().to_vec();
+        // Disk numbers, 65535 entries, a central directory larger than the file.
+        eocd_overrun.extend_from_slice(&[0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        eocd_overrun.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0]);
+        let mut local_only = recover::LOCAL_HEADER_SIGNATURE.to_le_bytes().to_vec();
+        local_only.extend_from_slice(&[0xff; 26]);
+        for malformed in [&b""[..], b"PK", &[0xA5; 64], &eocd_overrun, &local_only] {
+            assert!(lat_core::try_decompress(malformed, &ZipFormat).is_err());
+        }
+
+        let entries = vec![ArchiveEntry::builder("a.txt")
+            .data(b"fuzz me ".repeat(64))
+            .build()];
+        let archive = ZipCompressor::new().compress(&entries, None).unwrap();
+        for len in 0..archive.len() {
+            assert!(
+                lat_core::try_decompress(&archive[..len], &ZipFormat).is_err(),
+                "prefix of {} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_zip_quick_verify_samples_entries() {
+        let payload = |i: usize| format!("entry {:02} ", i).repeat(50).into_bytes();
+        let entries: Vec<ArchiveEntry> = (0..20)
+            .map(|i| {
+                ArchiveEntry::builder(format!("e{}.txt", i))
+                    .data(payload(i))
+                    .build()
+            })
+            .collect();
+        let compressor = ZipCompressor::new().with_method(CompressionMethod::Stored);
+        let archive = compressor.compress(&entries, None).unwrap();
+        let sample = EntrySample::new(0.2).with_seed(42);
+        let picked = sample.pick(entries.len());
+        assert_eq!(
+            compressor.quick_verify(&archive, None, &sample).unwrap(),
+            VerifyReport {
+                entries_checked: 4,
+                entries_total: 20
+            }
+        );
+
+        // Stored, so each payload appears verbatim in the archive.
+        let corrupt = |index: usize| {
+            let mut damaged = archive.clone();
+            let needle = payload(index);
+            let at = damaged
+                .windows(needle.len())
+                .position(|window| window == needle)
+                .unwrap();
+            damaged[at + 100] ^= 0xff;
+            damaged
+        };
+        let sampled = corrupt(picked[0]);
+        assert!(matches!(
+            compressor.quick_verify(&sampled, None, &sample),
+            Err(LatError::CorruptArchive(_))
+        ));
+        // Damage outside the sample is only caught by the full check.
+        let unsampled = corrupt((0..20).find(|i| !picked.contains(i)).unwrap());
+        assert!(compressor.quick_verify(&unsampled, None, &sample).is_ok());
+        assert!(compressor.verify(&unsampled, None).is_err());
+    }
+
+    #[test]
+    fn test_zip_archives_piped_data_under_a_name() {
+        let piped: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let entry = lat_core::entry_from_reader("data.bin", Cursor::new(piped.clone())).unwrap();
+
+        let compressor = ZipCompressor::new();
+        let archive = compressor.compress(&[entry], None).unwrap();
+        let extracted = compressor.decompress(&archive, None).unwrap();
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(extracted[0].name, "data.bin");
+        assert_eq!(extracted[0].data, piped);
+
+        assert!(lat_core::entry_from_reader(" ", Cursor::new(b"x")).is_err());
+    }
+
+    #[test]
+    fn test_zip_reports_format() {
+        let compressor: Box<dyn Compressor> = Box::new(ZipCompressor::new());
+        assert_eq!(compressor.format(), ArchiveFormat::Zip);
+        assert_eq!(compressor.format().extension(), "zip");
+    }
 }
@@ -0,0 +1,140 @@
+//! Reading entries from local file headers alone, for archives whose central directory is
+//! missing: a [`ZipAppender`](crate::ZipAppender) that was never finalized, or a copy
+//! that was cut short.
+
+use crate::{extra, read_u16, read_u32};
+use flate2::{Decompress, FlushDecompress, Status};
+use lat_core::ArchiveEntry;
+
+pub(crate) const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+pub(crate) const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+pub(crate) const LOCAL_HEADER_LEN: usize = 30;
+/// General purpose bit 3: CRC and sizes follow the data instead of sitting in the header.
+pub(crate) const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+pub(crate) const METHOD_STORED: u16 = 0;
+pub(crate) const METHOD_DEFLATED: u16 = 8;
+
+/// An entry whose local header, data and CRC all checked out.
+pub(crate) struct LocalEntry {
+    pub entry: ArchiveEntry,
+    pub offset: usize,
+    pub flags: u16,
+    pub method: u16,
+    /// DOS time in the low half, date in the high half, as stored in the header.
+    pub dos_datetime: u32,
+    pub crc: u32,
+    pub compressed: usize,
+}
+
+/// Walks local headers from the start of `archive`, stopping at the central directory (or
+/// anything else that isn't a local header) and at the first entry that is truncated or
+/// fails its CRC. Returns the good entries and the offset just past the last of them.
+pub(crate) fn scan(archive: &[u8]) -> (Vec<LocalEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while let Some((entry, end)) = read_local(archive, pos) {
+        entries.push(entry);
+        pos = end;
+    }
+    (entries, pos)
+}
+
+fn read_local(archive: &[u8], at: usize) -> Option<(LocalEntry, usize)> {
+    if read_u32(archive, at)? != LOCAL_HEADER_SIGNATURE {
+        return None;
+    }
+    let flags = read_u16(archive, at + 6)?;
+    let method = read_u16(archive, at + 8)?;
+    let dos_datetime = read_u32(archive, at + 10)?;
+    let name_len = read_u16(archive, at + 26)? as usize;
+    let extra_len = read_u16(archive, at + 28)? as usize;
+    // Encrypted entries can't be checked without the password.
+    if flags & 1 != 0 {
+        return None;
+    }
+
+    let name_start = at + LOCAL_HEADER_LEN;
+    let extra_start = name_start + name_len;
+    let data_start = extra_start + extra_len;
+    let name = std::str::from_utf8(archive.get(name_start..extra_start)?).ok()?;
+    let extra_field = archive.get(extra_start..data_start)?;
+
+    let descriptor = flags & FLAG_DATA_DESCRIPTOR != 0;
+    let (data, data_end) = match method {
+        // Without a descriptor the header has the size; with one, only a self-terminating
+        // stream says where the data ends.
+        METHOD_STORED if !descriptor => {
+            let len = read_u32(archive, at + 18)? as usize;
+            let end = data_start.checked_add(len)?;
+            (archive.get(data_start..end)?.to_vec(), end)
+        }
+        METHOD_DEFLATED => {
+            let (data, used) = inflate(archive.get(data_start..)?)?;
+            (data, data_start + used)
+        }
+        _ => return None,
+    };
+
+    let (crc, compressed, size, end) = if descriptor {
+        let mut pos = data_end;
+        // The descriptor signature is optional.
+        if read_u32(archive, pos)? == DATA_DESCRIPTOR_SIGNATURE {
+            pos += 4;
+        }
+        (
+            read_u32(archive, pos)?,
+            read_u32(archive, pos + 4)? as usize,
+            read_u32(archive, pos + 8)? as usize,
+            pos + 12,
+        )
+    } else {
+        (
+            read_u32(archive, at + 14)?,
+            read_u32(archive, at + 18)? as usize,
+            read_u32(archive, at + 22)? as usize,
+            data_end,
+        )
+    };
+    if compressed != data_end - data_start || size != data.len() || crc32fast::hash(&data) != crc {
+        return None;
+    }
+
+    let mut entry = ArchiveEntry::builder(name.to_string()).data(data).build();
+    extra::decode(extra_field, &mut entry);
+    Some((
+        LocalEntry {
+            entry,
+            offset: at,
+            flags,
+            method,
+            dos_datetime,
+            crc,
+            compressed,
+        },
+        end,
+    ))
+}
+
+/// Inflates one raw DEFLATE stream from the start of `input`, returning the data and the
+/// number of input bytes it took. `None` if the stream is truncated or malformed.
+fn inflate(input: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut inflater = Decompress::new(false);
+    let mut out = Vec::with_capacity(input.len().saturating_mul(2).min(1 << 20));
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.len().max(64 * 1024));
+        }
+        let (before_in, before_out) = (inflater.total_in(), inflater.total_out());
+        let consumed = before_in as usize;
+        let status = inflater
+            .decompress_vec(&input[consumed..], &mut out, FlushDecompress::None)
+            .ok()?;
+        if status == Status::StreamEnd {
+            return Some((out, inflater.total_in() as usize));
+        }
+        if inflater.total_in() == before_in && inflater.total_out() == before_out {
+            // No progress with room to write: the input ran out mid-stream.
+            return None;
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! ZIP's text flag: bit 0 of a member's internal attributes, which tools such as
+//! `unzip -a` use to decide whether to convert line endings.
+//!
+//! The `zip` crate always writes the internal attributes as zero, so [`Tap`] keeps a copy
+//! of the central directory as it is written and the flags are patched in place
+//! afterwards, without reading the output back.
+
+use crate::{central_headers, find_central_directory};
+use lat_core::LatError;
+use std::cell::RefCell;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::rc::Rc;
+
+const TEXT_FLAG: u16 = 1;
+/// Offset of the internal attributes in a central directory file header.
+const INTERNAL_ATTRIBUTES: usize = 36;
+/// An end record field that has moved to the Zip64 record.
+const ZIP64_MARKER: usize = 0xFFFF_FFFF;
+
+/// Whether `data` looks like text: non-empty, valid UTF-8 and free of NUL bytes.
+pub fn looks_like_text(data: &[u8]) -> bool {
+    !data.is_empty() && !data.contains(&0) && std::str::from_utf8(data).is_ok()
+}
+
+struct Capture {
+    pos: u64,
+    from: Option<u64>,
+    bytes: Vec<u8>,
+}
+
+/// Passes writes through to the archive's writer, keeping a copy of everything written
+/// after [`TapHandle::capture_from_here`].
+pub struct Tap<W> {
+    inner: W,
+    capture: Rc<RefCell<Capture>>,
+}
+
+/// Starts the capture while the `ZipWriter` owns the [`Tap`].
+pub struct TapHandle(Rc<RefCell<Capture>>);
+
+impl TapHandle {
+    pub fn capture_from_here(&self) {
+        let mut capture = self.0.borrow_mut();
+        capture.from = Some(capture.pos);
+    }
+}
+
+impl<W: Write + Seek> Tap<W> {
+    pub fn new(mut inner: W) -> io::Result<(Self, TapHandle)> {
+        let capture = Rc::new(RefCell::new(Capture {
+            pos: inner.stream_position()?,
+            from: None,
+            bytes: Vec::new(),
+        }));
+        let handle = TapHandle(capture.clone());
+        Ok((Self { inner, capture }, handle))
+    }
+
+    /// Sets the text flag of each member marked in `text` (in archive order) in the
+    /// captured central directory, and returns the writer positioned at the end. Archives
+    /// with a Zip64 central directory are left unflagged.
+    pub fn finish(mut self, text: &[bool]) -> Result<W, LatError> {
+        let patches = {
+            let capture = self.capture.borrow();
+            let (Some(from), Ok((cd_offset, cd_size))) =
+                (capture.from, find_central_directory(&capture.bytes))
+            else {
+                return Ok(self.inner);
+            };
+            if cd_offset == ZIP64_MARKER || cd_size == ZIP64_MARKER {
+                return Ok(self.inner);
+            }
+            // The capture can start with the tail of the last member's data, which the
+            // writer flushes on finishing.
+            let cd = (cd_offset as u64)
+                .checked_sub(from)
+                .map(|start| start as usize)
+                .and_then(|start| capture.bytes.get(start..start.checked_add(cd_size)?))
+                .ok_or_else(|| {
+                    LatError::CorruptArchive("central directory out of bounds".to_string())
+                })?;
+            let offsets: Vec<u64> = central_headers(cd)?
+                .into_iter()
+                .zip(text)
+                .filter(|(_, &text)| text)
+                .map(|(pos, _)| (cd_offset + pos + INTERNAL_ATTRIBUTES) as u64)
+                .collect();
+            (offsets, capture.pos)
+        };
+        let (offsets, end) = patches;
+        for offset in offsets {
+            self.inner.seek(SeekFrom::Start(offset))?;
+            self.inner.write_all(&TEXT_FLAG.to_le_bytes())?;
+        }
+        self.inner.seek(SeekFrom::Start(end))?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Tap<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let mut capture = self.capture.borrow_mut();
+        if capture.from.is_some_and(|from| capture.pos >= from) {
+            capture.bytes.extend_from_slice(&buf[..n]);
+        }
+        capture.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for Tap<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = self.inner.seek(pos)?;
+        self.capture.borrow_mut().pos = pos;
+        Ok(pos)
+    }
+}
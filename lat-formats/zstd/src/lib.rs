@@ -0,0 +1,360 @@
+pub mod seekable;
+
+use lat_core::store::BlockStore;
+use lat_core::{
+    glob_matcher, ArchiveEntry, ArchiveFormat, ArchiveInfo, Compressor, CompressorConfig,
+    FormatFactory, LatError,
+};
+use std::io::{Read, Seek, SeekFrom};
+
+pub use seekable::SeekableReader;
+
+/// Entry name given to a plain `.zst` stream written by another tool.
+const RAW_STREAM_NAME: &str = "data";
+
+/// Uncompressed bytes per seekable frame unless configured otherwise.
+pub const DEFAULT_FRAME_SIZE: usize = 1 << 20;
+
+/// Zstandard as a standalone format, written in the seekable layout (see [`seekable`]).
+/// Entries are packed with [`lat_core::index`] first; a plain `.zst` from `zstd`
+/// decompresses to one entry named `data`.
+///
+/// Smaller frames make random access cheaper and compression slightly worse.
+#[derive(Clone)]
+pub struct ZstdCompressor {
+    level: i32,
+    frame_size: usize,
+}
+
+impl Default for ZstdCompressor {
+    fn default() -> Self {
+        Self {
+            // Same default as the `zstd` tool.
+            level: 3,
+            frame_size: DEFAULT_FRAME_SIZE,
+        }
+    }
+}
+
+/// Builds [`ZstdCompressor`]s for [`lat_core::CompressorBuilder`]. Understands `level`
+/// (1-22); `threads` and `method` have no effect.
+pub struct ZstdFormat;
+
+impl FormatFactory for ZstdFormat {
+    fn build(&self, config: &CompressorConfig) -> Result<Box<dyn Compressor>, LatError> {
+        let mut compressor = ZstdCompressor::new();
+        if let Some(level) = config.level {
+            compressor = compressor.with_level(level);
+        }
+        Ok(Box::new(compressor))
+    }
+}
+
+impl ZstdCompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the zstd level, clamped to 1-22.
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level.clamp(1, 22) as i32;
+        self
+    }
+
+    /// Sets how many uncompressed bytes go into each frame, clamped to
+    /// 1..=[`seekable::MAX_FRAME_SIZE`].
+    pub fn with_frame_size(mut self, bytes: usize) -> Self {
+        self.frame_size = bytes.clamp(1, seekable::MAX_FRAME_SIZE);
+        self
+    }
+}
+
+fn corrupt(e: std::io::Error) -> LatError {
+    LatError::CorruptArchive(format!("zstd: {}", e))
+}
+
+/// The entries whose names pass `matches`, read by seeking past the others. `None` when the
+/// stream doesn't start with an entry index.
+fn seek_matching<S: BlockStore>(
+    mut reader: SeekableReader<S>,
+    matches: impl Fn(&str) -> bool,
+) -> Result<Option<Vec<ArchiveEntry>>, LatError> {
+    let Some(index) = lat_core::index::read_index(&mut reader)? else {
+        return Ok(None);
+    };
+    let mut offset = reader.stream_position()?;
+    let mut entries = Vec::new();
+    for (name, size) in index {
+        let end = offset
+            .checked_add(size)
+            .filter(|&end| end <= reader.len())
+            .ok_or_else(|| LatError::CorruptArchive("entry index: truncated".to_string()))?;
+        if matches(&name) {
+            // Sizes come from the archive, so the buffer grows with what actually decodes.
+            let mut data = Vec::new();
+            reader.seek(SeekFrom::Start(offset))?;
+            reader
+                .by_ref()
+                .take(size)
+                .read_to_end(&mut data)
+                .map_err(corrupt)?;
+            if data.len() as u64 != size {
+                return Err(LatError::CorruptArchive(format!("{}: truncated", name)));
+            }
+            entries.push(ArchiveEntry::builder(name).data(data).build());
+        }
+        offset = end;
+    }
+    Ok(Some(entries))
+}
+
+impl Compressor for ZstdCompressor {
+    fn compress(
+        &self,
+        entries: &[ArchiveEntry],
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, LatError> {
+        self.check_password(password)?;
+        lat_core::validate_entries(entries)?;
+        let packed = lat_core::index::pack(entries)?;
+        seekable::write(&packed, self.frame_size, self.level)
+    }
+
+    fn decompress(
+        &self,
+        archive_data: &[u8],
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        self.check_password(password)?;
+        // The seek table is a skippable frame, so one pass reads either layout.
+        let stream = zstd::stream::decode_all(archive_data).map_err(corrupt)?;
+        lat_core::index::unpack(stream, RAW_STREAM_NAME)
+    }
+
+    /// With a seek table, reads the entry index from the first frame and then decodes
+    /// only the frames holding matching entries.
+    fn decompress_matching(
+        &self,
+        archive_data: &[u8],
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let matcher = glob_matcher(pattern)?;
+        if seekable::is_seekable(archive_data) {
+            self.check_password(password)?;
+            if let Some(entries) = seek_matching(SeekableReader::new(archive_data)?, |name| {
+                matcher.is_match(name)
+            })? {
+                return Ok(entries);
+            }
+        }
+        let mut entries = self.decompress(archive_data, password)?;
+        entries.retain(|entry| matcher.is_match(&entry.name));
+        Ok(entries)
+    }
+
+    /// With a seek table, fetches only the table and the frames holding the entry index
+    /// and the matching entries.
+    fn decompress_matching_from(
+        &self,
+        store: &dyn BlockStore,
+        pattern: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, LatError> {
+        let matcher = glob_matcher(pattern)?;
+        self.check_password(password)?;
+        match SeekableReader::new(store) {
+            Ok(reader) => {
+                if let Some(entries) = seek_matching(reader, |name| matcher.is_match(name))? {
+                    return Ok(entries);
+                }
+            }
+            Err(LatError::UnsupportedFormat(_)) => {}
+            Err(e) => return Err(e),
+        }
+        let mut entries = self.decompress(&lat_core::store::read_all(store)?, password)?;
+        entries.retain(|entry| matcher.is_match(&entry.name));
+        Ok(entries)
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Zstd
+    }
+
+    fn archive_info(&self, archive_data: &[u8]) -> Result<ArchiveInfo, LatError> {
+        if !archive_data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            return Err(LatError::CorruptArchive(
+                "missing zstd signature".to_string(),
+            ));
+        }
+        // The entry index opens the first frame, so only that much is decoded.
+        let mut decoder =
+            zstd::stream::read::Decoder::with_buffer(archive_data).map_err(corrupt)?;
+        let entry_count = lat_core::index::entry_count(&mut decoder).map_err(|e| match e {
+            LatError::Io(e) => corrupt(e),
+            e => e,
+        })?;
+        Ok(ArchiveInfo {
+            format: "zstd",
+            creator_version: None,
+            host_os: None,
+            entry_count,
+            is_encrypted: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lat_core::store::CountingStore;
+
+    fn entries() -> Vec<ArchiveEntry> {
+        vec![
+            ArchiveEntry::builder("a.txt")
+                .data(b"zstd likes repetition ".repeat(400))
+                .build(),
+            ArchiveEntry::builder("empty").build(),
+        ]
+    }
+
+    #[test]
+    fn test_zstd_round_trip() {
+        let entries = entries();
+        let compressor = ZstdCompressor::new().with_level(19).with_frame_size(1000);
+        let compressed = compressor.compress(&entries, None).unwrap();
+        assert_eq!(compressor.archive_info(&compressed).unwrap().entry_count, 2);
+
+        let decompressed = compressor.decompress(&compressed, None).unwrap();
+        assert_eq!(decompressed.len(), entries.len());
+        for (a, b) in entries.iter().zip(&decompressed) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.data, b.data);
+        }
+
+        // A plain stream from the `zstd` tool has no seek table and no index.
+        let plain = zstd::bulk::compress(b"plain", 3).unwrap();
+        let raw = compressor.decompress(&plain, None).unwrap();
+        assert_eq!(
+            (raw[0].name.as_str(), raw[0].data.as_slice()),
+            ("data", &b"plain"[..])
+        );
+        assert!(matches!(
+            SeekableReader::new(&plain),
+            Err(LatError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_zstd_reads_a_range_without_earlier_frames() {
+        let stream: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let mut archive = seekable::write(&stream, 4096, 3).unwrap();
+        {
+            let reader = SeekableReader::new(&archive).unwrap();
+            assert_eq!(reader.len(), stream.len() as u64);
+            assert_eq!(reader.frame_count(), stream.len().div_ceil(4096));
+        }
+
+        // Break the first frame: a range further in must still read, since only the
+        // frames holding it are decompressed.
+        archive[4..12].fill(0xFF);
+        let mut reader = SeekableReader::new(&archive).unwrap();
+        let mut range = vec![0; 10_000];
+        reader.seek(SeekFrom::Start(200_001)).unwrap();
+        reader.read_exact(&mut range).unwrap();
+        assert_eq!(range, stream[200_001..210_001]);
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert!(reader.read_exact(&mut [0; 16]).is_err());
+    }
+
+    #[test]
+    fn test_zstd_rejects_seek_tables_claiming_too_much() {
+        // A few bytes of table whose empty frames each claim the largest frame size.
+        let mut archive = Vec::new();
+        archive.extend_from_slice(&0x184D_2A5Eu32.to_le_bytes());
+        archive.extend_from_slice(&(1000 * 8 + 9u32).to_le_bytes());
+        for _ in 0..1000 {
+            archive.extend_from_slice(&0u32.to_le_bytes());
+            archive.extend_from_slice(&(seekable::MAX_FRAME_SIZE as u32).to_le_bytes());
+        }
+        archive.extend_from_slice(&1000u32.to_le_bytes());
+        archive.push(0);
+        archive.extend_from_slice(&0x8F92_EAB1u32.to_le_bytes());
+        assert!(matches!(
+            SeekableReader::new(&archive),
+            Err(LatError::CorruptArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_zstd_extracts_one_entry_by_seeking() {
+        let entries = vec![
+            ArchiveEntry::builder("big.bin")
+                .data(
+                    (0..50_000u32)
+                        .flat_map(|i| i.to_le_bytes())
+                        .collect::<Vec<u8>>(),
+                )
+                .build(),
+            ArchiveEntry::builder("notes.txt")
+                .data(b"only this one".to_vec())
+                .build(),
+        ];
+        let compressor = ZstdCompressor::new().with_frame_size(4096);
+        let archive = compressor.compress(&entries, None).unwrap();
+        let matched = compressor
+            .decompress_matching(&archive, "*.txt", None)
+            .unwrap();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].name, "notes.txt");
+        assert_eq!(matched[0].data, b"only this one");
+    }
+
+    #[test]
+    fn test_zstd_compresses_a_mapped_file_like_a_read_one() {
+        let path = std::env::temp_dir().join(format!("lat-zstd-mmap-{}", std::process::id()));
+        let stream: Vec<u8> = (0..100_000u32)
+            .flat_map(|i| (i / 7).to_le_bytes())
+            .collect();
+        std::fs::write(&path, &stream).unwrap();
+
+        let mapped = lat_core::mmap::MappedInput::open_with_threshold(&path, 0).unwrap();
+        assert!(mapped.is_mapped());
+        let from_map = seekable::write(&mapped, 4096, 3).unwrap();
+        mapped.check_unchanged().unwrap();
+        let read = std::fs::read(&path).unwrap();
+        assert_eq!(from_map, seekable::write(&read, 4096, 3).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_zstd_fetches_only_matching_frames_from_a_store() {
+        let entries: Vec<ArchiveEntry> = (0..200u32)
+            .map(|i| {
+                let data: Vec<u8> = (0..2000u32).flat_map(|j| (i * j).to_le_bytes()).collect();
+                ArchiveEntry::builder(format!("part{:03}.bin", i))
+                    .data(data)
+                    .build()
+            })
+            .collect();
+        let compressor = ZstdCompressor::new().with_frame_size(4096);
+        let store = CountingStore::new(compressor.compress(&entries, None).unwrap());
+
+        let matched = compressor
+            .decompress_matching_from(&store, "part150.bin", None)
+            .unwrap();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].data, entries[150].data);
+        assert!(
+            store.fetched() * 10 < store.len(),
+            "fetched {} of {} bytes",
+            store.fetched(),
+            store.len()
+        );
+
+        let all = compressor
+            .decompress_matching_from(&store, "*", None)
+            .unwrap();
+        assert_eq!(all.len(), entries.len());
+    }
+}
@@ -0,0 +1,242 @@
+//! The seekable zstd format: the stream is cut into independently compressed frames,
+//! followed by a seek table in a skippable frame, so any byte range can be read by
+//! decompressing only the frames that hold it. Decoders that don't know the format skip
+//! the table and see an ordinary `.zst`.
+//!
+//! ```text
+//! frame* | 0x184D2A5E u32 | table_len u32 | (compressed u32 | decompressed u32 [| checksum u32])*
+//!        | frame_count u32 | descriptor u8 | 0x8F92EAB1 u32
+//! ```
+//!
+//! All integers little-endian. Bit 7 of the descriptor says whether entries carry a
+//! checksum; this crate writes none and ignores the ones it reads.
+
+use lat_core::store::BlockStore;
+use lat_core::LatError;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+const SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+const SKIPPABLE_HEADER_LEN: usize = 8;
+const FOOTER_LEN: usize = 9;
+const CHECKSUM_FLAG: u8 = 0x80;
+const RESERVED_BITS: u8 = 0x7C;
+
+/// Largest frame this crate writes or reads; the seek table decides how much a frame
+/// decompresses to, so it bounds what a hostile table can make us allocate.
+pub const MAX_FRAME_SIZE: usize = 1 << 30;
+
+/// Most a zstd frame decompresses to per compressed byte: an RLE block, the densest
+/// encoding, spends 4 bytes on at most 128 KiB. Seek table entries claiming more are
+/// corrupt, which keeps the sizes a table can claim proportional to the archive.
+const MAX_FRAME_EXPANSION: usize = 32 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    compressed_offset: u64,
+    compressed_len: usize,
+    decompressed_offset: u64,
+    decompressed_len: usize,
+}
+
+fn corrupt(what: &str) -> LatError {
+    LatError::CorruptArchive(format!("zstd seek table: {}", what))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Compresses `stream` as frames of `frame_size` bytes (the last may be shorter), then
+/// appends the seek table.
+pub fn write(stream: &[u8], frame_size: usize, level: i32) -> Result<Vec<u8>, LatError> {
+    let frame_size = frame_size.clamp(1, MAX_FRAME_SIZE);
+    let mut out = Vec::with_capacity(stream.len() / 2);
+    let mut table = Vec::new();
+    for chunk in stream.chunks(frame_size) {
+        let frame = zstd::bulk::compress(chunk, level)?;
+        let compressed = u32::try_from(frame.len())
+            .map_err(|_| LatError::InvalidInput("zstd frame too large".to_string()))?;
+        table.extend_from_slice(&compressed.to_le_bytes());
+        table.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        out.extend_from_slice(&frame);
+    }
+    let frame_count = u32::try_from(table.len() / 8)
+        .map_err(|_| LatError::InvalidInput("too many zstd frames".to_string()))?;
+    out.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+    out.extend_from_slice(&((table.len() + FOOTER_LEN) as u32).to_le_bytes());
+    out.extend_from_slice(&table);
+    out.extend_from_slice(&frame_count.to_le_bytes());
+    out.push(0);
+    out.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+    Ok(out)
+}
+
+/// Whether `archive` ends with a seek table.
+pub fn is_seekable(archive: &[u8]) -> bool {
+    archive.len() >= FOOTER_LEN && u32_at(archive, archive.len() - 4) == SEEKABLE_MAGIC
+}
+
+/// Reads the seek table from the end of `store`, fetching only the table. The frames must
+/// cover everything before it.
+fn parse_table<S: BlockStore>(store: &S) -> Result<Vec<Frame>, LatError> {
+    let archive_len = store.len();
+    let footer = match archive_len.checked_sub(FOOTER_LEN as u64) {
+        Some(at) => store.read_at(at, FOOTER_LEN)?,
+        None => Vec::new(),
+    };
+    if footer.len() != FOOTER_LEN || u32_at(&footer, FOOTER_LEN - 4) != SEEKABLE_MAGIC {
+        return Err(LatError::UnsupportedFormat(
+            "zstd data has no seek table".to_string(),
+        ));
+    }
+    let count = u32_at(&footer, 0) as usize;
+    let descriptor = footer[4];
+    if descriptor & RESERVED_BITS != 0 {
+        return Err(corrupt("reserved bits set"));
+    }
+    let entry_len = if descriptor & CHECKSUM_FLAG != 0 {
+        12
+    } else {
+        8
+    };
+    let table_len = count
+        .checked_mul(entry_len)
+        .and_then(|len| len.checked_add(SKIPPABLE_HEADER_LEN + FOOTER_LEN))
+        .filter(|&len| len as u64 <= archive_len)
+        .ok_or_else(|| corrupt("truncated"))?;
+    let table_start = archive_len - table_len as u64;
+    let table = store.read_at(table_start, table_len)?;
+    if table.len() != table_len
+        || u32_at(&table, 0) != SKIPPABLE_MAGIC
+        || u32_at(&table, 4) as usize != table_len - SKIPPABLE_HEADER_LEN
+    {
+        return Err(corrupt("bad frame header"));
+    }
+
+    let mut frames = Vec::with_capacity(count);
+    let (mut compressed_offset, mut decompressed_offset) = (0, 0);
+    for entry in table[SKIPPABLE_HEADER_LEN..]
+        .chunks_exact(entry_len)
+        .take(count)
+    {
+        let compressed_len = u32_at(entry, 0) as usize;
+        let decompressed_len = u32_at(entry, 4) as usize;
+        if decompressed_len > MAX_FRAME_SIZE {
+            return Err(corrupt("frame too large"));
+        }
+        if decompressed_len > compressed_len.saturating_mul(MAX_FRAME_EXPANSION) {
+            return Err(corrupt("frame expands more than zstd can"));
+        }
+        frames.push(Frame {
+            compressed_offset,
+            compressed_len,
+            decompressed_offset,
+            decompressed_len,
+        });
+        compressed_offset += compressed_len as u64;
+        decompressed_offset += decompressed_len as u64;
+    }
+    if compressed_offset != table_start {
+        return Err(corrupt("frame sizes don't match the data"));
+    }
+    Ok(frames)
+}
+
+/// Random access to the decompressed stream of a seekable archive. A read decompresses
+/// only the frames it touches, and the last frame stays cached for the next read.
+///
+/// The archive can be in memory (`&[u8]`) or any [`BlockStore`], from which only the seek
+/// table and the frames read are fetched.
+pub struct SeekableReader<S> {
+    store: S,
+    frames: Vec<Frame>,
+    len: u64,
+    pos: u64,
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+impl<S: BlockStore> SeekableReader<S> {
+    /// Fails with [`LatError::UnsupportedFormat`] for zstd data without a seek table.
+    pub fn new(store: S) -> Result<Self, LatError> {
+        let frames = parse_table(&store)?;
+        let len = frames.last().map_or(0, |last| {
+            last.decompressed_offset + last.decompressed_len as u64
+        });
+        Ok(Self {
+            store,
+            frames,
+            len,
+            pos: 0,
+            cached: None,
+        })
+    }
+
+    /// Length of the decompressed stream.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of frames in the archive.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn frame(&mut self, index: usize) -> io::Result<&[u8]> {
+        if self.cached.as_ref().map(|(cached, _)| *cached) != Some(index) {
+            let frame = self.frames[index];
+            let compressed = self
+                .store
+                .read_at(frame.compressed_offset, frame.compressed_len)
+                .map_err(io::Error::other)?;
+            let data = zstd::bulk::decompress(&compressed, frame.decompressed_len)?;
+            if data.len() != frame.decompressed_len {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "zstd frame size doesn't match the seek table",
+                ));
+            }
+            self.cached = Some((index, data));
+        }
+        Ok(&self.cached.as_ref().unwrap().1)
+    }
+}
+
+impl<S: BlockStore> Read for SeekableReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+        let pos = self.pos;
+        let index = self
+            .frames
+            .partition_point(|f| f.decompressed_offset + f.decompressed_len as u64 <= pos);
+        let from = (pos - self.frames[index].decompressed_offset) as usize;
+        let data = &self.frame(index)?[from..];
+        let n = buf.len().min(data.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: BlockStore> Seek for SeekableReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start of the stream",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
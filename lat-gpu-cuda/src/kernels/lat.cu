@@ -11,3 +11,27 @@ extern "C" __global__ void lat_find_matches(
         // ...
     }
 }
+
+// Byte histogram: each block counts its grid-stride share into shared memory, then adds
+// its partial counts into the global histogram.
+extern "C" __global__ void lat_byte_histogram(
+    const unsigned char* data,
+    unsigned long long data_len,
+    unsigned long long* histogram
+) {
+    __shared__ unsigned int local[256];
+    for (int i = threadIdx.x; i < 256; i += blockDim.x) {
+        local[i] = 0;
+    }
+    __syncthreads();
+
+    unsigned long long stride = (unsigned long long)blockDim.x * gridDim.x;
+    for (unsigned long long i = blockIdx.x * blockDim.x + threadIdx.x; i < data_len; i += stride) {
+        atomicAdd(&local[data[i]], 1u);
+    }
+    __syncthreads();
+
+    for (int i = threadIdx.x; i < 256; i += blockDim.x) {
+        atomicAdd(&histogram[i], (unsigned long long)local[i]);
+    }
+}
@@ -1,21 +1,35 @@
+#define SQUASH_LIMIT 12.0f
+
+// Linear interpolation in `samples` (table_size + 1 values) at t in [0, 1]; matches
+// lat_core::logistic::LogisticTable so the GPU and CPU mixers agree.
+__device__ float interpolate(const float* samples, int table_size, float t) {
+    float pos = t * table_size;
+    int i = min((int)pos, table_size - 1);
+    float frac = pos - i;
+    return samples[i] + (samples[i + 1] - samples[i]) * frac;
+}
+
 extern "C" __global__ void paq_mix_probabilities(
     const float* model_probs,
     const float* weights,
+    const float* stretch_table,
+    const float* squash_table,
     float* output_probs,
     int num_models,
-    int num_bits
+    int num_bits,
+    int table_size
 ) {
     int idx = blockIdx.x * blockDim.x + threadIdx.x;
     if (idx < num_bits) {
-        float mixed_p = 0.0f;
-        float total_w = 0.0f;
+        float dot = 0.0f;
         // Bolt ⚡ Optimization: Using coalesced memory access pattern [num_models][num_bits].
         // This allows adjacent threads to access adjacent memory, significantly improving throughput.
         for (int i = 0; i < num_models; ++i) {
             float w = weights[i * num_bits + idx];
-            mixed_p += w * model_probs[i * num_bits + idx];
-            total_w += w;
+            float p = fminf(fmaxf(model_probs[i * num_bits + idx], 0.0f), 1.0f);
+            dot += w * interpolate(stretch_table, table_size, p);
         }
-        output_probs[idx] = mixed_p / total_w;
+        float x = fminf(fmaxf(dot, -SQUASH_LIMIT), SQUASH_LIMIT);
+        output_probs[idx] = interpolate(squash_table, table_size, (x + SQUASH_LIMIT) / (2.0f * SQUASH_LIMIT));
     }
 }
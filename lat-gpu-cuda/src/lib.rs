@@ -1,15 +1,131 @@
+use cudarc::driver::sys::CUdevice_attribute;
 use cudarc::driver::{CudaDevice, DriverError};
+use lat_core::inflight::InFlightLimit;
+use lat_core::logistic::LogisticTable;
+use lat_core::profile::GpuStage;
+use lat_core::throttle::Throttle;
 use lat_core::GpuAccelerator;
 use std::sync::Arc;
 
+#[cfg(feature = "profiling")]
+use lat_core::profile::GpuProfile;
+#[cfg(feature = "profiling")]
+use std::{sync::Mutex, time::Instant};
+
 pub struct CudaAccelerator {
-    _device: Arc<CudaDevice>,
+    device: Arc<CudaDevice>,
+    throttle: Throttle,
+    in_flight: InFlightLimit,
+    logistic: LogisticTable,
+    #[cfg(feature = "profiling")]
+    profile: Mutex<GpuProfile>,
+}
+
+/// One operation per copy engine plus one computing, so uploads, readbacks and kernels
+/// can overlap; more would only pile up buffers on the device.
+fn default_max_in_flight(device: &CudaDevice) -> usize {
+    device
+        .attribute(CUdevice_attribute::CU_DEVICE_ATTRIBUTE_ASYNC_ENGINE_COUNT)
+        .map_or(2, |engines| engines.max(0) as usize + 1)
 }
 
 impl CudaAccelerator {
+    /// Cheap check for a CUDA device. Only initializes the driver and counts devices; no
+    /// context is created, so backends can be listed before committing to one.
+    pub fn is_available() -> bool {
+        CudaDevice::count().is_ok_and(|count| count > 0)
+    }
+
     pub fn new() -> Result<Self, String> {
         let device = CudaDevice::new(0).map_err(|e: DriverError| format!("CUDA error: {:?}", e))?;
-        Ok(Self { _device: device })
+        let in_flight = InFlightLimit::new(default_max_in_flight(&device));
+        Ok(Self {
+            device,
+            throttle: Throttle::default(),
+            in_flight,
+            logistic: LogisticTable::default(),
+            #[cfg(feature = "profiling")]
+            profile: Mutex::default(),
+        })
+    }
+
+    /// Pauses at least `gap` between dispatches so other GPU clients (e.g. the desktop
+    /// compositor) get a turn; `None` runs at full speed.
+    pub fn with_throttle(mut self, gap: Option<std::time::Duration>) -> Self {
+        self.throttle = Throttle::new(gap);
+        self
+    }
+
+    /// Caps how many operations may run on the device at once when the accelerator is
+    /// shared between threads; callers beyond `max` wait for a slot. Defaults to the
+    /// device's copy engine count plus one.
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.in_flight = InFlightLimit::new(max);
+        self
+    }
+
+    /// Logistic table uploaded for the mixing kernel's stretch and squash lookups, so it
+    /// mixes like [`lat_core::cpu::CpuAccelerator::with_logistic_table`] with the same
+    /// table. Defaults to [`LogisticTable::default`].
+    pub fn with_logistic_table(mut self, table: LogisticTable) -> Self {
+        self.logistic = table;
+        self
+    }
+
+    /// Waits for all pending work on the device to complete.
+    pub fn synchronize(&self) -> Result<(), String> {
+        self.device
+            .synchronize()
+            .map_err(|e: DriverError| format!("CUDA error: {:?}", e))
+    }
+
+    /// Synchronizes and releases the device context, reporting any pending failure.
+    ///
+    /// Dropping the accelerator has the same effect but silently ignores errors.
+    pub fn shutdown(self) -> Result<(), String> {
+        self.synchronize()
+    }
+
+    /// Clears the accumulated stage timings.
+    #[cfg(feature = "profiling")]
+    pub fn reset_profile(&self) {
+        *self.profile.lock().unwrap() = GpuProfile::default();
+    }
+
+    /// Runs one stage of an operation. With the `profiling` feature the stage waits for the
+    /// device, so asynchronous work is charged to the stage that queued it, and the time is
+    /// added to the profile.
+    ///
+    /// These are host timers; once the kernels are real, dispatch time should come from
+    /// CUDA events recorded on the stream to leave out launch overhead.
+    #[cfg(feature = "profiling")]
+    fn stage<T>(
+        &self,
+        stage: GpuStage,
+        work: impl FnOnce() -> Result<T, String>,
+    ) -> Result<T, String> {
+        let start = Instant::now();
+        let out = work()?;
+        self.synchronize()?;
+        self.profile.lock().unwrap().record(stage, start.elapsed());
+        Ok(out)
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    fn stage<T>(
+        &self,
+        _stage: GpuStage,
+        work: impl FnOnce() -> Result<T, String>,
+    ) -> Result<T, String> {
+        work()
+    }
+}
+
+impl Drop for CudaAccelerator {
+    fn drop(&mut self) {
+        // The primary context is released when the last `Arc<CudaDevice>` goes away;
+        // make sure no kernel is still using it at that point.
+        let _ = self.synchronize();
     }
 }
 
@@ -21,7 +137,11 @@ impl GpuAccelerator for CudaAccelerator {
     fn run_kernel(&self, name: &str, _data: &mut [u8]) -> Result<(), String> {
         // This is a simplified wrapper. Real implementation would involve
         // loading the PTX/fatbin and managing buffers.
-        println!("Running CUDA kernel: {}", name);
+        self.in_flight.run(|| {
+            self.throttle.run(|| {
+                println!("Running CUDA kernel: {}", name);
+            })
+        });
         Ok(())
     }
 
@@ -31,14 +151,171 @@ impl GpuAccelerator for CudaAccelerator {
         _weights: &[f32],
         num_bits: usize,
     ) -> Result<Vec<f32>, String> {
+        // The slot covers the whole operation, since its buffers live until readback.
+        self.in_flight.run(|| {
+            // In a real implementation, we would:
+            // 1. Allocate GPU memory
+            // 2. Copy model_probs and weights (in [num_models][num_bits] layout) to GPU,
+            //    with the logistic table's stretch and squash samples
+            self.stage(GpuStage::Upload, || {
+                let _tables = (
+                    self.logistic.stretch_values(),
+                    self.logistic.squash_values(),
+                );
+                Ok(())
+            })?;
+            // 3. Launch the 'paq_mix_probabilities' kernel (optimized for coalesced access)
+            self.throttle.run(|| {
+                self.stage(GpuStage::Dispatch, || {
+                    println!("Mixing probabilities on CUDA for {} bits", num_bits);
+                    Ok(())
+                })
+            })?;
+            // 4. Copy the result back
+
+            // Mocking the result for now
+            self.stage(GpuStage::Readback, || Ok(vec![0.5; num_bits]))
+        })
+    }
+
+    fn aes_ctr_apply(
+        &self,
+        key: &[u8; 32],
+        counter: &[u8; 16],
+        data: &mut [u8],
+    ) -> Result<(), String> {
         // In a real implementation, we would:
-        // 1. Allocate GPU memory
-        // 2. Copy model_probs and weights (in [num_models][num_bits] layout) to GPU
-        // 3. Launch the 'paq_mix_probabilities' kernel (optimized for coalesced access)
-        // 4. Copy the result back
-        println!("Mixing probabilities on CUDA for {} bits", num_bits);
-
-        // Mocking the result for now
-        Ok(vec![0.5; num_bits])
+        // 1. Expand the key schedule on the host and upload it with the data
+        // 2. Launch the 'aes_ctr' kernel, one thread per 16-byte counter block
+        // 3. Copy the ciphertext back; GHASH stays on the CPU (see aes_gcm_encrypt)
+        // Until the kernel lands, use the CPU reference so the output stays identical.
+        lat_core::crypto::aes_ctr_apply(key, counter, data);
+        Ok(())
+    }
+
+    fn byte_histogram(&self, data: &[u8]) -> Result<[u64; 256], String> {
+        // In a real implementation, we would:
+        // 1. Upload `data` and a zeroed 256-entry counter buffer
+        // 2. Launch the 'lat_byte_histogram' kernel (shared-memory partial counts per block)
+        // 3. Copy the counters back
+        // Until the kernel lands, count on the CPU so estimates stay identical.
+        Ok(lat_core::entropy::byte_histogram(data))
+    }
+
+    #[cfg(feature = "profiling")]
+    fn profile(&self) -> Option<GpuProfile> {
+        Some(*self.profile.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_available_matches_construction() {
+        assert_eq!(
+            CudaAccelerator::is_available(),
+            CudaAccelerator::new().is_ok()
+        );
+    }
+
+    #[test]
+    fn test_create_and_drop_in_loop() {
+        // Skips silently on machines without a CUDA device.
+        let Ok(first) = CudaAccelerator::new() else {
+            return;
+        };
+        first.shutdown().expect("Shutdown failed");
+
+        for _ in 0..64 {
+            let accel = CudaAccelerator::new().expect("Device handle leaked");
+            accel.shutdown().expect("Shutdown failed");
+        }
+    }
+
+    #[test]
+    fn test_aes_gcm_matches_cpu() {
+        let Ok(accel) = CudaAccelerator::new() else {
+            return;
+        };
+        let key = [0x42u8; 32];
+        let nonce = [0x24u8; 12];
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 256) as u8).collect();
+
+        let gpu = accel
+            .aes_gcm_encrypt(&key, &nonce, b"aad", &data)
+            .expect("GPU encryption failed");
+        let cpu = lat_core::crypto::aes_gcm_encrypt(&key, &nonce, b"aad", &data)
+            .expect("CPU encryption failed");
+        assert_eq!(gpu, cpu);
+    }
+
+    #[test]
+    fn test_aes_ctr_matches_cpu() {
+        let Ok(accel) = CudaAccelerator::new() else {
+            return;
+        };
+        let key = [0x42u8; 32];
+        // The low 32 bits wrap partway through, and the tail is a partial block.
+        let mut counter = [0x24u8; 16];
+        counter[12..].copy_from_slice(&(u32::MAX - 3).to_be_bytes());
+        let data: Vec<u8> = (0..100_003u32).map(|i| (i % 251) as u8).collect();
+
+        let mut gpu = data.clone();
+        accel
+            .aes_ctr_apply(&key, &counter, &mut gpu)
+            .expect("GPU encryption failed");
+        let mut cpu = data;
+        lat_core::crypto::aes_ctr_apply(&key, &counter, &mut cpu);
+        assert_eq!(gpu, cpu);
+    }
+
+    #[test]
+    fn test_byte_histogram_matches_cpu() {
+        let Ok(accel) = CudaAccelerator::new() else {
+            return;
+        };
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+
+        let gpu = accel.byte_histogram(&data).expect("GPU histogram failed");
+        assert_eq!(gpu, lat_core::entropy::byte_histogram(&data));
+    }
+
+    #[test]
+    fn test_throttle_delays_dispatches() {
+        use std::time::{Duration, Instant};
+
+        let Ok(accel) = CudaAccelerator::new() else {
+            return;
+        };
+        let gap = Duration::from_millis(20);
+        let accel = accel.with_throttle(Some(gap));
+        let start = Instant::now();
+        for _ in 0..4 {
+            accel
+                .mix_probabilities(&[0.5; 64], &[0.25; 64], 16)
+                .expect("Mixing failed");
+        }
+        assert!(start.elapsed() >= gap * 3);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_profile_records_mix_stages() {
+        use std::time::Duration;
+
+        let Ok(accel) = CudaAccelerator::new() else {
+            return;
+        };
+        accel.reset_profile();
+        accel
+            .mix_probabilities(&[0.5; 64], &[0.25; 64], 16)
+            .expect("Mixing failed");
+
+        let profile = accel.profile().expect("Profiling is enabled");
+        assert!(profile.upload > Duration::ZERO);
+        assert!(profile.dispatch > Duration::ZERO);
+        assert!(profile.readback > Duration::ZERO);
     }
 }
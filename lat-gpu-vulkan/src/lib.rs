@@ -1,39 +1,286 @@
+mod shaders;
+
+pub use shaders::{ShaderRegistry, ShaderSource};
+
+use lat_core::inflight::InFlightLimit;
+use lat_core::logistic::LogisticTable;
+use lat_core::profile::GpuStage;
+use lat_core::throttle::Throttle;
 use lat_core::GpuAccelerator;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(feature = "profiling")]
+use lat_core::profile::GpuProfile;
+#[cfg(feature = "profiling")]
+use std::{sync::Mutex, time::Instant};
 
 pub struct VulkanAccelerator {
     // wgpu abstracts over Vulkan/Metal/DX12
-    _device: wgpu::Device,
+    device: wgpu::Device,
     _queue: wgpu::Queue,
+    adapter: wgpu::AdapterInfo,
+    throttle: Throttle,
+    in_flight: InFlightLimit,
+    logistic: LogisticTable,
+    shaders: ShaderRegistry,
+    #[cfg(feature = "profiling")]
+    profile: Mutex<GpuProfile>,
+}
+
+/// wgpu submits everything to a single queue, so two operations in flight let one
+/// upload while the other runs; more would only pile up buffers on the device.
+const DEFAULT_MAX_IN_FLIGHT: usize = 2;
+
+/// Why a device request failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterError {
+    /// No adapter is present at all; retrying won't help.
+    NoGpu,
+    /// An adapter exists but couldn't be opened right now (driver still initializing,
+    /// device busy, ...).
+    Transient(String),
+}
+
+impl std::fmt::Display for AdapterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdapterError::NoGpu => write!(f, "Failed to find a GPU adapter"),
+            AdapterError::Transient(e) => write!(f, "GPU adapter unavailable: {}", e),
+        }
+    }
+}
+
+/// How often and how patiently to retry transient adapter failures.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one.
+    pub attempts: u32,
+    /// Delay before the first retry; doubled after every failed attempt.
+    pub initial_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            initial_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Calls `request` until it succeeds, fails with [`AdapterError::NoGpu`], or the policy's
+/// attempts are used up. The backoff sleeps the calling thread, which is fine for the
+/// `pollster::block_on` callers this is meant for.
+pub async fn request_with_retry<T, F, Fut>(
+    policy: RetryPolicy,
+    mut request: F,
+) -> Result<T, AdapterError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AdapterError>>,
+{
+    let mut delay = policy.initial_delay;
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Err(AdapterError::Transient(_)) if attempt < policy.attempts => {
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Requests a high-performance adapter and, if none comes back, retries once with the
+/// fallback (software) adapter before giving up.
+///
+/// `request` is called with the power preference and whether to force the fallback
+/// adapter; `adapter_count` is how many adapters the instance enumerated and is only used
+/// to tell "nothing there" apart from "all adapters rejected". Validation layers and
+/// headless sessions can make the first request fail spuriously, in which case lavapipe
+/// (or another software adapter) is still better than dropping to the CPU path; the
+/// accelerator's [`adapter_info`](VulkanAccelerator::adapter_info) shows which one won.
+async fn request_adapter_with_fallback<A, F, Fut>(
+    adapter_count: usize,
+    mut request: F,
+) -> Result<A, AdapterError>
+where
+    F: FnMut(wgpu::PowerPreference, bool) -> Fut,
+    Fut: Future<Output = Option<A>>,
+{
+    if let Some(adapter) = request(wgpu::PowerPreference::HighPerformance, false).await {
+        return Ok(adapter);
+    }
+    if let Some(adapter) = request(wgpu::PowerPreference::LowPower, true).await {
+        return Ok(adapter);
+    }
+    match adapter_count {
+        0 => Err(AdapterError::NoGpu),
+        _ => Err(AdapterError::Transient(
+            "adapter present but not available".to_string(),
+        )),
+    }
+}
+
+async fn request_device() -> Result<(wgpu::Device, wgpu::Queue, wgpu::AdapterInfo), AdapterError> {
+    let instance = wgpu::Instance::default();
+    let adapter_count = instance.enumerate_adapters(wgpu::Backends::all()).len();
+    let adapter =
+        request_adapter_with_fallback(adapter_count, |power_preference, force_fallback_adapter| {
+            let instance = &instance;
+            async move {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference,
+                        force_fallback_adapter,
+                        compatible_surface: None,
+                    })
+                    .await
+            }
+        })
+        .await?;
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("VulkanAccelerator"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .map_err(|e| AdapterError::Transient(e.to_string()))?;
+    Ok((device, queue, adapter.get_info()))
 }
 
 impl VulkanAccelerator {
+    /// Cheap check for a usable adapter. Enumerates adapters without opening a device, so
+    /// backends can be listed before committing to one.
+    pub fn is_available() -> bool {
+        !wgpu::Instance::default()
+            .enumerate_adapters(wgpu::Backends::all())
+            .is_empty()
+    }
+
     pub async fn new() -> Result<Self, String> {
-        let instance = wgpu::Instance::default();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                ..Default::default()
-            })
-            .await
-            .ok_or("Failed to find a GPU adapter")?;
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("VulkanAccelerator"),
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                },
-                None,
-            )
+        Self::with_retry(RetryPolicy::default()).await
+    }
+
+    /// Like [`VulkanAccelerator::new`], retrying transient adapter failures per `policy`.
+    pub async fn with_retry(policy: RetryPolicy) -> Result<Self, String> {
+        let (device, queue, adapter) = request_with_retry(policy, request_device)
             .await
             .map_err(|e| e.to_string())?;
 
         Ok(Self {
-            _device: device,
+            device,
             _queue: queue,
+            adapter,
+            throttle: Throttle::default(),
+            in_flight: InFlightLimit::new(DEFAULT_MAX_IN_FLIGHT),
+            logistic: LogisticTable::default(),
+            shaders: ShaderRegistry::from_env(),
+            #[cfg(feature = "profiling")]
+            profile: Mutex::default(),
         })
     }
+
+    /// Pauses at least `gap` between dispatches so other GPU clients (e.g. the desktop
+    /// compositor) get a turn; `None` runs at full speed.
+    pub fn with_throttle(mut self, gap: Option<Duration>) -> Self {
+        self.throttle = Throttle::new(gap);
+        self
+    }
+
+    /// Caps how many operations may run on the device at once when the accelerator is
+    /// shared between threads; callers beyond `max` wait for a slot. Defaults to two.
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.in_flight = InFlightLimit::new(max);
+        self
+    }
+
+    /// Logistic table uploaded for the mixing kernel's stretch and squash lookups, so it
+    /// mixes like [`lat_core::cpu::CpuAccelerator::with_logistic_table`] with the same
+    /// table. Defaults to [`LogisticTable::default`].
+    pub fn with_logistic_table(mut self, table: LogisticTable) -> Self {
+        self.logistic = table;
+        self
+    }
+
+    /// The adapter the device was opened on. A `device_type` of `Cpu` means only the
+    /// software fallback adapter could be used.
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter
+    }
+
+    /// Where shader sources come from; in debug builds, shaders can be pointed at files
+    /// here while developing them.
+    pub fn shaders(&self) -> &ShaderRegistry {
+        &self.shaders
+    }
+
+    /// Compiles shader `name` from its current source, so a shader read from a file picks
+    /// up edits on the next load. Also returns the registered file when it was missing and
+    /// the embedded source was compiled instead.
+    pub fn load_shader(&self, name: &str) -> Result<(wgpu::ShaderModule, Option<PathBuf>), String> {
+        let source = self.shaders.source(name)?;
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(name),
+                source: wgpu::ShaderSource::Wgsl(source.text),
+            });
+        Ok((module, source.missing_file))
+    }
+
+    /// Blocks until all submitted work on the queue has completed.
+    pub fn synchronize(&self) {
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+
+    /// Waits for pending work and releases the device and queue.
+    ///
+    /// Dropping the accelerator has the same effect; this makes the teardown point explicit
+    /// when switching backends.
+    pub fn shutdown(self) {
+        self.synchronize();
+    }
+
+    /// Clears the accumulated stage timings.
+    #[cfg(feature = "profiling")]
+    pub fn reset_profile(&self) {
+        *self.profile.lock().unwrap() = GpuProfile::default();
+    }
+
+    /// Runs one stage of an operation. With the `profiling` feature the stage waits for its
+    /// queued work, so the time is charged to the right stage, and is added to the profile.
+    ///
+    /// These are host timers; once the shaders are real, dispatch time should come from
+    /// timestamp queries (`Features::TIMESTAMP_QUERY`) to leave out submission overhead.
+    #[cfg(feature = "profiling")]
+    fn stage<T>(&self, stage: GpuStage, work: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = work();
+        self.synchronize();
+        self.profile.lock().unwrap().record(stage, start.elapsed());
+        out
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    fn stage<T>(&self, _stage: GpuStage, work: impl FnOnce() -> T) -> T {
+        work()
+    }
+}
+
+impl Drop for VulkanAccelerator {
+    fn drop(&mut self) {
+        self.synchronize();
+    }
 }
 
 impl GpuAccelerator for VulkanAccelerator {
@@ -42,7 +289,11 @@ impl GpuAccelerator for VulkanAccelerator {
     }
 
     fn run_kernel(&self, name: &str, _data: &mut [u8]) -> Result<(), String> {
-        println!("Running Vulkan (wgpu) compute shader: {}", name);
+        self.in_flight.run(|| {
+            self.throttle.run(|| {
+                println!("Running Vulkan (wgpu) compute shader: {}", name);
+            })
+        });
         Ok(())
     }
 
@@ -52,12 +303,249 @@ impl GpuAccelerator for VulkanAccelerator {
         _weights: &[f32],
         num_bits: usize,
     ) -> Result<Vec<f32>, String> {
+        // The slot covers the whole operation, since its buffers live until readback.
+        self.in_flight.run(|| {
+            // In a real implementation, we would:
+            // 1. Map model_probs and weights (in [num_models][num_bits] layout) to GPU buffers,
+            //    plus the logistic table's stretch and squash samples
+            self.stage(GpuStage::Upload, || {
+                let _tables = (
+                    self.logistic.stretch_values(),
+                    self.logistic.squash_values(),
+                );
+            });
+            // 2. Dispatch the 'paqg' compute shader (optimized for coalesced access)
+            self.throttle.run(|| {
+                self.stage(GpuStage::Dispatch, || {
+                    println!("Mixing probabilities on Vulkan for {} bits", num_bits);
+                })
+            });
+            // 3. Retrieve the result from the output buffer
+            // Mock result
+            Ok(self.stage(GpuStage::Readback, || vec![0.5; num_bits]))
+        })
+    }
+
+    fn aes_ctr_apply(
+        &self,
+        key: &[u8; 32],
+        counter: &[u8; 16],
+        data: &mut [u8],
+    ) -> Result<(), String> {
+        // In a real implementation, we would:
+        // 1. Upload the expanded key schedule and data to storage buffers
+        // 2. Dispatch the 'aes_ctr' compute shader, one invocation per counter block
+        // 3. Read back the ciphertext; GHASH stays on the CPU (see aes_gcm_encrypt)
+        // Until the shader lands, use the CPU reference so the output stays identical.
+        lat_core::crypto::aes_ctr_apply(key, counter, data);
+        Ok(())
+    }
+
+    fn byte_histogram(&self, data: &[u8]) -> Result<[u64; 256], String> {
         // In a real implementation, we would:
-        // 1. Map model_probs and weights (in [num_models][num_bits] layout) to GPU buffers
-        // 2. Dispatch the 'paqg' compute shader (optimized for coalesced access)
-        // 3. Retrieve the result from the output buffer
-        println!("Mixing probabilities on Vulkan for {} bits", num_bits);
-        // Mock result
-        Ok(vec![0.5; num_bits])
+        // 1. Upload `data` and a zeroed 256-entry counter buffer to storage buffers
+        // 2. Dispatch a histogram compute shader (workgroup-shared partial counts)
+        // 3. Read back the counters
+        // Until the kernel lands, count on the CPU so estimates stay identical.
+        Ok(lat_core::entropy::byte_histogram(data))
+    }
+
+    #[cfg(feature = "profiling")]
+    fn profile(&self) -> Option<GpuProfile> {
+        Some(*self.profile.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    fn fast_policy(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            initial_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_retry_recovers_from_transient_failures() {
+        let calls = Cell::new(0);
+        let factory = || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move {
+                if call <= 2 {
+                    Err(AdapterError::Transient("driver initializing".to_string()))
+                } else {
+                    Ok("adapter")
+                }
+            }
+        };
+
+        let result = pollster::block_on(request_with_retry(fast_policy(4), factory));
+        assert_eq!(result, Ok("adapter"));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_retry_gives_up_after_budget() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = pollster::block_on(request_with_retry(fast_policy(2), || {
+            calls.set(calls.get() + 1);
+            async { Err(AdapterError::Transient("busy".to_string())) }
+        }));
+        assert!(matches!(result, Err(AdapterError::Transient(_))));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_no_gpu_is_not_retried() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = pollster::block_on(request_with_retry(fast_policy(5), || {
+            calls.set(calls.get() + 1);
+            async { Err(AdapterError::NoGpu) }
+        }));
+        assert_eq!(result, Err(AdapterError::NoGpu));
+        assert_eq!(calls.get(), 1);
+    }
+
+    /// Mimics a setup where the high-performance request rejects every adapter, so only
+    /// a forced fallback request can pick up the software one.
+    fn fake_request(
+        adapters: &[&'static str],
+        calls: &Cell<u32>,
+        power_preference: wgpu::PowerPreference,
+        force_fallback: bool,
+    ) -> impl Future<Output = Option<&'static str>> {
+        calls.set(calls.get() + 1);
+        let picked = adapters.iter().copied().find(|name| {
+            force_fallback
+                && power_preference == wgpu::PowerPreference::LowPower
+                && name.starts_with("llvmpipe")
+        });
+        async move { picked }
+    }
+
+    #[test]
+    fn test_fallback_adapter_selected_when_only_software_exists() {
+        let adapters = ["llvmpipe (LLVM 17.0.6, 256 bits)"];
+        let calls = Cell::new(0);
+        let result = pollster::block_on(request_adapter_with_fallback(adapters.len(), |p, f| {
+            fake_request(&adapters, &calls, p, f)
+        }));
+        assert_eq!(result, Ok(adapters[0]));
+        assert_eq!(calls.get(), 2);
+
+        let calls = Cell::new(0);
+        let result = pollster::block_on(request_adapter_with_fallback(0, |p, f| {
+            fake_request(&[], &calls, p, f)
+        }));
+        assert_eq!(result, Err(AdapterError::NoGpu));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_is_available_matches_construction() {
+        let constructed = pollster::block_on(VulkanAccelerator::with_retry(fast_policy(3)));
+        assert_eq!(VulkanAccelerator::is_available(), constructed.is_ok());
+    }
+
+    #[test]
+    fn test_create_and_drop_in_loop() {
+        // Skips silently on machines without a usable adapter.
+        let Ok(first) = pollster::block_on(VulkanAccelerator::new()) else {
+            return;
+        };
+        first.shutdown();
+
+        for _ in 0..32 {
+            let accel = pollster::block_on(VulkanAccelerator::new()).expect("Device handle leaked");
+            accel.shutdown();
+        }
+    }
+
+    #[test]
+    fn test_aes_gcm_matches_cpu() {
+        let Ok(accel) = pollster::block_on(VulkanAccelerator::new()) else {
+            return;
+        };
+        let key = [0x42u8; 32];
+        let nonce = [0x24u8; 12];
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 256) as u8).collect();
+
+        let gpu = accel
+            .aes_gcm_encrypt(&key, &nonce, b"aad", &data)
+            .expect("GPU encryption failed");
+        let cpu = lat_core::crypto::aes_gcm_encrypt(&key, &nonce, b"aad", &data)
+            .expect("CPU encryption failed");
+        assert_eq!(gpu, cpu);
+    }
+
+    #[test]
+    fn test_aes_ctr_matches_cpu() {
+        let Ok(accel) = pollster::block_on(VulkanAccelerator::new()) else {
+            return;
+        };
+        let key = [0x42u8; 32];
+        // The low 32 bits wrap partway through, and the tail is a partial block.
+        let mut counter = [0x24u8; 16];
+        counter[12..].copy_from_slice(&(u32::MAX - 3).to_be_bytes());
+        let data: Vec<u8> = (0..100_003u32).map(|i| (i % 251) as u8).collect();
+
+        let mut gpu = data.clone();
+        accel
+            .aes_ctr_apply(&key, &counter, &mut gpu)
+            .expect("GPU encryption failed");
+        let mut cpu = data;
+        lat_core::crypto::aes_ctr_apply(&key, &counter, &mut cpu);
+        assert_eq!(gpu, cpu);
+    }
+
+    #[test]
+    fn test_byte_histogram_matches_cpu() {
+        let Ok(accel) = pollster::block_on(VulkanAccelerator::new()) else {
+            return;
+        };
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+
+        let gpu = accel.byte_histogram(&data).expect("GPU histogram failed");
+        assert_eq!(gpu, lat_core::entropy::byte_histogram(&data));
+    }
+
+    #[test]
+    fn test_throttle_delays_dispatches() {
+        use std::time::Instant;
+
+        let Ok(accel) = pollster::block_on(VulkanAccelerator::new()) else {
+            return;
+        };
+        let gap = Duration::from_millis(20);
+        let accel = accel.with_throttle(Some(gap));
+        let start = Instant::now();
+        for _ in 0..4 {
+            accel
+                .mix_probabilities(&[0.5; 64], &[0.25; 64], 16)
+                .expect("Mixing failed");
+        }
+        assert!(start.elapsed() >= gap * 3);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_profile_records_mix_stages() {
+        let Ok(accel) = pollster::block_on(VulkanAccelerator::new()) else {
+            return;
+        };
+        accel.reset_profile();
+        accel
+            .mix_probabilities(&[0.5; 64], &[0.25; 64], 16)
+            .expect("Mixing failed");
+
+        let profile = accel.profile().expect("Profiling is enabled");
+        assert!(profile.upload > Duration::ZERO);
+        assert!(profile.dispatch > Duration::ZERO);
+        assert!(profile.readback > Duration::ZERO);
     }
 }
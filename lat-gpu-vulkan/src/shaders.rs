@@ -0,0 +1,139 @@
+//! WGSL shader sources.
+//!
+//! Shaders are embedded at build time. In debug builds a shader can instead be read from a
+//! file each time it is loaded ([`ShaderRegistry::register_path`], or `LAT_SHADER_DIR`
+//! for all of them), so shader edits take effect on the next run without rebuilding the
+//! crate. Release builds always use the embedded source.
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+#[cfg(debug_assertions)]
+use std::{collections::HashMap, sync::Mutex};
+
+/// Name and embedded source of every shader.
+const EMBEDDED: &[(&str, &str)] = &[("paqg", include_str!("shaders/paqg.wgsl"))];
+
+/// Embedded source of shader `name`.
+pub fn embedded(name: &str) -> Result<&'static str, String> {
+    EMBEDDED
+        .iter()
+        .find(|(shader, _)| *shader == name)
+        .map(|(_, source)| *source)
+        .ok_or_else(|| format!("unknown shader '{}'", name))
+}
+
+/// Source of one shader, as returned by [`ShaderRegistry::source`].
+#[derive(Debug)]
+pub struct ShaderSource {
+    pub text: Cow<'static, str>,
+    /// The registered file, when it didn't exist and the embedded source was used instead.
+    pub missing_file: Option<PathBuf>,
+}
+
+/// Where each shader's source comes from.
+#[derive(Debug, Default)]
+pub struct ShaderRegistry {
+    #[cfg(debug_assertions)]
+    overrides: Mutex<HashMap<&'static str, PathBuf>>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry that reads `<dir>/<name>.wgsl` for every shader when `LAT_SHADER_DIR`
+    /// is set in a debug build, and uses the embedded sources otherwise.
+    pub fn from_env() -> Self {
+        let registry = Self::new();
+        #[cfg(debug_assertions)]
+        {
+            if let Some(dir) = std::env::var_os("LAT_SHADER_DIR") {
+                let dir = PathBuf::from(dir);
+                let mut overrides = registry.overrides.lock().unwrap();
+                for (name, _) in EMBEDDED {
+                    overrides.insert(*name, dir.join(format!("{}.wgsl", name)));
+                }
+            }
+        }
+        registry
+    }
+
+    /// Reads shader `name` from `path` from now on. Debug builds only.
+    #[cfg(debug_assertions)]
+    pub fn register_path(&self, name: &str, path: impl Into<PathBuf>) -> Result<(), String> {
+        let (name, _) = EMBEDDED
+            .iter()
+            .find(|(shader, _)| *shader == name)
+            .ok_or_else(|| format!("unknown shader '{}'", name))?;
+        self.overrides.lock().unwrap().insert(*name, path.into());
+        Ok(())
+    }
+
+    /// Current source of shader `name`: the registered file if there is one, the embedded
+    /// source if not or if the file doesn't exist (naming the file in `missing_file`).
+    pub fn source(&self, name: &str) -> Result<ShaderSource, String> {
+        let embedded = embedded(name)?;
+        #[cfg(debug_assertions)]
+        {
+            if let Some(path) = self.overrides.lock().unwrap().get(name) {
+                match std::fs::read_to_string(path) {
+                    Ok(source) => {
+                        return Ok(ShaderSource {
+                            text: Cow::Owned(source),
+                            missing_file: None,
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        return Ok(ShaderSource {
+                            text: Cow::Borrowed(embedded),
+                            missing_file: Some(path.clone()),
+                        });
+                    }
+                    Err(e) => return Err(format!("reading shader {}: {}", path.display(), e)),
+                }
+            }
+        }
+        Ok(ShaderSource {
+            text: Cow::Borrowed(embedded),
+            missing_file: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_shader_is_an_error() {
+        assert!(embedded("paqg").unwrap().contains("@compute"));
+        assert!(ShaderRegistry::new().source("nope").is_err());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_registered_file_matches_embedded() {
+        let path = std::env::temp_dir().join(format!("lat-paqg-{}.wgsl", std::process::id()));
+        std::fs::write(&path, embedded("paqg").unwrap()).unwrap();
+
+        let registry = ShaderRegistry::new();
+        registry.register_path("paqg", &path).unwrap();
+        let from_file = registry.source("paqg").unwrap().text;
+        assert!(matches!(from_file, Cow::Owned(_)));
+        assert_eq!(
+            from_file,
+            ShaderRegistry::new().source("paqg").unwrap().text
+        );
+
+        // Edits show up on the next load; a deleted file falls back to the embedded source.
+        std::fs::write(&path, "// edited").unwrap();
+        assert_eq!(registry.source("paqg").unwrap().text, "// edited");
+        std::fs::remove_file(&path).unwrap();
+        let fallback = registry.source("paqg").unwrap();
+        assert_eq!(fallback.text, embedded("paqg").unwrap());
+        assert_eq!(fallback.missing_file, Some(path.clone()));
+
+        assert!(registry.register_path("nope", &path).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+// WGSL port of paqg.comp: logistic mixing of model probabilities, one bit per
+// invocation. Inputs are laid out [num_models][num_bits]; stretch and squash are
+// looked up in lat_core::logistic::LogisticTable samples (table_size + 1 of each).
+
+struct Params {
+    num_models: u32,
+    num_bits: u32,
+    table_size: u32,
+}
+
+const SQUASH_LIMIT: f32 = 12.0;
+
+@group(0) @binding(0) var<storage, read> model_probs: array<f32>;
+@group(0) @binding(1) var<storage, read> weights: array<f32>;
+@group(0) @binding(2) var<storage, read_write> output_probs: array<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+@group(0) @binding(4) var<storage, read> stretch_table: array<f32>;
+@group(0) @binding(5) var<storage, read> squash_table: array<f32>;
+
+// Spelled like lat_core::logistic's interpolation rather than with mix(), which may round
+// differently, so the GPU and CPU mixers agree.
+fn lerp(a: f32, b: f32, frac: f32) -> f32 {
+    return a + (b - a) * frac;
+}
+
+fn stretch(p: f32) -> f32 {
+    let pos = clamp(p, 0.0, 1.0) * f32(params.table_size);
+    let i = min(u32(pos), params.table_size - 1u);
+    return lerp(stretch_table[i], stretch_table[i + 1u], pos - f32(i));
+}
+
+fn squash(x: f32) -> f32 {
+    let t = (clamp(x, -SQUASH_LIMIT, SQUASH_LIMIT) + SQUASH_LIMIT) / (2.0 * SQUASH_LIMIT);
+    let pos = t * f32(params.table_size);
+    let i = min(u32(pos), params.table_size - 1u);
+    return lerp(squash_table[i], squash_table[i + 1u], pos - f32(i));
+}
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let idx = id.x;
+    if (idx >= params.num_bits) {
+        return;
+    }
+    var dot = 0.0;
+    // Bolt ⚡ Optimization: Using coalesced memory access pattern [num_models][num_bits].
+    // This allows adjacent threads to access adjacent memory, significantly improving throughput.
+    for (var i = 0u; i < params.num_models; i++) {
+        let w = weights[i * params.num_bits + idx];
+        dot += w * stretch(model_probs[i * params.num_bits + idx]);
+    }
+    output_probs[idx] = squash(dot);
+}
@@ -0,0 +1,615 @@
+use crate::format::format_size;
+use crate::metadata::{self, Ownership};
+use lat_core::path::{SanitizeMode, sanitize_for_filesystem};
+use lat_core::{ArchiveEntry, EntryKind, LatError};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Outcome of writing extracted entries to disk.
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    pub total: usize,
+    pub written: usize,
+    /// Entry name and reason for every entry that was not written.
+    pub failures: Vec<(String, String)>,
+    /// Entry name and note for every link that was copied or skipped instead of created.
+    pub warnings: Vec<(String, String)>,
+}
+
+impl ExtractReport {
+    /// Status-bar summary, e.g. "Extracted 8 of 10 files; 2 failed (a.txt: ...)".
+    pub fn summary(&self) -> String {
+        if self.failures.is_empty() {
+            return match self.warnings.first() {
+                None => "Extraction complete".to_string(),
+                Some((name, note)) => format!(
+                    "Extraction complete; {} link(s) not created ({}: {})",
+                    self.warnings.len(),
+                    name,
+                    note
+                ),
+            };
+        }
+        let reasons: Vec<String> = self
+            .failures
+            .iter()
+            .take(3)
+            .map(|(name, reason)| format!("{}: {}", name, reason))
+            .collect();
+        let more = if self.failures.len() > 3 { "; ..." } else { "" };
+        format!(
+            "Extracted {} of {} files; {} failed ({}{})",
+            self.written,
+            self.total,
+            self.failures.len(),
+            reasons.join("; "),
+            more
+        )
+    }
+}
+
+/// Checks that `dest_dir` accepts new files and has room for `required` bytes.
+pub fn preflight(dest_dir: &Path, required: u64) -> Result<(), String> {
+    let probe = dest_dir.join(format!(".lat-write-test-{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| format!("Destination is not writable: {}", e))?;
+    let _ = fs::remove_file(&probe);
+
+    // Free space is best-effort: some filesystems can't report it.
+    match fs2::available_space(dest_dir) {
+        Ok(available) if available < required => Err(format!(
+            "Not enough free space: {} needed, {} available",
+            format_size(required),
+            format_size(available)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// What to do with symbolic link entries. Creating a link on Windows needs Developer Mode
+/// or administrator rights, so there the default copies the target instead. Under every
+/// policy, links to absolute paths or out of the archive are skipped with a warning, so an
+/// archive can't plant a link that later writes go through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Create a real link. Fails the entry where that isn't permitted.
+    CreateSymlink,
+    /// Write a copy of the target as a regular file, if the archive holds the target.
+    #[default]
+    CopyTarget,
+    /// Leave the link out.
+    SkipWithWarning,
+}
+
+impl SymlinkPolicy {
+    /// The policy [`extract_entries`] uses: the default on Windows, real links elsewhere.
+    pub fn for_platform() -> Self {
+        if cfg!(windows) {
+            Self::default()
+        } else {
+            Self::CreateSymlink
+        }
+    }
+}
+
+/// Progress through an extraction, reported after each entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractProgress {
+    /// Entries handled so far, whether written or failed.
+    pub entries_done: usize,
+    pub bytes_written: u64,
+}
+
+/// Writes `entries` below `dest_dir`, creating directories as needed, and calls
+/// `on_progress` after each one. Every file is flushed before it is reported. Failures
+/// are collected per entry instead of aborting, so one bad entry doesn't lose the rest;
+/// an error from `entries` itself (the archive failing to decode) ends the extraction.
+/// Symbolic links follow [`SymlinkPolicy::for_platform`], and files belong to the user
+/// extracting them.
+pub fn extract_entries(
+    entries: impl IntoIterator<Item = Result<ArchiveEntry, LatError>>,
+    dest_dir: &Path,
+    on_progress: impl FnMut(ExtractProgress),
+) -> ExtractReport {
+    extract_entries_with(
+        entries,
+        dest_dir,
+        SymlinkPolicy::for_platform(),
+        Ownership::default(),
+        on_progress,
+    )
+}
+
+/// [`extract_entries`] with a caller-chosen [`SymlinkPolicy`] and [`Ownership`]. Links are
+/// written after every other entry, so their targets exist by then and nothing is ever
+/// written through a link.
+pub fn extract_entries_with(
+    entries: impl IntoIterator<Item = Result<ArchiveEntry, LatError>>,
+    dest_dir: &Path,
+    symlinks: SymlinkPolicy,
+    ownership: Ownership,
+    mut on_progress: impl FnMut(ExtractProgress),
+) -> ExtractReport {
+    let entries = entries.into_iter();
+    let mut report = ExtractReport::default();
+    let mut progress = ExtractProgress::default();
+
+    // Bolt ⚡ Optimization: Pre-seed created_dirs with the destination
+    // root and use a last_parent cache to skip redundant HashSet lookups
+    // and syscalls for consecutive files in the same directory.
+    let mut created_dirs = HashSet::with_capacity(entries.size_hint().0 / 4);
+    created_dirs.insert(dest_dir.to_path_buf());
+    let mut last_parent: Option<PathBuf> = None;
+    let mut links = Vec::new();
+
+    for entry in entries {
+        report.total += 1;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                progress.entries_done += 1;
+                report
+                    .failures
+                    .push(("(archive)".to_string(), e.to_string()));
+                on_progress(progress);
+                break;
+            }
+        };
+        if entry.kind == EntryKind::Symlink {
+            links.push(entry);
+            continue;
+        }
+        progress.entries_done += 1;
+        match write_entry(&entry, dest_dir, &mut created_dirs, &mut last_parent) {
+            Ok(path) => {
+                metadata::restore(&entry, &path, ownership);
+                report.written += 1;
+                progress.bytes_written += entry.data.len() as u64;
+            }
+            Err(e) => report.failures.push((entry.name, e)),
+        }
+        on_progress(progress);
+    }
+
+    for link in links {
+        progress.entries_done += 1;
+        match write_link(&link, dest_dir, symlinks) {
+            Ok(LinkOutcome::Created) => report.written += 1,
+            Ok(LinkOutcome::Copied(bytes)) => {
+                report.written += 1;
+                progress.bytes_written += bytes;
+                report
+                    .warnings
+                    .push((link.name, "copied the target instead".to_string()));
+            }
+            Ok(LinkOutcome::Skipped(note)) => report.warnings.push((link.name, note)),
+            Err(e) => report.failures.push((link.name, e)),
+        }
+        on_progress(progress);
+    }
+    report
+}
+
+/// What [`write_link`] did in place of, or as, the link.
+enum LinkOutcome {
+    Created,
+    /// Bytes copied from the target.
+    Copied(u64),
+    Skipped(String),
+}
+
+/// Writes one symbolic link entry according to `policy`.
+fn write_link(
+    entry: &ArchiveEntry,
+    dest_dir: &Path,
+    policy: SymlinkPolicy,
+) -> Result<LinkOutcome, String> {
+    let target =
+        std::str::from_utf8(&entry.data).map_err(|_| "link target is not UTF-8".to_string())?;
+    if policy == SymlinkPolicy::SkipWithWarning {
+        return Ok(LinkOutcome::Skipped(format!("link to {} skipped", target)));
+    }
+    let Some(resolved) = resolve_link(&entry.name, target) else {
+        return Ok(LinkOutcome::Skipped(format!(
+            "link to {} points outside the archive",
+            target
+        )));
+    };
+    let path = destination(dest_dir, &entry.name)?;
+    let parent = path.parent().unwrap_or(dest_dir);
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    if policy == SymlinkPolicy::CreateSymlink {
+        // Targets are resolved from the link's name, which only matches where it lands if
+        // no directory on the way is itself a link (`d -> .` makes `d/d/x` sit at `x`).
+        if !lands_as_named(dest_dir, &path) {
+            return Ok(LinkOutcome::Skipped(format!(
+                "link to {} is inside another link",
+                target
+            )));
+        }
+        create_symlink(target, &path).map_err(|e| e.to_string())?;
+        return Ok(LinkOutcome::Created);
+    }
+
+    let source = destination(dest_dir, &resolved)?;
+    if !source.is_file() {
+        return Ok(LinkOutcome::Skipped(format!(
+            "link target {} is not an extracted file",
+            target
+        )));
+    }
+    fs::copy(&source, &path)
+        .map(LinkOutcome::Copied)
+        .map_err(|e| e.to_string())
+}
+
+/// Archive name that `target` refers to from the link called `link_name`, or `None` for
+/// absolute targets and ones that climb out of the archive.
+fn resolve_link(link_name: &str, target: &str) -> Option<String> {
+    if target.starts_with(['/', '\\']) || target.contains(':') {
+        return None;
+    }
+    let mut parts: Vec<&str> = link_name.split(['/', '\\']).collect();
+    parts.pop();
+    for part in target.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Whether `path`'s directory, with every link on the way followed, is the one its name
+/// below `dest_dir` says.
+fn lands_as_named(dest_dir: &Path, path: &Path) -> bool {
+    let (Ok(root), Ok(named)) = (dest_dir.canonicalize(), path.strip_prefix(dest_dir)) else {
+        return false;
+    };
+    match path.parent().map(Path::canonicalize) {
+        Some(Ok(real)) => root.join(named).parent() == Some(real.as_path()),
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &str, path: &Path) -> std::io::Result<()> {
+    let target = target.replace('/', "\\");
+    let resolved = path.parent().unwrap_or(Path::new("")).join(&target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_symlink(_target: &str, _path: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symbolic links are not supported here",
+    ))
+}
+
+/// Where the entry called `name` goes below `dest_dir`. Names that can't be stored on
+/// this filesystem are remapped; ones that can't be remapped (e.g. containing NUL) or
+/// that would land outside `dest_dir` (`..` components, absolute paths) are refused.
+fn destination(dest_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = sanitize_for_filesystem(name, SanitizeMode::Replace).map_err(|e| e.to_string())?;
+    let relative = Path::new(name.as_ref());
+    let escapes = relative.components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if escapes {
+        return Err(format!("{} points outside the destination", name));
+    }
+    Ok(dest_dir.join(relative))
+}
+
+/// Writes one entry and flushes it, returning where it went.
+fn write_entry(
+    entry: &ArchiveEntry,
+    dest_dir: &Path,
+    created_dirs: &mut HashSet<PathBuf>,
+    last_parent: &mut Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let path = destination(dest_dir, &entry.name)?;
+    if entry.kind == EntryKind::Directory {
+        if !created_dirs.contains(&path) {
+            fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+            created_dirs.insert(path.clone());
+        }
+        return Ok(path);
+    }
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| last_parent.as_deref() != Some(*parent))
+    {
+        if !created_dirs.contains(parent) {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            created_dirs.insert(parent.to_path_buf());
+        }
+        *last_parent = Some(parent.to_path_buf());
+    }
+    let mut file = fs::File::create(&path).map_err(|e| e.to_string())?;
+    file.write_all(&entry.data)
+        .and_then(|()| file.flush())
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lat-extract-{}-{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries() -> Vec<ArchiveEntry> {
+        ["a.txt", "sub/b.txt"]
+            .iter()
+            .map(|name| {
+                ArchiveEntry::builder(name.to_string())
+                    .data(b"data".to_vec())
+                    .build()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_reports_success() {
+        let dir = temp_dir("ok");
+        assert!(preflight(&dir, 8).is_ok());
+        let mut entries = entries();
+        entries.push(
+            ArchiveEntry::builder("empty_dir")
+                .kind(EntryKind::Directory)
+                .build(),
+        );
+        let report = extract_entries(entries.into_iter().map(Ok), &dir, |_| {});
+        assert_eq!((report.written, report.total), (3, 3));
+        assert_eq!(report.summary(), "Extraction complete");
+        assert_eq!(fs::read(dir.join("sub/b.txt")).unwrap(), b"data");
+        assert!(dir.join("empty_dir").is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_reports_progress_per_entry() {
+        let dir = temp_dir("progress");
+        let mut items: Vec<Result<ArchiveEntry, LatError>> =
+            entries().into_iter().map(Ok).collect();
+        items.insert(1, Ok(ArchiveEntry::builder("bad\0name").build()));
+        items.push(Err(LatError::CorruptArchive("truncated".to_string())));
+        items.push(Ok(ArchiveEntry::builder("never.txt").build()));
+
+        let mut events = Vec::new();
+        let report = extract_entries(items, &dir, |progress| events.push(progress));
+        let counts: Vec<usize> = events.iter().map(|p| p.entries_done).collect();
+        assert_eq!(counts, [1, 2, 3, 4]);
+        let bytes: Vec<u64> = events.iter().map(|p| p.bytes_written).collect();
+        assert_eq!(bytes, [4, 4, 8, 8]);
+        // The decode error stops extraction; the entry after it is never written.
+        assert_eq!((report.written, report.failures.len()), (2, 2));
+        assert!(!dir.join("never.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_into_read_only_dir_reports_failures() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("ro");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        // Privileged users can write anyway; nothing to check then.
+        if fs::write(dir.join("probe"), b"").is_ok() {
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+            fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+
+        assert!(preflight(&dir, 8).is_err());
+        let report = extract_entries(entries().into_iter().map(Ok), &dir, |_| {});
+        assert_eq!(report.written, 0);
+        assert_eq!(report.failures.len(), 2);
+        assert!(
+            report
+                .summary()
+                .starts_with("Extracted 0 of 2 files; 2 failed")
+        );
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_refuses_names_outside_destination() {
+        let root = temp_dir("escape");
+        let dir = root.join("dest");
+        fs::create_dir_all(&dir).unwrap();
+        let outside = root.join("escaped.txt");
+        let names = [
+            "../escaped.txt",
+            "sub/../../escaped.txt",
+            "/lat-absolute.txt",
+            "ok.txt",
+        ];
+        let items = names.iter().map(|name| {
+            Ok(ArchiveEntry::builder(name.to_string())
+                .data(b"data".to_vec())
+                .build())
+        });
+
+        let report = extract_entries(items, &dir, |_| {});
+        assert_eq!(report.written, 1);
+        assert_eq!(report.failures.len(), 3);
+        assert!(report.failures[0].1.contains("outside the destination"));
+        assert!(!outside.exists());
+        assert!(!Path::new("/lat-absolute.txt").exists());
+        assert_eq!(fs::read(dir.join("ok.txt")).unwrap(), b"data");
+
+        // A link can't be placed outside either.
+        let link = ArchiveEntry::builder("../link")
+            .data(b"ok.txt".to_vec())
+            .kind(EntryKind::Symlink)
+            .build();
+        let report = extract_entries_with(
+            [Ok(link)],
+            &dir,
+            SymlinkPolicy::CopyTarget,
+            Ownership::default(),
+            |_| {},
+        );
+        assert_eq!(report.failures.len(), 1);
+        assert!(!root.join("link").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_link_stays_inside_archive() {
+        assert_eq!(
+            resolve_link("dir/link", "../a.txt").as_deref(),
+            Some("a.txt")
+        );
+        assert_eq!(
+            resolve_link("link", "sub/./b.txt").as_deref(),
+            Some("sub/b.txt")
+        );
+        assert_eq!(resolve_link("dir/link", "../../x"), None);
+        assert_eq!(resolve_link("link", "/etc/passwd"), None);
+        assert_eq!(resolve_link("link", "C:\\x"), None);
+    }
+
+    #[test]
+    fn test_symlink_policy_options() {
+        // The link comes first, so its target is only there because links are written last.
+        let with_link = || {
+            let link = ArchiveEntry::builder("link.txt")
+                .data(b"sub/b.txt".to_vec())
+                .kind(EntryKind::Symlink)
+                .build();
+            std::iter::once(link).chain(entries()).map(Ok)
+        };
+        assert_eq!(SymlinkPolicy::default(), SymlinkPolicy::CopyTarget);
+
+        let dir = temp_dir("link-copy");
+        let report = extract_entries_with(
+            with_link(),
+            &dir,
+            SymlinkPolicy::CopyTarget,
+            Ownership::default(),
+            |_| {},
+        );
+        assert_eq!((report.written, report.total), (3, 3));
+        assert_eq!(fs::read(dir.join("link.txt")).unwrap(), b"data");
+        assert!(
+            !fs::symlink_metadata(dir.join("link.txt"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.summary().contains("link.txt: copied the target"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let dir = temp_dir("link-skip");
+        let report = extract_entries_with(
+            with_link(),
+            &dir,
+            SymlinkPolicy::SkipWithWarning,
+            Ownership::default(),
+            |_| {},
+        );
+        assert_eq!(report.written, 2);
+        assert!(fs::symlink_metadata(dir.join("link.txt")).is_err());
+        assert_eq!(report.warnings[0].1, "link to sub/b.txt skipped");
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Elsewhere than Unix creating links may need privileges the test can't assume.
+        #[cfg(unix)]
+        {
+            let dir = temp_dir("link-create");
+            let report = extract_entries_with(
+                with_link(),
+                &dir,
+                SymlinkPolicy::CreateSymlink,
+                Ownership::default(),
+                |_| {},
+            );
+            assert_eq!(report.written, 3);
+            assert!(report.warnings.is_empty());
+            assert_eq!(
+                fs::read_link(dir.join("link.txt")).unwrap(),
+                Path::new("sub/b.txt")
+            );
+            assert_eq!(fs::read(dir.join("link.txt")).unwrap(), b"data");
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_created_links_stay_inside_destination() {
+        let link = |name: &str, target: &str| {
+            Ok(ArchiveEntry::builder(name.to_string())
+                .data(target.as_bytes().to_vec())
+                .kind(EntryKind::Symlink)
+                .build())
+        };
+        let root = temp_dir("link-escape");
+        let dir = root.join("dest");
+        fs::create_dir_all(&dir).unwrap();
+        let items = vec![
+            link("etc", "/etc"),
+            link("up", "../../.."),
+            link("here", "."),
+            // Lexically `x` is two levels down; through `here` it is at the top.
+            link("here/here/x", "../.."),
+            link("sibling", "a.txt"),
+        ];
+
+        let report = extract_entries_with(
+            items,
+            &dir,
+            SymlinkPolicy::CreateSymlink,
+            Ownership::default(),
+            |_| {},
+        );
+        assert_eq!(report.written, 2);
+        assert_eq!(report.warnings.len(), 3);
+        assert!(report.warnings[0].1.contains("outside the archive"));
+        assert!(report.warnings[2].1.contains("inside another link"));
+        for name in ["etc", "up", "x"] {
+            assert!(fs::symlink_metadata(dir.join(name)).is_err(), "{}", name);
+        }
+        assert!(
+            fs::symlink_metadata(dir.join("sibling"))
+                .unwrap()
+                .is_symlink()
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_preflight_rejects_missing_space() {
+        let dir = temp_dir("space");
+        assert!(preflight(&dir, u64::MAX).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Local};
+use slint::SharedString;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNITS: [&str; 6] = ["KB", "MB", "GB", "TB", "PB", "EB"];
+
+pub fn format_size(size: u64) -> SharedString {
+    // Bolt ⚡ Optimization: Return a static SharedString directly for zero-sized
+    // files. This avoids format! and heap allocation for the most common "empty" case.
+    if size == 0 {
+        return SharedString::from("0 B");
+    }
+    if size < 1024 {
+        return SharedString::from(format!("{} B", size));
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    // Promote to the next unit whenever one-decimal rounding would print "1024.0",
+    // e.g. 1_048_575 bytes is "1.0 MB", not "1024.0 KB".
+    while value >= 1023.95 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    // Bolt ⚡ Optimization: Return SharedString directly. While format! still
+    // allocates a String, this approach is more idiomatic for Slint and
+    // simplifies the call sites by avoiding redundant .into() conversions.
+    SharedString::from(format!("{:.1} {}", value, UNITS[unit]))
+}
+
+/// Formats a modification time in local time.
+///
+/// `None` means the platform could not report a time and renders as "Unknown"; a genuine
+/// epoch-0 (or pre-1970) mtime is a real timestamp and is formatted like any other.
+pub fn format_date(modified: Option<SystemTime>) -> SharedString {
+    let Some(time) = modified else {
+        // Bolt ⚡ Optimization: Return a static SharedString directly for the
+        // common "Unknown" case, avoiding a heap-allocated String.
+        return SharedString::from("Unknown");
+    };
+
+    let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => (i64::try_from(after.as_secs()).ok(), after.subsec_nanos()),
+        Err(e) => {
+            let before = e.duration();
+            let secs = i64::try_from(before.as_secs()).ok().map(|s| -s);
+            match before.subsec_nanos() {
+                0 => (secs, 0),
+                n => (secs.and_then(|s| s.checked_sub(1)), 1_000_000_000 - n),
+            }
+        }
+    };
+
+    // SystemTime can represent instants far outside chrono's range; don't panic on them.
+    match secs.and_then(|secs| DateTime::from_timestamp(secs, nanos)) {
+        Some(utc) => {
+            let datetime: DateTime<Local> = utc.with_timezone(&Local);
+            SharedString::from(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+        }
+        None => SharedString::from("Invalid date"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_size_boundaries() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1), "1 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KB");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(1024 * 1024 - 1), "1.0 MB");
+        assert_eq!(format_size(1024 * 1024), "1.0 MB");
+        assert_eq!(format_size(1024 * 1024 * 1024), "1.0 GB");
+        assert_eq!(format_size(1024u64.pow(4)), "1.0 TB");
+        assert_eq!(format_size(u64::MAX), "16.0 EB");
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(None), "Unknown");
+
+        let epoch = format_date(Some(UNIX_EPOCH));
+        assert_ne!(epoch, "Unknown");
+        // Local offset may move the date to 1969-12-31.
+        assert!(epoch.starts_with("1970-01-01") || epoch.starts_with("1969-12-31"));
+
+        let before_epoch = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_ne!(format_date(Some(before_epoch)), "Invalid date");
+    }
+}
@@ -0,0 +1,89 @@
+use lat_core::{ArchiveFormat, FormatFactory};
+
+/// Formats in the compress dropdown, in display order.
+const FORMATS: [ArchiveFormat; 6] = [
+    ArchiveFormat::Zip,
+    ArchiveFormat::SevenZ,
+    ArchiveFormat::Lat,
+    ArchiveFormat::Paqg,
+    ArchiveFormat::Gzip,
+    ArchiveFormat::Bzip2,
+];
+
+/// Factory for `format`, or `None` if the GUI doesn't offer it or it wasn't compiled in.
+pub fn factory(format: ArchiveFormat) -> Option<&'static dyn FormatFactory> {
+    if FORMATS.contains(&format) {
+        lat_formats::factory(format)
+    } else {
+        None
+    }
+}
+
+/// One dropdown entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatChoice {
+    pub format: ArchiveFormat,
+    /// Why the format can't be used right now; `None` when it can.
+    pub unavailable: Option<&'static str>,
+}
+
+impl FormatChoice {
+    /// Text for the dropdown: unusable formats are marked so they stand out before the
+    /// user picks one.
+    pub fn display(&self) -> String {
+        let label = self.format.display_name();
+        match self.unavailable {
+            Some(_) => format!("{} (unavailable)", label),
+            None => label.to_string(),
+        }
+    }
+}
+
+/// Every offered format that was compiled in, with the ones that need a GPU marked
+/// unavailable when there is no accelerator.
+pub fn format_choices(has_accelerator: bool) -> Vec<FormatChoice> {
+    FORMATS
+        .iter()
+        .filter_map(|&format| factory(format).map(|factory| (format, factory)))
+        .map(|(format, factory)| FormatChoice {
+            format,
+            unavailable: (factory.requires_accelerator() && !has_accelerator)
+                .then_some("Needs a GPU accelerator, and none was detected"),
+        })
+        .collect()
+}
+
+/// Labels of the formats that can compress with or without an accelerator.
+pub fn available_formats(has_accelerator: bool) -> Vec<&'static str> {
+    format_choices(has_accelerator)
+        .into_iter()
+        .filter(|choice| choice.unavailable.is_none())
+        .map(|choice| choice.format.display_name())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_formats_follow_accelerator() {
+        assert_eq!(available_formats(false), ["Zip", "7z", "gzip", "bzip2"]);
+        assert_eq!(
+            available_formats(true),
+            ["Zip", "7z", ".lat", "PAQG", "gzip", "bzip2"]
+        );
+
+        let choices = format_choices(false);
+        assert_eq!(choices.len(), 6);
+        assert_eq!(choices[2].display(), ".lat (unavailable)");
+        assert!(choices[3].unavailable.is_some());
+        assert_eq!(choices[0].display(), "Zip");
+
+        for choice in &choices {
+            let needs_gpu = factory(choice.format).unwrap().requires_accelerator();
+            assert_eq!(needs_gpu, choice.unavailable.is_some());
+        }
+        assert!(factory(ArchiveFormat::Xz).is_none());
+    }
+}
@@ -1,47 +1,90 @@
 slint::include_modules!();
 
-use chrono::{DateTime, Local};
-use lat_7z::SevenZCompressor;
-use lat_core::{ArchiveEntry, Compressor};
-use lat_format::LatCompressor;
+mod collect;
+mod extract;
+mod format;
+mod formats;
+mod metadata;
+mod os_path;
+mod recommend;
+mod relative;
+mod save;
+mod search;
+mod selection;
+mod separate;
+
+use collect::{collect_entries, compress_summary, empty_archive_warning};
+use extract::{extract_entries, preflight};
+use format::{format_date, format_size};
+use formats::format_choices;
+use lat_core::mmap::MappedInput;
+use lat_core::path::{NameLimits, OnDuplicate, normalize_entry_names, resolve_duplicates};
+use lat_core::sample::EntrySample;
+use lat_core::{ArchiveFormat, Compressor, CompressorBuilder, LatError};
+use lat_formats::sevenz::SevenZCompressor;
+use lat_formats::zip::{ZipCompressor, ZipFormat};
 use lat_gpu_cuda::CudaAccelerator;
 use lat_gpu_vulkan::VulkanAccelerator;
-use lat_paqg::PaqgCompressor;
-use lat_zip::ZipCompressor;
-use rfd::FileDialog;
+use recommend::{probe_files, recommend};
+use relative::relative_names;
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use save::{OverwritePolicy, save_archive};
+use selection::{scan_folder, selected_row, unique_new_paths};
+use separate::{check_format, compress_separately};
 use slint::{Color, Model, ModelRc, SharedString, VecModel};
-use std::collections::HashSet;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
 
+/// Archives above this size are tested on a sample of their entries.
+const QUICK_TEST_ABOVE: u64 = 256 * 1024 * 1024;
+const QUICK_TEST_FRACTION: f64 = 0.1;
+
 fn main() -> Result<(), slint::PlatformError> {
     let ui = AppWindow::new()?;
 
     // --- State ---
     let files_model = Rc::new(VecModel::<FileEntry>::default());
     ui.set_files(ModelRc::new(files_model.clone()));
+    let next_id = Rc::new(Cell::new(0));
 
     // --- GPU Detection ---
     let (gpu_name, gpu_color, accelerator) = detect_gpu();
     ui.set_gpu_status(SharedString::from(gpu_name));
     ui.set_gpu_color(gpu_color);
     let accelerator: Option<Arc<dyn lat_core::GpuAccelerator>> = accelerator;
+    let choices = format_choices(accelerator.is_some());
+    let labels: Vec<SharedString> = choices.iter().map(|c| c.display().into()).collect();
+    let notes: Vec<SharedString> = choices
+        .iter()
+        .map(|c| c.unavailable.unwrap_or_default().into())
+        .collect();
+    ui.set_format_labels(ModelRc::new(VecModel::from(labels)));
+    ui.set_format_notes(ModelRc::new(VecModel::from(notes)));
 
     // --- Callbacks ---
 
     let ui_handle = ui.as_weak();
     let files_model_clone = files_model.clone();
+    let next_id_clone = next_id.clone();
+    let accel_clone = accelerator.clone();
     ui.on_add_clicked(move || {
         let ui = ui_handle.unwrap();
-        if let Some(paths) = FileDialog::new().pick_files() {
-            for path in paths {
-                if let Ok(metadata) = fs::metadata(&path) {
+        if let Some(picked) = FileDialog::new().pick_files() {
+            add_files(
+                &ui,
+                &files_model_clone,
+                &next_id_clone,
+                accel_clone.as_deref(),
+                picked,
+                |path| {
                     // Bolt ⚡ Optimization: Minimize path component lookups by nesting the
                     // file name extraction. Using direct SharedString conversion from &str
                     // avoids intermediate String allocations for the UTF-8 fast path.
-                    let name_ss = if let Some(file_name) = path.file_name() {
+                    if let Some(file_name) = path.file_name() {
                         if let Some(s) = file_name.to_str() {
                             SharedString::from(s)
                         } else {
@@ -49,31 +92,46 @@ fn main() -> Result<(), slint::PlatformError> {
                         }
                     } else {
                         SharedString::from("")
-                    };
-
-                    let path_ss = if let Some(s) = path.to_str() {
-                        SharedString::from(s)
-                    } else {
-                        SharedString::from(path.to_string_lossy().as_ref())
-                    };
+                    }
+                },
+                "files",
+            );
+        }
+    });
 
-                    let size = if metadata.is_dir() {
-                        SharedString::from("DIR")
-                    } else {
-                        format_size(metadata.len())
-                    };
-                    let date = format_date(metadata.modified().ok());
-
-                    files_model_clone.push(FileEntry {
-                        name: name_ss,
-                        size,
-                        date,
-                        path: path_ss,
-                    });
-                }
+    let ui_handle = ui.as_weak();
+    let files_model_clone = files_model.clone();
+    let next_id_clone = next_id.clone();
+    let accel_clone = accelerator.clone();
+    ui.on_add_folder_clicked(move || {
+        let ui = ui_handle.unwrap();
+        if let Some(folder) = FileDialog::new().pick_folder() {
+            let (files, unreadable) = scan_folder(&folder);
+            if unreadable > 0 {
+                MessageDialog::new()
+                    .set_title("Unreadable items skipped")
+                    .set_description(format!(
+                        "Skipped {} unreadable item(s) in {}",
+                        unreadable,
+                        folder.display()
+                    ))
+                    .set_level(MessageLevel::Warning)
+                    .set_buttons(MessageButtons::Ok)
+                    .show();
             }
-            ui.set_status_text(
-                format!("Added files. Total: {}", files_model_clone.row_count()).into(),
+            let names: HashMap<PathBuf, String> = files
+                .iter()
+                .map(|(name, path)| (path.clone(), name.clone()))
+                .collect();
+            let paths = files.into_iter().map(|(_, path)| path).collect();
+            add_files(
+                &ui,
+                &files_model_clone,
+                &next_id_clone,
+                accel_clone.as_deref(),
+                paths,
+                |path| SharedString::from(names[path].as_str()),
+                "folder",
             );
         }
     });
@@ -82,62 +140,131 @@ fn main() -> Result<(), slint::PlatformError> {
     let files_model_clone = files_model.clone();
     ui.on_delete_clicked(move || {
         let ui = ui_handle.unwrap();
-        let index = ui.get_selected_index();
-        if index >= 0 && (index as usize) < files_model_clone.row_count() {
-            files_model_clone.remove(index as usize);
-            ui.set_selected_index(-1);
+        if let Some((row, _)) = selected_file(&ui, &files_model_clone) {
+            files_model_clone.remove(row);
+            ui.set_selected_id(-1);
+            ui.set_search_count(apply_search(&files_model_clone, &ui.get_search_text()).into());
             ui.set_status_text("Item removed".into());
         }
     });
 
+    let ui_handle = ui.as_weak();
+    let files_model_clone = files_model.clone();
+    ui.on_search_changed(move |query| {
+        let ui = ui_handle.unwrap();
+        ui.set_search_count(apply_search(&files_model_clone, &query).into());
+        // Don't leave an invisible row selected.
+        let selected_hidden =
+            selected_file(&ui, &files_model_clone).is_some_and(|(_, file)| file.hidden);
+        if selected_hidden {
+            ui.set_selected_id(-1);
+        }
+    });
+
     let ui_handle = ui.as_weak();
     let files_model_clone = files_model.clone();
     let accel_clone = accelerator.clone();
-    ui.on_compress_clicked(move |format| {
+    let choices_clone = choices.clone();
+    ui.on_compress_clicked(move |index| {
         let ui = ui_handle.unwrap();
 
-        // Bolt ⚡ Optimization: Determine the file extension and compressor via a
-        // zero-allocation match on the format string slice. This avoids redundant
-        // to_lowercase() and trim_start_matches allocations per click.
-        let (ext, compressor): (&str, Box<dyn Compressor>) = match format.as_str() {
-            "7z" => ("7z", Box::new(SevenZCompressor)),
-            ".lat" => ("lat", Box::new(LatCompressor::new(accel_clone.clone()))),
-            "PAQG" => ("paq", Box::new(PaqgCompressor::new(accel_clone.clone()))),
-            _ => ("zip", Box::new(ZipCompressor)),
+        let Some(factory) = usize::try_from(index)
+            .ok()
+            .and_then(|index| choices_clone.get(index))
+            .and_then(|choice| formats::factory(choice.format))
+        else {
+            return;
         };
+        let compressor = match CompressorBuilder::new(factory)
+            .accelerator(accel_clone.clone())
+            .build()
+        {
+            Ok(compressor) => compressor,
+            Err(e) => {
+                ui.set_status_text(format!("Error: {}", e).into());
+                return;
+            }
+        };
+
+        if ui.get_separate_files() {
+            compress_files_separately(&ui, &files_model_clone, compressor.as_ref());
+            return;
+        }
 
+        let format = compressor.format();
         let dest = FileDialog::new()
-            .set_file_name(format!("archive.{}", ext))
+            .set_file_name(format!("archive.{}", format.extension()))
             .save_file();
 
         if let Some(dest_path) = dest {
-            ui.set_status_text(format!("Compressing to {}...", format).into());
-
-            // Bolt ⚡ Optimization: Pre-allocate the entries vector with the known number of files.
-            // This avoids multiple expensive reallocations and memcpys during the collection phase.
-            let count = files_model_clone.row_count();
-            let mut entries = Vec::with_capacity(count);
-            for i in 0..count {
-                if let Some(file) = files_model_clone.row_data(i) {
-                    // Bolt ⚡ Optimization: Read directly using SharedString's slice to avoid
-                    // an unnecessary PathBuf (and thus String) heap allocation per file.
-                    if let Ok(data) = fs::read(file.path.as_str()) {
-                        entries.push(ArchiveEntry {
-                            name: file.name.to_string(),
-                            data,
-                        });
-                    }
+            ui.set_status_text(format!("Compressing to {}...", format.display_name()).into());
+
+            let (entries, warnings, renamed) =
+                collect_entries(entry_sources(&ui, &files_model_clone));
+            if let Some(message) = empty_archive_warning(&entries, &warnings) {
+                let create = MessageDialog::new()
+                    .set_title("Empty archive")
+                    .set_description(message)
+                    .set_buttons(MessageButtons::YesNo)
+                    .show()
+                    == MessageDialogResult::Yes;
+                if !create {
+                    ui.set_status_text("Compression cancelled".into());
+                    return;
                 }
             }
 
-            match compressor.compress(&entries, None) {
-                Ok(data) => {
-                    if let Err(e) = fs::write(dest_path, data) {
-                        ui.set_status_text(format!("Error: {}", e).into());
+            match compressor.compress_detailed(&entries, None) {
+                Ok(mut result) => {
+                    result.warnings = warnings;
+                    let confirm = || {
+                        let name = dest_path.file_name().unwrap_or_default().to_string_lossy();
+                        MessageDialog::new()
+                            .set_title("Overwrite")
+                            .set_description(format!("Overwrite {}?", name))
+                            .set_buttons(MessageButtons::YesNo)
+                            .show()
+                            == MessageDialogResult::Yes
+                    };
+                    let policy = if ui.get_confirm_overwrite() {
+                        OverwritePolicy::Confirm
                     } else {
-                        ui.set_status_text(format!("Successfully compressed to {}", format).into());
+                        OverwritePolicy::Overwrite
+                    };
+                    match save_archive(&dest_path, &result.data, policy, confirm) {
+                        Ok(true) => {
+                            let mut status = compress_summary(
+                                format.display_name(),
+                                entries.len(),
+                                &result.warnings,
+                            );
+                            if !renamed.is_empty() {
+                                let details: Vec<String> = renamed
+                                    .iter()
+                                    .map(|warning| format!("{}: {}", warning.name, warning.reason))
+                                    .collect();
+                                MessageDialog::new()
+                                    .set_title("Names that aren't valid UTF-8")
+                                    .set_description(details.join("\n"))
+                                    .set_level(MessageLevel::Warning)
+                                    .set_buttons(MessageButtons::Ok)
+                                    .show();
+                                status.push_str(&format!(
+                                    " ({} non-UTF-8 names stored with lossy spelling)",
+                                    renamed.len()
+                                ));
+                            }
+                            ui.set_status_text(status.into());
+                        }
+                        Ok(false) => ui.set_status_text("Compression cancelled".into()),
+                        Err(e) => ui.set_status_text(format!("Error: {}", e).into()),
                     }
                 }
+                // Context names the entry and stage, e.g. "Failed compressing
+                // 'foo/bar.bin' during encryption: ...".
+                Err(e @ LatError::Context { .. }) => {
+                    ui.set_status_text(format!("Failed compressing {}", e).into());
+                }
                 Err(e) => {
                     ui.set_status_text(format!("Compression failed: {}", e).into());
                 }
@@ -149,51 +276,79 @@ fn main() -> Result<(), slint::PlatformError> {
     let accel_clone = accelerator.clone();
     ui.on_extract_clicked(move || {
         let ui = ui_handle.unwrap();
-        if let Some(archive_path) = FileDialog::new().pick_file()
-            && let Some(dest_dir) = FileDialog::new().pick_folder()
+        // The folder picker is only shown once an archive has been chosen.
+        let Some(archive_path) = FileDialog::new().pick_file() else {
+            return;
+        };
+        let Some(dest_dir) = FileDialog::new().pick_folder() else {
+            return;
+        };
+        ui.set_status_text("Decompressing...".into());
+
+        // Unknown extensions are tried as ZIP, which also covers .jar, .docx and friends.
+        let factory = archive_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .and_then(ArchiveFormat::from_extension)
+            .and_then(formats::factory)
+            .unwrap_or(&ZipFormat);
+        let compressor = match CompressorBuilder::new(factory)
+            .accelerator(accel_clone.clone())
+            .build()
         {
-            ui.set_status_text("Decompressing...".into());
-
-            let ext = archive_path
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or_default();
-            let compressor: Box<dyn Compressor> = match ext {
-                "7z" => Box::new(SevenZCompressor),
-                "lat" => Box::new(LatCompressor::new(accel_clone.clone())),
-                "paq" => Box::new(PaqgCompressor::new(accel_clone.clone())),
-                _ => Box::new(ZipCompressor),
-            };
+            Ok(compressor) => compressor,
+            Err(e) => {
+                ui.set_status_text(format!("Error: {}", e).into());
+                return;
+            }
+        };
 
-            match fs::read(&archive_path) {
-                Ok(archive_data) => match compressor.decompress(&archive_data, None) {
-                    Ok(entries) => {
-                        // Bolt ⚡ Optimization: Pre-seed created_dirs with the destination
-                        // root and use a last_parent cache to skip redundant HashSet lookups
-                        // and syscalls for consecutive files in the same directory.
-                        let mut created_dirs = HashSet::with_capacity(entries.len() / 4);
-                        created_dirs.insert(dest_dir.to_path_buf());
-                        let mut last_parent: Option<PathBuf> = None;
-
-                        for entry in entries {
-                            let path = dest_dir.join(entry.name);
-                            if let Some(parent) = path.parent()
-                                && last_parent.as_deref() != Some(parent)
-                            {
-                                if !created_dirs.contains(parent) {
-                                    let _ = fs::create_dir_all(parent);
-                                    created_dirs.insert(parent.to_path_buf());
-                                }
-                                last_parent = Some(parent.to_path_buf());
-                            }
-                            let _ = fs::write(path, entry.data);
+        let normalize = ui.get_normalize_names();
+        // Large archives are mapped rather than copied into memory; the decoder reads the
+        // mapping directly, and the result is discarded if the file changed meanwhile.
+        match MappedInput::open(&archive_path) {
+            Ok(archive_data) => match compressor
+                .decompress(&archive_data, None)
+                .and_then(|entries| archive_data.check_unchanged().map(|()| entries))
+                .map(|mut entries| {
+                    if normalize {
+                        normalize_entry_names(&mut entries);
+                    }
+                    entries
+                })
+                .and_then(|entries| {
+                    NameLimits::default()
+                        .check_entries(&entries)
+                        .map(|()| entries)
+                })
+                .and_then(|entries| resolve_duplicates(entries, OnDuplicate::default()))
+            {
+                Ok(entries) => {
+                    let required = entries.iter().map(|e| e.data.len() as u64).sum();
+                    match preflight(&dest_dir, required) {
+                        Ok(()) => {
+                            let report = extract_entries(
+                                entries.into_iter().map(Ok),
+                                &dest_dir,
+                                |progress| {
+                                    ui.set_status_text(
+                                        format!(
+                                            "Extracting... {} files, {}",
+                                            progress.entries_done,
+                                            format_size(progress.bytes_written)
+                                        )
+                                        .into(),
+                                    )
+                                },
+                            );
+                            ui.set_status_text(report.summary().into());
                         }
-                        ui.set_status_text("Extraction complete".into());
+                        Err(e) => ui.set_status_text(e.into()),
                     }
-                    Err(e) => ui.set_status_text(format!("Decompression failed: {}", e).into()),
-                },
-                Err(e) => ui.set_status_text(format!("Error reading archive: {}", e).into()),
-            }
+                }
+                Err(e) => ui.set_status_text(format!("Decompression failed: {}", e).into()),
+            },
+            Err(e) => ui.set_status_text(format!("Error reading archive: {}", e).into()),
         }
     });
 
@@ -201,21 +356,41 @@ fn main() -> Result<(), slint::PlatformError> {
     let files_model_clone = files_model.clone();
     ui.on_test_clicked(move || {
         let ui = ui_handle.unwrap();
-        let index = ui.get_selected_index();
-        if index >= 0
-            && (index as usize) < files_model_clone.row_count()
-            && let Some(file) = files_model_clone.row_data(index as usize)
-        {
+        if let Some((_, file)) = selected_file(&ui, &files_model_clone) {
             ui.set_status_text(format!("Testing {}...", file.name).into());
-            // Bolt ⚡ Optimization: Read directly using SharedString's slice to avoid
-            // an unnecessary PathBuf (and thus String) heap allocation.
-            if let Ok(data) = fs::read(file.path.as_str()) {
-                if ZipCompressor.decompress(&data, None).is_ok() {
-                    ui.set_status_text("Archive integrity verified (ZIP)".into());
-                } else if SevenZCompressor.decompress(&data, None).is_ok() {
-                    ui.set_status_text("Archive integrity verified (7z)".into());
+            if let Ok(data) = MappedInput::open(os_path::decode(&file.path)) {
+                // Huge archives get a sampled check so the button stays responsive.
+                let fraction = if data.len() as u64 > QUICK_TEST_ABOVE {
+                    QUICK_TEST_FRACTION
                 } else {
-                    ui.set_status_text("Could not verify archive format".into());
+                    1.0
+                };
+                let sample = EntrySample::new(fraction);
+                let candidates: [Box<dyn Compressor>; 2] = [
+                    Box::new(ZipCompressor::new()),
+                    Box::new(SevenZCompressor::new()),
+                ];
+                let verified = candidates.iter().find_map(|compressor| {
+                    let report = compressor.quick_verify(&data, None, &sample).ok()?;
+                    Some((compressor.format(), report))
+                });
+                match data.check_unchanged().map(|()| verified) {
+                    Err(e) => ui.set_status_text(format!("Error: {}", e).into()),
+                    Ok(Some((format, report))) if report.entries_checked < report.entries_total => {
+                        ui.set_status_text(
+                            format!(
+                                "Sampled {} of {} entries, no errors ({})",
+                                report.entries_checked,
+                                report.entries_total,
+                                format.display_name()
+                            )
+                            .into(),
+                        )
+                    }
+                    Ok(Some((format, _))) => ui.set_status_text(
+                        format!("Archive integrity verified ({})", format.display_name()).into(),
+                    ),
+                    Ok(None) => ui.set_status_text("Could not verify archive format".into()),
                 }
             }
         }
@@ -225,15 +400,13 @@ fn main() -> Result<(), slint::PlatformError> {
     let files_model_clone = files_model.clone();
     ui.on_info_clicked(move || {
         let ui = ui_handle.unwrap();
-        let index = ui.get_selected_index();
-        if index >= 0
-            && (index as usize) < files_model_clone.row_count()
-            && let Some(file) = files_model_clone.row_data(index as usize)
-        {
+        if let Some((_, file)) = selected_file(&ui, &files_model_clone) {
             ui.set_status_text(
                 format!(
                     "File: {} | Size: {} | Path: {}",
-                    file.name, file.size, file.path
+                    file.name,
+                    file.size,
+                    os_path::display(&file.path)
                 )
                 .into(),
             );
@@ -243,40 +416,151 @@ fn main() -> Result<(), slint::PlatformError> {
     ui.run()
 }
 
-fn format_size(size: u64) -> SharedString {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    // Bolt ⚡ Optimization: Return a static SharedString directly for zero-sized
-    // files. This avoids format! and heap allocation for the most common "empty" case.
-    if size == 0 {
-        return SharedString::from("0 B");
+/// Compresses every listed file into its own archive in a folder the user picks.
+fn compress_files_separately(
+    ui: &AppWindow,
+    files_model: &VecModel<FileEntry>,
+    compressor: &dyn Compressor,
+) {
+    // Say so before the folder picker rather than after.
+    if let Err(e) = check_format(compressor.format()) {
+        ui.set_status_text(format!("Error: {}", e).into());
+        return;
+    }
+    let Some(out_dir) = FileDialog::new().pick_folder() else {
+        return;
+    };
+    let paths: Vec<PathBuf> = files_model
+        .iter()
+        .map(|f| os_path::decode(&f.path))
+        .collect();
+    match compress_separately(compressor, &paths, &out_dir) {
+        Ok(written) => ui.set_status_text(
+            format!(
+                "Compressed {} files separately into {}",
+                written.len(),
+                out_dir.display()
+            )
+            .into(),
+        ),
+        Err(e @ LatError::Context { .. }) => {
+            ui.set_status_text(format!("Failed compressing {}", e).into())
+        }
+        Err(e) => ui.set_status_text(format!("Compression failed: {}", e).into()),
     }
+}
 
-    // Bolt ⚡ Optimization: Return SharedString directly. While format! still
-    // allocates a String, this approach is more idiomatic for Slint and
-    // simplifies the call sites by avoiding redundant .into() conversions.
-    if size >= GB {
-        SharedString::from(format!("{:.1} GB", size as f64 / GB as f64))
-    } else if size >= MB {
-        SharedString::from(format!("{:.1} MB", size as f64 / MB as f64))
-    } else if size >= KB {
-        SharedString::from(format!("{:.1} KB", size as f64 / KB as f64))
-    } else {
-        SharedString::from(format!("{} B", size))
+/// The selected row and its entry, if the selected entry is still listed.
+fn selected_file(ui: &AppWindow, files_model: &VecModel<FileEntry>) -> Option<(usize, FileEntry)> {
+    let row = selected_row(files_model.iter().map(|f| f.id), ui.get_selected_id())?;
+    files_model.row_data(row).map(|file| (row, file))
+}
+
+/// `(name, path)` of every listed file for [`collect_entries`]: the list name, or with
+/// "Relative paths" checked, the path below the files' common folder.
+fn entry_sources(
+    ui: &AppWindow,
+    files_model: &VecModel<FileEntry>,
+) -> Vec<(SharedString, SharedString)> {
+    let files: Vec<FileEntry> = files_model.iter().collect();
+    if !ui.get_relative_names() {
+        return files
+            .into_iter()
+            .map(|file| (file.name, file.path))
+            .collect();
     }
+    let paths: Vec<PathBuf> = files.iter().map(|f| os_path::decode(&f.path)).collect();
+    relative_names(&paths)
+        .into_iter()
+        .map(SharedString::from)
+        .zip(files.into_iter().map(|file| file.path))
+        .collect()
 }
 
-fn format_date(modified: Option<std::time::SystemTime>) -> SharedString {
-    match modified {
-        Some(time) => {
-            let datetime: DateTime<Local> = time.into();
-            SharedString::from(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+/// Appends `picked` to the file list, named by `name_of`, skipping files already listed,
+/// then refreshes the search count and the format suggestion. `what` ("files",
+/// "folder") goes into the status line. New rows take ids from `next_id`.
+fn add_files(
+    ui: &AppWindow,
+    files_model: &VecModel<FileEntry>,
+    next_id: &Cell<i32>,
+    accelerator: Option<&dyn lat_core::GpuAccelerator>,
+    picked: Vec<PathBuf>,
+    name_of: impl Fn(&Path) -> SharedString,
+    what: &str,
+) {
+    let existing = files_model.iter().map(|f| os_path::decode(&f.path));
+    let (paths, skipped) = unique_new_paths(existing, picked);
+    for path in paths {
+        if let Ok(metadata) = fs::metadata(&path) {
+            // The name is only for display; the model keeps the exact path to read.
+            let path_ss = SharedString::from(os_path::encode(&path));
+
+            let size = if metadata.is_dir() {
+                SharedString::from("DIR")
+            } else {
+                format_size(metadata.len())
+            };
+            let date = format_date(metadata.modified().ok());
+
+            files_model.push(FileEntry {
+                name: name_of(&path),
+                size,
+                date,
+                path: path_ss,
+                id: next_id.replace(next_id.get() + 1),
+                ..Default::default()
+            });
         }
-        // Bolt ⚡ Optimization: Return a static SharedString directly for the
-        // common "Unknown" case, avoiding a heap-allocated String.
-        None => SharedString::from("Unknown"),
+    }
+    ui.set_search_count(apply_search(files_model, &ui.get_search_text()).into());
+    // Quick probe over the whole selection so the hint reflects every file.
+    let paths = files_model.iter().map(|f| os_path::decode(&f.path));
+    let hint = recommend(&probe_files(paths, accelerator));
+    let level = match hint.level {
+        Some(0) => " (store)".to_string(),
+        Some(level) => format!(" (level {})", level),
+        None => String::new(),
+    };
+    let skipped = match skipped {
+        0 => String::new(),
+        1 => " (Skipped 1 duplicate)".to_string(),
+        n => format!(" (Skipped {} duplicates)", n),
+    };
+    ui.set_status_text(
+        format!(
+            "Added {}{}. Total: {} | Suggested: {}{} - {}",
+            what,
+            skipped,
+            files_model.row_count(),
+            hint.format,
+            level,
+            hint.reason
+        )
+        .into(),
+    );
+}
+
+/// Collapses the rows whose names don't match `query` and tints the ones that do,
+/// returning the match count for the search box (empty when there is no query).
+fn apply_search(model: &VecModel<FileEntry>, query: &str) -> String {
+    let names: Vec<SharedString> = model.iter().map(|f| f.name).collect();
+    let matches = search::matching_indices(names.iter().map(|n| n.as_str()), query);
+    let active = !query.trim().is_empty();
+    let mut next = matches.iter().peekable();
+    for index in 0..model.row_count() {
+        let Some(mut row) = model.row_data(index) else {
+            continue;
+        };
+        let matched = next.next_if(|&&m| m == index).is_some();
+        row.hidden = !matched;
+        row.highlighted = active && matched;
+        model.set_row_data(index, row);
+    }
+    if active {
+        format!("{} of {} match", matches.len(), model.row_count())
+    } else {
+        String::new()
     }
 }
 
@@ -285,7 +569,8 @@ fn detect_gpu() -> (
     Color,
     Option<Arc<dyn lat_core::GpuAccelerator>>,
 ) {
-    if let Ok(cuda) = CudaAccelerator::new() {
+    // Probe first so only the backend that's actually used gets a context/device.
+    if let Some(Ok(cuda)) = CudaAccelerator::is_available().then(CudaAccelerator::new) {
         return (
             "CUDA (Active)",
             Color::from_rgb_u8(46, 204, 113),
@@ -293,8 +578,9 @@ fn detect_gpu() -> (
         );
     }
 
-    let vulkan_future = VulkanAccelerator::new();
-    if let Ok(vulkan) = pollster::block_on(vulkan_future) {
+    if let Some(Ok(vulkan)) =
+        VulkanAccelerator::is_available().then(|| pollster::block_on(VulkanAccelerator::new()))
+    {
         return (
             "Vulkan (Active)",
             Color::from_rgb_u8(52, 152, 219),
@@ -0,0 +1,118 @@
+use lat_core::ArchiveEntry;
+use std::path::Path;
+
+/// Records `path`'s owner and extended attributes on `entry`. Attributes that can't be
+/// read are left out; elsewhere than Unix this does nothing.
+#[cfg(unix)]
+pub fn capture(entry: &mut ArchiveEntry, path: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        entry.uid = Some(metadata.uid());
+        entry.gid = Some(metadata.gid());
+    }
+    let Ok(names) = xattr::list(path) else {
+        return;
+    };
+    for name in names {
+        let Some(name) = name.to_str().map(str::to_string) else {
+            continue;
+        };
+        if let Ok(Some(value)) = xattr::get(path, &name) {
+            entry.xattrs.push((name, value));
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn capture(_entry: &mut ArchiveEntry, _path: &Path) {}
+
+/// Who owns extracted files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Ownership {
+    /// The user running the extraction.
+    #[default]
+    Extractor,
+    /// The owner recorded in the archive, like `tar --same-owner`. Only for archives from
+    /// a trusted source, and only takes effect with the privileges to change owners.
+    FromArchive,
+}
+
+/// Applies an extracted entry's extended attributes, and its owner if `ownership` says
+/// so, to the file at `path`.
+///
+/// Only `user.*` attributes are restored, as `tar --xattrs` does by default: the
+/// `security.*`, `trusted.*` and `system.*` namespaces hold labels and ACLs that an
+/// archive from elsewhere shouldn't choose. Best effort: not every filesystem stores
+/// extended attributes, and changing the owner needs privileges, so failures leave the
+/// file as written rather than failing the extraction.
+#[cfg(unix)]
+pub fn restore(entry: &ArchiveEntry, path: &Path, ownership: Ownership) {
+    if ownership == Ownership::FromArchive && (entry.uid.is_some() || entry.gid.is_some()) {
+        let _ = std::os::unix::fs::chown(path, entry.uid, entry.gid);
+    }
+    for (name, value) in &entry.xattrs {
+        if name.starts_with("user.") {
+            let _ = xattr::set(path, name, value);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn restore(_entry: &ArchiveEntry, _path: &Path, _ownership: Ownership) {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::extract::extract_entries;
+    use lat_core::Compressor;
+    use lat_formats::zip::ZipCompressor;
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_owner_and_xattrs_round_trip() {
+        let dir = std::env::temp_dir().join(format!("lat-metadata-{}", std::process::id()));
+        fs::create_dir_all(dir.join("out")).unwrap();
+        let source = dir.join("tagged.txt");
+        fs::write(&source, b"tagged").unwrap();
+        if xattr::set(&source, "user.lat.test", b"round trip").is_err() {
+            // The temp filesystem doesn't store user attributes.
+            fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+        let owner = fs::metadata(&source).unwrap();
+
+        let mut entry = ArchiveEntry::builder("tagged.txt")
+            .data(fs::read(&source).unwrap())
+            .build();
+        capture(&mut entry, &source);
+        assert_eq!(entry.uid, Some(owner.uid()));
+        entry
+            .xattrs
+            .push(("trusted.lat.test".to_string(), b"privileged".to_vec()));
+
+        let compressor = ZipCompressor::new();
+        let archive = compressor.compress(&[entry], None).unwrap();
+        let entries = compressor.decompress(&archive, None).unwrap();
+        assert_eq!(
+            (entries[0].uid, entries[0].gid),
+            (Some(owner.uid()), Some(owner.gid()))
+        );
+        let report = extract_entries(entries.into_iter().map(Ok), &dir.join("out"), |_| {});
+        assert_eq!(report.written, 1);
+
+        let extracted = dir.join("out").join("tagged.txt");
+        assert_eq!(
+            xattr::get(&extracted, "user.lat.test").unwrap().as_deref(),
+            Some(&b"round trip"[..])
+        );
+        assert_eq!(
+            xattr::get(&extracted, "trusted.lat.test").ok().flatten(),
+            None
+        );
+        let restored = fs::metadata(&extracted).unwrap();
+        assert_eq!((restored.uid(), restored.gid()), (owner.uid(), owner.gid()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Carrying file paths through the UI model, which only holds strings, without losing
+//! names that aren't valid UTF-8.
+
+use std::path::{Path, PathBuf};
+
+/// Starts a stored path that holds the hex of the raw OS bytes. No real path contains NUL,
+/// so this can't be mistaken for one.
+const RAW_PREFIX: char = '\0';
+
+/// The string to keep in the model for `path`: the path itself when it is UTF-8, otherwise
+/// its raw bytes, so [`decode`] gives back exactly the file that was picked.
+pub fn encode(path: &Path) -> String {
+    if let Some(s) = path.to_str() {
+        return s.to_string();
+    }
+    raw(path)
+}
+
+#[cfg(unix)]
+fn raw(path: &Path) -> String {
+    use std::fmt::Write;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut out = String::from(RAW_PREFIX);
+    for byte in path.as_os_str().as_bytes() {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+// Elsewhere paths that aren't UTF-8 can't be spelled as bytes; keep the lossy form.
+#[cfg(not(unix))]
+fn raw(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// The path a model string stands for.
+pub fn decode(stored: &str) -> PathBuf {
+    match stored.strip_prefix(RAW_PREFIX) {
+        Some(hex) => from_raw(hex),
+        None => PathBuf::from(stored),
+    }
+}
+
+#[cfg(unix)]
+fn from_raw(hex: &str) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect();
+    PathBuf::from(OsStr::from_bytes(&bytes))
+}
+
+#[cfg(not(unix))]
+fn from_raw(hex: &str) -> PathBuf {
+    PathBuf::from(hex)
+}
+
+/// A stored path as text for the user, with `�` standing in for bytes that aren't UTF-8.
+pub fn display(stored: &str) -> String {
+    decode(stored).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_paths_are_stored_as_is() {
+        let path = Path::new("/tmp/résumé.txt");
+        assert_eq!(encode(path), "/tmp/résumé.txt");
+        assert_eq!(decode(&encode(path)), path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/caf\xe9.txt"));
+        let stored = encode(path);
+        assert_eq!(decode(&stored), path);
+        assert_eq!(display(&stored), "/tmp/caf\u{fffd}.txt");
+    }
+}
@@ -0,0 +1,183 @@
+use lat_core::entropy::estimate_entropy_bits_per_byte;
+use lat_core::{ArchiveEntry, Compressor, GpuAccelerator};
+use lat_formats::zip::ZipCompressor;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes read from the start of each file for the probe.
+const SAMPLE_PER_FILE: u64 = 64 * 1024;
+/// Upper bound on the total sample, so the probe stays fast for large selections.
+const SAMPLE_TOTAL: usize = 1024 * 1024;
+/// Order-0 entropy (bits per byte) below which byte frequencies are skewed enough for
+/// LZMA2's range coder to beat Deflate's Huffman codes on moderately compressible data.
+const SKEWED_BITS: f32 = 6.0;
+
+/// Result of a quick Deflate level-1 probe over a sample of the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SampleStats {
+    pub sampled: u64,
+    pub probe_compressed: u64,
+    /// Size of the whole selection, sampled or not.
+    pub total: u64,
+    /// Order-0 entropy of the sample in bits per byte.
+    pub entropy_bits: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatRecommendation {
+    /// Name as shown in the format selector.
+    pub format: &'static str,
+    /// Suggested level; `Some(0)` means store without compression.
+    pub level: Option<u32>,
+    pub reason: &'static str,
+}
+
+/// Picks a format from probe results: store data that doesn't shrink, spend effort on data
+/// that does, and keep to a faster setting when the selection is large.
+pub fn recommend(stats: &SampleStats) -> FormatRecommendation {
+    if stats.sampled == 0 {
+        return FormatRecommendation {
+            format: "Zip",
+            level: None,
+            reason: "nothing to sample",
+        };
+    }
+
+    let ratio = stats.probe_compressed as f64 / stats.sampled as f64;
+    if ratio > 0.95 {
+        FormatRecommendation {
+            format: "Zip",
+            level: Some(0),
+            reason: "data looks already compressed; storing it is fastest",
+        }
+    } else if ratio < 0.5 && stats.total > 1024 * 1024 * 1024 {
+        FormatRecommendation {
+            format: "7z",
+            level: Some(5),
+            reason: "compresses well, but the selection is large",
+        }
+    } else if ratio < 0.5 {
+        FormatRecommendation {
+            format: "7z",
+            level: Some(9),
+            reason: "highly compressible data benefits from LZMA2",
+        }
+    } else if stats.entropy_bits < SKEWED_BITS {
+        FormatRecommendation {
+            format: "7z",
+            level: Some(6),
+            reason: "skewed byte frequencies suit LZMA2's range coder",
+        }
+    } else {
+        FormatRecommendation {
+            format: "Zip",
+            level: Some(6),
+            reason: "moderately compressible data; Deflate is a good balance",
+        }
+    }
+}
+
+/// Probes an in-memory sample with fast Deflate and measures its byte entropy, which
+/// runs on `accelerator` when there is one. The probe decides how compressible the sample
+/// is; entropy only breaks ties between the candidates.
+pub fn probe_sample(
+    sample: &[u8],
+    total: u64,
+    accelerator: Option<&dyn GpuAccelerator>,
+) -> SampleStats {
+    let entries = [ArchiveEntry::builder("sample")
+        .data(sample.to_vec())
+        .build()];
+    // Per-entry stats give the payload size without ZIP header overhead.
+    let probe_compressed = ZipCompressor::new()
+        .with_level(1)
+        .compress_detailed(&entries, None)
+        .ok()
+        .and_then(|result| result.per_entry.first().map(|stat| stat.compressed))
+        .unwrap_or(sample.len() as u64);
+    SampleStats {
+        sampled: sample.len() as u64,
+        probe_compressed,
+        total,
+        entropy_bits: estimate_entropy_bits_per_byte(sample, accelerator),
+    }
+}
+
+/// Reads a prefix of each file (skipping unreadable ones) and probes it.
+pub fn probe_files<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+    accelerator: Option<&dyn GpuAccelerator>,
+) -> SampleStats {
+    let mut sample = Vec::new();
+    let mut total = 0;
+    for path in paths {
+        let Ok(file) = File::open(path.as_ref()) else {
+            continue;
+        };
+        total += file.metadata().map_or(0, |m| m.len());
+        if sample.len() < SAMPLE_TOTAL {
+            let _ = file.take(SAMPLE_PER_FILE).read_to_end(&mut sample);
+        }
+    }
+    sample.truncate(SAMPLE_TOTAL);
+    probe_sample(&sample, total, accelerator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lat_core::testing::noise;
+
+    #[test]
+    fn test_text_recommends_high_ratio_codec() {
+        let text = b"fn main() { println!(\"hello, world\"); }\n".repeat(2000);
+        let stats = probe_sample(&text, text.len() as u64, None);
+        assert!(stats.probe_compressed < stats.sampled / 2);
+
+        let recommendation = recommend(&stats);
+        assert_eq!(recommendation.format, "7z");
+        assert_eq!(recommendation.level, Some(9));
+    }
+
+    #[test]
+    fn test_precompressed_recommends_store() {
+        let noise = noise(256 * 1024, 0x2545_f491);
+        let recommendation = recommend(&probe_sample(&noise, noise.len() as u64, None));
+        assert_eq!(recommendation.format, "Zip");
+        assert_eq!(recommendation.level, Some(0));
+    }
+
+    #[test]
+    fn test_repeated_noise_is_not_stored() {
+        // Every byte value is equally likely, but each 16 KiB block repeats within
+        // Deflate's window.
+        let block = noise(16 * 1024, 0x2545_f491);
+        let data = block.repeat(16);
+        let stats = probe_sample(&data, data.len() as u64, None);
+        assert!(stats.entropy_bits > 7.95);
+        assert!(stats.probe_compressed < stats.sampled / 4);
+        assert_ne!(recommend(&stats).level, Some(0));
+    }
+
+    #[test]
+    fn test_recommend_thresholds() {
+        let stats = |sampled, probe_compressed, total| SampleStats {
+            sampled,
+            probe_compressed,
+            total,
+            entropy_bits: 7.0,
+        };
+        assert_eq!(recommend(&stats(0, 0, 0)).level, None);
+        assert_eq!(recommend(&stats(100, 70, 100)).format, "Zip");
+        assert_eq!(recommend(&stats(100, 70, 100)).level, Some(6));
+        assert_eq!(recommend(&stats(100, 20, 2 << 30)).level, Some(5));
+        // Entropy only ranks the candidates for data the probe found compressible.
+        let skewed = |probe_compressed| SampleStats {
+            entropy_bits: 4.0,
+            ..stats(100, probe_compressed, 100)
+        };
+        assert_eq!(recommend(&skewed(70)).format, "7z");
+        assert_eq!(recommend(&skewed(99)).level, Some(0));
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Entry names for `paths` relative to the deepest directory that contains all of them,
+/// `/`-separated, so files with the same name from different folders keep the subpaths
+/// that tell them apart (`a/config.txt`, `b/config.txt`). A single file, or files with no
+/// common ancestor (different drives), are named by their file name alone.
+pub fn relative_names(paths: &[PathBuf]) -> Vec<String> {
+    let parents: Vec<Vec<Component>> = paths
+        .iter()
+        .map(|path| {
+            path.parent()
+                .map_or_else(Vec::new, |dir| dir.components().collect())
+        })
+        .collect();
+    let common = match parents.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.len(), |len, dirs| {
+            len.min(first.iter().zip(dirs).take_while(|(a, b)| a == b).count())
+        }),
+        None => 0,
+    };
+    paths
+        .iter()
+        .map(|path| {
+            if common == 0 {
+                return file_name(path);
+            }
+            path.components()
+                .skip(common)
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_keep_distinguishing_subpaths() {
+        let root = std::env::temp_dir().join("lat-relative");
+        let paths = [
+            root.join("app/config.txt"),
+            root.join("db/config.txt"),
+            root.join("db/schema/tables.sql"),
+        ];
+        assert_eq!(
+            relative_names(&paths),
+            ["app/config.txt", "db/config.txt", "db/schema/tables.sql"]
+        );
+
+        assert_eq!(relative_names(&paths[1..2]), ["config.txt"]);
+        assert_eq!(
+            relative_names(&paths[1..]),
+            ["config.txt", "schema/tables.sql"]
+        );
+
+        // Relative paths from unrelated folders share no ancestor.
+        let unrelated = [PathBuf::from("one/a.txt"), PathBuf::from("two/b.txt")];
+        assert_eq!(relative_names(&unrelated), ["a.txt", "b.txt"]);
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What to do when the destination of a compression already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Ask before replacing the file.
+    Confirm,
+    /// Replace the file without asking (the OS save dialog already warned).
+    Overwrite,
+}
+
+/// Whether writing to a destination may go ahead. `confirm` is only asked when the file
+/// exists and the policy says to.
+pub fn may_write(exists: bool, policy: OverwritePolicy, confirm: impl FnOnce() -> bool) -> bool {
+    !exists || policy == OverwritePolicy::Overwrite || confirm()
+}
+
+/// Sibling temp file the archive is written to before being renamed over `dest`.
+fn temp_path(dest: &Path) -> PathBuf {
+    let name = dest
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    dest.with_file_name(format!(".{}.lat-tmp", name))
+}
+
+/// Writes `data` to `dest` via a temp file and a rename, so an interrupted write or a
+/// declined overwrite never leaves the original half-replaced. Returns `Ok(false)` without
+/// touching anything when `confirm` declines.
+pub fn save_archive(
+    dest: &Path,
+    data: &[u8],
+    policy: OverwritePolicy,
+    confirm: impl FnOnce() -> bool,
+) -> io::Result<bool> {
+    if !may_write(dest.exists(), policy, confirm) {
+        return Ok(false);
+    }
+    let temp = temp_path(dest);
+    if let Err(e) = fs::write(&temp, data).and_then(|()| fs::rename(&temp, dest)) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overwrite_confirmation() {
+        let dir = std::env::temp_dir().join(format!("lat-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("archive.zip");
+        fs::write(&dest, b"original").unwrap();
+
+        // Declining keeps the original and leaves no temp file behind.
+        let saved = save_archive(&dest, b"new", OverwritePolicy::Confirm, || false).unwrap();
+        assert!(!saved);
+        assert_eq!(fs::read(&dest).unwrap(), b"original");
+        assert!(!temp_path(&dest).exists());
+
+        let saved = save_archive(&dest, b"new", OverwritePolicy::Confirm, || true).unwrap();
+        assert!(saved);
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+
+        // New files and the overwrite policy never prompt.
+        assert!(may_write(
+            false,
+            OverwritePolicy::Confirm,
+            || unreachable!()
+        ));
+        assert!(may_write(
+            true,
+            OverwritePolicy::Overwrite,
+            || unreachable!()
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
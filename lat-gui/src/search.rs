@@ -0,0 +1,53 @@
+/// Whether `query` is a glob rather than plain text.
+fn is_glob(query: &str) -> bool {
+    query.contains(['*', '?', '['])
+}
+
+/// Indices of the names matching `query`, ignoring case. A query with `*`, `?` or `[` is a
+/// glob over the whole name (falling back to plain text if it doesn't compile); anything
+/// else matches as a substring. A blank query matches everything.
+pub fn matching_indices<'a>(names: impl IntoIterator<Item = &'a str>, query: &str) -> Vec<usize> {
+    let query = query.trim().to_lowercase();
+    let glob = is_glob(&query)
+        .then(|| lat_core::glob_matcher(&query).ok())
+        .flatten();
+    names
+        .into_iter()
+        .enumerate()
+        .filter(|(_, name)| {
+            let name = name.to_lowercase();
+            match &glob {
+                Some(glob) => glob.is_match(&name),
+                None => name.contains(&query),
+            }
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 5] = [
+        "Report.PDF",
+        "report-draft.txt",
+        "photo.jpg",
+        "notes.txt",
+        "a[1].txt",
+    ];
+
+    #[test]
+    fn test_substring_glob_and_case() {
+        assert_eq!(matching_indices(NAMES, "report"), [0, 1]);
+        assert_eq!(matching_indices(NAMES, "REPORT"), [0, 1]);
+        assert_eq!(matching_indices(NAMES, "*.txt"), [1, 3, 4]);
+        assert_eq!(matching_indices(NAMES, "*.pdf"), [0]);
+        assert_eq!(matching_indices(NAMES, "?hoto.*"), [2]);
+        // Globs match the whole name, not a part of it.
+        assert!(matching_indices(NAMES, "*.tx").is_empty());
+        // An unclosed class isn't a valid glob, so it's searched for literally.
+        assert_eq!(matching_indices(NAMES, "a[1"), [4]);
+        assert_eq!(matching_indices(NAMES, "  ").len(), NAMES.len());
+    }
+}
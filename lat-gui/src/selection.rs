@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Path used to compare files: canonical when the file exists, so `./a.txt`, `a.txt` and
+/// symlinks to it all compare equal, and as given otherwise.
+fn identity(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Filters `candidates` down to files not already in `existing` and not repeated within
+/// `candidates`, keeping their order. Returns the new paths and how many were skipped.
+pub fn unique_new_paths<P: AsRef<Path>>(
+    existing: impl IntoIterator<Item = P>,
+    candidates: Vec<PathBuf>,
+) -> (Vec<PathBuf>, usize) {
+    let mut seen: HashSet<PathBuf> = existing
+        .into_iter()
+        .map(|path| identity(path.as_ref()))
+        .collect();
+    let total = candidates.len();
+    let fresh: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|path| seen.insert(identity(path)))
+        .collect();
+    let skipped = total - fresh.len();
+    (fresh, skipped)
+}
+
+/// Row of the selected entry, given the listed entries' ids in display order, or `None`
+/// when nothing is selected (`selected` is -1) or the entry has since been removed.
+/// Selecting by id rather than by row keeps the selection on the same file when rows
+/// are added, removed or reordered.
+pub fn selected_row(ids: impl IntoIterator<Item = i32>, selected: i32) -> Option<usize> {
+    if selected < 0 {
+        return None;
+    }
+    ids.into_iter().position(|id| id == selected)
+}
+
+/// Every file below the folder `root`, paired with its archive name: the path from
+/// `root`'s parent with `/` separators, so the folder itself is kept (`photos/2024/a.jpg`).
+/// Files come sorted by name. Symlinked folders aren't followed; folders that can't be
+/// read are skipped and counted.
+pub fn scan_folder(root: &Path) -> (Vec<(String, PathBuf)>, usize) {
+    let base = root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut files = Vec::new();
+    let mut unreadable = 0;
+    let mut pending = vec![(base, root.to_path_buf())];
+    while let Some((prefix, dir)) = pending.pop() {
+        let Ok(items) = fs::read_dir(&dir) else {
+            unreadable += 1;
+            continue;
+        };
+        for item in items.flatten() {
+            let name = item.file_name().to_string_lossy().into_owned();
+            let name = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            match item.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push((name, item.path())),
+                Ok(_) => files.push((name, item.path())),
+                Err(_) => unreadable += 1,
+            }
+        }
+    }
+    files.sort();
+    (files, unreadable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_selections_are_deduplicated() {
+        let dir = std::env::temp_dir().join(format!("lat-selection-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let a = dir.join("a.txt");
+        let existing = [a.to_str().unwrap(), dir.join("b.txt").to_str().unwrap()].map(String::from);
+
+        let candidates = vec![
+            dir.join("sub/../a.txt"), // same file as `a`, spelled differently
+            dir.join("c.txt"),
+            dir.join("b.txt"),
+            dir.join("c.txt"), // repeated within the new selection
+        ];
+        let (fresh, skipped) = unique_new_paths(existing.iter().map(String::as_str), candidates);
+        assert_eq!(fresh, [dir.join("c.txt")]);
+        assert_eq!(skipped, 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_selection_follows_the_entry() {
+        let mut ids = vec![10, 11, 12];
+        assert_eq!(selected_row(ids.iter().copied(), -1), None);
+        assert_eq!(selected_row(ids.iter().copied(), 11), Some(1));
+
+        // Rows added before and after it move it, but it stays selected.
+        ids.insert(0, 13);
+        ids.push(14);
+        assert_eq!(selected_row(ids.iter().copied(), 11), Some(2));
+
+        // Removing another row shifts it back.
+        ids.retain(|&id| id != 10);
+        assert_eq!(selected_row(ids.iter().copied(), 11), Some(1));
+        ids.reverse();
+        assert_eq!(selected_row(ids.iter().copied(), 11), Some(2));
+
+        // Removing it clears the selection instead of landing on a neighbour.
+        ids.retain(|&id| id != 11);
+        assert_eq!(selected_row(ids.iter().copied(), 11), None);
+    }
+
+    #[test]
+    fn test_folder_scan_names_files_relative_to_the_folder() {
+        let dir = std::env::temp_dir().join(format!("lat-scan-{}", std::process::id()));
+        let root = dir.join("project");
+        fs::create_dir_all(root.join("src/deep")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        for name in ["readme.md", "src/main.rs", "src/deep/util.rs"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+
+        let (files, unreadable) = scan_folder(&root);
+        assert_eq!(unreadable, 0);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "project/readme.md",
+                "project/src/deep/util.rs",
+                "project/src/main.rs"
+            ]
+        );
+        for (name, path) in &files {
+            assert_eq!(path, &dir.join(name));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
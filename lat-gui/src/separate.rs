@@ -0,0 +1,74 @@
+use lat_core::batch::{OutputTemplate, compress_each};
+use lat_core::{ArchiveFormat, Compressor, LatError};
+use std::path::{Path, PathBuf};
+
+/// Output names for per-file compression: `a.txt` becomes `a.txt.gz`.
+const TEMPLATE: &str = "{name}.{ext}";
+
+/// Checks that `format` writes one file per archive; formats that bundle files fail with
+/// [`LatError::InvalidInput`].
+pub fn check_format(format: ArchiveFormat) -> Result<(), LatError> {
+    if format.is_single_stream() {
+        return Ok(());
+    }
+    Err(LatError::InvalidInput(format!(
+        "{} bundles files; choose gzip or bzip2 to compress each file separately",
+        format.display_name()
+    )))
+}
+
+/// Compresses each of `paths` into its own archive in `out_dir` (see [`compress_each`]),
+/// returning the archives written. The format is checked with [`check_format`] before
+/// anything is written.
+pub fn compress_separately(
+    compressor: &dyn Compressor,
+    paths: &[PathBuf],
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, LatError> {
+    check_format(compressor.format())?;
+    compress_each(
+        compressor,
+        paths,
+        out_dir,
+        &OutputTemplate::parse(TEMPLATE)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lat_formats::gzip::GzipCompressor;
+    use lat_formats::zip::ZipCompressor;
+    use std::fs;
+
+    #[test]
+    fn test_each_file_gets_its_own_archive() {
+        let dir = std::env::temp_dir().join(format!("lat-separate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let paths: Vec<PathBuf> = ["a.txt", "b.bin"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, name.repeat(20)).unwrap();
+                path
+            })
+            .collect();
+
+        let zip = compress_separately(&ZipCompressor::new(), &paths, &out);
+        assert!(matches!(zip, Err(LatError::InvalidInput(_))));
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 0);
+
+        let gzip = GzipCompressor::new();
+        let written = compress_separately(&gzip, &paths, &out).unwrap();
+        assert_eq!(written, [out.join("a.txt.gz"), out.join("b.bin.gz")]);
+        for (archive, name) in written.iter().zip(["a.txt", "b.bin"]) {
+            let entries = gzip.decompress(&fs::read(archive).unwrap(), None).unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, name);
+            assert_eq!(entries[0].data, name.repeat(20).as_bytes());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}